@@ -0,0 +1,867 @@
+//! Audiobookshelf endpoint handlers
+
+use super::models::{
+    AbsAudioTrack, AbsBookMedia, AbsBookMetadata, AbsChapter, AbsFolder, AbsLibrariesResponse,
+    AbsLibrary, AbsLibraryItem, AbsLibraryItemsQuery, AbsLibraryItemsResponse, AbsLoginResponse,
+    AbsMediaProgress, AbsPermissions, AbsPerson, AbsPlayRequest, AbsPlaybackSession,
+    AbsProgressUpdate, AbsServerSettings, AbsSessionSync, AbsShelf, AbsStatusResponse, AbsUser,
+};
+use super::{AbsState, ABS_SERVER_VERSION};
+use crate::api::handlers::media::{proxy_cover, stream_chapter, ProxyCoverQuery, StreamQuery};
+use crate::api::handlers::AppState;
+use crate::api::utils::request_info_from_headers;
+use crate::api::ws::handler::broadcast_progress_update;
+use crate::auth::handlers::login_with_password;
+use crate::auth::middleware::AuthUser;
+use crate::auth::models::LoginRequest;
+use crate::core::error::{Result, TingError};
+use crate::db::models::{Book, Chapter, Library, Progress, User};
+use crate::db::repository::progress::{COMPLETION_RATIO, COMPLETION_TAIL_SECS};
+use crate::db::repository::Repository;
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+/// Books on each home screen shelf
+const SHELF_SIZE: usize = 10;
+
+/// Handler for GET /abs/ping
+pub async fn ping() -> impl IntoResponse {
+    Json(serde_json::json!({ "success": true }))
+}
+
+/// Handler for GET /abs/status - Lets clients recognise the server before login
+pub async fn status() -> Json<AbsStatusResponse> {
+    Json(AbsStatusResponse {
+        app: "audiobookshelf".to_string(),
+        server_version: ABS_SERVER_VERSION.to_string(),
+        is_init: true,
+        language: "en-us".to_string(),
+        auth_methods: vec!["local".to_string()],
+        auth_form_data: serde_json::json!({}),
+    })
+}
+
+/// Handler for POST /abs/login - Password login returning a Ting Reader token
+pub async fn login(
+    State(abs): State<AbsState>,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(req): Json<LoginRequest>,
+) -> Result<Json<AbsLoginResponse>> {
+    let peer_addr = connect_info.map(|ConnectInfo(addr)| addr);
+    let request_info = request_info_from_headers(&headers, peer_addr);
+    let (user, token) =
+        login_with_password(&abs.app, &req, &request_info, "audiobookshelf").await?;
+    Ok(Json(login_response(&abs.app, &user, token).await?))
+}
+
+/// Handler for POST /abs/api/authorize - Validate a stored token
+pub async fn authorize(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    headers: HeaderMap,
+) -> Result<Json<AbsLoginResponse>> {
+    let user = load_user(&abs.app, &user).await?;
+    Ok(Json(
+        login_response(&abs.app, &user, request_token(&headers)).await?,
+    ))
+}
+
+/// Handler for GET /abs/api/me
+pub async fn get_me(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    headers: HeaderMap,
+) -> Result<Json<AbsUser>> {
+    let user = load_user(&abs.app, &user).await?;
+    Ok(Json(
+        abs_user(&abs.app, &user, request_token(&headers)).await?,
+    ))
+}
+
+/// Handler for GET /abs/api/me/progress/:id
+pub async fn get_progress(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    Path(item_id): Path<String>,
+) -> Result<Json<AbsMediaProgress>> {
+    let book = load_book(&abs.app, &user, &item_id).await?;
+    let progress = abs
+        .app
+        .progress_repo
+        .get_by_book(&user.id, &book.id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("No progress for item {}", item_id)))?;
+    let chapters = abs.app.chapter_repo.find_by_book(&book.id).await?;
+    Ok(Json(media_progress(&book.id, &chapters, &progress)))
+}
+
+/// Handler for PATCH /abs/api/me/progress/:id - Set the position or mark finished
+pub async fn update_progress(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    Path(item_id): Path<String>,
+    Json(req): Json<AbsProgressUpdate>,
+) -> Result<Json<AbsMediaProgress>> {
+    let book = load_book(&abs.app, &user, &item_id).await?;
+    let chapters = abs.app.chapter_repo.find_by_book(&book.id).await?;
+
+    let time = match (req.is_finished, req.current_time) {
+        (Some(true), _) => finished_time(&chapters),
+        (_, Some(time)) => time,
+        (Some(false), None) => 0.0,
+        (None, None) => {
+            return Err(TingError::InvalidRequest(
+                "currentTime or isFinished is required".to_string(),
+            ))
+        }
+    };
+    let progress = save_position(&abs.app, &user.id, &book.id, &chapters, time).await?;
+    Ok(Json(media_progress(&book.id, &chapters, &progress)))
+}
+
+/// Handler for GET /abs/api/libraries
+pub async fn list_libraries(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+) -> Result<Json<AbsLibrariesResponse>> {
+    let libraries = accessible_libraries(&abs.app, &user)
+        .await?
+        .into_iter()
+        .enumerate()
+        .map(|(idx, library)| abs_library(library, idx + 1))
+        .collect();
+    Ok(Json(AbsLibrariesResponse { libraries }))
+}
+
+/// Handler for GET /abs/api/libraries/:id
+pub async fn get_library(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    Path(library_id): Path<String>,
+) -> Result<Json<AbsLibrary>> {
+    let library = find_library(&abs.app, &user, &library_id).await?;
+    Ok(Json(abs_library(library, 1)))
+}
+
+/// Handler for GET /abs/api/libraries/:id/items - Page through a library's books
+pub async fn list_library_items(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    Path(library_id): Path<String>,
+    Query(query): Query<AbsLibraryItemsQuery>,
+) -> Result<Json<AbsLibraryItemsResponse>> {
+    let library = find_library(&abs.app, &user, &library_id).await?;
+    let books = abs
+        .app
+        .book_repo
+        .find_with_filters(&user.id, is_admin(&user), None, None, Some(library.id))
+        .await?;
+
+    let total = books.len();
+    let limit = query.limit.unwrap_or(0);
+    let page = query.page.unwrap_or(0);
+    let page_books: Vec<Book> = if limit == 0 {
+        books
+    } else {
+        books.into_iter().skip(page * limit).take(limit).collect()
+    };
+
+    let mut results = Vec::with_capacity(page_books.len());
+    for book in page_books {
+        results.push(library_item(&abs.app, &user.id, book).await?);
+    }
+
+    Ok(Json(AbsLibraryItemsResponse {
+        results,
+        total,
+        limit,
+        page,
+        media_type: "book".to_string(),
+        minified: false,
+    }))
+}
+
+/// Handler for GET /abs/api/libraries/:id/personalized - Home screen shelves
+pub async fn get_personalized(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    Path(library_id): Path<String>,
+) -> Result<Json<Vec<AbsShelf>>> {
+    let library = find_library(&abs.app, &user, &library_id).await?;
+
+    let mut seen = HashSet::new();
+    let mut continue_listening = Vec::new();
+    for progress in abs
+        .app
+        .progress_repo
+        .get_recent(&user.id, Some(100))
+        .await?
+    {
+        if continue_listening.len() >= SHELF_SIZE || !seen.insert(progress.book_id.clone()) {
+            continue;
+        }
+        let Some(book) = abs.app.book_repo.find_by_id(&progress.book_id).await? else {
+            continue;
+        };
+        if book.library_id != library.id
+            || !abs
+                .app
+                .book_repo
+                .check_access(&book.id, &user.id, is_admin(&user))
+                .await?
+        {
+            continue;
+        }
+        let item = library_item(&abs.app, &user.id, book).await?;
+        if item
+            .user_media_progress
+            .as_ref()
+            .is_some_and(|progress| !progress.is_finished)
+        {
+            continue_listening.push(item);
+        }
+    }
+
+    let recent_books = abs
+        .app
+        .book_repo
+        .find_with_filters(&user.id, is_admin(&user), None, None, Some(library.id))
+        .await?;
+    let mut recently_added = Vec::new();
+    for book in recent_books.into_iter().take(SHELF_SIZE) {
+        recently_added.push(library_item(&abs.app, &user.id, book).await?);
+    }
+
+    Ok(Json(vec![
+        shelf(
+            "continue-listening",
+            "Continue Listening",
+            continue_listening,
+        ),
+        shelf("recently-added", "Recently Added", recently_added),
+    ]))
+}
+
+/// Handler for GET /abs/api/items/:id
+pub async fn get_item(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    Path(item_id): Path<String>,
+) -> Result<Json<AbsLibraryItem>> {
+    let book = load_book(&abs.app, &user, &item_id).await?;
+    Ok(Json(library_item(&abs.app, &user.id, book).await?))
+}
+
+/// Handler for GET /abs/api/items/:id/cover
+pub async fn get_cover(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    Path(item_id): Path<String>,
+) -> Result<Response> {
+    let book = load_book(&abs.app, &user, &item_id).await?;
+    let path = book
+        .cover_url
+        .clone()
+        .filter(|path| !path.trim().is_empty())
+        .ok_or_else(|| TingError::NotFound(format!("Item {} has no cover", item_id)))?;
+    let params = ProxyCoverQuery {
+        path,
+        library_id: Some(book.library_id),
+        book_id: Some(book.id),
+    };
+    proxy_cover(State(abs.app), Query(params))
+        .await
+        .map(IntoResponse::into_response)
+}
+
+/// Handler for GET /abs/api/items/:id/file/:fileId - Stream one track (a chapter)
+pub async fn stream_track(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    Path((item_id, chapter_id)): Path<(String, String)>,
+    query: Query<StreamQuery>,
+    method: Method,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let chapter = abs
+        .app
+        .chapter_repo
+        .find_by_id(&chapter_id)
+        .await?
+        .filter(|chapter| chapter.book_id == item_id)
+        .ok_or_else(|| TingError::NotFound(format!("Track {} not found", chapter_id)))?;
+    stream_chapter(
+        State(abs.app),
+        Path(chapter.id),
+        query,
+        method,
+        headers,
+        Some(user),
+    )
+    .await
+}
+
+/// Handler for POST /abs/api/items/:id/play - Open a direct-play session
+pub async fn start_playback(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    Path(item_id): Path<String>,
+    body: Option<Json<AbsPlayRequest>>,
+) -> Result<Json<AbsPlaybackSession>> {
+    let book = load_book(&abs.app, &user, &item_id).await?;
+    let chapters = abs.app.chapter_repo.find_by_book(&book.id).await?;
+    if chapters.is_empty() {
+        return Err(TingError::InvalidRequest(format!(
+            "Item {} has no audio",
+            item_id
+        )));
+    }
+    let progress = abs
+        .app
+        .progress_repo
+        .get_by_book(&user.id, &book.id)
+        .await?;
+    let library_item = build_library_item(book, &chapters, progress.as_ref());
+    let current_time = library_item
+        .user_media_progress
+        .as_ref()
+        .filter(|progress| !progress.is_finished)
+        .map_or(0.0, |progress| progress.current_time);
+
+    let session_id = abs.open_session(&user.id, &library_item.id).await;
+    let now = chrono::Utc::now().timestamp_millis();
+    let media = &library_item.media;
+    Ok(Json(AbsPlaybackSession {
+        id: session_id,
+        user_id: user.id,
+        library_id: library_item.library_id.clone(),
+        library_item_id: library_item.id.clone(),
+        episode_id: None,
+        media_type: "book".to_string(),
+        media_metadata: media.metadata.clone(),
+        chapters: media.chapters.clone(),
+        display_title: media.metadata.title.clone(),
+        display_author: media.metadata.author_name.clone(),
+        cover_path: media.cover_path.clone(),
+        duration: media.duration,
+        play_method: 0,
+        media_player: body
+            .and_then(|Json(req)| req.media_player)
+            .unwrap_or_else(|| "unknown".to_string()),
+        server_version: ABS_SERVER_VERSION.to_string(),
+        start_time: current_time,
+        current_time,
+        started_at: now,
+        updated_at: now,
+        audio_tracks: media.tracks.clone(),
+        library_item,
+    }))
+}
+
+/// Handler for POST /abs/api/session/:id/sync - Store the session's position
+pub async fn sync_session(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    Path(session_id): Path<String>,
+    body: Option<Json<AbsSessionSync>>,
+) -> Result<StatusCode> {
+    let book_id = abs.session_book(&session_id, &user.id).await?;
+    if let Some(time) = body.and_then(|Json(req)| req.current_time) {
+        let chapters = abs.app.chapter_repo.find_by_book(&book_id).await?;
+        save_position(&abs.app, &user.id, &book_id, &chapters, time).await?;
+    }
+    Ok(StatusCode::OK)
+}
+
+/// Handler for POST /abs/api/session/:id/close - Store the final position and end the session
+pub async fn close_session(
+    State(abs): State<AbsState>,
+    user: AuthUser,
+    Path(session_id): Path<String>,
+    body: Option<Json<AbsSessionSync>>,
+) -> Result<StatusCode> {
+    let status = sync_session(State(abs.clone()), user, Path(session_id.clone()), body).await?;
+    abs.close_session(&session_id).await;
+    Ok(status)
+}
+
+fn is_admin(user: &AuthUser) -> bool {
+    user.role == "admin"
+}
+
+fn request_token(headers: &HeaderMap) -> String {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Milliseconds since the epoch of a stored timestamp, 0 when it cannot be read
+fn epoch_ms(value: &str) -> i64 {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|at| at.timestamp_millis())
+        .or_else(|_| {
+            chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+                .map(|at| at.and_utc().timestamp_millis())
+        })
+        .unwrap_or(0)
+}
+
+async fn load_user(state: &AppState, user: &AuthUser) -> Result<User> {
+    state
+        .user_repo
+        .find_by_id(&user.id)
+        .await?
+        .ok_or_else(|| TingError::AuthenticationError("用户不存在".to_string()))
+}
+
+async fn load_book(state: &AppState, user: &AuthUser, book_id: &str) -> Result<Book> {
+    let book = state
+        .book_repo
+        .find_by_id(book_id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("Item {} not found", book_id)))?;
+    if !state
+        .book_repo
+        .check_access(&book.id, &user.id, is_admin(user))
+        .await?
+    {
+        return Err(TingError::PermissionDenied(
+            "No access to this item".to_string(),
+        ));
+    }
+    Ok(book)
+}
+
+async fn accessible_libraries(state: &AppState, user: &AuthUser) -> Result<Vec<Library>> {
+    if is_admin(user) {
+        state.library_repo.find_all().await
+    } else {
+        state.library_repo.find_by_user_access(&user.id).await
+    }
+}
+
+async fn find_library(state: &AppState, user: &AuthUser, library_id: &str) -> Result<Library> {
+    accessible_libraries(state, user)
+        .await?
+        .into_iter()
+        .find(|library| library.id == library_id)
+        .ok_or_else(|| TingError::NotFound(format!("Library {} not found", library_id)))
+}
+
+async fn login_response(state: &AppState, user: &User, token: String) -> Result<AbsLoginResponse> {
+    let user = abs_user(state, user, token).await?;
+    let user_default_library_id = if user.permissions.access_all_libraries {
+        state
+            .library_repo
+            .find_all()
+            .await?
+            .into_iter()
+            .next()
+            .map(|library| library.id)
+    } else {
+        user.libraries_accessible.first().cloned()
+    };
+
+    Ok(AbsLoginResponse {
+        user,
+        user_default_library_id,
+        server_settings: AbsServerSettings {
+            id: "server-settings".to_string(),
+            version: ABS_SERVER_VERSION.to_string(),
+            sorting_ignore_prefix: false,
+            chromecast_enabled: false,
+        },
+        source: "ting-reader".to_string(),
+    })
+}
+
+async fn abs_user(state: &AppState, user: &User, token: String) -> Result<AbsUser> {
+    let admin = user.role == "admin";
+    let libraries_accessible = if admin {
+        Vec::new()
+    } else {
+        state.user_repo.get_accessible_libraries(&user.id).await?
+    };
+
+    let mut seen = HashSet::new();
+    let mut media_progress = Vec::new();
+    for progress in state.progress_repo.get_recent(&user.id, Some(200)).await? {
+        if !seen.insert(progress.book_id.clone()) {
+            continue;
+        }
+        let chapters = state.chapter_repo.find_by_book(&progress.book_id).await?;
+        media_progress.push(self::media_progress(
+            &progress.book_id,
+            &chapters,
+            &progress,
+        ));
+    }
+
+    Ok(AbsUser {
+        id: user.id.clone(),
+        username: user.username.clone(),
+        user_type: if admin { "admin" } else { "user" }.to_string(),
+        token,
+        media_progress,
+        series_hide_from_continue_listening: Vec::new(),
+        bookmarks: Vec::new(),
+        is_active: true,
+        is_locked: false,
+        created_at: epoch_ms(&user.created_at),
+        permissions: AbsPermissions {
+            download: true,
+            update: admin,
+            delete: admin,
+            upload: admin,
+            access_all_libraries: admin,
+            access_all_tags: true,
+            access_explicit_content: true,
+        },
+        libraries_accessible,
+        item_tags_accessible: Vec::new(),
+    })
+}
+
+fn abs_library(library: Library, display_order: usize) -> AbsLibrary {
+    let created_at = epoch_ms(&library.created_at);
+    let full_path = if library.root_path.trim().is_empty() {
+        library.url.clone()
+    } else {
+        library.root_path.clone()
+    };
+    AbsLibrary {
+        folders: vec![AbsFolder {
+            id: library.id.clone(),
+            full_path,
+            library_id: library.id.clone(),
+        }],
+        id: library.id,
+        name: library.name,
+        display_order,
+        icon: "audiobookshelf".to_string(),
+        media_type: "book".to_string(),
+        provider: "audible".to_string(),
+        settings: serde_json::json!({
+            "coverAspectRatio": 1,
+            "disableWatcher": true,
+        }),
+        created_at,
+        last_update: library
+            .last_scanned_at
+            .as_deref()
+            .map(epoch_ms)
+            .unwrap_or(created_at),
+    }
+}
+
+fn shelf(id: &str, label: &str, entities: Vec<AbsLibraryItem>) -> AbsShelf {
+    AbsShelf {
+        id: id.to_string(),
+        label: label.to_string(),
+        shelf_type: "book".to_string(),
+        total: entities.len(),
+        entities,
+    }
+}
+
+async fn library_item(state: &AppState, user_id: &str, book: Book) -> Result<AbsLibraryItem> {
+    let chapters = state.chapter_repo.find_by_book(&book.id).await?;
+    let progress = state.progress_repo.get_by_book(user_id, &book.id).await?;
+    Ok(build_library_item(book, &chapters, progress.as_ref()))
+}
+
+fn build_library_item(
+    book: Book,
+    chapters: &[Chapter],
+    progress: Option<&Progress>,
+) -> AbsLibraryItem {
+    let author_name = book.author.clone().unwrap_or_default();
+    let narrator_name = book.narrator.clone().unwrap_or_default();
+    let tracks = audio_tracks(&book.id, chapters);
+    let duration = tracks.iter().map(|track| track.duration).sum();
+    let added_at = epoch_ms(&book.created_at);
+
+    let metadata = AbsBookMetadata {
+        title: book.title.clone(),
+        subtitle: None,
+        authors: split_names(&author_name)
+            .into_iter()
+            .map(|name| AbsPerson {
+                id: name.clone(),
+                name,
+            })
+            .collect(),
+        narrators: split_names(&narrator_name),
+        series: Vec::new(),
+        genres: split_names(book.genre.as_deref().unwrap_or_default()),
+        published_year: book.year.map(|year| year.to_string()),
+        description: book.description.clone(),
+        explicit: false,
+        author_name,
+        narrator_name,
+    };
+
+    AbsLibraryItem {
+        id: book.id.clone(),
+        ino: book.id.clone(),
+        library_id: book.library_id.clone(),
+        folder_id: book.library_id.clone(),
+        path: book.path.clone(),
+        rel_path: book.path.clone(),
+        is_file: false,
+        added_at,
+        updated_at: added_at,
+        is_missing: false,
+        is_invalid: false,
+        media_type: "book".to_string(),
+        media: AbsBookMedia {
+            library_item_id: book.id.clone(),
+            metadata,
+            cover_path: book.cover_url.clone(),
+            tags: split_names(book.tags.as_deref().unwrap_or_default()),
+            chapters: tracks
+                .iter()
+                .enumerate()
+                .map(|(idx, track)| AbsChapter {
+                    id: idx,
+                    start: track.start_offset,
+                    end: track.start_offset + track.duration,
+                    title: track.title.clone(),
+                })
+                .collect(),
+            duration,
+            size: 0,
+            num_tracks: tracks.len(),
+            num_audio_files: tracks.len(),
+            num_chapters: tracks.len(),
+            tracks,
+        },
+        num_files: chapters.len(),
+        size: 0,
+        user_media_progress: progress.map(|progress| media_progress(&book.id, chapters, progress)),
+    }
+}
+
+/// Split a comma or 、 separated list of names
+fn split_names(value: &str) -> Vec<String> {
+    value
+        .split([',', '，', '、'])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn chapter_length(chapter: &Chapter) -> f64 {
+    chapter.slice_length().unwrap_or(0.0)
+}
+
+/// One track per chapter, laid end to end
+fn audio_tracks(book_id: &str, chapters: &[Chapter]) -> Vec<AbsAudioTrack> {
+    let mut start = 0.0;
+    chapters
+        .iter()
+        .enumerate()
+        .map(|(idx, chapter)| {
+            let duration = chapter_length(chapter);
+            let mime_type = mime_guess::from_path(&chapter.path)
+                .first()
+                .map(|mime| mime.to_string())
+                .filter(|mime| mime.starts_with("audio/"))
+                .unwrap_or_else(|| "audio/mpeg".to_string());
+            let track = AbsAudioTrack {
+                index: idx + 1,
+                start_offset: start,
+                duration,
+                title: chapter
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| format!("Chapter {}", idx + 1)),
+                content_url: format!("/api/items/{}/file/{}", book_id, chapter.id),
+                mime_type,
+            };
+            start += duration;
+            track
+        })
+        .collect()
+}
+
+/// Seconds from the start of the book to `position` inside `chapter_id`
+fn book_time(chapters: &[Chapter], chapter_id: Option<&str>, position: f64) -> f64 {
+    let mut start = 0.0;
+    for chapter in chapters {
+        if Some(chapter.id.as_str()) == chapter_id {
+            return start + position;
+        }
+        start += chapter_length(chapter);
+    }
+    position
+}
+
+/// Chapter playing `time` seconds into the book, and the position inside it
+fn chapter_at(chapters: &[Chapter], time: f64) -> Option<(&Chapter, f64)> {
+    let time = time.max(0.0);
+    let mut start = 0.0;
+    for (idx, chapter) in chapters.iter().enumerate() {
+        let length = chapter_length(chapter);
+        if time < start + length || idx + 1 == chapters.len() {
+            return Some((chapter, (time - start).clamp(0.0, length)));
+        }
+        start += length;
+    }
+    None
+}
+
+/// Book time at the end of the last chapter that is not an extra
+fn finished_time(chapters: &[Chapter]) -> f64 {
+    chapters
+        .iter()
+        .rev()
+        .find(|chapter| chapter.is_extra == 0)
+        .map_or(0.0, |last| {
+            book_time(chapters, Some(&last.id), chapter_length(last))
+        })
+}
+
+/// Whether the position is at the end of the last chapter that is not an extra
+fn is_finished(chapters: &[Chapter], chapter_id: Option<&str>, position: f64) -> bool {
+    let Some(last) = chapters.iter().rev().find(|chapter| chapter.is_extra == 0) else {
+        return false;
+    };
+    let length = chapter_length(last);
+    Some(last.id.as_str()) == chapter_id
+        && length > 0.0
+        && (position >= length * COMPLETION_RATIO || position >= length - COMPLETION_TAIL_SECS)
+}
+
+fn media_progress(book_id: &str, chapters: &[Chapter], progress: &Progress) -> AbsMediaProgress {
+    let duration: f64 = chapters.iter().map(chapter_length).sum();
+    let current_time = book_time(chapters, progress.chapter_id.as_deref(), progress.position);
+    let finished = is_finished(chapters, progress.chapter_id.as_deref(), progress.position);
+    let last_update = epoch_ms(&progress.updated_at);
+    AbsMediaProgress {
+        id: progress.id.clone(),
+        library_item_id: book_id.to_string(),
+        episode_id: None,
+        duration,
+        progress: if finished {
+            1.0
+        } else if duration > 0.0 {
+            (current_time / duration).min(1.0)
+        } else {
+            0.0
+        },
+        current_time,
+        is_finished: finished,
+        hide_from_continue_listening: false,
+        last_update,
+        started_at: last_update,
+        finished_at: finished.then_some(last_update),
+    }
+}
+
+/// Store a book-wide position as chapter progress and tell the user's other devices
+async fn save_position(
+    state: &AppState,
+    user_id: &str,
+    book_id: &str,
+    chapters: &[Chapter],
+    time: f64,
+) -> Result<Progress> {
+    let (chapter, position) = chapter_at(chapters, time)
+        .ok_or_else(|| TingError::InvalidRequest("Item has no audio".to_string()))?;
+    let progress = Progress {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: user_id.to_string(),
+        book_id: book_id.to_string(),
+        chapter_id: Some(chapter.id.clone()),
+        position,
+        duration: chapter.slice_length(),
+        updated_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    state.progress_repo.upsert(&progress).await?;
+    state
+        .sleep_timers
+        .record_position(user_id, book_id, progress.chapter_id.as_deref(), position)
+        .await;
+    broadcast_progress_update(&state.ws_manager, user_id, &progress).await;
+    Ok(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(id: &str, duration: i32, is_extra: i32) -> Chapter {
+        Chapter {
+            id: id.to_string(),
+            book_id: "book-1".to_string(),
+            title: Some(id.to_string()),
+            path: format!("/book/{}.mp3", id),
+            duration: Some(duration),
+            chapter_index: None,
+            is_extra,
+            hash: None,
+            manual_corrected: 0,
+            created_at: String::new(),
+            start_offset: None,
+            end_offset: None,
+            loudness_lufs: None,
+            true_peak_dbtp: None,
+            skip_ranges: None,
+        }
+    }
+
+    #[test]
+    fn chapters_become_consecutive_tracks() {
+        let chapters = vec![chapter("one", 100, 0), chapter("two", 50, 0)];
+        let tracks = audio_tracks("book-1", &chapters);
+        assert_eq!(tracks[1].start_offset, 100.0);
+        assert_eq!(tracks[1].content_url, "/api/items/book-1/file/two");
+        assert_eq!(tracks[1].mime_type, "audio/mpeg");
+
+        assert_eq!(book_time(&chapters, Some("two"), 20.0), 120.0);
+        let (found, position) = chapter_at(&chapters, 120.0).unwrap();
+        assert_eq!((found.id.as_str(), position), ("two", 20.0));
+        let (found, position) = chapter_at(&chapters, 500.0).unwrap();
+        assert_eq!((found.id.as_str(), position), ("two", 50.0));
+    }
+
+    #[test]
+    fn finishing_ignores_extras() {
+        let chapters = vec![
+            chapter("one", 100, 0),
+            chapter("two", 100, 0),
+            chapter("bonus", 30, 1),
+        ];
+        assert_eq!(finished_time(&chapters), 200.0);
+        assert!(is_finished(&chapters, Some("two"), 96.0));
+        assert!(!is_finished(&chapters, Some("one"), 100.0));
+
+        let progress = Progress {
+            id: "p".to_string(),
+            user_id: "user-1".to_string(),
+            book_id: "book-1".to_string(),
+            chapter_id: Some("two".to_string()),
+            position: 100.0,
+            duration: Some(100.0),
+            updated_at: "2024-01-01T00:00:00Z".to_string(),
+        };
+        let progress = media_progress("book-1", &chapters, &progress);
+        assert!(progress.is_finished);
+        assert_eq!(progress.progress, 1.0);
+        assert_eq!(progress.current_time, 200.0);
+        assert_eq!(progress.last_update, 1_704_067_200_000);
+    }
+}
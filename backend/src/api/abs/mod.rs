@@ -0,0 +1,119 @@
+//! Audiobookshelf-compatible API
+//!
+//! An optional facade mounted under `/abs` when `server.enable_abs_api` is set,
+//! so the Audiobookshelf apps can log in, browse, stream and sync progress
+//! against Ting Reader. Library items are books, audio tracks are the book's
+//! chapters laid end to end, and book-wide positions are converted to and from
+//! per-chapter progress. Clients use the same JWT as the web app.
+
+pub mod handlers;
+pub mod models;
+
+use crate::api::handlers::AppState;
+use crate::auth::middleware::authenticate;
+use crate::core::error::{Result, TingError};
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Audiobookshelf version the facade reports to clients
+pub const ABS_SERVER_VERSION: &str = "2.17.0";
+/// Play sessions not closed by the client are forgotten after this long
+const SESSION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+struct PlaySession {
+    user_id: String,
+    book_id: String,
+    opened_at: Instant,
+}
+
+/// Router state: the application state plus open play sessions
+#[derive(Clone)]
+pub struct AbsState {
+    pub app: AppState,
+    sessions: Arc<Mutex<HashMap<String, PlaySession>>>,
+}
+
+impl AbsState {
+    pub fn new(app: AppState) -> Self {
+        Self {
+            app,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    async fn open_session(&self, user_id: &str, book_id: &str) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut sessions = self.sessions.lock().await;
+        sessions.retain(|_, session| session.opened_at.elapsed() < SESSION_TTL);
+        sessions.insert(
+            id.clone(),
+            PlaySession {
+                user_id: user_id.to_string(),
+                book_id: book_id.to_string(),
+                opened_at: Instant::now(),
+            },
+        );
+        id
+    }
+
+    /// Book played by one of the user's sessions
+    async fn session_book(&self, session_id: &str, user_id: &str) -> Result<String> {
+        let sessions = self.sessions.lock().await;
+        sessions
+            .get(session_id)
+            .filter(|session| session.user_id == user_id)
+            .map(|session| session.book_id.clone())
+            .ok_or_else(|| TingError::NotFound(format!("Session {} not found", session_id)))
+    }
+
+    async fn close_session(&self, session_id: &str) {
+        self.sessions.lock().await.remove(session_id);
+    }
+}
+
+/// Build the routes served under `/abs`
+pub fn build_abs_routes(state: AppState) -> Router {
+    let public_routes = Router::new()
+        .route("/ping", get(handlers::ping))
+        .route("/status", get(handlers::status))
+        .route("/login", post(handlers::login));
+
+    let protected_routes = Router::new()
+        .route("/api/authorize", post(handlers::authorize))
+        .route("/api/me", get(handlers::get_me))
+        .route(
+            "/api/me/progress/:id",
+            get(handlers::get_progress).patch(handlers::update_progress),
+        )
+        .route("/api/libraries", get(handlers::list_libraries))
+        .route("/api/libraries/:id", get(handlers::get_library))
+        .route(
+            "/api/libraries/:id/items",
+            get(handlers::list_library_items),
+        )
+        .route(
+            "/api/libraries/:id/personalized",
+            get(handlers::get_personalized),
+        )
+        .route("/api/items/:id", get(handlers::get_item))
+        .route("/api/items/:id/cover", get(handlers::get_cover))
+        .route(
+            "/api/items/:id/file/:fileId",
+            get(handlers::stream_track).head(handlers::stream_track),
+        )
+        .route("/api/items/:id/play", post(handlers::start_playback))
+        .route("/api/session/:id/sync", post(handlers::sync_session))
+        .route("/api/session/:id/close", post(handlers::close_session))
+        .layer(middleware::from_fn_with_state(state.clone(), authenticate));
+
+    public_routes
+        .merge(protected_routes)
+        .with_state(AbsState::new(state))
+}
@@ -0,0 +1,277 @@
+//! Audiobookshelf wire format
+//!
+//! Only the fields the official clients read are filled in. Times are
+//! milliseconds since the epoch and positions are seconds from the start of the
+//! book, as in Audiobookshelf.
+
+use serde::{Deserialize, Serialize};
+
+/// Response for POST /login and POST /api/authorize
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsLoginResponse {
+    pub user: AbsUser,
+    pub user_default_library_id: Option<String>,
+    pub server_settings: AbsServerSettings,
+    #[serde(rename = "Source")]
+    pub source: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsUser {
+    pub id: String,
+    pub username: String,
+    /// `admin` or `user`
+    #[serde(rename = "type")]
+    pub user_type: String,
+    pub token: String,
+    pub media_progress: Vec<AbsMediaProgress>,
+    pub series_hide_from_continue_listening: Vec<String>,
+    pub bookmarks: Vec<serde_json::Value>,
+    pub is_active: bool,
+    pub is_locked: bool,
+    pub created_at: i64,
+    pub permissions: AbsPermissions,
+    pub libraries_accessible: Vec<String>,
+    pub item_tags_accessible: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsPermissions {
+    pub download: bool,
+    pub update: bool,
+    pub delete: bool,
+    pub upload: bool,
+    pub access_all_libraries: bool,
+    pub access_all_tags: bool,
+    pub access_explicit_content: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsServerSettings {
+    pub id: String,
+    pub version: String,
+    pub sorting_ignore_prefix: bool,
+    pub chromecast_enabled: bool,
+}
+
+/// Response for GET /status
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsStatusResponse {
+    pub app: String,
+    pub server_version: String,
+    pub is_init: bool,
+    pub language: String,
+    pub auth_methods: Vec<String>,
+    pub auth_form_data: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsLibrary {
+    pub id: String,
+    pub name: String,
+    pub folders: Vec<AbsFolder>,
+    pub display_order: usize,
+    pub icon: String,
+    pub media_type: String,
+    pub provider: String,
+    pub settings: serde_json::Value,
+    pub created_at: i64,
+    pub last_update: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsFolder {
+    pub id: String,
+    pub full_path: String,
+    pub library_id: String,
+}
+
+/// Response for GET /api/libraries
+#[derive(Debug, Serialize)]
+pub struct AbsLibrariesResponse {
+    pub libraries: Vec<AbsLibrary>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AbsLibraryItemsQuery {
+    pub limit: Option<usize>,
+    pub page: Option<usize>,
+}
+
+/// Response for GET /api/libraries/:id/items
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsLibraryItemsResponse {
+    pub results: Vec<AbsLibraryItem>,
+    pub total: usize,
+    pub limit: usize,
+    pub page: usize,
+    pub media_type: String,
+    pub minified: bool,
+}
+
+/// One shelf of GET /api/libraries/:id/personalized
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsShelf {
+    pub id: String,
+    pub label: String,
+    #[serde(rename = "type")]
+    pub shelf_type: String,
+    pub entities: Vec<AbsLibraryItem>,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsLibraryItem {
+    pub id: String,
+    pub ino: String,
+    pub library_id: String,
+    pub folder_id: String,
+    pub path: String,
+    pub rel_path: String,
+    pub is_file: bool,
+    pub added_at: i64,
+    pub updated_at: i64,
+    pub is_missing: bool,
+    pub is_invalid: bool,
+    pub media_type: String,
+    pub media: AbsBookMedia,
+    pub num_files: usize,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_media_progress: Option<AbsMediaProgress>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsBookMedia {
+    pub library_item_id: String,
+    pub metadata: AbsBookMetadata,
+    pub cover_path: Option<String>,
+    pub tags: Vec<String>,
+    pub chapters: Vec<AbsChapter>,
+    pub tracks: Vec<AbsAudioTrack>,
+    pub duration: f64,
+    pub size: u64,
+    pub num_tracks: usize,
+    pub num_audio_files: usize,
+    pub num_chapters: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsBookMetadata {
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub authors: Vec<AbsPerson>,
+    pub narrators: Vec<String>,
+    pub series: Vec<serde_json::Value>,
+    pub genres: Vec<String>,
+    pub published_year: Option<String>,
+    pub description: Option<String>,
+    pub explicit: bool,
+    pub author_name: String,
+    pub narrator_name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AbsPerson {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AbsChapter {
+    pub id: usize,
+    pub start: f64,
+    pub end: f64,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsAudioTrack {
+    /// 1-based
+    pub index: usize,
+    pub start_offset: f64,
+    pub duration: f64,
+    pub title: String,
+    /// Relative to the `/abs` base the client logged in with
+    pub content_url: String,
+    pub mime_type: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsMediaProgress {
+    pub id: String,
+    pub library_item_id: String,
+    pub episode_id: Option<String>,
+    pub duration: f64,
+    pub progress: f64,
+    pub current_time: f64,
+    pub is_finished: bool,
+    pub hide_from_continue_listening: bool,
+    pub last_update: i64,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+/// Body of PATCH /api/me/progress/:id
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsProgressUpdate {
+    pub current_time: Option<f64>,
+    pub is_finished: Option<bool>,
+}
+
+/// Body of POST /api/session/:id/sync and /close
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsSessionSync {
+    pub current_time: Option<f64>,
+}
+
+/// Body of POST /api/items/:id/play
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsPlayRequest {
+    pub media_player: Option<String>,
+}
+
+/// Response for POST /api/items/:id/play
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsPlaybackSession {
+    pub id: String,
+    pub user_id: String,
+    pub library_id: String,
+    pub library_item_id: String,
+    pub episode_id: Option<String>,
+    pub media_type: String,
+    pub media_metadata: AbsBookMetadata,
+    pub chapters: Vec<AbsChapter>,
+    pub display_title: Option<String>,
+    pub display_author: String,
+    pub cover_path: Option<String>,
+    pub duration: f64,
+    /// 0 is direct play
+    pub play_method: u8,
+    pub media_player: String,
+    pub server_version: String,
+    pub start_time: f64,
+    pub current_time: f64,
+    pub started_at: i64,
+    pub updated_at: i64,
+    pub audio_tracks: Vec<AbsAudioTrack>,
+    pub library_item: AbsLibraryItem,
+}
@@ -0,0 +1,183 @@
+use super::AppState;
+use crate::api::models::{
+    BookmarkQuery, BookmarkResponse, CreateBookmarkRequest, UpdateBookmarkRequest,
+};
+use crate::api::ws::handler::{broadcast_bookmark_change, BookmarkAction};
+use crate::auth::middleware::AuthUser;
+use crate::core::error::{Result, TingError};
+use crate::db::models::{Bookmark, Chapter};
+use crate::db::repository::Repository;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use uuid::Uuid;
+
+/// Handler for GET /api/v1/bookmarks - List the user's bookmarks, optionally for one book
+pub async fn list_bookmarks(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<BookmarkQuery>,
+) -> Result<impl IntoResponse> {
+    let bookmarks = state
+        .bookmark_repo
+        .find_by_user(&user.id, query.book_id.as_deref())
+        .await?;
+
+    Ok(Json(
+        bookmarks
+            .into_iter()
+            .map(BookmarkResponse::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Handler for POST /api/v1/bookmarks - Bookmark a chapter position or clip a range
+pub async fn create_bookmark(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Json(req): Json<CreateBookmarkRequest>,
+) -> Result<impl IntoResponse> {
+    let chapter = state
+        .chapter_repo
+        .find_by_id(&req.chapter_id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("Chapter {} not found", req.chapter_id)))?;
+
+    let can_access = state
+        .book_repo
+        .check_access(&chapter.book_id, &user.id, user.role == "admin")
+        .await?;
+    if !can_access {
+        return Err(TingError::PermissionDenied(format!(
+            "User cannot access book {}",
+            chapter.book_id
+        )));
+    }
+
+    let (position, end_position) = clip_positions(&chapter, req.position, req.end_position)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let bookmark = Bookmark {
+        id: Uuid::new_v4().to_string(),
+        user_id: user.id.clone(),
+        book_id: chapter.book_id,
+        chapter_id: chapter.id,
+        position,
+        end_position,
+        note: normalize_note(req.note),
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    state.bookmark_repo.create(&bookmark).await?;
+
+    let response = BookmarkResponse::from(bookmark);
+    broadcast_bookmark_change(
+        &state.ws_manager,
+        &user.id,
+        BookmarkAction::Created,
+        response.clone(),
+    )
+    .await;
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Handler for PATCH /api/v1/bookmarks/:id - Move a bookmark or edit its note
+pub async fn update_bookmark(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateBookmarkRequest>,
+) -> Result<impl IntoResponse> {
+    let existing = state
+        .bookmark_repo
+        .find_by_user_and_id(&id, &user.id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("Bookmark with id {} not found", id)))?;
+    let chapter = state
+        .chapter_repo
+        .find_by_id(&existing.chapter_id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("Chapter {} not found", existing.chapter_id)))?;
+
+    let (position, end_position) = clip_positions(
+        &chapter,
+        req.position.unwrap_or(existing.position),
+        req.end_position.unwrap_or(existing.end_position),
+    )?;
+    let updated = Bookmark {
+        position,
+        end_position,
+        note: match req.note {
+            Some(note) => normalize_note(note),
+            None => existing.note.clone(),
+        },
+        updated_at: chrono::Utc::now().to_rfc3339(),
+        ..existing
+    };
+    state.bookmark_repo.update(&updated).await?;
+
+    let response = BookmarkResponse::from(updated);
+    broadcast_bookmark_change(
+        &state.ws_manager,
+        &user.id,
+        BookmarkAction::Updated,
+        response.clone(),
+    )
+    .await;
+    Ok(Json(response))
+}
+
+/// Handler for DELETE /api/v1/bookmarks/:id - Remove a bookmark
+pub async fn delete_bookmark(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse> {
+    let existing = state
+        .bookmark_repo
+        .find_by_user_and_id(&id, &user.id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("Bookmark with id {} not found", id)))?;
+
+    state.bookmark_repo.delete(&existing.id).await?;
+    broadcast_bookmark_change(
+        &state.ws_manager,
+        &user.id,
+        BookmarkAction::Deleted,
+        BookmarkResponse::from(existing),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Check a bookmark range and keep it inside the chapter.
+fn clip_positions(
+    chapter: &Chapter,
+    position: f64,
+    end_position: Option<f64>,
+) -> Result<(f64, Option<f64>)> {
+    if !position.is_finite() || position < 0.0 {
+        return Err(TingError::ValidationError(
+            "position must be a non-negative number of seconds".to_string(),
+        ));
+    }
+    let length = chapter.slice_length().filter(|length| *length > 0.0);
+    let position = length.map_or(position, |length| position.min(length));
+    let end_position = match end_position {
+        Some(end) if !end.is_finite() || end <= position => {
+            return Err(TingError::ValidationError(
+                "end_position must be greater than position".to_string(),
+            ));
+        }
+        Some(end) => Some(length.map_or(end, |length| end.min(length))),
+        None => None,
+    };
+    Ok((position, end_position))
+}
+
+fn normalize_note(note: Option<String>) -> Option<String> {
+    note.map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty())
+}
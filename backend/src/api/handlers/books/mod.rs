@@ -1,12 +1,16 @@
 pub mod scrape;
+pub mod skip;
 
 pub use scrape::{apply_scrape_result, scrape_book_diff};
+pub use skip::{analyze_book_skips, apply_skip_proposal, discard_skip_proposal, get_skip_proposal};
 
 use super::AppState;
 use crate::api::models::{
     BatchUpdateChaptersRequest, BookResponse, ChapterResponse, ChaptersPageResponse, ChaptersQuery,
-    CreateBookRequest, MergeBooksRequest, MoveChaptersRequest, SearchQuery, SearchResponse,
-    StatsResponse, UpdateBookCorrectionRequest, UpdateBookRequest, UpdateChapterRequest,
+    CreateBookRequest, JoinChaptersRequest, LibrarySearchItem, LibrarySearchQuery,
+    LibrarySearchResponse, LoudnessAnalysisRequest, MergeBooksRequest, MoveChaptersRequest,
+    SearchQuery, SearchResponse, SplitChapterRequest, StatsResponse, UpdateBookCorrectionRequest,
+    UpdateBookRequest, UpdateChapterRequest,
 };
 use crate::core::error::{Result, TingError};
 use crate::core::local_paths::{ensure_path_inside_root, resolve_existing_local_library_root};
@@ -781,6 +785,65 @@ pub async fn search_books(
     }))
 }
 
+/// Handler for GET /api/v1/books/search - Ranked full-text search of the library
+pub async fn search_library(
+    State(state): State<AppState>,
+    Query(query): Query<LibrarySearchQuery>,
+    user: crate::auth::middleware::AuthUser,
+) -> Result<impl IntoResponse> {
+    let page = query.page.max(1);
+    let page_size = query.page_size.clamp(1, 100);
+    let (hits, total) = state
+        .book_repo
+        .search(
+            &user.id,
+            user.role == "admin",
+            &query.q,
+            query.library_id.clone(),
+            page_size,
+            (page - 1) * page_size,
+        )
+        .await?;
+
+    let chapter_repo = ChapterRepository::new(state.book_repo.db().clone());
+    let mut chapters_by_book: std::collections::HashMap<String, Vec<ChapterResponse>> =
+        std::collections::HashMap::new();
+    for chapter in chapter_repo
+        .find_by_title_terms(
+            hits.iter().map(|hit| hit.book.id.clone()).collect(),
+            &query.q,
+            3,
+        )
+        .await?
+    {
+        chapters_by_book
+            .entry(chapter.book_id.clone())
+            .or_default()
+            .push(ChapterResponse::from(chapter));
+    }
+
+    let items = hits
+        .into_iter()
+        .map(|hit| {
+            let matched_chapters = chapters_by_book.remove(&hit.book.id).unwrap_or_default();
+            let mut book = BookResponse::from(hit.book);
+            book.library_type = hit.library_type;
+            LibrarySearchItem {
+                book,
+                score: hit.score,
+                matched_chapters,
+            }
+        })
+        .collect();
+
+    Ok(Json(LibrarySearchResponse {
+        items,
+        total,
+        page,
+        page_size,
+    }))
+}
+
 /// Handler for GET /api/v1/books/:id/chapters - Get chapters for a book
 pub async fn get_book_chapters(
     State(state): State<AppState>,
@@ -908,6 +971,87 @@ pub async fn get_book_chapters(
     Ok(Json(chapter_responses).into_response())
 }
 
+/// Apply requested offset changes to a chapter.
+///
+/// A missing field keeps the current value and `null` clears it. Clearing the
+/// start turns the chapter back into the whole file. A new start on a
+/// whole-file chapter needs an end, which defaults to the file length.
+/// Length of the file behind `chapter`. Local files are probed; otherwise the
+/// furthest end of the slices cut from the file is the best estimate.
+async fn whole_file_duration(
+    state: &AppState,
+    chapter_repo: &ChapterRepository,
+    chapter: &crate::db::models::Chapter,
+) -> Option<f64> {
+    let path = std::path::PathBuf::from(&chapter.path);
+    if path.is_file() {
+        let audio_streamer = state.audio_streamer.clone();
+        let probed = tokio::task::spawn_blocking(move || audio_streamer.get_duration(&path))
+            .await
+            .ok()
+            .and_then(|duration| duration.ok())
+            .map(|duration| duration.as_secs_f64())
+            .filter(|duration| *duration > 0.0);
+        if probed.is_some() {
+            return probed;
+        }
+    }
+    let chapters = chapter_repo.find_by_book(&chapter.book_id).await.ok()?;
+    furthest_slice_end(&chapters, &chapter.path)
+}
+
+/// Furthest end of the slices of `chapters` cut from `path`
+fn furthest_slice_end(chapters: &[crate::db::models::Chapter], path: &str) -> Option<f64> {
+    chapters
+        .iter()
+        .filter(|chapter| chapter.path == path)
+        .filter_map(|chapter| chapter.end_offset)
+        .reduce(f64::max)
+}
+
+fn resolve_chapter_offsets(
+    chapter: &crate::db::models::Chapter,
+    start_offset: Option<Option<f64>>,
+    end_offset: Option<Option<f64>>,
+) -> Result<(Option<f64>, Option<f64>)> {
+    for (name, value) in [("start_offset", start_offset), ("end_offset", end_offset)] {
+        if let Some(Some(value)) = value {
+            if !value.is_finite() || value < 0.0 {
+                return Err(TingError::ValidationError(format!(
+                    "{} must be a non-negative number of seconds",
+                    name
+                )));
+            }
+        }
+    }
+
+    let start = start_offset.unwrap_or(chapter.start_offset);
+    let Some(start) = start else {
+        if matches!(end_offset, Some(Some(_))) {
+            return Err(TingError::ValidationError(
+                "end_offset requires start_offset".to_string(),
+            ));
+        }
+        return Ok((None, None));
+    };
+
+    let end = match end_offset {
+        Some(end) => end,
+        None if chapter.is_virtual() => chapter.end_offset,
+        // The whole-file duration is where a new slice of it ends
+        None => chapter.duration.map(f64::from),
+    };
+    let end = end.ok_or_else(|| {
+        TingError::ValidationError("end_offset is required when start_offset is set".to_string())
+    })?;
+    if end <= start {
+        return Err(TingError::ValidationError(
+            "end_offset must be greater than start_offset".to_string(),
+        ));
+    }
+    Ok((Some(start), Some(end)))
+}
+
 /// Handler for PATCH /api/v1/chapters/:id - Update a chapter
 pub async fn update_chapter(
     State(state): State<AppState>,
@@ -921,7 +1065,12 @@ pub async fn update_chapter(
         .await?
         .ok_or_else(|| TingError::NotFound(format!("Chapter with id {} not found", id)))?;
 
-    let updated_chapter = crate::db::models::Chapter {
+    let offsets_changed = req.start_offset.is_some() || req.end_offset.is_some();
+    let was_virtual = existing_chapter.is_virtual();
+    let (start_offset, end_offset) =
+        resolve_chapter_offsets(&existing_chapter, req.start_offset, req.end_offset)?;
+
+    let mut updated_chapter = crate::db::models::Chapter {
         id: existing_chapter.id,
         book_id: existing_chapter.book_id,
         title: req.title.or(existing_chapter.title),
@@ -932,9 +1081,41 @@ pub async fn update_chapter(
         hash: existing_chapter.hash,
         created_at: existing_chapter.created_at,
         manual_corrected: existing_chapter.manual_corrected,
+        start_offset,
+        end_offset,
+        loudness_lufs: None,
+        true_peak_dbtp: None,
+        skip_ranges: None,
     };
 
+    if offsets_changed {
+        // A hand-edited slice must survive rescans
+        updated_chapter.manual_corrected = 1;
+        if req.duration.is_none() && updated_chapter.is_virtual() {
+            if let Some(length) = updated_chapter.slice_length() {
+                updated_chapter.duration = Some(length.round() as i32);
+            }
+        } else if req.duration.is_none() && was_virtual {
+            // The slice covers the whole file again
+            updated_chapter.duration = whole_file_duration(&state, &chapter_repo, &updated_chapter)
+                .await
+                .map(|duration| duration.round() as i32);
+        }
+    }
+
+    if let Some(ranges) = &req.skip_ranges {
+        skip::validate_skip_ranges(&updated_chapter, ranges)?;
+    }
+
     chapter_repo.update(&updated_chapter).await?;
+    if let Some(ranges) = &req.skip_ranges {
+        chapter_repo.set_skip_ranges(&id, ranges).await?;
+    }
+    // Loudness and skip ranges are kept or dropped by the repository
+    let updated_chapter = chapter_repo
+        .find_by_id(&id)
+        .await?
+        .unwrap_or(updated_chapter);
 
     // Regenerate metadata.json if enabled
     let book = state
@@ -1290,6 +1471,123 @@ pub async fn move_chapters(
     })))
 }
 
+/// Handler for POST /api/v1/chapters/:id/split - Split a chapter into time slices
+pub async fn split_chapter(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+    Json(req): Json<SplitChapterRequest>,
+) -> Result<impl IntoResponse> {
+    if user.role != "admin" {
+        return Err(TingError::PermissionDenied(
+            "Admin access required".to_string(),
+        ));
+    }
+
+    let slices = state
+        .merge_service
+        .split_chapter(&id, req.offsets, req.titles)
+        .await?;
+
+    Ok(Json(
+        slices
+            .into_iter()
+            .map(ChapterResponse::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Handler for POST /api/v1/books/:id/chapters/join - Join adjacent slices or neighbouring chapters
+pub async fn join_chapters(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+    Json(req): Json<JoinChaptersRequest>,
+) -> Result<impl IntoResponse> {
+    if user.role != "admin" {
+        return Err(TingError::PermissionDenied(
+            "Admin access required".to_string(),
+        ));
+    }
+
+    // Chapters from several files are concatenated by FFmpeg, which can
+    // neither follow .strm links nor decode plugin formats
+    let mut paths = Vec::new();
+    for chapter_id in &req.chapter_ids {
+        if let Some(chapter) = state.chapter_repo.find_by_id(chapter_id).await? {
+            if !paths.contains(&chapter.path) {
+                paths.push(chapter.path);
+            }
+        }
+    }
+    if paths.len() > 1 {
+        for path in &paths {
+            let file_path = std::path::Path::new(path);
+            let is_strm = file_path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("strm"));
+            if is_strm
+                || state
+                    .plugin_manager
+                    .find_plugin_for_format(file_path)
+                    .await
+                    .is_some()
+            {
+                return Err(TingError::ValidationError(format!(
+                    "{} cannot be joined with chapters from other files",
+                    path
+                )));
+            }
+        }
+    }
+
+    let joined = state
+        .merge_service
+        .join_chapters(&id, req.chapter_ids, req.title)
+        .await?;
+
+    Ok(Json(ChapterResponse::from(joined)))
+}
+
+/// Handler for POST /api/v1/books/:id/loudness-analysis - Measure chapter loudness
+pub async fn analyze_book_loudness(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+    req: Option<Json<LoudnessAnalysisRequest>>,
+) -> Result<impl IntoResponse> {
+    if user.role != "admin" {
+        return Err(TingError::PermissionDenied(
+            "Admin access required".to_string(),
+        ));
+    }
+
+    let book = state
+        .book_repo
+        .find_by_id(&id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("Book with id {} not found", id)))?;
+    let force = req.map(|Json(req)| req.force).unwrap_or(false);
+
+    let task_id = state
+        .task_queue
+        .enqueue_loudness_analysis(
+            format!("响度分析: {}", book.title.unwrap_or_default()),
+            &book.library_id,
+            Some(&book.id),
+            force,
+        )
+        .await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "Loudness analysis task submitted",
+            "task_id": task_id
+        })),
+    ))
+}
+
 /// Handler for POST /api/v1/books/:id/write-metadata - Write metadata to audio files
 pub async fn write_book_metadata_to_files(
     State(state): State<AppState>,
@@ -1327,3 +1625,46 @@ pub async fn write_book_metadata_to_files(
         "task_id": task_id
     })))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{furthest_slice_end, resolve_chapter_offsets};
+    use crate::db::models::Chapter;
+
+    fn slice(id: &str, path: &str, start: f64, end: f64) -> Chapter {
+        Chapter {
+            id: id.to_string(),
+            book_id: "b".to_string(),
+            title: None,
+            path: path.to_string(),
+            duration: Some((end - start).round() as i32),
+            chapter_index: None,
+            is_extra: 0,
+            hash: None,
+            manual_corrected: 0,
+            created_at: String::new(),
+            start_offset: Some(start),
+            end_offset: Some(end),
+            loudness_lufs: None,
+            true_peak_dbtp: None,
+            skip_ranges: None,
+        }
+    }
+
+    #[test]
+    fn cleared_offsets_take_the_length_of_the_whole_file() {
+        let chapter = slice("c1", "/book.mp3", 0.0, 600.0);
+        assert_eq!(
+            resolve_chapter_offsets(&chapter, Some(None), Some(None)).unwrap(),
+            (None, None)
+        );
+
+        let chapters = vec![
+            chapter,
+            slice("c2", "/book.mp3", 600.0, 1500.5),
+            slice("c3", "/other.mp3", 0.0, 5000.0),
+        ];
+        assert_eq!(furthest_slice_end(&chapters, "/book.mp3"), Some(1500.5));
+        assert_eq!(furthest_slice_end(&chapters, "/missing.mp3"), None);
+    }
+}
@@ -0,0 +1,212 @@
+use super::AppState;
+use crate::api::models::{
+    ApplySkipProposalRequest, ChapterSkipProposalResponse, SkipProposalResponse,
+};
+use crate::api::require_admin;
+use crate::core::error::{Result, TingError};
+use crate::db::models::{Book, Chapter, SkipProposal, SkipRange};
+use crate::db::repository::Repository;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use std::collections::HashSet;
+
+/// Skip ranges may end a little past the stored duration, which is rounded
+/// to whole seconds
+const LENGTH_TOLERANCE_SECS: f64 = 1.0;
+
+async fn find_book(state: &AppState, id: &str) -> Result<Book> {
+    state
+        .book_repo
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("Book with id {} not found", id)))
+}
+
+/// Check that `ranges` lie inside `chapter`
+pub(super) fn validate_skip_ranges(chapter: &Chapter, ranges: &[SkipRange]) -> Result<()> {
+    let length = chapter.slice_length();
+    for range in ranges {
+        if !range.start.is_finite()
+            || !range.end.is_finite()
+            || range.start < 0.0
+            || range.end <= range.start
+        {
+            return Err(TingError::ValidationError(format!(
+                "Invalid skip range {}-{}",
+                range.start, range.end
+            )));
+        }
+        if let Some(length) = length {
+            if range.end > length + LENGTH_TOLERANCE_SECS {
+                return Err(TingError::ValidationError(format!(
+                    "Skip range {}-{} ends after the chapter ({} s)",
+                    range.start, range.end, length
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handler for POST /api/v1/books/:id/skip-analysis - Detect intros and outros
+pub async fn analyze_book_skips(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+    let book = find_book(&state, &id).await?;
+
+    let task_id = state
+        .task_queue
+        .enqueue_skip_analysis(
+            format!("片头片尾分析: {}", book.title.unwrap_or_default()),
+            &book.id,
+        )
+        .await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "Skip analysis task submitted",
+            "task_id": task_id
+        })),
+    ))
+}
+
+/// Handler for GET /api/v1/books/:id/skip-proposal - Review the detected skips
+pub async fn get_skip_proposal(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+    let book = find_book(&state, &id).await?;
+    let proposal = state
+        .chapter_repo
+        .find_skip_proposal(&id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("No skip proposal for book {}", id)))?;
+
+    let mut chapters = Vec::with_capacity(proposal.chapters.len());
+    for proposed in proposal.chapters {
+        // Chapters deleted or moved since the analysis are left out
+        let Some(chapter) = state.chapter_repo.find_by_id(&proposed.chapter_id).await? else {
+            continue;
+        };
+        if chapter.book_id != id {
+            continue;
+        }
+        chapters.push(ChapterSkipProposalResponse {
+            chapter_id: proposed.chapter_id,
+            current_ranges: chapter.parsed_skip_ranges(),
+            title: chapter.title,
+            duration: chapter.duration,
+            ranges: proposed.ranges,
+        });
+    }
+
+    Ok(Json(SkipProposalResponse {
+        book_id: proposal.book_id,
+        skip_intro: proposal.skip_intro,
+        skip_outro: proposal.skip_outro,
+        current_skip_intro: book.skip_intro,
+        current_skip_outro: book.skip_outro,
+        chapters,
+        created_at: proposal.created_at,
+    }))
+}
+
+/// Handler for POST /api/v1/books/:id/skip-proposal/apply - Apply the detected skips
+pub async fn apply_skip_proposal(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+    req: Option<Json<ApplySkipProposalRequest>>,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+    find_book(&state, &id).await?;
+    let proposal = state
+        .chapter_repo
+        .find_skip_proposal(&id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("No skip proposal for book {}", id)))?;
+    let req = req.map(|Json(req)| req).unwrap_or_default();
+    let selected: Option<HashSet<String>> = req.chapter_ids.map(|ids| ids.into_iter().collect());
+
+    let mut remaining = SkipProposal {
+        chapters: Vec::new(),
+        ..proposal.clone()
+    };
+    let mut book_skips = None;
+    if req.book.unwrap_or(true) && (proposal.skip_intro.is_some() || proposal.skip_outro.is_some())
+    {
+        book_skips = Some((proposal.skip_intro, proposal.skip_outro));
+        remaining.skip_intro = None;
+        remaining.skip_outro = None;
+    }
+
+    let mut chapter_ranges = Vec::new();
+    for proposed in proposal.chapters {
+        // Chapters that were not selected stay in the proposal for later
+        if selected
+            .as_ref()
+            .is_some_and(|selected| !selected.contains(&proposed.chapter_id))
+        {
+            remaining.chapters.push(proposed);
+            continue;
+        }
+        let Some(chapter) = state.chapter_repo.find_by_id(&proposed.chapter_id).await? else {
+            continue;
+        };
+        if chapter.book_id != id {
+            continue;
+        }
+        chapter_ranges.push((
+            chapter.id.clone(),
+            merge_skip_ranges(&chapter, proposed.ranges),
+        ));
+    }
+
+    let book_updated = book_skips.is_some();
+    let chapters_updated = chapter_ranges.len();
+    state
+        .chapter_repo
+        .apply_skip_proposal(book_skips, chapter_ranges, remaining)
+        .await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Skip proposal applied",
+        "book_updated": book_updated,
+        "chapters_updated": chapters_updated
+    })))
+}
+
+/// Proposed ranges replace the chapter's ranges of the same kind
+fn merge_skip_ranges(chapter: &Chapter, proposed: Vec<SkipRange>) -> Vec<SkipRange> {
+    let mut ranges: Vec<SkipRange> = chapter
+        .parsed_skip_ranges()
+        .into_iter()
+        .filter(|range| !proposed.iter().any(|new| new.kind == range.kind))
+        .collect();
+    ranges.extend(proposed);
+    ranges.sort_by(|a, b| a.start.total_cmp(&b.start));
+    ranges
+}
+
+/// Handler for DELETE /api/v1/books/:id/skip-proposal - Discard the detected skips
+pub async fn discard_skip_proposal(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+    find_book(&state, &id).await?;
+    state.chapter_repo.delete_skip_proposal(&id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,532 @@
+//! Podcast feed export
+//!
+//! Users create a signed feed URL for a book, series, playlist or library and
+//! subscribe to it in any podcast app. The feed is RSS 2.0 with the iTunes
+//! namespace; every chapter is an episode whose enclosure is a signed
+//! `/public/media` URL, so no JWT ever leaves the server. Feed signatures
+//! include the user's feed token version, which the user bumps to revoke every
+//! link handed out so far.
+
+use super::media::stream::stream_mime_type_from_path;
+use super::playlists::playlist_books;
+use super::AppState;
+use crate::api::models::{CreateFeedLinkRequest, FeedKind, FeedLinkResponse, SignedFeedQuery};
+use crate::auth::middleware::AuthUser;
+use crate::core::error::{Result, TingError};
+use crate::core::signing::{
+    constant_time_eq, sign_media_stream_request, sign_podcast_feed_request,
+    signature_expires_from_ttl, signature_has_expired, MAX_MEDIA_SIGNATURE_TTL_SECONDS,
+};
+use crate::db::models::{Book, Chapter};
+use crate::db::repository::Repository;
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use quick_xml::escape::escape;
+
+/// Upper bound on episodes per feed, mostly relevant for library feeds
+const MAX_FEED_EPISODES: usize = 2000;
+
+/// Channel-level data of a feed
+struct PodcastFeed {
+    title: String,
+    description: Option<String>,
+    author: Option<String>,
+    cover_url: Option<String>,
+    books: Vec<Book>,
+}
+
+struct Episode {
+    guid: String,
+    title: String,
+    url: String,
+    mime_type: String,
+    duration: Option<i32>,
+    season: Option<usize>,
+    number: usize,
+    published: DateTime<Utc>,
+}
+
+/// Handler for POST /api/v1/me/feeds - Create a signed podcast feed URL
+pub async fn create_feed_link(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Json(req): Json<CreateFeedLinkRequest>,
+) -> Result<impl IntoResponse> {
+    let id = req.id.trim();
+    if id.is_empty() {
+        return Err(TingError::ValidationError(
+            "Feed id is required".to_string(),
+        ));
+    }
+    load_feed(&state, &user, req.kind, id).await?;
+    let base_url = public_base_url(&state).await?;
+    let token_version = state
+        .user_repo
+        .find_feed_token_version(&user.id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("User with id {} not found", user.id)))?;
+
+    // Podcast subscriptions are long-lived, so links default to the longest
+    // lifetime signed media URLs allow.
+    let expires = signature_expires_from_ttl(
+        req.expires_in_seconds,
+        MAX_MEDIA_SIGNATURE_TTL_SECONDS,
+        MAX_MEDIA_SIGNATURE_TTL_SECONDS,
+    );
+    let signature = sign_podcast_feed_request(
+        state.encryption_key.as_ref(),
+        req.kind.as_str(),
+        id,
+        expires,
+        &user.id,
+        token_version,
+    );
+    let url = format!(
+        "{}/api/v1/public/feeds/{}/{}?expires={}&user={}&signature={}",
+        base_url,
+        req.kind.as_str(),
+        urlencoding::encode(id),
+        expires,
+        urlencoding::encode(&user.id),
+        signature
+    );
+
+    Ok(Json(FeedLinkResponse { url, expires }))
+}
+
+/// Handler for DELETE /api/v1/me/feeds - Revoke all feed links of the user
+pub async fn revoke_feed_links(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Result<impl IntoResponse> {
+    state.user_repo.bump_feed_token_version(&user.id).await?;
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+/// Handler for GET /api/v1/public/feeds/:kind/:id - Serve a signed podcast feed
+pub async fn get_signed_feed(
+    State(state): State<AppState>,
+    Path((kind, id)): Path<(FeedKind, String)>,
+    Query(params): Query<SignedFeedQuery>,
+) -> Result<Response> {
+    let token_version = state
+        .user_repo
+        .find_feed_token_version(signed_feed_user(&params)?)
+        .await?
+        .ok_or_else(|| TingError::PermissionDenied("Signed feed user not found".to_string()))?;
+    let (user_id, expires) = verify_feed_signature(
+        state.encryption_key.as_ref(),
+        kind,
+        &id,
+        &params,
+        token_version,
+    )?;
+    let signed_user = state
+        .user_repo
+        .find_by_id(&user_id)
+        .await?
+        .ok_or_else(|| TingError::PermissionDenied("Signed feed user not found".to_string()))?;
+    let user = AuthUser {
+        user_id: signed_user.id.clone(),
+        id: signed_user.id,
+        username: signed_user.username,
+        role: signed_user.role,
+    };
+
+    // Access is checked again on every fetch, so revoked permissions apply to
+    // feeds that were already handed out.
+    let feed = load_feed(&state, &user, kind, &id).await?;
+    let base_url = public_base_url(&state).await?;
+    let episodes = feed_episodes(&state, &feed.books, &user.id, expires, &base_url).await?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+        render_feed(&feed, &episodes, &base_url),
+    )
+        .into_response())
+}
+
+/// Base URL of absolute links in feeds. Request headers are client-controlled,
+/// so only the configured `server.public_url` is used.
+async fn public_base_url(state: &AppState) -> Result<String> {
+    state
+        .config
+        .read()
+        .await
+        .server
+        .public_base_url()
+        .ok_or_else(|| {
+            TingError::ConfigError(
+                "server.public_url must be set to export podcast feeds".to_string(),
+            )
+        })
+}
+
+fn signed_feed_user(params: &SignedFeedQuery) -> Result<&str> {
+    params
+        .user
+        .as_deref()
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| TingError::PermissionDenied("Missing signed feed user".to_string()))
+}
+
+/// Check a feed signature against the user's current feed token version,
+/// returning the signed user and expiry
+fn verify_feed_signature(
+    signing_key: &[u8; 32],
+    kind: FeedKind,
+    id: &str,
+    params: &SignedFeedQuery,
+    token_version: i64,
+) -> Result<(String, i64)> {
+    let expires = params
+        .expires
+        .ok_or_else(|| TingError::PermissionDenied("Missing signed feed expiry".to_string()))?;
+    if signature_has_expired(expires) {
+        return Err(TingError::PermissionDenied(
+            "Signed feed URL has expired".to_string(),
+        ));
+    }
+
+    let user_id = signed_feed_user(params)?;
+    let signature = params
+        .signature
+        .as_deref()
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| TingError::PermissionDenied("Missing signed feed signature".to_string()))?;
+    let expected = sign_podcast_feed_request(
+        signing_key,
+        kind.as_str(),
+        id,
+        expires,
+        user_id,
+        token_version,
+    );
+    if !constant_time_eq(signature.as_bytes(), expected.as_bytes()) {
+        return Err(TingError::PermissionDenied(
+            "Invalid signed feed signature".to_string(),
+        ));
+    }
+
+    Ok((user_id.to_string(), expires))
+}
+
+async fn load_feed(
+    state: &AppState,
+    user: &AuthUser,
+    kind: FeedKind,
+    id: &str,
+) -> Result<PodcastFeed> {
+    let is_admin = user.role == "admin";
+    match kind {
+        FeedKind::Book => {
+            let book = state
+                .book_repo
+                .find_by_id(id)
+                .await?
+                .ok_or_else(|| TingError::NotFound(format!("Book with id {} not found", id)))?;
+            if !state
+                .book_repo
+                .check_access(&book.id, &user.id, is_admin)
+                .await?
+            {
+                return Err(TingError::PermissionDenied(
+                    "No access to this book".to_string(),
+                ));
+            }
+            Ok(PodcastFeed {
+                title: book_title(&book),
+                description: book.description.clone(),
+                author: book.author.clone(),
+                cover_url: book.cover_url.clone(),
+                books: vec![book],
+            })
+        }
+        FeedKind::Series => {
+            let series =
+                state.series_repo.find_by_id(id).await?.ok_or_else(|| {
+                    TingError::NotFound(format!("Series with id {} not found", id))
+                })?;
+            if !state
+                .series_repo
+                .check_access(&series.id, &user.id, is_admin)
+                .await?
+            {
+                return Err(TingError::PermissionDenied(
+                    "No access to this series".to_string(),
+                ));
+            }
+            let books = state
+                .series_repo
+                .find_books_by_series_with_filters(&series.id, &user.id, is_admin)
+                .await?
+                .into_iter()
+                .map(|(book, _)| book)
+                .collect();
+            Ok(PodcastFeed {
+                title: series.title,
+                description: series.description,
+                author: series.author,
+                cover_url: series.cover_url,
+                books,
+            })
+        }
+        FeedKind::Playlist => {
+            let playlist = state
+                .playlist_repo
+                .find_by_user_and_id(id, &user.id)
+                .await?
+                .ok_or_else(|| TingError::NotFound(format!("Playlist with id {} not found", id)))?;
+            let books = playlist_books(state, &playlist, &user.id, is_admin).await?;
+            Ok(PodcastFeed {
+                title: playlist.title,
+                description: playlist.description,
+                author: None,
+                cover_url: books.first().and_then(|book| book.cover_url.clone()),
+                books,
+            })
+        }
+        FeedKind::Library => {
+            let library =
+                state.library_repo.find_by_id(id).await?.ok_or_else(|| {
+                    TingError::NotFound(format!("Library with id {} not found", id))
+                })?;
+            if !is_admin
+                && !state
+                    .user_repo
+                    .get_accessible_libraries(&user.id)
+                    .await?
+                    .contains(&library.id)
+            {
+                return Err(TingError::PermissionDenied(
+                    "No access to this library".to_string(),
+                ));
+            }
+            let books = state
+                .book_repo
+                .find_with_filters(&user.id, is_admin, None, None, Some(library.id))
+                .await?;
+            Ok(PodcastFeed {
+                title: library.name,
+                description: None,
+                author: None,
+                cover_url: None,
+                books,
+            })
+        }
+    }
+}
+
+async fn feed_episodes(
+    state: &AppState,
+    books: &[Book],
+    user_id: &str,
+    expires: i64,
+    base_url: &str,
+) -> Result<Vec<Episode>> {
+    let multiple_books = books.len() > 1;
+    let mut episodes: Vec<Episode> = Vec::new();
+
+    for (book_index, book) in books.iter().enumerate() {
+        let added = parse_timestamp(&book.created_at);
+        let chapters = state.chapter_repo.find_by_book(&book.id).await?;
+        for (chapter_index, chapter) in chapters.iter().enumerate() {
+            if episodes.len() >= MAX_FEED_EPISODES {
+                return Ok(episodes);
+            }
+
+            // Podcast apps order by date, so dates must follow play order
+            // even when books were added out of order.
+            let published = match episodes.last() {
+                Some(previous) => added.max(previous.published + Duration::minutes(1)),
+                None => added,
+            };
+            let signature = sign_media_stream_request(
+                state.encryption_key.as_ref(),
+                &chapter.id,
+                expires,
+                user_id,
+                None,
+                None,
+                false,
+            );
+            episodes.push(Episode {
+                guid: chapter.id.clone(),
+                title: episode_title(book, chapter, chapter_index, multiple_books),
+                url: format!(
+                    "{}/api/v1/public/media/{}?expires={}&user={}&signature={}",
+                    base_url,
+                    urlencoding::encode(&chapter.id),
+                    expires,
+                    urlencoding::encode(user_id),
+                    signature
+                ),
+                mime_type: stream_mime_type_from_path(&chapter.path),
+                duration: chapter.duration,
+                season: multiple_books.then_some(book_index + 1),
+                number: episodes.len() + 1,
+                published,
+            });
+        }
+    }
+
+    Ok(episodes)
+}
+
+fn book_title(book: &Book) -> String {
+    book.title
+        .clone()
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| "Untitled".to_string())
+}
+
+fn episode_title(book: &Book, chapter: &Chapter, index: usize, with_book: bool) -> String {
+    let chapter_title = chapter
+        .title
+        .clone()
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| format!("Chapter {}", index + 1));
+    if with_book {
+        format!("{} - {}", book_title(book), chapter_title)
+    } else {
+        chapter_title
+    }
+}
+
+fn parse_timestamp(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.with_timezone(&Utc))
+        .or_else(|_| {
+            chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").map(|at| at.and_utc())
+        })
+        .unwrap_or_default()
+}
+
+fn push_text(xml: &mut String, name: &str, text: &str) {
+    xml.push_str(&format!("<{0}>{1}</{0}>", name, escape(text)));
+}
+
+fn render_feed(feed: &PodcastFeed, episodes: &[Episode], link: &str) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push_str(
+        r#"<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd"><channel>"#,
+    );
+    push_text(&mut xml, "title", &feed.title);
+    push_text(&mut xml, "link", link);
+    push_text(
+        &mut xml,
+        "description",
+        feed.description.as_deref().unwrap_or(&feed.title),
+    );
+    push_text(&mut xml, "generator", "Ting Reader");
+    if let Some(author) = feed.author.as_deref() {
+        push_text(&mut xml, "itunes:author", author);
+    }
+    // Local covers sit behind authentication, which podcast apps cannot use
+    if let Some(cover_url) = feed
+        .cover_url
+        .as_deref()
+        .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+    {
+        xml.push_str(&format!(r#"<itunes:image href="{}"/>"#, escape(cover_url)));
+    }
+    push_text(&mut xml, "itunes:type", "serial");
+    push_text(&mut xml, "itunes:explicit", "false");
+
+    for episode in episodes {
+        xml.push_str("<item>");
+        push_text(&mut xml, "title", &episode.title);
+        xml.push_str(&format!(
+            r#"<guid isPermaLink="false">{}</guid>"#,
+            escape(&episode.guid)
+        ));
+        xml.push_str(&format!(
+            r#"<enclosure url="{}" length="0" type="{}"/>"#,
+            escape(&episode.url),
+            escape(&episode.mime_type)
+        ));
+        push_text(&mut xml, "pubDate", &episode.published.to_rfc2822());
+        if let Some(duration) = episode.duration {
+            push_text(&mut xml, "itunes:duration", &duration.to_string());
+        }
+        if let Some(season) = episode.season {
+            push_text(&mut xml, "itunes:season", &season.to_string());
+        }
+        push_text(&mut xml, "itunes:episode", &episode.number.to_string());
+        push_text(&mut xml, "itunes:episodeType", "full");
+        xml.push_str("</item>");
+    }
+
+    xml.push_str("</channel></rss>");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feed_signature_is_bound_to_target() {
+        let key = [3_u8; 32];
+        let params = SignedFeedQuery {
+            expires: Some(0),
+            user: Some("u1".to_string()),
+            signature: Some(sign_podcast_feed_request(&key, "series", "s1", 0, "u1", 0)),
+        };
+
+        let (user_id, expires) =
+            verify_feed_signature(&key, FeedKind::Series, "s1", &params, 0).unwrap();
+        assert_eq!((user_id.as_str(), expires), ("u1", 0));
+        assert!(verify_feed_signature(&key, FeedKind::Series, "s2", &params, 0).is_err());
+        assert!(verify_feed_signature(&key, FeedKind::Book, "s1", &params, 0).is_err());
+        // Revoked by bumping the user's feed token version
+        assert!(verify_feed_signature(&key, FeedKind::Series, "s1", &params, 1).is_err());
+
+        let expired = SignedFeedQuery {
+            expires: Some(1),
+            user: Some("u1".to_string()),
+            signature: Some(sign_podcast_feed_request(&key, "series", "s1", 1, "u1", 0)),
+        };
+        assert!(verify_feed_signature(&key, FeedKind::Series, "s1", &expired, 0).is_err());
+    }
+
+    #[test]
+    fn renders_podcast_rss() {
+        let feed = PodcastFeed {
+            title: "Tom & Jerry".to_string(),
+            description: None,
+            author: Some("Hanna".to_string()),
+            cover_url: Some("/covers/local.jpg".to_string()),
+            books: Vec::new(),
+        };
+        let episodes = vec![Episode {
+            guid: "c1".to_string(),
+            title: "Chapter 1".to_string(),
+            url: "https://ting.example.com/api/v1/public/media/c1?expires=0&user=u1&signature=x"
+                .to_string(),
+            mime_type: "audio/mpeg".to_string(),
+            duration: Some(90),
+            season: None,
+            number: 1,
+            published: parse_timestamp("2026-01-02 03:04:05"),
+        }];
+
+        let xml = render_feed(&feed, &episodes, "https://ting.example.com");
+        assert!(xml.contains("<title>Tom &amp; Jerry</title>"));
+        assert!(xml.contains("<description>Tom &amp; Jerry</description>"));
+        assert!(xml.contains("<itunes:author>Hanna</itunes:author>"));
+        assert!(!xml.contains("itunes:image"));
+        assert!(xml.contains(
+            r#"<enclosure url="https://ting.example.com/api/v1/public/media/c1?expires=0&amp;user=u1&amp;signature=x" length="0" type="audio/mpeg"/>"#
+        ));
+        assert!(xml.contains("<pubDate>Fri, 2 Jan 2026 03:04:05 +0000</pubDate>"));
+        assert!(xml.contains("<itunes:duration>90</itunes:duration>"));
+        assert!(!xml.contains("itunes:season"));
+        assert!(xml.ends_with("</item></channel></rss>"));
+    }
+}
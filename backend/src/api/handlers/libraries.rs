@@ -1,7 +1,8 @@
 use super::AppState;
 use crate::api::models::{
     CreateLibraryRequest, FolderInfo, LibraryResponse, LibraryScanRequest, LibraryScanResponse,
-    StorageRootInfo, TestWebDavRequest, TestWebDavResponse, UpdateLibraryRequest,
+    LoudnessAnalysisRequest, StorageRootInfo, TestWebDavRequest, TestWebDavResponse,
+    UpdateLibraryRequest,
 };
 use crate::api::require_admin;
 use crate::core::error::{Result, TingError};
@@ -30,6 +31,45 @@ fn scraper_config_value_requires_writes(config: Option<&serde_json::Value>) -> b
         .unwrap_or(false)
 }
 
+/// RSS libraries only keep their refresh schedule and episode download
+/// settings from `scraper_config`
+fn rss_scraper_config(config: Option<&serde_json::Value>) -> Result<Option<String>> {
+    let Some(value) = config else {
+        return Ok(None);
+    };
+    let config: crate::db::models::ScraperConfig = serde_json::from_value(value.clone())
+        .map_err(|e| TingError::ValidationError(format!("Invalid scraper config: {}", e)))?;
+    crate::core::task_queue::validate_rss_refresh_config(&config)
+        .map_err(TingError::ValidationError)?;
+
+    let rss = crate::db::models::ScraperConfig {
+        rss_refresh_interval_minutes: config
+            .rss_refresh_interval_minutes
+            .filter(|minutes| *minutes > 0),
+        rss_refresh_cron: config
+            .rss_refresh_cron
+            .map(|expression| expression.trim().to_string())
+            .filter(|expression| !expression.is_empty()),
+        rss_download_episodes: config.rss_download_episodes,
+        rss_keep_latest_episodes: config.rss_keep_latest_episodes.filter(|count| *count > 0),
+        rss_delete_played_episodes: config.rss_delete_played_episodes,
+        rss_max_download_mb: config.rss_max_download_mb.filter(|mb| *mb > 0),
+        ..Default::default()
+    };
+    if rss.rss_refresh_interval_minutes.is_none()
+        && rss.rss_refresh_cron.is_none()
+        && !rss.rss_download_episodes
+        && rss.rss_keep_latest_episodes.is_none()
+        && !rss.rss_delete_played_episodes
+        && rss.rss_max_download_mb.is_none()
+    {
+        return Ok(None);
+    }
+    serde_json::to_string(&rss)
+        .map(Some)
+        .map_err(|e| TingError::SerializationError(e.to_string()))
+}
+
 fn scraper_config_str_requires_writes(config: Option<&str>) -> bool {
     config
         .and_then(|value| serde_json::from_str::<crate::db::models::ScraperConfig>(value).ok())
@@ -154,7 +194,7 @@ pub async fn create_library(
     };
 
     let scraper_config = if library_type == "rss" {
-        None
+        rss_scraper_config(req.scraper_config.as_ref())?
     } else {
         req.scraper_config.map(|v| v.to_string())
     };
@@ -339,6 +379,13 @@ pub async fn update_library(
                     "RSS feed URL must start with http:// or https://".to_string(),
                 ));
             }
+            if rss_feed_url != library.url {
+                // Validators of the old feed mean nothing to the new one
+                state
+                    .library_repo
+                    .save_rss_validators(&library.id, &Default::default())
+                    .await?;
+            }
             library.url = rss_feed_url;
         }
         if !is_http_url(&library.url) {
@@ -369,7 +416,13 @@ pub async fn update_library(
     }
 
     if library.library_type == "rss" {
-        library.scraper_config = None;
+        let config = req.scraper_config.or_else(|| {
+            library
+                .scraper_config
+                .as_deref()
+                .and_then(|json| serde_json::from_str(json).ok())
+        });
+        library.scraper_config = rss_scraper_config(config.as_ref())?;
     } else if let Some(config) = req.scraper_config {
         library.scraper_config = Some(config.to_string());
     }
@@ -506,6 +559,24 @@ pub async fn delete_library(
         }
     }
 
+    // Downloaded RSS episodes lost their rows with the library
+    let episode_dir = state
+        .config
+        .read()
+        .await
+        .storage
+        .episode_dir()
+        .join(&library_id);
+    if episode_dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&episode_dir) {
+            tracing::warn!(
+                path = %episode_dir.display(),
+                error = %e,
+                "Failed to delete downloaded RSS episodes"
+            );
+        }
+    }
+
     state.library_watcher.stop_watching(&library_id).await;
 
     tracing::info!(
@@ -621,6 +692,42 @@ pub async fn scan_library(
     ))
 }
 
+/// Handler for POST /api/libraries/:id/loudness-analysis - Measure the loudness
+/// of every chapter in a library
+pub async fn analyze_library_loudness(
+    State(state): State<AppState>,
+    Path(library_id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+    req: Option<Json<LoudnessAnalysisRequest>>,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+
+    let library = state
+        .library_repo
+        .find_by_id(&library_id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("Library {} not found", library_id)))?;
+    let force = req.map(|Json(req)| req.force).unwrap_or(false);
+
+    let task_id = state
+        .task_queue
+        .enqueue_loudness_analysis(
+            format!("loudness_analysis_{}", library.id),
+            &library.id,
+            None,
+            force,
+        )
+        .await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": format!("Loudness analysis started for '{}'", library.name),
+            "task_id": task_id
+        })),
+    ))
+}
+
 /// Handler for GET /api/storage/roots - Get authorized local storage roots
 pub async fn get_storage_roots(
     State(state): State<AppState>,
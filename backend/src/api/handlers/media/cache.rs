@@ -3,6 +3,7 @@
 use crate::api::handlers::AppState;
 use crate::api::models::{
     CacheInfoResponse, CacheListResponse, CacheOperationResponse, ClearCacheResponse,
+    TranscodeCacheResponse,
 };
 use crate::api::require_admin;
 use crate::core::error::{Result, TingError};
@@ -187,10 +188,16 @@ pub async fn get_cache_list(
     }
 
     let total = caches.len();
+    let transcode = state.transcode_cache.stats().await;
     Ok(Json(CacheListResponse {
         caches,
         total,
         total_size,
+        transcode: TranscodeCacheResponse {
+            segments: transcode.segments,
+            total_size: transcode.total_size,
+            max_size: transcode.max_size,
+        },
     }))
 }
 
@@ -223,7 +230,7 @@ pub async fn delete_chapter_cache(
     }))
 }
 
-/// DELETE /api/cache - Clear all caches
+/// DELETE /api/cache - Clear all caches, including shared HLS segments
 pub async fn clear_all_caches(
     State(state): State<AppState>,
     user: crate::auth::middleware::AuthUser,
@@ -237,9 +244,15 @@ pub async fn clear_all_caches(
         ))
     })?;
 
+    let transcode_segments_deleted = state.transcode_cache.clear().await?;
+
     Ok(Json(ClearCacheResponse {
         success: true,
         deleted_count,
-        message: format!("Cleared {} cached chapters", deleted_count),
+        transcode_segments_deleted,
+        message: format!(
+            "Cleared {} cached chapters and {} transcoded segments",
+            deleted_count, transcode_segments_deleted
+        ),
     }))
 }
@@ -1,4 +1,8 @@
+use super::hls_abr::{AdaptiveStream, HlsRendition};
+use super::normalize::volume_filter;
+use super::virtual_chapter::{chapter_seek_window, parse_seek_seconds};
 use crate::api::handlers::AppState;
+use crate::cache::transcode::TranscodeCache;
 use crate::core::error::{Result, TingError};
 use crate::db::models::{Book, Chapter, Library};
 use axum::{
@@ -10,6 +14,10 @@ use tokio::io::AsyncReadExt;
 use tokio::process::Command;
 
 /// 处理 HLS 转码请求
+///
+/// `renditions` 不为空且章节时长已知时返回自适应码率的主播放列表，
+/// 否则启动单码率的连续转码。`gain_db` 为响度均衡增益。
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_hls_request(
     state: AppState,
     chapter: Chapter,
@@ -17,6 +25,8 @@ pub async fn handle_hls_request(
     library: Library,
     is_strm: bool,
     seek: Option<String>,
+    renditions: Option<Vec<HlsRendition>>,
+    gain_db: Option<f64>,
 ) -> Result<Response> {
     // 1. 获取输入源 URL
     let input_url = get_input_url(&state, &chapter, &library, is_strm).await?;
@@ -40,6 +50,65 @@ pub async fn handle_hls_request(
                 TingError::ExternalError(e)
             }
         })?;
+    state
+        .hls_session_manager
+        .set_gain(&session_id, gain_db)
+        .await;
+
+    let adaptive = match (renditions, chapter.slice_length()) {
+        (Some(renditions), Some(duration)) if !renditions.is_empty() && duration > 0.0 => {
+            let start = chapter.start_offset.unwrap_or(0.0);
+            Some(AdaptiveStream {
+                input_url: input_url.clone(),
+                is_strm: is_remote_input,
+                // 增益不同的分片内容不同，不能共享
+                content_id: chapter.hash.as_deref().map(|hash| match gain_db {
+                    Some(gain_db) => TranscodeCache::content_id(
+                        &format!("{}|{:.2}dB", hash, gain_db),
+                        start,
+                        duration,
+                    ),
+                    None => TranscodeCache::content_id(hash, start, duration),
+                }),
+                start,
+                duration,
+                start_time: seek
+                    .is_some()
+                    .then(|| parse_seek_seconds(seek.as_deref()).min(duration)),
+                renditions,
+                gain_db,
+            })
+        }
+        (Some(_), _) => {
+            tracing::info!(
+                "Chapter {} has no known duration; falling back to single-bitrate HLS",
+                chapter.id
+            );
+            None
+        }
+        _ => None,
+    };
+    if let Some(adaptive) = adaptive {
+        // 自适应会话不启动常驻进程，分片在被请求时才转码
+        let renditions: Vec<String> = adaptive.renditions.iter().map(|r| r.name()).collect();
+        state
+            .hls_session_manager
+            .set_adaptive(&session_id, adaptive)
+            .await;
+        return Ok((
+            StatusCode::OK,
+            axum::Json(serde_json::json!({
+                "type": "hls",
+                "session_id": session_id,
+                "playlist_url": format!("/api/stream/hls/{}/master.m3u8", session_id),
+                "is_strm": is_strm,
+                "ready": true,
+                "abr": true,
+                "renditions": renditions
+            })),
+        )
+            .into_response());
+    }
 
     let temp_dir = state
         .hls_session_manager
@@ -47,7 +116,8 @@ pub async fn handle_hls_request(
         .await
         .ok_or_else(|| TingError::ExternalError("Failed to create session".to_string()))?;
 
-    // 3. 启动 FFmpeg 转码
+    // 3. 启动 FFmpeg 转码（虚拟章节只转码文件中的对应片段）
+    let (seek, limit) = chapter_seek_window(&chapter, seek.as_deref());
     start_hls_transcoding(
         &state,
         &session_id,
@@ -55,6 +125,7 @@ pub async fn handle_hls_request(
         &input_url,
         is_remote_input,
         seek.as_deref(),
+        limit,
     )
     .await?;
 
@@ -95,8 +166,9 @@ pub async fn start_hls_transcoding_internal(
     input_url: &str,
     is_strm: bool,
     seek: Option<&str>,
+    limit: Option<f64>,
 ) -> Result<()> {
-    start_hls_transcoding(state, session_id, temp_dir, input_url, is_strm, seek).await
+    start_hls_transcoding(state, session_id, temp_dir, input_url, is_strm, seek, limit).await
 }
 
 /// 获取输入源 URL（内部使用）
@@ -157,6 +229,7 @@ async fn start_hls_transcoding(
     input_url: &str,
     is_strm: bool,
     seek: Option<&str>,
+    limit: Option<f64>,
 ) -> Result<()> {
     let ffmpeg_path = state
         .plugin_manager
@@ -199,10 +272,17 @@ async fn start_hls_transcoding(
     if let Some(seek_time) = seek {
         cmd.arg("-ss").arg(seek_time);
     }
+    // 时长限制（虚拟章节的结束位置）
+    if let Some(limit) = limit {
+        cmd.arg("-t").arg(format!("{:.3}", limit));
+    }
 
-    cmd.arg("-i")
-        .arg(input_url)
-        .arg("-c:a")
+    cmd.arg("-i").arg(input_url);
+    // 响度均衡
+    if let Some(gain_db) = state.hls_session_manager.get_gain(session_id).await {
+        cmd.arg("-af").arg(volume_filter(gain_db));
+    }
+    cmd.arg("-c:a")
         .arg("aac")
         .arg("-b:a")
         .arg("128k")
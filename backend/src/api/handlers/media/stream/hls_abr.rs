@@ -0,0 +1,448 @@
+//! 自适应码率 HLS
+//!
+//! 请求 `transcode=hls&abr=1` 且章节时长已知时，会话不再启动常驻的 FFmpeg 进程，
+//! 而是返回包含多个码率版本的主播放列表。各版本的媒体播放列表按固定分片时长
+//! 直接生成，分片在首次被请求时才单独转码，并缓存到跨会话共享的转码缓存中。
+//!
+//! 可用版本由用户设置中的 `hls_renditions` 选择，`hls_max_bitrate` 为该用户的
+//! 默认码率上限。
+
+use super::normalize::volume_filter;
+use crate::api::handlers::AppState;
+use crate::cache::transcode::TranscodeCache;
+use crate::core::error::{Result, TingError};
+use serde_json::Value;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// 每个分片的时长（秒），与单码率 HLS 的 `-hls_time` 一致
+pub const SEGMENT_SECONDS: f64 = 4.0;
+
+/// 单个分片的最长转码时间
+const SEGMENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 可选码率（kbps）
+const BITRATES: [u32; 3] = [32, 64, 128];
+
+/// 未设置 `hls_renditions` 时使用的版本
+const DEFAULT_RENDITIONS: [HlsRendition; 3] = [
+    HlsRendition::new(HlsCodec::Aac, 32),
+    HlsRendition::new(HlsCodec::Aac, 64),
+    HlsRendition::new(HlsCodec::Aac, 128),
+];
+
+/// 版本使用的音频编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HlsCodec {
+    Aac,
+    Opus,
+}
+
+impl HlsCodec {
+    fn as_str(self) -> &'static str {
+        match self {
+            HlsCodec::Aac => "aac",
+            HlsCodec::Opus => "opus",
+        }
+    }
+
+    /// 主播放列表中 `CODECS` 属性的取值
+    fn codecs_attribute(self) -> &'static str {
+        match self {
+            HlsCodec::Aac => "mp4a.40.2",
+            HlsCodec::Opus => "opus",
+        }
+    }
+}
+
+/// 自适应流的一个码率版本，名称形如 `aac_64k`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HlsRendition {
+    pub codec: HlsCodec,
+    pub bitrate_kbps: u32,
+}
+
+impl HlsRendition {
+    const fn new(codec: HlsCodec, bitrate_kbps: u32) -> Self {
+        Self {
+            codec,
+            bitrate_kbps,
+        }
+    }
+
+    /// 解析版本名称，不支持的编码或码率返回 `None`
+    pub fn parse(name: &str) -> Option<Self> {
+        let (codec, bitrate) = name.split_once('_')?;
+        let codec = match codec {
+            "aac" => HlsCodec::Aac,
+            "opus" => HlsCodec::Opus,
+            _ => return None,
+        };
+        let bitrate_kbps: u32 = bitrate.strip_suffix('k')?.parse().ok()?;
+        BITRATES
+            .contains(&bitrate_kbps)
+            .then_some(Self::new(codec, bitrate_kbps))
+    }
+
+    pub fn name(&self) -> String {
+        format!("{}_{}k", self.codec.as_str(), self.bitrate_kbps)
+    }
+}
+
+/// 按用户设置选出的版本，码率从高到低排列。
+///
+/// 播放器通常从主播放列表的第一个版本起播，因此起播码率就是用户的码率上限。
+/// 上限低于所有已选版本时保留码率最低的一个。
+pub fn renditions_from_settings(settings: Option<&Value>) -> Vec<HlsRendition> {
+    let selected: Vec<HlsRendition> = settings
+        .and_then(|settings| settings.get("hls_renditions"))
+        .and_then(|value| value.as_array())
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| name.as_str().and_then(HlsRendition::parse))
+                .collect()
+        })
+        .filter(|selected: &Vec<HlsRendition>| !selected.is_empty())
+        .unwrap_or_else(|| DEFAULT_RENDITIONS.to_vec());
+    let max_bitrate = settings
+        .and_then(|settings| settings.get("hls_max_bitrate"))
+        .and_then(|value| value.as_u64());
+
+    let mut renditions: Vec<HlsRendition> = selected
+        .iter()
+        .copied()
+        .filter(|rendition| max_bitrate.map_or(true, |max| rendition.bitrate_kbps as u64 <= max))
+        .collect();
+    if renditions.is_empty() {
+        renditions.extend(selected.iter().copied().min_by_key(|r| r.bitrate_kbps));
+    }
+    renditions.sort_by_key(|rendition| std::cmp::Reverse(rendition.bitrate_kbps));
+    renditions.dedup();
+    renditions
+}
+
+/// 校验用户提交的 `hls_renditions` 与 `hls_max_bitrate`
+pub fn validate_settings(settings: &Value) -> Result<()> {
+    if let Some(renditions) = settings.get("hls_renditions") {
+        let names = renditions.as_array().ok_or_else(|| {
+            TingError::ValidationError("hls_renditions must be an array".to_string())
+        })?;
+        for name in names {
+            if name.as_str().and_then(HlsRendition::parse).is_none() {
+                return Err(TingError::ValidationError(format!(
+                    "Unsupported HLS rendition: {}",
+                    name
+                )));
+            }
+        }
+    }
+    if let Some(max_bitrate) = settings.get("hls_max_bitrate") {
+        if !max_bitrate.is_null() && max_bitrate.as_u64().map_or(true, |max| max == 0) {
+            return Err(TingError::ValidationError(
+                "hls_max_bitrate must be a positive number of kbps".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 自适应会话的转码参数
+#[derive(Debug, Clone)]
+pub struct AdaptiveStream {
+    pub input_url: String,
+    pub is_strm: bool,
+    /// 转码缓存中的内容 ID，章节没有哈希时为 `None`
+    pub content_id: Option<String>,
+    /// 章节在输入文件中的起始位置（秒）
+    pub start: f64,
+    /// 章节时长（秒）
+    pub duration: f64,
+    /// 播放器起播位置（秒）
+    pub start_time: Option<f64>,
+    pub renditions: Vec<HlsRendition>,
+    /// 响度均衡增益（dB）
+    pub gain_db: Option<f64>,
+}
+
+impl AdaptiveStream {
+    pub fn segment_count(&self) -> usize {
+        (self.duration / SEGMENT_SECONDS).ceil().max(1.0) as usize
+    }
+
+    /// 第 `index` 个分片在章节内的起始位置和时长
+    fn segment_window(&self, index: usize) -> (f64, f64) {
+        let offset = index as f64 * SEGMENT_SECONDS;
+        (offset, (self.duration - offset).clamp(0.0, SEGMENT_SECONDS))
+    }
+
+    pub fn rendition(&self, name: &str) -> Option<HlsRendition> {
+        HlsRendition::parse(name).filter(|rendition| self.renditions.contains(rendition))
+    }
+
+    /// 主播放列表
+    pub fn master_playlist(&self) -> String {
+        let mut playlist = String::from("#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-INDEPENDENT-SEGMENTS\n");
+        for rendition in &self.renditions {
+            // 预留约 10% 的 MPEG-TS 封装开销
+            let bandwidth = rendition.bitrate_kbps as u64 * 1100;
+            let _ = write!(
+                playlist,
+                "#EXT-X-STREAM-INF:BANDWIDTH={},CODECS=\"{}\"\n{}/playlist.m3u8\n",
+                bandwidth,
+                rendition.codec.codecs_attribute(),
+                rendition.name()
+            );
+        }
+        playlist
+    }
+
+    /// 某个版本的媒体播放列表，分片路径相对于播放列表
+    pub fn media_playlist(&self) -> String {
+        let mut playlist = format!(
+            "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:{}\n#EXT-X-MEDIA-SEQUENCE:0\n#EXT-X-PLAYLIST-TYPE:VOD\n",
+            SEGMENT_SECONDS.ceil() as u64
+        );
+        if let Some(start_time) = self.start_time.filter(|start| *start > 0.0) {
+            let _ = writeln!(playlist, "#EXT-X-START:TIME-OFFSET={:.3}", start_time);
+        }
+        for index in 0..self.segment_count() {
+            let (_, length) = self.segment_window(index);
+            let _ = write!(
+                playlist,
+                "#EXTINF:{:.3},\n{}\n",
+                length,
+                segment_file_name(index)
+            );
+        }
+        playlist.push_str("#EXT-X-ENDLIST\n");
+        playlist
+    }
+}
+
+pub fn segment_file_name(index: usize) -> String {
+    format!("segment_{:03}.ts", index)
+}
+
+/// 从 `segment_005.ts` 解析分片序号
+pub fn parse_segment_index(file_name: &str) -> Option<usize> {
+    file_name
+        .strip_prefix("segment_")?
+        .strip_suffix(".ts")?
+        .parse()
+        .ok()
+}
+
+/// 返回分片文件路径，分片不存在时先转码生成。
+///
+/// 章节有哈希时分片写入跨会话共享的转码缓存，否则写入会话目录。
+pub async fn ensure_segment(
+    state: &AppState,
+    session_dir: &Path,
+    stream: &AdaptiveStream,
+    rendition: HlsRendition,
+    index: usize,
+) -> Result<PathBuf> {
+    if index >= stream.segment_count() {
+        return Err(TingError::NotFound(format!("Segment {} not found", index)));
+    }
+    let file_name = segment_file_name(index);
+    let cache_key = stream
+        .content_id
+        .as_deref()
+        .map(|content_id| TranscodeCache::segment_key(content_id, &rendition.name(), &file_name));
+    let session_path = session_dir.join(rendition.name()).join(&file_name);
+    if let Some(path) = existing_segment(state, cache_key.as_deref(), &session_path).await {
+        return Ok(path);
+    }
+
+    let ffmpeg_path = state
+        .plugin_manager
+        .get_ffmpeg_path()
+        .await
+        .ok_or_else(|| {
+            TingError::IoError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "FFmpeg not found",
+            ))
+        })?;
+
+    let _slot = state.hls_session_manager.acquire_segment_slot().await;
+    // 等待期间可能已由并发请求生成
+    if let Some(path) = existing_segment(state, cache_key.as_deref(), &session_path).await {
+        return Ok(path);
+    }
+
+    // 先写入临时文件再改名，避免并发请求读到未写完的分片
+    let partial = match &cache_key {
+        Some(key) => state.transcode_cache.partial_path(key).await?,
+        None => {
+            let dir = session_dir.join(rendition.name());
+            tokio::fs::create_dir_all(&dir).await?;
+            dir.join(format!("{}.{}.part", file_name, uuid::Uuid::new_v4()))
+        }
+    };
+    let (offset, length) = stream.segment_window(index);
+
+    let mut cmd = Command::new(&ffmpeg_path);
+    cmd.arg("-y").arg("-loglevel").arg("error");
+    if stream.is_strm {
+        cmd.arg("-reconnect")
+            .arg("1")
+            .arg("-reconnect_streamed")
+            .arg("1")
+            .arg("-reconnect_delay_max")
+            .arg("5")
+            .arg("-timeout")
+            .arg("10000000");
+    }
+    cmd.arg("-ss")
+        .arg(format!("{:.3}", stream.start + offset))
+        .arg("-t")
+        .arg(format!("{:.3}", length))
+        .arg("-i")
+        .arg(&stream.input_url);
+    if let Some(gain_db) = stream.gain_db {
+        cmd.arg("-af").arg(volume_filter(gain_db));
+    }
+    cmd.arg("-vn").arg("-ac").arg("2");
+    match rendition.codec {
+        HlsCodec::Aac => {
+            cmd.arg("-c:a")
+                .arg("aac")
+                .arg("-aac_coder")
+                .arg("fast")
+                .arg("-ar")
+                .arg("44100");
+        }
+        HlsCodec::Opus => {
+            cmd.arg("-c:a").arg("libopus").arg("-ar").arg("48000");
+        }
+    }
+    // 时间戳从分片在章节中的位置开始，播放器才能无缝衔接相邻分片
+    cmd.arg("-b:a")
+        .arg(format!("{}k", rendition.bitrate_kbps))
+        .arg("-output_ts_offset")
+        .arg(format!("{:.3}", offset))
+        .arg("-f")
+        .arg("mpegts")
+        .arg(&partial)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let output = match tokio::time::timeout(SEGMENT_TIMEOUT, cmd.output()).await {
+        Ok(output) => output?,
+        Err(_) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(TingError::ExternalError(format!(
+                "Timed out transcoding segment {} ({})",
+                index,
+                rendition.name()
+            )));
+        }
+    };
+    if !output.status.success() {
+        let _ = tokio::fs::remove_file(&partial).await;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        tracing::warn!(
+            rendition = %rendition.name(),
+            segment = index,
+            "HLS segment transcoding failed: {}",
+            stderr.trim()
+        );
+        return Err(TingError::ExternalError(format!(
+            "Failed to transcode segment {} ({})",
+            index,
+            rendition.name()
+        )));
+    }
+
+    match &cache_key {
+        Some(key) => Ok(state.transcode_cache.insert(key, &partial).await?),
+        None => {
+            tokio::fs::rename(&partial, &session_path).await?;
+            Ok(session_path)
+        }
+    }
+}
+
+/// 已生成的分片：共享缓存中的分片，或会话目录中的分片
+async fn existing_segment(
+    state: &AppState,
+    cache_key: Option<&str>,
+    session_path: &Path,
+) -> Option<PathBuf> {
+    match cache_key {
+        Some(key) => state.transcode_cache.get(key).await,
+        None => session_path.exists().then(|| session_path.to_path_buf()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn names(renditions: &[HlsRendition]) -> Vec<String> {
+        renditions.iter().map(HlsRendition::name).collect()
+    }
+
+    #[test]
+    fn settings_select_and_cap_renditions() {
+        assert_eq!(
+            names(&renditions_from_settings(None)),
+            ["aac_128k", "aac_64k", "aac_32k"]
+        );
+        assert_eq!(
+            names(&renditions_from_settings(Some(
+                &json!({ "hls_max_bitrate": 64 })
+            ))),
+            ["aac_64k", "aac_32k"]
+        );
+        assert_eq!(
+            names(&renditions_from_settings(Some(&json!({
+                "hls_renditions": ["opus_64k", "aac_128k", "flac_900k"],
+                "hls_max_bitrate": 16
+            })))),
+            ["opus_64k"]
+        );
+
+        assert!(validate_settings(&json!({ "hls_renditions": ["aac_64k"] })).is_ok());
+        assert!(validate_settings(&json!({ "hls_renditions": ["aac_65k"] })).is_err());
+        assert!(validate_settings(&json!({ "hls_max_bitrate": 0 })).is_err());
+        assert!(validate_settings(&json!({ "hls_max_bitrate": null })).is_ok());
+    }
+
+    #[test]
+    fn playlists_cover_the_whole_chapter() {
+        let stream = AdaptiveStream {
+            input_url: "/books/a.mp3".to_string(),
+            is_strm: false,
+            content_id: None,
+            start: 30.0,
+            duration: 10.0,
+            start_time: Some(5.0),
+            renditions: renditions_from_settings(None),
+            gain_db: None,
+        };
+        assert_eq!(stream.segment_count(), 3);
+        assert_eq!(stream.segment_window(2), (8.0, 2.0));
+
+        let master = stream.master_playlist();
+        assert!(master.contains("BANDWIDTH=140800,CODECS=\"mp4a.40.2\"\naac_128k/playlist.m3u8"));
+        assert!(master.find("aac_128k").unwrap() < master.find("aac_32k").unwrap());
+
+        let media = stream.media_playlist();
+        assert!(media.contains("#EXT-X-START:TIME-OFFSET=5.000\n"));
+        assert!(media.contains("#EXTINF:2.000,\nsegment_002.ts\n#EXT-X-ENDLIST"));
+        assert_eq!(parse_segment_index("segment_002.ts"), Some(2));
+        assert_eq!(parse_segment_index("../segment_002.ts"), None);
+        assert_eq!(stream.rendition("aac_64k"), HlsRendition::parse("aac_64k"));
+        assert_eq!(stream.rendition("opus_64k"), None);
+    }
+}
@@ -113,6 +113,77 @@ pub async fn get_hls_segment(
     ))
 }
 
+/// 获取自适应码率会话的主播放列表
+pub async fn get_hls_master_playlist(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> Result<impl IntoResponse> {
+    let (_, adaptive) = state
+        .hls_session_manager
+        .get_adaptive(&session_id)
+        .await
+        .ok_or_else(|| TingError::NotFound("Session not found".to_string()))?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            ("Content-Type", "application/vnd.apple.mpegurl"),
+            ("Access-Control-Allow-Origin", "*"),
+            ("Cache-Control", "no-cache"),
+        ],
+        adaptive.master_playlist(),
+    ))
+}
+
+/// 获取自适应码率会话中某个版本的媒体播放列表或分片，分片按需转码
+pub async fn get_hls_rendition_file(
+    State(state): State<AppState>,
+    Path((session_id, rendition, filename)): Path<(String, String, String)>,
+) -> Result<axum::response::Response> {
+    use axum::http::header;
+
+    let (temp_dir, adaptive) = state
+        .hls_session_manager
+        .get_adaptive(&session_id)
+        .await
+        .ok_or_else(|| TingError::NotFound("Session not found".to_string()))?;
+    let rendition = adaptive
+        .rendition(&rendition)
+        .ok_or_else(|| TingError::NotFound(format!("Rendition {} not found", rendition)))?;
+
+    if filename == "playlist.m3u8" {
+        return Ok((
+            StatusCode::OK,
+            [
+                ("Content-Type", "application/vnd.apple.mpegurl"),
+                ("Access-Control-Allow-Origin", "*"),
+                ("Cache-Control", "no-cache"),
+            ],
+            adaptive.media_playlist(),
+        )
+            .into_response());
+    }
+
+    // 只接受 segment_NNN.ts，文件名由序号重新生成，不会越出会话目录
+    let index = super::hls_abr::parse_segment_index(&filename)
+        .ok_or_else(|| TingError::InvalidRequest("Invalid file type".to_string()))?;
+    let segment_path =
+        super::hls_abr::ensure_segment(&state, &temp_dir, &adaptive, rendition, index).await?;
+    let content = tokio::fs::read(&segment_path).await?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "video/mp2t"),
+            (header::ACCESS_CONTROL_ALLOW_ORIGIN, "*"),
+            // 分片内容固定，可以缓存
+            (header::CACHE_CONTROL, "private, max-age=3600"),
+        ],
+        content,
+    )
+        .into_response())
+}
+
 /// Seek 操作
 #[derive(Debug, serde::Deserialize)]
 pub struct SeekQuery {
@@ -133,6 +204,18 @@ pub async fn seek_hls_stream(
 
     let (chapter_id, library_id, _book_id, is_strm, original_url) = session_data;
 
+    // 自适应会话的播放列表覆盖整个章节，由播放器直接跳转
+    if state
+        .hls_session_manager
+        .get_adaptive(&session_id)
+        .await
+        .is_some()
+    {
+        return Err(TingError::InvalidRequest(
+            "Adaptive HLS sessions seek within the playlist".to_string(),
+        ));
+    }
+
     // 终止当前 FFmpeg 进程
     state.hls_session_manager.kill_session(&session_id).await;
 
@@ -178,6 +261,8 @@ pub async fn seek_hls_stream(
 
     // 重新启动转码，带上 seek 参数
     let seek_time = params.seek.map(|s| s.to_string());
+    let (seek_time, limit) =
+        super::virtual_chapter::chapter_seek_window(&chapter, seek_time.as_deref());
     crate::api::handlers::media::stream::hls::start_hls_transcoding_internal(
         &state,
         &session_id,
@@ -185,6 +270,7 @@ pub async fn seek_hls_stream(
         &input_url,
         is_strm,
         seek_time.as_deref(),
+        limit,
     )
     .await?;
 
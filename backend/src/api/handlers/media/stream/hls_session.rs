@@ -1,8 +1,9 @@
+use super::hls_abr::AdaptiveStream;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::process::Child;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, Semaphore, SemaphorePermit};
 use uuid::Uuid;
 
 /// HLS 会话信息
@@ -18,6 +19,10 @@ pub struct HlsSession {
     pub original_url: Option<String>,
     pub library_id: String,
     pub book_id: String,
+    /// 自适应码率会话的转码参数，单码率会话为 `None`
+    pub adaptive: Option<AdaptiveStream>,
+    /// 响度均衡增益（dB），不做均衡时为 `None`
+    pub gain_db: Option<f64>,
 }
 
 /// HLS 会话管理器
@@ -25,6 +30,8 @@ pub struct HlsSessionManager {
     sessions: Arc<RwLock<HashMap<String, HlsSession>>>,
     base_temp_dir: PathBuf,
     max_concurrent: usize,
+    /// 限制同时转码的自适应分片数
+    segment_slots: Semaphore,
 }
 
 impl HlsSessionManager {
@@ -34,6 +41,7 @@ impl HlsSessionManager {
             sessions: Arc::new(RwLock::new(HashMap::new())),
             base_temp_dir,
             max_concurrent: 3,
+            segment_slots: Semaphore::new(3),
         }
     }
 
@@ -100,6 +108,8 @@ impl HlsSessionManager {
             seq: 0,
             is_strm,
             original_url: url,
+            adaptive: None,
+            gain_db: None,
         };
 
         self.sessions
@@ -134,6 +144,41 @@ impl HlsSessionManager {
         }
     }
 
+    /// 将会话设为自适应码率会话
+    pub async fn set_adaptive(&self, session_id: &str, adaptive: AdaptiveStream) {
+        let mut sessions = self.sessions.write().await;
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.adaptive = Some(adaptive);
+        }
+    }
+
+    /// 设置会话转码时应用的响度均衡增益
+    pub async fn set_gain(&self, session_id: &str, gain_db: Option<f64>) {
+        let mut sessions = self.sessions.write().await;
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.gain_db = gain_db;
+        }
+    }
+
+    /// 获取会话的响度均衡增益
+    pub async fn get_gain(&self, session_id: &str) -> Option<f64> {
+        self.sessions.read().await.get(session_id)?.gain_db
+    }
+
+    /// 获取自适应码率会话的临时目录和转码参数
+    pub async fn get_adaptive(&self, session_id: &str) -> Option<(PathBuf, AdaptiveStream)> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions.get_mut(session_id)?;
+        session.last_accessed = std::time::Instant::now();
+        let adaptive = session.adaptive.clone()?;
+        Some((session.temp_dir.clone(), adaptive))
+    }
+
+    /// 等待一个分片转码名额
+    pub async fn acquire_segment_slot(&self) -> Option<SemaphorePermit<'_>> {
+        self.segment_slots.acquire().await.ok()
+    }
+
     /// 终止会话的 FFmpeg 进程
     pub async fn kill_session(&self, session_id: &str) {
         let mut sessions = self.sessions.write().await;
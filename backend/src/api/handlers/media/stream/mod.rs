@@ -1,9 +1,13 @@
 mod decrypt;
 mod hls;
+mod hls_abr;
 mod hls_serve;
 mod hls_session;
+mod native;
+mod normalize;
 mod preload;
 mod strm;
+mod virtual_chapter;
 
 use crate::api::handlers::AppState;
 use crate::auth::middleware::AuthUser;
@@ -20,8 +24,13 @@ use axum::{
 };
 pub(crate) use decrypt::create_decrypted_stream;
 pub use hls::handle_hls_request;
-pub use hls_serve::{get_hls_playlist, get_hls_segment, seek_hls_stream};
+pub use hls_abr::validate_settings as validate_hls_settings;
+pub use hls_serve::{
+    get_hls_master_playlist, get_hls_playlist, get_hls_rendition_file, get_hls_segment,
+    seek_hls_stream,
+};
 pub use hls_session::HlsSessionManager;
+pub use normalize::validate_settings as validate_normalize_settings;
 use std::process::Stdio;
 use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tokio::process::Command;
@@ -34,6 +43,8 @@ pub struct StreamQuery {
     pub transcode: Option<String>,
     pub seek: Option<String>,
     pub download: Option<String>,
+    /// With `transcode=hls`, serve an adaptive-bitrate master playlist
+    pub abr: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -44,9 +55,11 @@ pub struct SignedStreamQuery {
     pub transcode: Option<String>,
     pub seek: Option<String>,
     pub download: Option<String>,
+    /// With `transcode=hls`, serve an adaptive-bitrate master playlist
+    pub abr: Option<String>,
 }
 
-fn stream_mime_type_from_path(path: &str) -> String {
+pub(crate) fn stream_mime_type_from_path(path: &str) -> String {
     let ext = std::path::Path::new(path)
         .extension()
         .and_then(|e| e.to_str())
@@ -68,8 +81,8 @@ fn stream_mime_type_from_path(path: &str) -> String {
     }
 }
 
-fn is_download_query(params: &StreamQuery) -> bool {
-    let Some(value) = params.download.as_deref() else {
+fn query_flag(value: Option<&str>) -> bool {
+    let Some(value) = value else {
         return false;
     };
     matches!(
@@ -78,6 +91,10 @@ fn is_download_query(params: &StreamQuery) -> bool {
     )
 }
 
+fn is_download_query(params: &StreamQuery) -> bool {
+    query_flag(params.download.as_deref())
+}
+
 async fn get_remote_media_reader(
     state: &AppState,
     library: &Library,
@@ -107,6 +124,18 @@ async fn get_remote_media_reader(
     )))
 }
 
+/// Settings JSON of the requesting user, if any
+async fn user_settings(state: &AppState, user_id: Option<&str>) -> Option<serde_json::Value> {
+    state
+        .settings_repo
+        .get_by_user(user_id?)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|settings| settings.settings_json)
+        .and_then(|json| serde_json::from_str(&json).ok())
+}
+
 async fn transcode_plugin_stream(
     state: &AppState,
     chapter: &Chapter,
@@ -252,7 +281,7 @@ pub async fn stream_chapter(
 
     let is_head_request = method == axum::http::Method::HEAD;
 
-    let chapter = state
+    let mut chapter = state
         .chapter_repo
         .find_by_id(&chapter_id)
         .await?
@@ -266,12 +295,26 @@ pub async fn stream_chapter(
 
     ensure_user_can_stream_book(&state, user.as_ref(), &book.id).await?;
 
-    let library = state
+    let mut library = state
         .library_repo
         .find_by_id(&book.library_id)
         .await?
         .ok_or_else(|| TingError::NotFound(format!("Library {} not found", book.library_id)))?;
 
+    // Downloaded RSS episodes play from the local copy, like a local library
+    if library.library_type == "rss" {
+        if let Some(download) = state
+            .library_repo
+            .find_episode_download(&chapter.id)
+            .await?
+        {
+            if std::path::Path::new(&download.file_path).exists() {
+                chapter.path = download.file_path;
+                library.library_type = "local".to_string();
+            }
+        }
+    }
+
     let ext = std::path::Path::new(&chapter.path)
         .extension()
         .and_then(|e| e.to_str())
@@ -279,6 +322,14 @@ pub async fn stream_chapter(
         .to_lowercase();
 
     let is_download_request = is_download_query(&params);
+    let settings = user_settings(&state, user.as_ref().map(|user| user.id.as_str())).await;
+    let hls_renditions =
+        if params.transcode.as_deref() == Some("hls") && query_flag(params.abr.as_deref()) {
+            Some(hls_abr::renditions_from_settings(settings.as_ref()))
+        } else {
+            None
+        };
+    let gain_db = normalize::gain_from_settings(settings.as_ref(), &chapter);
 
     // Handle .strm files (URL Redirect or Proxy)
     if ext == "strm" {
@@ -290,6 +341,34 @@ pub async fn stream_chapter(
             library,
             &params,
             &headers,
+            hls_renditions,
+        )
+        .await;
+    }
+    // Chapters joined from several files play their segments back to back
+    let segments = state.chapter_repo.find_segments(&chapter.id).await?;
+    if !segments.is_empty() {
+        return virtual_chapter::handle_joined_chapter_stream(
+            &state,
+            &chapter,
+            &segments,
+            &library,
+            &params,
+            is_head_request,
+        )
+        .await;
+    }
+    // Chapter slices of a shared file are cut by time, not by byte range;
+    // HLS requests apply the same window inside the HLS handler.
+    if chapter.is_virtual() && params.transcode.as_deref() != Some("hls") {
+        return virtual_chapter::handle_virtual_chapter_stream(
+            &state,
+            &chapter,
+            &library,
+            &params,
+            gain_db,
+            headers.get(header::RANGE).and_then(|h| h.to_str().ok()),
+            is_head_request,
         )
         .await;
     }
@@ -304,6 +383,8 @@ pub async fn stream_chapter(
                 library,
                 ext == "strm",
                 params.seek.clone(),
+                hls_renditions,
+                gain_db,
             )
             .await;
         }
@@ -323,16 +404,27 @@ pub async fn stream_chapter(
             }
         };
 
-        let ffmpeg_path = state
-            .plugin_manager
-            .get_ffmpeg_path()
-            .await
-            .ok_or_else(|| {
-                TingError::IoError(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    "FFmpeg plugin binary not found",
-                ))
-            })?;
+        // Without the FFmpeg plugin, decode with symphonia and serve WAV instead
+        let Some(ffmpeg_path) = state.plugin_manager.get_ffmpeg_path().await else {
+            return native::stream_native_transcode(
+                &state,
+                &chapter,
+                &library,
+                virtual_chapter::parse_seek_seconds(params.seek.as_deref()),
+                None,
+                gain_db,
+                headers.get(header::RANGE).and_then(|h| h.to_str().ok()),
+                vec![
+                    (header::ACCESS_CONTROL_ALLOW_ORIGIN, "*".to_string()),
+                    (header::ACCEPT_RANGES, "none".to_string()),
+                    (
+                        "Cross-Origin-Resource-Policy".parse().unwrap(),
+                        "cross-origin".to_string(),
+                    ),
+                ],
+            )
+            .await;
+        };
         let cache_path = state.cache_manager.get_cache_path(&chapter_id);
         let plugin_info = state
             .plugin_manager
@@ -469,6 +561,9 @@ pub async fn stream_chapter(
 
             // Use URL as input directly (FFmpeg will handle HTTP/HTTPS)
             cmd.arg("-i").arg(&webdav_url_str);
+            if let Some(gain_db) = gain_db {
+                cmd.arg("-af").arg(normalize::volume_filter(gain_db));
+            }
 
             // Add transcoding parameters
             if format == "mp3" {
@@ -693,6 +788,9 @@ pub async fn stream_chapter(
                 cmd.arg("-");
                 cmd.stdin(Stdio::piped());
             }
+            if let Some(gain_db) = gain_db {
+                cmd.arg("-af").arg(normalize::volume_filter(gain_db));
+            }
 
             if format == "mp3" {
                 cmd.arg("-fflags")
@@ -1151,6 +1249,7 @@ pub async fn stream_signed_chapter(
             transcode: params.transcode,
             seek: params.seek,
             download: params.download,
+            abr: params.abr,
         }),
         method,
         headers,
@@ -0,0 +1,841 @@
+//! Transcoding without FFmpeg
+//!
+//! When the FFmpeg plugin is not installed, transcode requests fall back to
+//! decoding the chapter with symphonia and streaming the samples as 16-bit PCM
+//! WAV, which every browser can play. Only the codecs compiled into symphonia
+//! are supported, and plugin-backed formats still need FFmpeg.
+//!
+//! When the source reports its length the size of the WAV is known up front,
+//! so the response carries a `Content-Length` and byte ranges are served by
+//! seeking the decoder to the frame the range starts in.
+
+use super::get_remote_media_reader;
+use crate::api::handlers::AppState;
+use crate::core::audio_streamer::{AudioTrack, BlockingReader};
+use crate::core::error::{Result, TingError};
+use crate::db::models::{Chapter, ChapterSegment, Library};
+use axum::{
+    body::Body,
+    http::{header, HeaderName, StatusCode},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+use symphonia::core::audio::{SampleBuffer, SignalSpec};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::io::{MediaSource, ReadOnlySource};
+use symphonia::core::probe::Hint;
+use tokio::sync::mpsc;
+
+/// Decoded chunks buffered ahead of the client
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Size of the header `wav_header` writes
+const WAV_HEADER_LEN: u64 = 44;
+
+/// Frames of silence sent at a time when a track ends early
+const PADDING_FRAMES: u64 = 4096;
+
+/// A file the native transcoder decodes
+struct NativeInput {
+    /// Read straight from disk when set
+    local_path: Option<PathBuf>,
+    library: Library,
+    /// Path in the library, or an http(s) URL
+    path: String,
+}
+
+impl NativeInput {
+    fn new(library: &Library, path: &str) -> Self {
+        let is_url = path.starts_with("http://") || path.starts_with("https://");
+        Self {
+            local_path: (library.library_type == "local" && !is_url).then(|| PathBuf::from(path)),
+            library: library.clone(),
+            path: path.to_string(),
+        }
+    }
+
+    async fn open(&self, state: &AppState) -> Result<Box<dyn MediaSource>> {
+        if let Some(path) = &self.local_path {
+            return Ok(Box::new(std::fs::File::open(path)?));
+        }
+        let (reader, _) = if self.path.starts_with("http://") || self.path.starts_with("https://") {
+            state
+                .storage_service
+                .get_http_reader(&self.path, None)
+                .await
+                .map_err(|e| TingError::NotFound(format!("Remote media not found: {}", e)))?
+        } else {
+            get_remote_media_reader(state, &self.library, &self.path, None).await?
+        };
+        Ok(Box::new(ReadOnlySource::new(BlockingReader::new(reader))))
+    }
+
+    fn hint(&self) -> Hint {
+        let mut hint = Hint::new();
+        let path = self.path.split(['?', '#']).next().unwrap_or_default();
+        if let Some(ext) = std::path::Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+        {
+            hint.with_extension(ext);
+        }
+        hint
+    }
+
+    /// Open the file and position a decoder `start` seconds in
+    async fn decoder_at(&self, state: &AppState, start: f64) -> Result<Opened> {
+        let source = self.open(state).await?;
+        let hint = self.hint();
+        tokio::task::spawn_blocking(move || NativeDecoder::open(source, hint, start))
+            .await
+            .map_err(|e| TingError::ExternalError(format!("Native transcoder failed: {}", e)))?
+    }
+}
+
+/// Stream `limit` seconds (or the rest of the file) from `start` seconds into
+/// the chapter's file as WAV.
+///
+/// `gain_db` is applied to the samples for loudness normalization, and `range`
+/// is the client's `Range` header. `response_headers` are sent along; the
+/// content type, range support and duration are filled in here.
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn stream_native_transcode(
+    state: &AppState,
+    chapter: &Chapter,
+    library: &Library,
+    start: f64,
+    limit: Option<f64>,
+    gain_db: Option<f64>,
+    range: Option<&str>,
+    response_headers: Vec<(HeaderName, String)>,
+) -> Result<Response> {
+    let cache_path = state.cache_manager.get_cache_path(&chapter.id);
+    let input = if cache_path.exists() {
+        NativeInput {
+            local_path: Some(cache_path),
+            library: library.clone(),
+            path: chapter.path.clone(),
+        }
+    } else {
+        reject_plugin_format(state, &chapter.path).await?;
+        NativeInput::new(library, &chapter.path)
+    };
+
+    tracing::info!(chapter_id = %chapter.id, start = start, "Transcoding without FFmpeg");
+    stream_input(state, input, start, limit, gain_db, range, response_headers).await
+}
+
+/// Stream the audio behind a `.strm` URL as WAV, from `start` seconds on
+pub(super) async fn stream_native_url(
+    state: &AppState,
+    library: &Library,
+    url: &str,
+    start: f64,
+    range: Option<&str>,
+    response_headers: Vec<(HeaderName, String)>,
+) -> Result<Response> {
+    tracing::info!(url = %url, start = start, "Transcoding URL without FFmpeg");
+    stream_input(
+        state,
+        NativeInput::new(library, url),
+        start,
+        None,
+        None,
+        range,
+        response_headers,
+    )
+    .await
+}
+
+/// Stream a chapter joined from several files as one WAV, `seek` seconds in.
+///
+/// The segments are decoded one after another and must share the sample rate
+/// and channel layout of the first one played; the stream ends at the first
+/// segment that does not. The total size is not known, so byte ranges are
+/// not supported.
+pub(super) async fn stream_native_joined(
+    state: &AppState,
+    segments: &[ChapterSegment],
+    library: &Library,
+    seek: f64,
+    response_headers: Vec<(HeaderName, String)>,
+) -> Result<Response> {
+    for segment in segments {
+        reject_plugin_format(state, &segment.path).await?;
+    }
+
+    // Find the segment the seek lands in and open it to learn the format
+    let mut remaining_seek = seek;
+    let mut parts = segments.iter();
+    let first = loop {
+        let Some(segment) = parts.next() else {
+            return Err(TingError::InvalidRequest(
+                "No audio to transcode".to_string(),
+            ));
+        };
+        if let Some(decoder) = open_segment(state, library, segment, &mut remaining_seek).await? {
+            break decoder;
+        }
+    };
+    let spec = first.decoder.spec;
+    let rest: Vec<ChapterSegment> = parts.cloned().collect();
+
+    tracing::info!(
+        segments = segments.len(),
+        seek = seek,
+        sample_rate = spec.rate,
+        channels = spec.channels.count(),
+        "Transcoding joined chapter without FFmpeg"
+    );
+
+    let (tx, rx) = mpsc::channel::<io::Result<Bytes>>(CHANNEL_CAPACITY);
+    if tx.send(Ok(wav_header(&spec, None))).await.is_ok() {
+        let state = state.clone();
+        let library = library.clone();
+        tokio::spawn(async move {
+            let mut next = Some(first);
+            let mut parts = rest.iter();
+            while let Some(part) = next.take() {
+                if part.decoder.spec != spec {
+                    tracing::warn!("Joined files differ in format; native transcoding stopped");
+                    return;
+                }
+                let part_tx = tx.clone();
+                let output = PcmOutput::frames(part.limit_frames);
+                let finished =
+                    tokio::task::spawn_blocking(move || part.decoder.run(output, None, &part_tx))
+                        .await
+                        .unwrap_or(false);
+                if !finished {
+                    return;
+                }
+
+                let mut no_seek = 0.0;
+                for segment in parts.by_ref() {
+                    match open_segment(&state, &library, segment, &mut no_seek).await {
+                        Ok(Some(opened)) => {
+                            next = Some(opened);
+                            break;
+                        }
+                        Ok(None) => continue,
+                        Err(e) => {
+                            tracing::warn!(error = %e, "Native transcoding stopped");
+                            let _ = tx
+                                .send(Err(io::Error::new(io::ErrorKind::Other, e.to_string())))
+                                .await;
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    let mut response_headers = response_headers;
+    set_wav_headers(&mut response_headers, None);
+    Ok(build_response(
+        StatusCode::OK,
+        receiver_body(rx),
+        response_headers,
+    ))
+}
+
+/// A segment of a joined chapter, opened where playback starts in it
+struct OpenedSegment {
+    decoder: NativeDecoder,
+    limit_frames: Option<u64>,
+}
+
+/// Open `segment` `seek` seconds in. When the seek lies past the segment,
+/// `seek` is reduced by the segment's length and `None` returned.
+async fn open_segment(
+    state: &AppState,
+    library: &Library,
+    segment: &ChapterSegment,
+    seek: &mut f64,
+) -> Result<Option<OpenedSegment>> {
+    let start = segment.start_offset.unwrap_or(0.0);
+    let length = segment.end_offset.map(|end| (end - start).max(0.0));
+    if length.is_some_and(|length| *seek >= length) {
+        *seek -= length.unwrap_or_default();
+        return Ok(None);
+    }
+
+    let input = NativeInput::new(library, &segment.path);
+    match input.decoder_at(state, start + *seek).await? {
+        Opened::Decoder(decoder) => {
+            let limit_frames =
+                length.map(|length| ((length - *seek) * decoder.spec.rate as f64).round() as u64);
+            *seek = 0.0;
+            Ok(Some(OpenedSegment {
+                decoder: *decoder,
+                limit_frames,
+            }))
+        }
+        Opened::Ended { seconds } => {
+            // Without a known length the seek is taken to end in this file
+            *seek = seconds.map_or(0.0, |seconds| (*seek - (seconds - start)).max(0.0));
+            Ok(None)
+        }
+    }
+}
+
+async fn reject_plugin_format(state: &AppState, path: &str) -> Result<()> {
+    if state
+        .plugin_manager
+        .find_plugin_for_format(std::path::Path::new(path))
+        .await
+        .is_some()
+    {
+        return Err(TingError::DependencyError(
+            "Transcoding plugin-backed formats requires the FFmpeg plugin".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Decode `input` from `start` seconds and stream it as WAV, honouring `range`
+/// when the length of the result is known
+async fn stream_input(
+    state: &AppState,
+    input: NativeInput,
+    start: f64,
+    limit: Option<f64>,
+    gain_db: Option<f64>,
+    range: Option<&str>,
+    mut response_headers: Vec<(HeaderName, String)>,
+) -> Result<Response> {
+    let decoder = match input.decoder_at(state, start).await? {
+        Opened::Decoder(decoder) => *decoder,
+        Opened::Ended { .. } => {
+            return Err(TingError::InvalidRequest(
+                "No audio to transcode".to_string(),
+            ))
+        }
+    };
+    let spec = decoder.spec;
+    let rate = spec.rate as f64;
+    let block_align = block_align(&spec);
+    let limit_frames = limit.map(|limit| (limit * rate).round() as u64);
+    let frames = match (decoder.total_frames, limit_frames) {
+        (Some(total), Some(limit)) => Some(total.min(limit)),
+        (total, limit) => total.or(limit),
+    };
+    let header = wav_header(&spec, frames);
+    // The size is only exact when the frames are known and fit a WAV file
+    let total_len = frames
+        .map(|frames| WAV_HEADER_LEN + frames * block_align)
+        .filter(|len| *len <= u32::MAX as u64);
+
+    let mut status = StatusCode::OK;
+    let (header_part, output, decoder) = match total_len {
+        Some(total_len) => {
+            let window = match range {
+                Some(range) => {
+                    let window = state.audio_streamer.parse_range_header(range, total_len)?;
+                    status = StatusCode::PARTIAL_CONTENT;
+                    response_headers.push((
+                        header::CONTENT_RANGE,
+                        format!("bytes {}-{}/{}", window.start, window.end - 1, total_len),
+                    ));
+                    window
+                }
+                None => 0..total_len,
+            };
+            response_headers.push((
+                header::CONTENT_LENGTH,
+                (window.end - window.start).to_string(),
+            ));
+
+            let plan = ByteWindow::new(window, block_align);
+            let decoder = if plan.first_frame > 0 {
+                // Ranges that start inside the audio are decoded from their frame on
+                match input
+                    .decoder_at(state, start + plan.first_frame as f64 / rate)
+                    .await?
+                {
+                    Opened::Decoder(decoder) if decoder.spec == spec => Some(*decoder),
+                    Opened::Decoder(_) => {
+                        return Err(TingError::ExternalError(
+                            "Audio format changed while seeking".to_string(),
+                        ))
+                    }
+                    Opened::Ended { .. } => None,
+                }
+            } else if plan.data.is_empty() {
+                None
+            } else {
+                Some(decoder)
+            };
+            let header_part = header.slice(plan.header.start as usize..plan.header.end as usize);
+            (header_part, plan.output(), decoder)
+        }
+        None => (header, PcmOutput::frames(limit_frames), Some(decoder)),
+    };
+
+    let (tx, rx) = mpsc::channel::<io::Result<Bytes>>(CHANNEL_CAPACITY);
+    tokio::task::spawn_blocking(move || {
+        if !header_part.is_empty() && tx.blocking_send(Ok(header_part)).is_err() {
+            return;
+        }
+        match decoder {
+            Some(decoder) => {
+                decoder.run(output, gain_db, &tx);
+            }
+            // Past the end of what could be decoded: silence keeps the length
+            None => {
+                send_silence(output, block_align, &tx);
+            }
+        }
+    });
+
+    set_wav_headers(
+        &mut response_headers,
+        frames.map(|frames| frames as f64 / rate),
+    );
+    if total_len.is_some() {
+        response_headers.retain(|(name, _)| name != header::ACCEPT_RANGES);
+        response_headers.push((header::ACCEPT_RANGES, "bytes".to_string()));
+    }
+    Ok(build_response(status, receiver_body(rx), response_headers))
+}
+
+fn set_wav_headers(response_headers: &mut Vec<(HeaderName, String)>, duration: Option<f64>) {
+    response_headers
+        .retain(|(name, _)| name != header::CONTENT_TYPE && name.as_str() != "x-audio-duration");
+    response_headers.push((header::CONTENT_TYPE, "audio/wav".to_string()));
+    response_headers.push(("X-Transcoder".parse().unwrap(), "native".to_string()));
+    if let Some(duration) = duration {
+        response_headers.push(("X-Audio-Duration".parse().unwrap(), duration.to_string()));
+    }
+}
+
+fn receiver_body(rx: mpsc::Receiver<io::Result<Bytes>>) -> Body {
+    Body::from_stream(futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    }))
+}
+
+fn build_response(
+    status: StatusCode,
+    body: Body,
+    response_headers: Vec<(HeaderName, String)>,
+) -> Response {
+    let mut response = (status, body).into_response();
+    for (name, value) in response_headers {
+        if let Ok(value) = value.parse() {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    response
+}
+
+/// Which bytes of a WAV response of known size to send
+#[derive(Debug, PartialEq)]
+struct ByteWindow {
+    /// Bytes of the header
+    header: Range<u64>,
+    /// Bytes of the PCM data, counted from its start
+    data: Range<u64>,
+    /// Frame the data bytes start in
+    first_frame: u64,
+    block_align: u64,
+}
+
+impl ByteWindow {
+    fn new(window: Range<u64>, block_align: u64) -> Self {
+        let data =
+            window.start.saturating_sub(WAV_HEADER_LEN)..window.end.saturating_sub(WAV_HEADER_LEN);
+        Self {
+            header: window.start.min(WAV_HEADER_LEN)..window.end.min(WAV_HEADER_LEN),
+            first_frame: data.start / block_align,
+            data,
+            block_align,
+        }
+    }
+
+    fn output(&self) -> PcmOutput {
+        let end_frame = (self.data.end + self.block_align - 1) / self.block_align;
+        PcmOutput {
+            frames: Some(end_frame.saturating_sub(self.first_frame)),
+            skip_bytes: (self.data.start - self.first_frame * self.block_align) as usize,
+            max_bytes: Some(self.data.end - self.data.start),
+            pad: true,
+        }
+    }
+}
+
+/// How much of the decoded PCM to send
+#[derive(Debug, PartialEq)]
+struct PcmOutput {
+    /// Frames to decode, `None` for the rest of the track
+    frames: Option<u64>,
+    /// Bytes dropped from the start of the first frame
+    skip_bytes: usize,
+    /// Bytes to send at most
+    max_bytes: Option<u64>,
+    /// Fill up to `frames` with silence when the track ends early, so the
+    /// announced `Content-Length` holds
+    pad: bool,
+}
+
+impl PcmOutput {
+    fn frames(frames: Option<u64>) -> Self {
+        Self {
+            frames,
+            skip_bytes: 0,
+            max_bytes: None,
+            pad: false,
+        }
+    }
+
+    /// Cut `bytes` to the window; `false` once nothing more is to be sent
+    fn take(&mut self, mut bytes: Vec<u8>) -> (Vec<u8>, bool) {
+        let skipped = self.skip_bytes.min(bytes.len());
+        bytes.drain(..skipped);
+        self.skip_bytes -= skipped;
+        if let Some(max_bytes) = self.max_bytes.as_mut() {
+            bytes.truncate((*max_bytes).min(bytes.len() as u64) as usize);
+            *max_bytes -= bytes.len() as u64;
+        }
+        let more = self.max_bytes != Some(0) && self.frames != Some(0);
+        (bytes, more)
+    }
+}
+
+fn send_silence(mut output: PcmOutput, block_align: u64, tx: &mpsc::Sender<io::Result<Bytes>>) {
+    while let Some(remaining) = output.frames.filter(|frames| *frames > 0) {
+        let frames = remaining.min(PADDING_FRAMES);
+        output.frames = Some(remaining - frames);
+        let (bytes, more) = output.take(vec![0; (frames * block_align) as usize]);
+        if (!bytes.is_empty() && tx.blocking_send(Ok(Bytes::from(bytes))).is_err()) || !more {
+            return;
+        }
+    }
+}
+
+fn block_align(spec: &SignalSpec) -> u64 {
+    spec.channels.count().max(1) as u64 * 2
+}
+
+/// Result of opening a source at a start position
+enum Opened {
+    Decoder(Box<NativeDecoder>),
+    /// The track ends before the start position; its length in seconds when
+    /// the container says
+    Ended {
+        seconds: Option<f64>,
+    },
+}
+
+/// A probed source positioned at the requested start, with the first
+/// decoded chunk held back so its format is known before streaming
+struct NativeDecoder {
+    track: AudioTrack,
+    spec: SignalSpec,
+    /// Frames in the file after the start position, when the source says
+    total_frames: Option<u64>,
+    /// Frames still to be dropped to reach the start position
+    skip_frames: u64,
+    pending: Option<Vec<i16>>,
+}
+
+impl NativeDecoder {
+    fn open(source: Box<dyn MediaSource>, hint: Hint, start: f64) -> Result<Opened> {
+        let mut track = AudioTrack::open(source, &hint).map_err(unsupported)?;
+        let seconds = track
+            .frames_after(0.0)
+            .map(|frames| frames as f64 / track.sample_rate() as f64);
+        let skip_frames = track.seek(start);
+        let mut decoder = Self {
+            spec: SignalSpec::new(track.sample_rate(), Default::default()),
+            total_frames: track.frames_after(start),
+            track,
+            skip_frames,
+            pending: None,
+        };
+
+        let Some((spec, samples)) = decoder.next_chunk()? else {
+            return Ok(Opened::Ended { seconds });
+        };
+        decoder.spec = spec;
+        decoder.pending = Some(samples);
+        Ok(Opened::Decoder(Box::new(decoder)))
+    }
+
+    /// Next decoded samples after the start position, interleaved; `None`
+    /// at the end of the track
+    fn next_chunk(&mut self) -> Result<Option<(SignalSpec, Vec<i16>)>> {
+        loop {
+            let packet = match self.track.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(None)
+                }
+                Err(SymphoniaError::ResetRequired) => return Ok(None),
+                Err(e) => return Err(TingError::ExternalError(e.to_string())),
+            };
+            if packet.track_id() != self.track.track_id {
+                continue;
+            }
+            let decoded = match self.track.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // A corrupt packet is skipped rather than ending the stream
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(e) => return Err(TingError::ExternalError(e.to_string())),
+            };
+            let spec = *decoded.spec();
+            let frames = decoded.frames() as u64;
+            let skipped = self.skip_frames.min(frames);
+            self.skip_frames -= skipped;
+            if skipped == frames {
+                continue;
+            }
+
+            let mut buffer = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+            buffer.copy_interleaved_ref(decoded);
+            let channels = spec.channels.count().max(1);
+            let samples = buffer.samples()[skipped as usize * channels..].to_vec();
+            return Ok(Some((spec, samples)));
+        }
+    }
+
+    /// Send the samples as little-endian PCM, `gain_db` louder, until the
+    /// track ends, `output` is complete, or the client goes away. Returns
+    /// whether the track was sent to its end.
+    fn run(
+        mut self,
+        mut output: PcmOutput,
+        gain_db: Option<f64>,
+        tx: &mpsc::Sender<io::Result<Bytes>>,
+    ) -> bool {
+        let channels = self.spec.channels.count().max(1);
+        let gain = gain_db.map(|gain_db| 10f32.powf(gain_db as f32 / 20.0));
+        let mut pending = self.pending.take();
+        loop {
+            let mut samples = match pending.take() {
+                Some(samples) => samples,
+                None => match self.next_chunk() {
+                    Ok(Some((_, samples))) => samples,
+                    Ok(None) => {
+                        if output.pad {
+                            send_silence(output, block_align(&self.spec), tx);
+                        }
+                        return true;
+                    }
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Native transcoding stopped");
+                        let _ = tx.blocking_send(Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            e.to_string(),
+                        )));
+                        return false;
+                    }
+                },
+            };
+            if let Some(remaining) = output.frames.as_mut() {
+                let frames = (samples.len() / channels) as u64;
+                samples.truncate((frames.min(*remaining) as usize) * channels);
+                *remaining -= frames.min(*remaining);
+            }
+
+            if let Some(gain) = gain {
+                for sample in samples.iter_mut() {
+                    *sample = (*sample as f32 * gain)
+                        .round()
+                        .clamp(i16::MIN as f32, i16::MAX as f32)
+                        as i16;
+                }
+            }
+
+            let mut bytes = Vec::with_capacity(samples.len() * 2);
+            for sample in samples {
+                bytes.extend_from_slice(&sample.to_le_bytes());
+            }
+            let (bytes, more) = output.take(bytes);
+            if !bytes.is_empty() && tx.blocking_send(Ok(Bytes::from(bytes))).is_err() {
+                return false;
+            }
+            if !more {
+                return true;
+            }
+        }
+    }
+}
+
+fn unsupported(e: SymphoniaError) -> TingError {
+    TingError::InvalidRequest(format!(
+        "Format is not supported without the FFmpeg plugin: {}",
+        e
+    ))
+}
+
+/// Header of a 16-bit PCM WAV file; the sizes are left at their maximum when
+/// the number of frames is not known
+fn wav_header(spec: &SignalSpec, frames: Option<u64>) -> Bytes {
+    let channels = spec.channels.count().max(1) as u16;
+    let block_align = channels * 2;
+    let data_size = frames
+        .map(|frames| frames.saturating_mul(block_align as u64))
+        .filter(|size| *size <= (u32::MAX - 36) as u64)
+        .map_or(u32::MAX - 36, |size| size as u32);
+
+    let mut header = Vec::with_capacity(WAV_HEADER_LEN as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(data_size + 36).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&spec.rate.to_le_bytes());
+    header.extend_from_slice(&(spec.rate * block_align as u32).to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_size.to_le_bytes());
+    Bytes::from(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use symphonia::core::audio::Channels;
+
+    /// 2000 frames of a 440 Hz tone, mono 16-bit at 8 kHz, in two FLAC frames
+    const TONE_FLAC: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/tone-440hz-8khz.flac"
+    ));
+
+    fn tone_sample(frame: usize) -> i16 {
+        ((2.0 * std::f64::consts::PI * 440.0 * frame as f64 / 8000.0).sin() * 8000.0).round() as i16
+    }
+
+    fn open_tone(start: f64) -> NativeDecoder {
+        let mut hint = Hint::new();
+        hint.with_extension("flac");
+        let source = Box::new(io::Cursor::new(TONE_FLAC));
+        match NativeDecoder::open(source, hint, start).unwrap() {
+            Opened::Decoder(decoder) => *decoder,
+            Opened::Ended { .. } => panic!("tone ended early"),
+        }
+    }
+
+    fn collect(decoder: NativeDecoder, output: PcmOutput) -> Vec<u8> {
+        let (tx, mut rx) = mpsc::channel(1024);
+        assert!(decoder.run(output, None, &tx));
+        drop(tx);
+        let mut bytes = Vec::new();
+        while let Ok(chunk) = rx.try_recv() {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        bytes
+    }
+
+    fn as_samples(bytes: &[u8]) -> Vec<i16> {
+        bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect()
+    }
+
+    #[test]
+    fn decodes_flac_from_the_start_and_from_a_seek() {
+        let decoder = open_tone(0.0);
+        assert_eq!(decoder.spec.rate, 8000);
+        assert_eq!(decoder.spec.channels.count(), 1);
+        assert_eq!(decoder.total_frames, Some(2000));
+        let samples = as_samples(&collect(decoder, PcmOutput::frames(None)));
+        assert_eq!(samples.len(), 2000);
+        assert!(samples
+            .iter()
+            .enumerate()
+            .all(|(frame, sample)| *sample == tone_sample(frame)));
+
+        // Lands in the second FLAC frame
+        let decoder = open_tone(0.13);
+        assert_eq!(decoder.total_frames, Some(960));
+        let samples = as_samples(&collect(decoder, PcmOutput::frames(Some(10))));
+        let expected: Vec<i16> = (1040..1050).map(tone_sample).collect();
+        assert_eq!(samples, expected);
+
+        let mut hint = Hint::new();
+        hint.with_extension("flac");
+        let past_end = NativeDecoder::open(Box::new(io::Cursor::new(TONE_FLAC)), hint, 1.0);
+        assert!(matches!(
+            past_end.unwrap(),
+            Opened::Ended { seconds: Some(seconds) } if seconds == 0.25
+        ));
+    }
+
+    #[test]
+    fn byte_ranges_map_to_frames_of_the_pcm_data() {
+        // Stereo: 4 bytes per frame
+        let window = ByteWindow::new(10..54, 4);
+        assert_eq!(window.header, 10..44);
+        assert_eq!(window.data, 0..10);
+        assert_eq!(
+            window.output(),
+            PcmOutput {
+                frames: Some(3),
+                skip_bytes: 0,
+                max_bytes: Some(10),
+                pad: true,
+            }
+        );
+
+        let window = ByteWindow::new(50..60, 4);
+        assert_eq!(window.header, 44..44);
+        assert_eq!(window.first_frame, 1);
+        let mut output = window.output();
+        assert_eq!(output.frames, Some(3));
+        assert_eq!(output.skip_bytes, 2);
+        // Frames 1-3 decoded, bytes 6..16 of the data sent
+        let (bytes, more) = output.take((4..16).collect());
+        assert_eq!(bytes, (6..16).collect::<Vec<u8>>());
+        assert!(!more);
+    }
+
+    #[test]
+    fn ranges_of_the_tone_match_the_whole_file() {
+        let whole = collect(open_tone(0.0), PcmOutput::frames(None));
+        let window = ByteWindow::new(WAV_HEADER_LEN + 2001..WAV_HEADER_LEN + 2501, 2);
+        let decoder = open_tone(window.first_frame as f64 / 8000.0);
+        assert_eq!(collect(decoder, window.output()), whole[2001..2501]);
+
+        // Padded with silence when the source is shorter than announced
+        let window = ByteWindow::new(WAV_HEADER_LEN + 3990..WAV_HEADER_LEN + 4010, 2);
+        let decoder = open_tone(window.first_frame as f64 / 8000.0);
+        let bytes = collect(decoder, window.output());
+        assert_eq!(bytes.len(), 20);
+        assert_eq!(bytes[..10], whole[3990..]);
+        assert!(bytes[10..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn wav_headers_describe_the_pcm_stream() {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+        let header = wav_header(&spec, Some(44100));
+        assert_eq!(header.len(), 44);
+        assert_eq!(&header[..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(header[4..8].try_into().unwrap()), 176436);
+        assert_eq!(u16::from_le_bytes(header[22..24].try_into().unwrap()), 2);
+        assert_eq!(
+            u32::from_le_bytes(header[28..32].try_into().unwrap()),
+            176400
+        );
+        assert_eq!(
+            u32::from_le_bytes(header[40..44].try_into().unwrap()),
+            176400
+        );
+
+        let streaming = wav_header(&spec, None);
+        assert_eq!(
+            u32::from_le_bytes(streaming[4..8].try_into().unwrap()),
+            u32::MAX
+        );
+    }
+}
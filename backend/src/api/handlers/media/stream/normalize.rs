@@ -0,0 +1,114 @@
+//! Loudness normalization of transcoded streams
+//!
+//! Users who turn on `normalize_volume` hear analysed chapters at the same
+//! loudness: transcodes and HLS segments apply a fixed gain computed from the
+//! chapter's measured loudness and true peak. Chapters that have not been
+//! analysed yet, and files served without transcoding, play unchanged.
+
+use crate::core::audio_streamer::loudness::{
+    normalization_gain_db, DEFAULT_TARGET_LUFS, TARGET_RANGE_LUFS,
+};
+use crate::core::error::{Result, TingError};
+use crate::db::models::Chapter;
+use serde_json::Value;
+
+/// Gains this small are inaudible and not worth a filter
+const MIN_GAIN_DB: f64 = 0.1;
+
+/// Gain to apply to `chapter` for a user with `settings`, or `None` when
+/// the stream should play unchanged
+pub fn gain_from_settings(settings: Option<&Value>, chapter: &Chapter) -> Option<f64> {
+    let settings = settings?;
+    if !settings
+        .get("normalize_volume")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+    {
+        return None;
+    }
+    let target = settings
+        .get("loudness_target")
+        .and_then(Value::as_f64)
+        .unwrap_or(DEFAULT_TARGET_LUFS);
+    let gain = normalization_gain_db(chapter.loudness_lufs?, chapter.true_peak_dbtp?, target);
+    (gain.abs() >= MIN_GAIN_DB).then_some(gain)
+}
+
+/// Validate the user-submitted `normalize_volume` and `loudness_target`
+pub fn validate_settings(settings: &Value) -> Result<()> {
+    if let Some(enabled) = settings.get("normalize_volume") {
+        if !enabled.is_null() && !enabled.is_boolean() {
+            return Err(TingError::ValidationError(
+                "normalize_volume must be a boolean".to_string(),
+            ));
+        }
+    }
+    if let Some(target) = settings.get("loudness_target") {
+        let (min, max) = TARGET_RANGE_LUFS;
+        if !target.is_null()
+            && target
+                .as_f64()
+                .map_or(true, |target| !(min..=max).contains(&target))
+        {
+            return Err(TingError::ValidationError(format!(
+                "loudness_target must be between {} and {} LUFS",
+                min, max
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// FFmpeg audio filter applying `gain_db`
+pub fn volume_filter(gain_db: f64) -> String {
+    format!("volume={:.2}dB", gain_db)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn gain_follows_the_user_target() {
+        let mut chapter = Chapter {
+            id: "c".to_string(),
+            book_id: "b".to_string(),
+            title: None,
+            path: "/book.mp3".to_string(),
+            duration: None,
+            chapter_index: None,
+            is_extra: 0,
+            hash: None,
+            manual_corrected: 0,
+            created_at: String::new(),
+            start_offset: None,
+            end_offset: None,
+            loudness_lufs: None,
+            true_peak_dbtp: None,
+            skip_ranges: None,
+        };
+        let enabled = json!({ "normalize_volume": true });
+        assert_eq!(gain_from_settings(Some(&enabled), &chapter), None);
+
+        chapter.loudness_lufs = Some(-20.0);
+        chapter.true_peak_dbtp = Some(-12.0);
+        assert_eq!(gain_from_settings(Some(&enabled), &chapter), Some(4.0));
+        assert_eq!(
+            gain_from_settings(
+                Some(&json!({ "normalize_volume": true, "loudness_target": -23.0 })),
+                &chapter
+            ),
+            Some(-3.0)
+        );
+        assert_eq!(gain_from_settings(Some(&json!({})), &chapter), None);
+        assert_eq!(gain_from_settings(None, &chapter), None);
+        assert_eq!(volume_filter(-3.0), "volume=-3.00dB");
+
+        assert!(validate_settings(&json!({ "normalize_volume": true })).is_ok());
+        assert!(validate_settings(&json!({ "normalize_volume": "yes" })).is_err());
+        assert!(validate_settings(&json!({ "loudness_target": -14 })).is_ok());
+        assert!(validate_settings(&json!({ "loudness_target": -2 })).is_err());
+        assert!(validate_settings(&json!({ "loudness_target": null })).is_ok());
+    }
+}
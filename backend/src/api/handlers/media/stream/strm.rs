@@ -1,4 +1,5 @@
-use super::{handle_hls_request, StreamQuery};
+use super::hls_abr::HlsRendition;
+use super::{handle_hls_request, native, virtual_chapter, StreamQuery};
 use crate::api::handlers::AppState;
 use crate::core::error::{Result, TingError};
 use crate::db::models::{Book, Chapter, Library};
@@ -13,6 +14,7 @@ use tokio::io::AsyncReadExt;
 use tokio::process::Command;
 use tokio_util::io::ReaderStream;
 
+#[allow(clippy::too_many_arguments)]
 pub(super) async fn handle_strm_stream(
     state: AppState,
     chapter_id: &str,
@@ -21,6 +23,7 @@ pub(super) async fn handle_strm_stream(
     library: Library,
     params: &StreamQuery,
     headers: &axum::http::HeaderMap,
+    hls_renditions: Option<Vec<HlsRendition>>,
 ) -> Result<Response> {
     use axum::http::header;
     // Read the URL from the file
@@ -81,6 +84,8 @@ pub(super) async fn handle_strm_stream(
                 library,
                 true, // is_strm
                 params.seek.clone(),
+                hls_renditions,
+                None, // .strm targets are never analysed for loudness
             )
             .await;
         }
@@ -97,16 +102,25 @@ pub(super) async fn handle_strm_stream(
             }
         };
 
-        let ffmpeg_tools = state
-            .plugin_manager
-            .get_ffmpeg_tool_paths()
-            .await
-            .ok_or_else(|| {
-                TingError::IoError(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    "FFmpeg plugin binaries not found",
-                ))
-            })?;
+        // Without the FFmpeg plugin, decode the URL with symphonia and serve WAV instead
+        let Some(ffmpeg_tools) = state.plugin_manager.get_ffmpeg_tool_paths().await else {
+            return native::stream_native_url(
+                &state,
+                &library,
+                &url,
+                virtual_chapter::parse_seek_seconds(params.seek.as_deref()),
+                headers.get(header::RANGE).and_then(|h| h.to_str().ok()),
+                vec![
+                    (header::ACCESS_CONTROL_ALLOW_ORIGIN, "*".to_string()),
+                    (header::ACCEPT_RANGES, "none".to_string()),
+                    (
+                        "Cross-Origin-Resource-Policy".parse().unwrap(),
+                        "cross-origin".to_string(),
+                    ),
+                ],
+            )
+            .await;
+        };
 
         // 优先使用数据库中的时长，避免重复调用 FFprobe
         let duration_seconds = if let Some(db_duration) = chapter.duration {
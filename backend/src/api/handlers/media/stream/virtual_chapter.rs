@@ -0,0 +1,467 @@
+use super::decrypt::create_decrypted_stream;
+use super::hls::get_input_url_for_seek;
+use super::native::{stream_native_joined, stream_native_transcode};
+use super::normalize::volume_filter;
+use super::StreamQuery;
+use crate::api::handlers::AppState;
+use crate::core::error::{Result, TingError};
+use crate::db::models::{Chapter, ChapterSegment, Library};
+use axum::{
+    body::Body,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use std::process::Stdio;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, Command};
+use tokio_util::io::ReaderStream;
+
+/// Parse a `seek` query value given either as seconds or as `HH:MM:SS(.ms)`.
+pub(super) fn parse_seek_seconds(seek: Option<&str>) -> f64 {
+    let Some(value) = seek.map(str::trim).filter(|value| !value.is_empty()) else {
+        return 0.0;
+    };
+    if let Ok(seconds) = value.parse::<f64>() {
+        return seconds.max(0.0);
+    }
+    value
+        .split(':')
+        .try_fold(0.0, |total, part| {
+            part.parse::<f64>().ok().map(|part| total * 60.0 + part)
+        })
+        .unwrap_or(0.0)
+        .max(0.0)
+}
+
+/// Translate a chapter-relative seek into FFmpeg `-ss`/`-t` values for the file.
+///
+/// Whole-file chapters keep the client's seek string untouched.
+pub(super) fn chapter_seek_window(
+    chapter: &Chapter,
+    seek: Option<&str>,
+) -> (Option<String>, Option<f64>) {
+    if !chapter.is_virtual() {
+        return (seek.map(str::to_string), None);
+    }
+    let (file_start, length) = chapter.file_window(parse_seek_seconds(seek));
+    (Some(format!("{:.3}", file_start)), length)
+}
+
+/// Audio codecs that fragmented MP4 can carry without transcoding
+const MP4_COPY_CODECS: &[&str] = &["aac", "alac", "mp3", "ac3", "eac3", "opus"];
+
+/// Codec of the first audio stream, or `None` when FFprobe cannot tell
+async fn probe_audio_codec(ffprobe_path: &str, input: &str) -> Option<String> {
+    let output = Command::new(ffprobe_path)
+        .arg("-v")
+        .arg("error")
+        .arg("-select_streams")
+        .arg("a:0")
+        .arg("-show_entries")
+        .arg("stream=codec_name")
+        .arg("-of")
+        .arg("default=noprint_wrappers=1:nokey=1")
+        .arg(input)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let codec = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .to_lowercase();
+    (!codec.is_empty()).then_some(codec)
+}
+
+/// Serve a virtual chapter by cutting its time range out of the shared file.
+///
+/// Byte ranges cannot express a time slice of a compressed file, so FFmpeg
+/// extracts `[start_offset + seek, end_offset)` and streams it. Audio is copied
+/// into fragmented MP4 when the source codec fits, otherwise it is transcoded
+/// to AAC; clients may also ask for MP3/WAV. Plugin-backed formats are decoded
+/// by their plugin and piped into FFmpeg.
+///
+/// The response length is unknown up front, so it has no `Content-Length` and
+/// `Range` is ignored. Clients seek within the chapter with the `seek` query
+/// parameter and read the chapter length from `X-Audio-Duration`.
+///
+/// `gain_db` normalizes the loudness, which rules out copying the audio.
+/// Without FFmpeg the slice is decoded natively, and `range` is honoured when
+/// its length is known.
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn handle_virtual_chapter_stream(
+    state: &AppState,
+    chapter: &Chapter,
+    library: &Library,
+    params: &StreamQuery,
+    gain_db: Option<f64>,
+    range: Option<&str>,
+    is_head_request: bool,
+) -> Result<Response> {
+    let start = chapter.start_offset.unwrap_or(0.0);
+    let (slice_start, slice_length) =
+        chapter.file_window(parse_seek_seconds(params.seek.as_deref()));
+    let end = chapter.slice_length().map(|length| start + length);
+
+    let (format, content_type) = output_format(params)?;
+
+    let mut response_headers = vec![
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (header::ACCESS_CONTROL_ALLOW_ORIGIN, "*".to_string()),
+        (header::ACCEPT_RANGES, "none".to_string()),
+        (
+            "Cross-Origin-Resource-Policy".parse().unwrap(),
+            "cross-origin".to_string(),
+        ),
+        ("X-Chapter-Start".parse().unwrap(), start.to_string()),
+    ];
+    if let Some(end) = end {
+        response_headers.push(("X-Chapter-End".parse().unwrap(), end.to_string()));
+        response_headers.push((
+            "X-Audio-Duration".parse().unwrap(),
+            (end - start).max(0.0).to_string(),
+        ));
+    }
+
+    if is_head_request {
+        return Ok(empty_response(response_headers));
+    }
+
+    let Some(ffmpeg_tools) = state.plugin_manager.get_ffmpeg_tool_paths().await else {
+        return stream_native_transcode(
+            state,
+            chapter,
+            library,
+            slice_start,
+            slice_length,
+            gain_db,
+            range,
+            response_headers,
+        )
+        .await;
+    };
+
+    // Remote files are opened by URL so FFmpeg can seek with range requests
+    // instead of reading everything before the chapter start. Plugin-backed
+    // formats must be decoded/decrypted before FFmpeg sees them.
+    let cache_path = state.cache_manager.get_cache_path(&chapter.id);
+    let plugin_info = if cache_path.exists() {
+        None
+    } else {
+        state
+            .plugin_manager
+            .find_plugin_for_format(std::path::Path::new(&chapter.path))
+            .await
+    };
+    let input = if plugin_info.is_some() {
+        "pipe:0".to_string()
+    } else if cache_path.exists() {
+        cache_path.to_string_lossy().to_string()
+    } else {
+        get_input_url_for_seek(state, chapter, library, false).await?
+    };
+    let copy_audio = format == "mp4"
+        && plugin_info.is_none()
+        && gain_db.is_none()
+        && probe_audio_codec(&ffmpeg_tools.ffprobe, &input)
+            .await
+            .is_some_and(|codec| MP4_COPY_CODECS.contains(&codec.as_str()));
+
+    let mut cmd = Command::new(&ffmpeg_tools.ffmpeg);
+    cmd.arg("-y").arg("-loglevel").arg("error");
+    cmd.arg("-ss").arg(format!("{:.3}", slice_start));
+    if let Some(length) = slice_length {
+        cmd.arg("-t").arg(format!("{:.3}", length));
+    }
+    cmd.arg("-i").arg(&input);
+    if let Some(gain_db) = gain_db {
+        cmd.arg("-af").arg(volume_filter(gain_db));
+    }
+
+    push_output_args(&mut cmd, format, copy_audio);
+    cmd.arg("pipe:1")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if plugin_info.is_some() {
+        cmd.stdin(Stdio::piped());
+    }
+
+    tracing::info!(
+        chapter_id = %chapter.id,
+        start = slice_start,
+        length = ?slice_length,
+        format = %format,
+        copy_audio = copy_audio,
+        "Streaming virtual chapter slice"
+    );
+
+    let mut child = cmd.spawn().map_err(TingError::IoError)?;
+
+    if let Some(plugin) = &plugin_info {
+        let (mut plugin_stream, _, _, _, _, _, _) =
+            create_decrypted_stream(state, chapter, library, plugin, None).await?;
+        let mut stdin = child.stdin.take().ok_or_else(|| {
+            TingError::IoError(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "Failed to capture ffmpeg stdin",
+            ))
+        })?;
+        tokio::spawn(async move {
+            while let Some(chunk) = plugin_stream.next().await {
+                let Ok(bytes) = chunk else {
+                    break;
+                };
+                // FFmpeg closes stdin once the slice end has been read
+                if stdin.write_all(&bytes).await.is_err() {
+                    break;
+                }
+            }
+            let _ = stdin.shutdown().await;
+        });
+    }
+
+    stream_ffmpeg_output(&mut child, response_headers)
+}
+
+/// Container and content type requested through the `transcode` parameter
+fn output_format(params: &StreamQuery) -> Result<(&'static str, &'static str)> {
+    match params.transcode.as_deref() {
+        None => Ok(("mp4", "audio/mp4")),
+        Some("mp3") => Ok(("mp3", "audio/mpeg")),
+        Some("wav") => Ok(("wav", "audio/wav")),
+        Some(_) => Err(TingError::InvalidRequest(
+            "Unsupported transcode format".to_string(),
+        )),
+    }
+}
+
+fn push_output_args(cmd: &mut Command, format: &str, copy_audio: bool) {
+    cmd.arg("-vn").arg("-map").arg("0:a:0");
+    match format {
+        "mp3" => {
+            cmd.arg("-acodec")
+                .arg("libmp3lame")
+                .arg("-b:a")
+                .arg("128k")
+                .arg("-ac")
+                .arg("2")
+                .arg("-ar")
+                .arg("44100")
+                .arg("-f")
+                .arg("mp3");
+        }
+        "wav" => {
+            cmd.arg("-f").arg("wav");
+        }
+        _ => {
+            if copy_audio {
+                cmd.arg("-c:a").arg("copy");
+            } else {
+                cmd.arg("-c:a").arg("aac").arg("-b:a").arg("128k");
+            }
+            // Piped MP4 output must be fragmented because the moov box cannot be rewritten
+            cmd.arg("-movflags")
+                .arg("frag_keyframe+empty_moov+default_base_moof")
+                .arg("-f")
+                .arg("mp4");
+        }
+    }
+}
+
+fn empty_response(response_headers: Vec<(header::HeaderName, String)>) -> Response {
+    let mut response = (StatusCode::OK, Body::empty()).into_response();
+    for (name, value) in response_headers {
+        if let Ok(value) = value.parse() {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    response
+}
+
+/// Stream FFmpeg's stdout as the response body and log its stderr.
+fn stream_ffmpeg_output(
+    child: &mut Child,
+    response_headers: Vec<(header::HeaderName, String)>,
+) -> Result<Response> {
+    if let Some(mut stderr) = child.stderr.take() {
+        tokio::spawn(async move {
+            let mut buffer = String::new();
+            if stderr.read_to_string(&mut buffer).await.is_ok() && !buffer.is_empty() {
+                tracing::warn!("FFmpeg stderr: {}", buffer);
+            }
+        });
+    }
+
+    let stdout = child.stdout.take().ok_or_else(|| {
+        TingError::IoError(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "Failed to capture ffmpeg stdout",
+        ))
+    })?;
+
+    let mut response =
+        (StatusCode::OK, Body::from_stream(ReaderStream::new(stdout))).into_response();
+    for (name, value) in response_headers {
+        if let Ok(value) = value.parse() {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    Ok(response)
+}
+
+/// Quote a path or URL for an FFmpeg concat list.
+fn concat_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Serve a chapter joined from several files by playing its segments back to back.
+///
+/// The segments are written to an FFmpeg concat list, so seeking and the
+/// response headers behave as for a single virtual chapter. Audio is always
+/// transcoded because the files may use different codecs. Without FFmpeg the
+/// segments are decoded natively one after another.
+pub(super) async fn handle_joined_chapter_stream(
+    state: &AppState,
+    chapter: &Chapter,
+    segments: &[ChapterSegment],
+    library: &Library,
+    params: &StreamQuery,
+    is_head_request: bool,
+) -> Result<Response> {
+    let (format, content_type) = output_format(params)?;
+    let seek = parse_seek_seconds(params.seek.as_deref());
+
+    let mut response_headers = vec![
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (header::ACCESS_CONTROL_ALLOW_ORIGIN, "*".to_string()),
+        (header::ACCEPT_RANGES, "none".to_string()),
+        (
+            "Cross-Origin-Resource-Policy".parse().unwrap(),
+            "cross-origin".to_string(),
+        ),
+    ];
+    if let Some(length) = chapter.slice_length() {
+        response_headers.push(("X-Audio-Duration".parse().unwrap(), length.to_string()));
+    }
+
+    if is_head_request {
+        return Ok(empty_response(response_headers));
+    }
+
+    let Some(ffmpeg_tools) = state.plugin_manager.get_ffmpeg_tool_paths().await else {
+        return stream_native_joined(state, segments, library, seek, response_headers).await;
+    };
+
+    let mut list = String::from("ffconcat version 1.0\n");
+    for segment in segments {
+        let mut part = chapter.clone();
+        part.path = segment.path.clone();
+        let input = get_input_url_for_seek(state, &part, library, false).await?;
+        list.push_str(&format!("file {}\n", concat_quote(&input)));
+        if let Some(start) = segment.start_offset {
+            list.push_str(&format!("inpoint {:.3}\n", start));
+        }
+        if let Some(end) = segment.end_offset {
+            list.push_str(&format!("outpoint {:.3}\n", end));
+        }
+    }
+
+    let list_dir = std::env::temp_dir().join("ting-reader-concat");
+    tokio::fs::create_dir_all(&list_dir).await?;
+    let list_path = list_dir.join(format!("{}.ffconcat", uuid::Uuid::new_v4()));
+    tokio::fs::write(&list_path, list).await?;
+
+    let mut cmd = Command::new(&ffmpeg_tools.ffmpeg);
+    cmd.arg("-y").arg("-loglevel").arg("error");
+    cmd.arg("-f")
+        .arg("concat")
+        .arg("-safe")
+        .arg("0")
+        .arg("-protocol_whitelist")
+        .arg("file,http,https,tcp,tls,crypto");
+    cmd.arg("-ss").arg(format!("{:.3}", seek));
+    cmd.arg("-i").arg(&list_path);
+    push_output_args(&mut cmd, format, false);
+    cmd.arg("pipe:1")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    tracing::info!(
+        chapter_id = %chapter.id,
+        segments = segments.len(),
+        seek = seek,
+        format = %format,
+        "Streaming joined chapter"
+    );
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&list_path).await;
+            return Err(TingError::IoError(e));
+        }
+    };
+    let response = stream_ffmpeg_output(&mut child, response_headers);
+
+    // The list must outlive FFmpeg, which reopens it while seeking
+    tokio::spawn(async move {
+        let _ = child.wait().await;
+        let _ = tokio::fs::remove_file(&list_path).await;
+    });
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chapter_seek_window, concat_quote, parse_seek_seconds};
+    use crate::db::models::Chapter;
+
+    #[test]
+    fn offsets_seek_for_virtual_chapters() {
+        let mut chapter = Chapter {
+            id: "c".to_string(),
+            book_id: "b".to_string(),
+            title: None,
+            path: "/book.mp3".to_string(),
+            duration: None,
+            chapter_index: None,
+            is_extra: 0,
+            hash: None,
+            manual_corrected: 0,
+            created_at: String::new(),
+            start_offset: None,
+            end_offset: None,
+            loudness_lufs: None,
+            true_peak_dbtp: None,
+            skip_ranges: None,
+        };
+        assert_eq!(
+            chapter_seek_window(&chapter, Some("00:01:00")),
+            (Some("00:01:00".to_string()), None)
+        );
+
+        chapter.start_offset = Some(600.0);
+        chapter.end_offset = Some(900.0);
+        assert_eq!(
+            chapter_seek_window(&chapter, Some("60")),
+            (Some("660.000".to_string()), Some(240.0))
+        );
+    }
+
+    #[test]
+    fn parses_seek_in_seconds_and_clock_format() {
+        assert_eq!(parse_seek_seconds(None), 0.0);
+        assert_eq!(parse_seek_seconds(Some("12.5")), 12.5);
+        assert_eq!(parse_seek_seconds(Some("01:02:03")), 3723.0);
+        assert_eq!(parse_seek_seconds(Some("bogus")), 0.0);
+    }
+
+    #[test]
+    fn quotes_concat_paths() {
+        assert_eq!(concat_quote("/books/a.mp3"), "'/books/a.mp3'");
+        assert_eq!(concat_quote("/books/it's.mp3"), r"'/books/it'\''s.mp3'");
+    }
+}
@@ -1,40 +1,49 @@
+pub mod bookmarks;
 pub mod books;
+pub mod feeds;
 pub mod libraries;
 pub mod media;
 pub mod notifications;
 pub mod playlists;
 pub mod plugins;
 pub mod series;
+pub mod stats;
 pub mod system;
+pub mod task_schedules;
 pub mod tools;
 pub mod users;
 
+pub use bookmarks::*;
 pub use books::*;
+pub use feeds::*;
 pub use libraries::*;
 pub use media::*;
 pub use notifications::*;
 pub use playlists::*;
 pub use plugins::*;
 pub use series::*;
+pub use stats::*;
 pub use system::*;
+pub use task_schedules::*;
 pub use tools::*;
 pub use users::*;
 
 use crate::api::handlers::media::stream::HlsSessionManager;
 use crate::api::ws::manager::WsSessionManager;
-use crate::cache::CacheManager;
+use crate::api::ws::sleep_timer::SleepTimerManager;
+use crate::cache::{transcode::TranscodeCache, CacheManager};
 use crate::core::audio_streamer::AudioStreamer;
 use crate::core::config::Config;
 use crate::core::library_watcher::LibraryWatcher;
 use crate::core::merge_service::MergeService;
 use crate::core::nfo_manager::NfoManager;
 use crate::core::services::{BookService, ScraperService};
-use crate::core::task_queue::TaskQueue;
+use crate::core::task_queue::{TaskQueue, TaskScheduleRunner};
 use crate::core::StorageService;
 use crate::db::repository::{
-    BookRepository, ChapterRepository, FavoriteRepository, LibraryRepository,
+    BookRepository, BookmarkRepository, ChapterRepository, FavoriteRepository, LibraryRepository,
     NotificationWebhookRepository, PlaylistRepository, ProgressRepository, SeriesRepository,
-    SystemSettingsRepository, UserRepository, UserSettingsRepository,
+    SystemSettingsRepository, TaskScheduleRepository, UserRepository, UserSettingsRepository,
 };
 use crate::plugin::config::PluginConfigManager;
 use crate::plugin::manager::PluginManager;
@@ -48,6 +57,7 @@ pub struct AppState {
     pub user_repo: Arc<UserRepository>,
     pub progress_repo: Arc<ProgressRepository>,
     pub favorite_repo: Arc<FavoriteRepository>,
+    pub bookmark_repo: Arc<BookmarkRepository>,
     pub settings_repo: Arc<UserSettingsRepository>,
     pub system_settings_repo: Arc<SystemSettingsRepository>,
     pub library_repo: Arc<LibraryRepository>,
@@ -55,6 +65,7 @@ pub struct AppState {
     pub series_repo: Arc<SeriesRepository>,
     pub playlist_repo: Arc<PlaylistRepository>,
     pub notification_repo: Arc<NotificationWebhookRepository>,
+    pub task_schedule_repo: Arc<TaskScheduleRepository>,
     pub book_service: Arc<BookService>,
     pub scraper_service: Arc<ScraperService>,
     pub plugin_manager: Arc<PluginManager>,
@@ -62,10 +73,12 @@ pub struct AppState {
     pub plugin_host_gateway: Arc<PluginHostGateway>,
     pub config_manager: Arc<PluginConfigManager>,
     pub task_queue: Arc<TaskQueue>,
+    pub task_schedule_runner: Arc<TaskScheduleRunner>,
     pub config: Arc<tokio::sync::RwLock<Config>>,
     pub jwt_secret: Arc<String>, // 保留用于向后兼容
     pub jwt_key_manager: Option<Arc<crate::auth::JwtKeyManager>>, // 新的密钥管理器
     pub cache_manager: Arc<CacheManager>,
+    pub transcode_cache: Arc<TranscodeCache>,
     pub encryption_key: Arc<[u8; 32]>,
     pub storage_service: Arc<StorageService>,
     pub preload_cache: Arc<
@@ -78,5 +91,6 @@ pub struct AppState {
         Arc<tokio::sync::Mutex<std::collections::HashMap<String, tokio::task::JoinHandle<()>>>>,
     pub library_watcher: Arc<LibraryWatcher>,
     pub ws_manager: Arc<WsSessionManager>,
+    pub sleep_timers: Arc<SleepTimerManager>,
     pub hls_session_manager: Arc<HlsSessionManager>,
 }
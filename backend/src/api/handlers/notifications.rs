@@ -20,6 +20,11 @@ const SUPPORTED_EVENTS: &[(&str, &str, &str)] = &[
     ("book.created", "作品入库", "作品被创建或入库"),
     ("book.deleted", "删除作品", "作品被删除"),
     ("library.scan_completed", "扫描完成", "媒体库扫描任务完成"),
+    (
+        "library.new_episodes",
+        "RSS 新单集",
+        "RSS 媒体库扫描发现新单集",
+    ),
 ];
 
 #[derive(Debug, Serialize)]
@@ -5,7 +5,8 @@ use crate::api::models::{
 };
 use crate::auth::middleware::AuthUser;
 use crate::core::error::{Result, TingError};
-use crate::db::models::{Playlist, PlaylistItem};
+use crate::db::models::{Book, Playlist, PlaylistItem};
+use crate::db::repository::playlist::{encode_smart_rules, smart_rules};
 use crate::db::repository::Repository;
 use axum::{
     extract::{Path, State},
@@ -58,22 +59,58 @@ pub async fn create_playlist(
         ));
     }
 
+    let playlist_type = req
+        .playlist_type
+        .as_deref()
+        .map(|value| value.trim().to_lowercase())
+        .unwrap_or_else(|| "manual".to_string());
+    let smart_rules = match playlist_type.as_str() {
+        "manual" => {
+            if req.rules.is_some() {
+                return Err(TingError::InvalidRequest(
+                    "Only smart playlists have rules".to_string(),
+                ));
+            }
+            None
+        }
+        "smart" => {
+            if req.items.is_some() || !req.book_ids.is_empty() {
+                return Err(TingError::InvalidRequest(
+                    "Smart playlists cannot have manual items".to_string(),
+                ));
+            }
+            let rules = req.rules.ok_or_else(|| {
+                TingError::InvalidRequest("Smart playlists require rules".to_string())
+            })?;
+            Some(encode_smart_rules(rules)?)
+        }
+        _ => {
+            return Err(TingError::InvalidRequest(
+                "playlist_type must be 'manual' or 'smart'".to_string(),
+            ))
+        }
+    };
+
     let now = chrono::Utc::now().to_rfc3339();
     let playlist = Playlist {
         id: Uuid::new_v4().to_string(),
         user_id: user.id.clone(),
         title: title.to_string(),
         description: req.description.map(|value| value.trim().to_string()),
+        playlist_type,
+        smart_rules,
         created_at: now.clone(),
         updated_at: now,
     };
 
     state.playlist_repo.create(&playlist).await?;
-    let items = normalize_playlist_items(req.items, req.book_ids)?;
-    state
-        .playlist_repo
-        .replace_items(&playlist.id, &user.id, user.role == "admin", items)
-        .await?;
+    if !playlist.is_smart() {
+        let items = normalize_playlist_items(req.items, req.book_ids)?;
+        state
+            .playlist_repo
+            .replace_items(&playlist.id, &user.id, user.role == "admin", items)
+            .await?;
+    }
 
     let response =
         build_playlist_response(&state, playlist, &user.id, user.role == "admin").await?;
@@ -104,6 +141,22 @@ pub async fn update_playlist(
         ));
     }
 
+    let has_items = req.items.is_some() || req.book_ids.is_some();
+    if existing.is_smart() && has_items {
+        return Err(TingError::InvalidRequest(
+            "Smart playlists cannot have manual items".to_string(),
+        ));
+    }
+    let smart_rules = match req.rules {
+        Some(rules) if existing.is_smart() => Some(encode_smart_rules(rules)?),
+        Some(_) => {
+            return Err(TingError::InvalidRequest(
+                "Only smart playlists have rules".to_string(),
+            ))
+        }
+        None => existing.smart_rules,
+    };
+
     let updated = Playlist {
         id: existing.id.clone(),
         user_id: existing.user_id,
@@ -113,13 +166,15 @@ pub async fn update_playlist(
         } else {
             existing.description
         },
+        playlist_type: existing.playlist_type,
+        smart_rules,
         created_at: existing.created_at,
         updated_at: chrono::Utc::now().to_rfc3339(),
     };
 
     state.playlist_repo.update(&updated).await?;
 
-    if req.items.is_some() || req.book_ids.is_some() {
+    if has_items {
         let items = normalize_playlist_items(req.items, req.book_ids.unwrap_or_default())?;
         state
             .playlist_repo
@@ -201,6 +256,27 @@ async fn build_playlist_response(
     is_admin: bool,
 ) -> Result<PlaylistResponse> {
     let mut response = PlaylistResponse::from(playlist.clone());
+    if playlist.is_smart() {
+        let rules = smart_rules(&playlist)?;
+        let books = state
+            .playlist_repo
+            .find_books_by_rules(&rules, user_id, is_admin)
+            .await?;
+        for (idx, book) in books.into_iter().enumerate() {
+            let book_response = BookResponse::from(book);
+            response.book_ids.push(book_response.id.clone());
+            response.books.push(book_response.clone());
+            response.items.push(PlaylistItemResponse {
+                item_type: "book".to_string(),
+                item_id: book_response.id.clone(),
+                order: (idx + 1) as i32,
+                book: Some(book_response),
+                series: None,
+            });
+        }
+        return Ok(response);
+    }
+
     let items = state
         .playlist_repo
         .find_items_by_playlist(&playlist.id)
@@ -269,3 +345,58 @@ async fn build_playlist_response(
 
     Ok(response)
 }
+
+/// Books of a playlist in play order, skipping anything the user cannot access.
+/// Series items expand to their books.
+pub(crate) async fn playlist_books(
+    state: &AppState,
+    playlist: &Playlist,
+    user_id: &str,
+    is_admin: bool,
+) -> Result<Vec<Book>> {
+    if playlist.is_smart() {
+        let rules = smart_rules(playlist)?;
+        return state
+            .playlist_repo
+            .find_books_by_rules(&rules, user_id, is_admin)
+            .await;
+    }
+
+    let mut books = Vec::new();
+    for item in state
+        .playlist_repo
+        .find_items_by_playlist(&playlist.id)
+        .await?
+    {
+        match item.item_type.as_str() {
+            "book" => {
+                if let Some(book) = state.book_repo.find_by_id(&item.item_id).await? {
+                    if state
+                        .book_repo
+                        .check_access(&book.id, user_id, is_admin)
+                        .await?
+                    {
+                        books.push(book);
+                    }
+                }
+            }
+            "series" => {
+                if !state
+                    .series_repo
+                    .check_access(&item.item_id, user_id, is_admin)
+                    .await?
+                {
+                    continue;
+                }
+                let series_books = state
+                    .series_repo
+                    .find_books_by_series_with_filters(&item.item_id, user_id, is_admin)
+                    .await?;
+                books.extend(series_books.into_iter().map(|(book, _)| book));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(books)
+}
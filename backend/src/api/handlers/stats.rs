@@ -0,0 +1,595 @@
+//! Per-user listening statistics
+//!
+//! Everything is computed from `listening_events` (one row per user, chapter and
+//! local day) and `listening_totals`. Days are calendar days in the application
+//! time zone, which is also how `listening_events.activity_date` is bucketed.
+
+use super::AppState;
+use crate::api::models::{
+    ListeningSummaryResponse, ListeningTimelinePoint, ListeningTimelineResponse,
+    ListeningTopResponse, ListeningWrappedResponse, StatsGranularity, StatsTimelineQuery,
+    StatsTopQuery, TopBookStatistics, TopPersonStatistics, WrappedMonth,
+};
+use crate::auth::middleware::AuthUser;
+use crate::core::error::{Result, TingError};
+use crate::core::time::{local_date, resolve_time_zone};
+use crate::db::repository::progress::{COMPLETION_RATIO, COMPLETION_TAIL_SECS};
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use rusqlite::Connection;
+
+/// Buckets returned by the timeline when no range is given
+const DEFAULT_TIMELINE_POINTS: u32 = 30;
+/// Longest timeline that can be requested, in days
+const MAX_TIMELINE_DAYS: i64 = 3660;
+const DEFAULT_TOP_LIMIT: usize = 10;
+const MAX_TOP_LIMIT: usize = 50;
+
+const FIRST_DAY: &str = "0000-01-01";
+const LAST_DAY: &str = "9999-12-31";
+
+/// Handler for GET /api/v1/me/stats/summary - Listening time, streaks and completions
+pub async fn get_listening_summary(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Result<impl IntoResponse> {
+    let (time_zone, today) = today(&state).await?;
+    let week_start = bucket_start(today, StatsGranularity::Week);
+    let month_start = bucket_start(today, StatsGranularity::Month);
+
+    let user_id = user.id;
+    let response = state
+        .book_repo
+        .db()
+        .execute(move |conn| {
+            let days = daily_seconds(conn, &user_id, None, Some(today))?;
+            let seconds_since = |start: NaiveDate| -> f64 {
+                days.iter()
+                    .filter(|(day, _)| *day >= start)
+                    .map(|(_, seconds)| seconds)
+                    .sum()
+            };
+            let active_days = days.iter().map(|(day, _)| *day).collect::<Vec<_>>();
+            let (current_streak, longest_streak) = streaks(&active_days, today);
+            let total_seconds: f64 = conn
+                .query_row(
+                    "SELECT COALESCE(SUM(listen_seconds), 0.0) FROM listening_totals WHERE user_id = ?",
+                    [&user_id],
+                    |row| row.get(0),
+                )
+                .map_err(TingError::DatabaseError)?;
+            let (completed_chapters, completed_books) =
+                completion_counts(conn, &user_id, None, None)?;
+
+            Ok(ListeningSummaryResponse {
+                time_zone,
+                today: today.to_string(),
+                today_seconds: seconds_since(today),
+                week_seconds: seconds_since(week_start),
+                month_seconds: seconds_since(month_start),
+                total_seconds,
+                current_streak,
+                longest_streak,
+                completed_chapters,
+                completed_books,
+            })
+        })
+        .await?;
+
+    Ok(Json(response))
+}
+
+/// Handler for GET /api/v1/me/stats/timeline - Listening time per day, week or month
+pub async fn get_listening_timeline(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<StatsTimelineQuery>,
+) -> Result<impl IntoResponse> {
+    let (time_zone, today) = today(&state).await?;
+    let granularity = query.granularity;
+    let to = parse_day(query.to.as_deref(), "to")?.unwrap_or(today);
+    let from = match parse_day(query.from.as_deref(), "from")? {
+        Some(from) => from,
+        None => default_timeline_start(to, granularity),
+    };
+    if from > to {
+        return Err(TingError::ValidationError(
+            "from must not be after to".to_string(),
+        ));
+    }
+    if (to - from).num_days() >= MAX_TIMELINE_DAYS {
+        return Err(TingError::ValidationError(format!(
+            "The timeline can span at most {} days",
+            MAX_TIMELINE_DAYS
+        )));
+    }
+
+    let user_id = user.id;
+    let days = state
+        .book_repo
+        .db()
+        .execute(move |conn| daily_seconds(conn, &user_id, Some(from), Some(to)))
+        .await?;
+
+    Ok(Json(ListeningTimelineResponse {
+        time_zone,
+        granularity,
+        from: from.to_string(),
+        to: to.to_string(),
+        points: build_timeline(&days, from, to, granularity),
+    }))
+}
+
+/// Handler for GET /api/v1/me/stats/top - Most listened books, authors and narrators
+pub async fn get_listening_top(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<StatsTopQuery>,
+) -> Result<impl IntoResponse> {
+    let from = parse_day(query.from.as_deref(), "from")?;
+    let to = parse_day(query.to.as_deref(), "to")?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_TOP_LIMIT)
+        .clamp(1, MAX_TOP_LIMIT);
+
+    let user_id = user.id;
+    let response = state
+        .book_repo
+        .db()
+        .execute(move |conn| {
+            Ok(ListeningTopResponse {
+                from: from.map(|day| day.to_string()),
+                to: to.map(|day| day.to_string()),
+                books: top_books(conn, &user_id, from, to, limit)?,
+                authors: top_people(conn, &user_id, Person::Author, from, to, limit)?,
+                narrators: top_people(conn, &user_id, Person::Narrator, from, to, limit)?,
+            })
+        })
+        .await?;
+
+    Ok(Json(response))
+}
+
+/// Handler for GET /api/v1/me/stats/wrapped/:year - Yearly listening summary
+pub async fn get_listening_wrapped(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(year): Path<i32>,
+) -> Result<impl IntoResponse> {
+    let (time_zone, _) = today(&state).await?;
+    let (from, to) = NaiveDate::from_ymd_opt(year, 1, 1)
+        .zip(NaiveDate::from_ymd_opt(year, 12, 31))
+        .filter(|_| year > 0)
+        .ok_or_else(|| TingError::ValidationError(format!("Invalid year: {}", year)))?;
+
+    let user_id = user.id;
+    let response = state
+        .book_repo
+        .db()
+        .execute(move |conn| {
+            let days = daily_seconds(conn, &user_id, Some(from), Some(to))?;
+            let active_days = days.iter().map(|(day, _)| *day).collect::<Vec<_>>();
+            let (_, longest_streak) = streaks(&active_days, to);
+            let months = build_timeline(&days, from, to, StatsGranularity::Month)
+                .into_iter()
+                .map(|point| WrappedMonth {
+                    month: point.start[..7].to_string(),
+                    listen_seconds: point.listen_seconds,
+                })
+                .collect::<Vec<_>>();
+            let top_month = months
+                .iter()
+                .filter(|month| month.listen_seconds > 0.0)
+                .max_by(|a, b| a.listen_seconds.total_cmp(&b.listen_seconds))
+                .map(|month| WrappedMonth {
+                    month: month.month.clone(),
+                    listen_seconds: month.listen_seconds,
+                });
+            let books_started: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM ( \
+                        SELECT book_id FROM listening_events WHERE user_id = ? \
+                        GROUP BY book_id HAVING MIN(activity_date) BETWEEN ? AND ? \
+                     )",
+                    rusqlite::params![&user_id, from.to_string(), to.to_string()],
+                    |row| row.get(0),
+                )
+                .map_err(TingError::DatabaseError)?;
+            let (completed_chapters, completed_books) =
+                completion_counts(conn, &user_id, Some(from), Some(to))?;
+
+            Ok(ListeningWrappedResponse {
+                year,
+                time_zone,
+                listen_seconds: days.iter().map(|(_, seconds)| seconds).sum(),
+                active_days: active_days.len() as i64,
+                longest_streak,
+                books_started,
+                completed_chapters,
+                completed_books,
+                top_month,
+                months,
+                top_books: top_books(conn, &user_id, Some(from), Some(to), DEFAULT_TOP_LIMIT)?,
+                top_authors: top_people(
+                    conn,
+                    &user_id,
+                    Person::Author,
+                    Some(from),
+                    Some(to),
+                    DEFAULT_TOP_LIMIT,
+                )?,
+                top_narrators: top_people(
+                    conn,
+                    &user_id,
+                    Person::Narrator,
+                    Some(from),
+                    Some(to),
+                    DEFAULT_TOP_LIMIT,
+                )?,
+            })
+        })
+        .await?;
+
+    Ok(Json(response))
+}
+
+/// Application time zone name and the current day in it
+async fn today(state: &AppState) -> Result<(String, NaiveDate)> {
+    let time_zone = state
+        .system_settings_repo
+        .application_time_zone_or_default()
+        .await?;
+    let today = local_date(&chrono::Utc::now(), resolve_time_zone(&time_zone));
+    Ok((time_zone, today))
+}
+
+fn parse_day(value: Option<&str>, name: &str) -> Result<Option<NaiveDate>> {
+    value
+        .map(|value| {
+            NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").map_err(|_| {
+                TingError::ValidationError(format!("{} must be a YYYY-MM-DD date", name))
+            })
+        })
+        .transpose()
+}
+
+/// First day of the bucket containing `day`; weeks start on Monday
+fn bucket_start(day: NaiveDate, granularity: StatsGranularity) -> NaiveDate {
+    match granularity {
+        StatsGranularity::Day => day,
+        StatsGranularity::Week => day - Duration::days(day.weekday().num_days_from_monday() as i64),
+        StatsGranularity::Month => day.with_day(1).unwrap_or(day),
+    }
+}
+
+fn next_bucket(start: NaiveDate, granularity: StatsGranularity) -> Option<NaiveDate> {
+    match granularity {
+        StatsGranularity::Day => start.succ_opt(),
+        StatsGranularity::Week => start.checked_add_signed(Duration::days(7)),
+        StatsGranularity::Month => start.checked_add_months(Months::new(1)),
+    }
+}
+
+fn default_timeline_start(to: NaiveDate, granularity: StatsGranularity) -> NaiveDate {
+    let last = bucket_start(to, granularity);
+    let back = DEFAULT_TIMELINE_POINTS - 1;
+    match granularity {
+        StatsGranularity::Day => last - Duration::days(back as i64),
+        StatsGranularity::Week => last - Duration::weeks(back as i64),
+        StatsGranularity::Month => last.checked_sub_months(Months::new(back)).unwrap_or(last),
+    }
+}
+
+/// Sum daily listening into buckets, including empty ones, from `from` to `to`
+fn build_timeline(
+    days: &[(NaiveDate, f64)],
+    from: NaiveDate,
+    to: NaiveDate,
+    granularity: StatsGranularity,
+) -> Vec<ListeningTimelinePoint> {
+    let mut points = Vec::new();
+    let mut start = Some(bucket_start(from, granularity));
+    while let Some(bucket) = start.filter(|bucket| *bucket <= to) {
+        let next = next_bucket(bucket, granularity);
+        let listen_seconds = days
+            .iter()
+            .filter(|(day, _)| *day >= from && *day <= to && *day >= bucket)
+            .filter(|(day, _)| next.map_or(true, |next| *day < next))
+            .map(|(_, seconds)| seconds)
+            .sum();
+        points.push(ListeningTimelinePoint {
+            start: bucket.to_string(),
+            listen_seconds,
+        });
+        start = next;
+    }
+    points
+}
+
+/// Current and longest run of consecutive active days.
+///
+/// The current streak is still alive when the last active day is today or
+/// yesterday, so it does not reset before the user had a chance to listen today.
+fn streaks(active_days: &[NaiveDate], today: NaiveDate) -> (i64, i64) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in active_days {
+        run = match previous {
+            Some(previous) if previous.succ_opt() == Some(*day) => run + 1,
+            Some(previous) if previous == *day => run,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*day);
+    }
+    let current = match previous {
+        Some(last) if last == today || last.succ_opt() == Some(today) => run,
+        _ => 0,
+    };
+    (current, longest)
+}
+
+/// Local days with listening time, oldest first
+fn daily_seconds(
+    conn: &Connection,
+    user_id: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<(NaiveDate, f64)>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT activity_date, SUM(listen_seconds) FROM listening_events \
+             WHERE user_id = ? AND activity_date BETWEEN ? AND ? \
+             GROUP BY activity_date HAVING SUM(listen_seconds) > 0 \
+             ORDER BY activity_date",
+        )
+        .map_err(TingError::DatabaseError)?;
+    let rows = stmt
+        .query_map(
+            rusqlite::params![user_id, bound(from, FIRST_DAY), bound(to, LAST_DAY)],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)),
+        )
+        .map_err(TingError::DatabaseError)?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(TingError::DatabaseError)?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(day, seconds)| {
+            NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+                .ok()
+                .map(|day| (day, seconds))
+        })
+        .collect())
+}
+
+/// Chapters and books first finished within the range.
+///
+/// A book is finished when every chapter outside its extras is, on the day the
+/// last of them was.
+fn completion_counts(
+    conn: &Connection,
+    user_id: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<(i64, i64)> {
+    conn.query_row(
+        &format!(
+            "WITH finished_chapters AS ( \
+                SELECT e.chapter_id, c.book_id, c.is_extra, MIN(e.activity_date) AS finished_on \
+                FROM listening_events e JOIN chapters c ON c.id = e.chapter_id \
+                WHERE e.user_id = ?1 AND COALESCE(e.duration, c.duration) > 0 \
+                  AND (e.position >= COALESCE(e.duration, c.duration) * {ratio} \
+                       OR e.position >= COALESCE(e.duration, c.duration) - {tail}) \
+                GROUP BY e.chapter_id \
+             ), finished_books AS ( \
+                SELECT f.book_id, MAX(f.finished_on) AS finished_on \
+                FROM finished_chapters f WHERE f.is_extra = 0 \
+                GROUP BY f.book_id \
+                HAVING COUNT(*) = (SELECT COUNT(*) FROM chapters c WHERE c.book_id = f.book_id AND c.is_extra = 0) \
+             ) \
+             SELECT \
+                (SELECT COUNT(*) FROM finished_chapters WHERE finished_on BETWEEN ?2 AND ?3), \
+                (SELECT COUNT(*) FROM finished_books WHERE finished_on BETWEEN ?2 AND ?3)",
+            ratio = COMPLETION_RATIO,
+            tail = COMPLETION_TAIL_SECS,
+        ),
+        rusqlite::params![user_id, bound(from, FIRST_DAY), bound(to, LAST_DAY)],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .map_err(TingError::DatabaseError)
+}
+
+fn top_books(
+    conn: &Connection,
+    user_id: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    limit: usize,
+) -> Result<Vec<TopBookStatistics>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT b.id, b.title, b.author, b.narrator, b.cover_url, SUM(e.listen_seconds) AS seconds \
+             FROM listening_events e JOIN books b ON b.id = e.book_id \
+             WHERE e.user_id = ? AND e.activity_date BETWEEN ? AND ? \
+             GROUP BY b.id HAVING seconds > 0 \
+             ORDER BY seconds DESC, b.title ASC LIMIT ?",
+        )
+        .map_err(TingError::DatabaseError)?;
+    let books = stmt
+        .query_map(
+            rusqlite::params![
+                user_id,
+                bound(from, FIRST_DAY),
+                bound(to, LAST_DAY),
+                limit as i64
+            ],
+            |row| {
+                Ok(TopBookStatistics {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    author: row.get(2)?,
+                    narrator: row.get(3)?,
+                    cover_url: row.get(4)?,
+                    listen_seconds: row.get(5)?,
+                })
+            },
+        )
+        .map_err(TingError::DatabaseError)?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(TingError::DatabaseError)?;
+    Ok(books)
+}
+
+#[derive(Clone, Copy)]
+enum Person {
+    Author,
+    Narrator,
+}
+
+fn top_people(
+    conn: &Connection,
+    user_id: &str,
+    person: Person,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    limit: usize,
+) -> Result<Vec<TopPersonStatistics>> {
+    let column = match person {
+        Person::Author => "b.author",
+        Person::Narrator => "b.narrator",
+    };
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT TRIM({column}) AS name, COUNT(DISTINCT b.id), SUM(e.listen_seconds) AS seconds \
+             FROM listening_events e JOIN books b ON b.id = e.book_id \
+             WHERE e.user_id = ? AND e.activity_date BETWEEN ? AND ? AND TRIM(COALESCE({column}, '')) != '' \
+             GROUP BY name HAVING seconds > 0 \
+             ORDER BY seconds DESC, name ASC LIMIT ?"
+        ))
+        .map_err(TingError::DatabaseError)?;
+    let people = stmt
+        .query_map(
+            rusqlite::params![
+                user_id,
+                bound(from, FIRST_DAY),
+                bound(to, LAST_DAY),
+                limit as i64
+            ],
+            |row| {
+                Ok(TopPersonStatistics {
+                    name: row.get(0)?,
+                    books: row.get(1)?,
+                    listen_seconds: row.get(2)?,
+                })
+            },
+        )
+        .map_err(TingError::DatabaseError)?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(TingError::DatabaseError)?;
+    Ok(people)
+}
+
+fn bound(day: Option<NaiveDate>, open: &str) -> String {
+    day.map_or_else(|| open.to_string(), |day| day.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::manager::DatabaseManager;
+
+    fn day(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn streak_survives_until_a_day_is_missed() {
+        let days = [
+            day("2024-01-01"),
+            day("2024-01-02"),
+            day("2024-01-03"),
+            day("2024-01-10"),
+            day("2024-01-11"),
+        ];
+        assert_eq!(streaks(&days, day("2024-01-12")), (2, 3));
+        assert_eq!(streaks(&days, day("2024-01-13")), (0, 3));
+        assert_eq!(streaks(&[], day("2024-01-13")), (0, 0));
+    }
+
+    #[test]
+    fn timeline_fills_empty_weeks() {
+        let days = [
+            (day("2024-01-02"), 60.0),
+            (day("2024-01-04"), 30.0),
+            (day("2024-01-16"), 10.0),
+        ];
+        let points = build_timeline(
+            &days,
+            day("2024-01-03"),
+            day("2024-01-16"),
+            StatsGranularity::Week,
+        );
+        assert_eq!(
+            points,
+            vec![
+                ListeningTimelinePoint {
+                    start: "2024-01-01".to_string(),
+                    listen_seconds: 30.0
+                },
+                ListeningTimelinePoint {
+                    start: "2024-01-08".to_string(),
+                    listen_seconds: 0.0
+                },
+                ListeningTimelinePoint {
+                    start: "2024-01-15".to_string(),
+                    listen_seconds: 10.0
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn counts_finished_chapters_and_books() {
+        // Daily rows older than 90 days are pruned on insert
+        let today = chrono::Utc::now().date_naive();
+        let days_ago = move |days: i64| today - Duration::days(days);
+        let db = DatabaseManager::new_in_memory().expect("create test database");
+        let (recent_counts, all_time) = db
+            .execute(move |conn| {
+                conn.execute_batch(&format!(
+                    "INSERT INTO users (id, username, password_hash, role) VALUES ('user-1', 'tester', 'hash', 'user'); \
+                     INSERT INTO libraries (id, name, type, url) VALUES ('library-1', 'Test', 'local', ''); \
+                     INSERT INTO books (id, library_id, title, path, hash) VALUES ('book-1', 'library-1', 'Book', '/book', 'h'); \
+                     INSERT INTO chapters (id, book_id, title, path, duration, is_extra) VALUES \
+                        ('chapter-1', 'book-1', 'One', '/1', 600, 0), \
+                        ('chapter-2', 'book-1', 'Two', '/2', 600, 0), \
+                        ('extra-1', 'book-1', 'Extra', '/3', 600, 1); \
+                     INSERT INTO listening_events (id, aggregate_key, user_id, book_id, chapter_id, activity_date, \
+                        position, duration, listen_seconds, progress_updates, created_at, last_active_at) VALUES \
+                        ('e1', 'k1', 'user-1', 'book-1', 'chapter-1', '{d30}', 590, NULL, 590, 1, '{d30}', '{d30}'), \
+                        ('e2', 'k2', 'user-1', 'book-1', 'chapter-2', '{d10}', 300, 600, 300, 1, '{d10}', '{d10}'), \
+                        ('e3', 'k3', 'user-1', 'book-1', 'chapter-2', '{d9}', 580, 600, 280, 1, '{d9}', '{d9}');",
+                    d30 = days_ago(30),
+                    d10 = days_ago(10),
+                    d9 = days_ago(9),
+                ))?;
+                let recent_counts =
+                    completion_counts(conn, "user-1", Some(days_ago(20)), Some(today))?;
+                let all_time = completion_counts(conn, "user-1", None, None)?;
+                Ok((recent_counts, all_time))
+            })
+            .await
+            .unwrap();
+
+        // Chapter one was finished before the range, so the book is finished in it
+        assert_eq!(recent_counts, (1, 1));
+        assert_eq!(all_time, (2, 1));
+    }
+}
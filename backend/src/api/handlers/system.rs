@@ -3,23 +3,36 @@ use crate::api::models::{
     AdminStatisticsOverview, AdminStatisticsResponse, ApplicationTimeZoneResponse,
     BatchDeleteTasksRequest, BatchDeleteTasksResponse, BookActivityStatistics, CancelTaskResponse,
     ClearTasksQuery, ClearTasksResponse, ComponentHealth, ComponentStatus, ComponentsHealth,
-    ConfigResponse, DatabaseConfigResponse, DatabaseMetrics, DeleteTaskResponse, HealthResponse,
-    HealthStatus, LibraryStatistics, LoggingConfigResponse, MetricsResponse, PluginMetrics,
-    PluginSystemConfigResponse, RecentActivityPoint, SecurityConfigResponse, ServerConfigResponse,
-    StorageConfigResponse, SystemMetrics, TaskDetailResponse, TaskInfoResponse,
-    TaskQueueConfigResponse, TaskQueueMetrics, TasksQuery, UpdateApplicationTimeZoneRequest,
-    UpdateConfigRequest, UpdateConfigResponse, UserActivityStatistics,
+    ConfigResponse, CreateWorkflowRequest, CreateWorkflowResponse, DatabaseConfigResponse,
+    DatabaseMetrics, DeleteTaskResponse, HealthResponse, HealthStatus, LibraryStatistics,
+    LoggingConfigResponse, MetricsResponse, PluginMetrics, PluginSystemConfigResponse,
+    RecentActivityPoint, SecurityConfigResponse, ServerConfigResponse, StorageConfigResponse,
+    SystemMetrics, TaskDetailResponse, TaskEventsQuery, TaskInfoResponse, TaskPoolMetrics,
+    TaskPoolSettings, TaskQueueConfigResponse, TaskQueueMetrics, TasksQuery,
+    UpdateApplicationTimeZoneRequest, UpdateConfigRequest, UpdateConfigResponse,
+    UserActivityStatistics,
 };
+use crate::api::require_admin;
+use crate::core::config::TaskPoolConfig;
 use crate::core::error::{Result, TingError};
+use crate::core::task_queue::{Priority, Task, TaskPayload, WORKFLOW_TASK_TYPE};
+use crate::db::models::TaskRecord;
 use crate::db::repository::Repository;
 use axum::{
     extract::{Path, Query, State},
-    response::IntoResponse,
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
     Json,
 };
 use chrono::Utc;
 use serde_json::Value;
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::path::PathBuf;
+use tokio::sync::broadcast::error::RecvError;
 
 #[path = "system/logs.rs"]
 mod logs;
@@ -106,28 +119,61 @@ pub async fn list_tasks(
         )
         .await?;
 
-    let tasks: Vec<TaskInfoResponse> = task_records
-        .into_iter()
-        .map(|record| TaskInfoResponse {
-            id: record.id,
-            task_type: record.task_type,
-            status: record.status,
-            payload: record.payload,
-            message: record.message,
-            message_key: record.message_key,
-            message_params: record.message_params,
-            error: record.error,
-            retries: record.retries,
-            max_retries: record.max_retries,
-            created_at: record.created_at,
-            started_at: None,  // TODO: Add started_at to TaskRecord
-            finished_at: None, // TODO: Add finished_at to TaskRecord
-        })
-        .collect();
+    let mut tasks = Vec::with_capacity(task_records.len());
+    for record in task_records {
+        tasks.push(task_info_with_steps(&state, record).await?);
+    }
 
     Ok(Json(tasks))
 }
 
+fn task_info(record: TaskRecord) -> TaskInfoResponse {
+    TaskInfoResponse {
+        depends_on: task_dependencies(&record),
+        id: record.id,
+        task_type: record.task_type,
+        status: record.status,
+        payload: record.payload,
+        message: record.message,
+        message_key: record.message_key,
+        message_params: record.message_params,
+        error: record.error,
+        retries: record.retries,
+        max_retries: record.max_retries,
+        created_at: record.created_at,
+        started_at: None,  // TODO: Add started_at to TaskRecord
+        finished_at: None, // TODO: Add finished_at to TaskRecord
+        steps: None,
+    }
+}
+
+/// Task info including the steps of a workflow
+async fn task_info_with_steps(state: &AppState, record: TaskRecord) -> Result<TaskInfoResponse> {
+    let steps = workflow_steps(state, &record).await?;
+    let mut info = task_info(record);
+    info.steps = steps;
+    Ok(info)
+}
+
+async fn workflow_steps(
+    state: &AppState,
+    record: &TaskRecord,
+) -> Result<Option<Vec<TaskInfoResponse>>> {
+    if record.task_type != WORKFLOW_TASK_TYPE {
+        return Ok(None);
+    }
+    let steps = state.task_queue.list_workflow_steps(&record.id).await?;
+    Ok(Some(steps.into_iter().map(task_info).collect()))
+}
+
+fn task_dependencies(record: &TaskRecord) -> Vec<String> {
+    record
+        .depends_on
+        .as_deref()
+        .and_then(|value| serde_json::from_str(value).ok())
+        .unwrap_or_default()
+}
+
 /// Handler for GET /api/v1/tasks/:id - Get task details
 pub async fn get_task(
     State(state): State<AppState>,
@@ -154,7 +200,11 @@ pub async fn get_task(
         None
     };
 
+    let steps = workflow_steps(&state, &task_record).await?;
     let response = TaskDetailResponse {
+        depends_on: task_dependencies(&task_record),
+        parent_id: task_record.parent_id.clone(),
+        steps,
         id: task_record.id,
         task_type: task_record.task_type,
         status: task_record.status,
@@ -174,6 +224,116 @@ pub async fn get_task(
     Ok(Json(response))
 }
 
+/// Handler for POST /api/v1/tasks/workflows - Submit a workflow of dependent tasks
+pub async fn create_workflow(
+    State(state): State<AppState>,
+    user: crate::auth::middleware::AuthUser,
+    Json(req): Json<CreateWorkflowRequest>,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err(TingError::ValidationError(
+            "Workflow name cannot be empty".to_string(),
+        ));
+    }
+
+    let mut step_ids: HashMap<String, String> = HashMap::new();
+    let mut steps = Vec::with_capacity(req.steps.len());
+    for step in req.steps {
+        if step_ids.contains_key(&step.key) {
+            return Err(TingError::ValidationError(format!(
+                "Duplicate workflow step key: {}",
+                step.key
+            )));
+        }
+        let mut payload: TaskPayload = serde_json::from_value(step.payload).map_err(|e| {
+            TingError::ValidationError(format!("Invalid payload for step {}: {}", step.key, e))
+        })?;
+        state
+            .task_schedule_runner
+            .prepare_payload(&mut payload)
+            .await?;
+        // Keys of earlier steps become their task IDs; anything else is
+        // taken as the ID of an existing task
+        let depends_on = step
+            .depends_on
+            .iter()
+            .map(|key| step_ids.get(key).cloned().unwrap_or_else(|| key.clone()))
+            .collect();
+        let task = Task::new(
+            step.name.unwrap_or_else(|| step.key.clone()),
+            Priority::Normal,
+            payload,
+        )
+        .with_dependencies(depends_on);
+        step_ids.insert(step.key, task.id.clone());
+        steps.push(task);
+    }
+
+    let id = state.task_queue.submit_workflow(name, steps).await?;
+
+    tracing::info!(
+        target: "audit::task",
+        workflow_id = %id,
+        workflow_name = %name,
+        actor = %user.username,
+        "Workflow submitted"
+    );
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(CreateWorkflowResponse {
+            id,
+            steps: step_ids,
+        }),
+    ))
+}
+
+/// Handler for GET /api/v1/tasks/events - Stream task status and progress
+///
+/// Events are Server-Sent Events named `status` or `progress` carrying a
+/// `TaskEvent` as JSON. A client that falls behind gets a `lagged` event and
+/// should reload the task list, since the skipped events are gone.
+pub async fn task_events(
+    State(state): State<AppState>,
+    user: crate::auth::middleware::AuthUser,
+    Query(query): Query<TaskEventsQuery>,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+
+    let events = state.task_queue.subscribe_events();
+    let stream = futures::stream::unfold(
+        (events, query.task_id),
+        |(mut events, task_id)| async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => {
+                        if task_id
+                            .as_deref()
+                            .is_some_and(|task_id| task_id != event.task_id())
+                        {
+                            continue;
+                        }
+                        Event::default().event(event.name()).json_data(&event)
+                    }
+                    Err(RecvError::Lagged(skipped)) => Event::default()
+                        .event("lagged")
+                        .json_data(serde_json::json!({ "skipped": skipped })),
+                    Err(RecvError::Closed) => return None,
+                };
+                match event {
+                    Ok(event) => return Some((Ok::<_, Infallible>(event), (events, task_id))),
+                    Err(e) => tracing::warn!(error = %e, "Failed to serialize task event"),
+                }
+            }
+        },
+    );
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
 /// Handler for POST /api/v1/tasks/:id/cancel - Cancel a task
 pub async fn cancel_task(
     State(state): State<AppState>,
@@ -657,6 +817,19 @@ async fn collect_task_queue_metrics(state: &AppState) -> TaskQueueMetrics {
         total_tasks,
         avg_processing_time_ms: 0.0,
         failure_rate,
+        pools: state
+            .task_queue
+            .pool_stats()
+            .await
+            .into_iter()
+            .map(|pool| TaskPoolMetrics {
+                task_type: pool.task_type,
+                queued_tasks: pool.queued,
+                running_tasks: pool.running,
+                max_concurrent: pool.max_concurrent,
+                per_library: pool.per_library,
+            })
+            .collect(),
     }
 }
 
@@ -805,6 +978,32 @@ fn format_prometheus_metrics(
     ));
     output.push_str("\n");
 
+    output.push_str("# HELP ting_reader_task_pool_tasks Queued and running tasks by task type\n");
+    output.push_str("# TYPE ting_reader_task_pool_tasks gauge\n");
+    for pool in &task_queue.pools {
+        output.push_str(&format!(
+            "ting_reader_task_pool_tasks{{task_type=\"{}\",status=\"queued\"}} {}\n",
+            pool.task_type, pool.queued_tasks
+        ));
+        output.push_str(&format!(
+            "ting_reader_task_pool_tasks{{task_type=\"{}\",status=\"running\"}} {}\n",
+            pool.task_type, pool.running_tasks
+        ));
+    }
+    output.push('\n');
+
+    output.push_str("# HELP ting_reader_task_pool_limit Tasks of a type allowed to run at once\n");
+    output.push_str("# TYPE ting_reader_task_pool_limit gauge\n");
+    for pool in &task_queue.pools {
+        if let Some(limit) = pool.max_concurrent {
+            output.push_str(&format!(
+                "ting_reader_task_pool_limit{{task_type=\"{}\"}} {}\n",
+                pool.task_type, limit
+            ));
+        }
+    }
+    output.push('\n');
+
     output.push_str("# HELP ting_reader_task_failure_rate Task failure rate\n");
     output.push_str("# TYPE ting_reader_task_failure_rate gauge\n");
     output.push_str(&format!(
@@ -889,6 +1088,20 @@ pub async fn get_config(State(state): State<AppState>) -> Result<impl IntoRespon
             max_concurrent_tasks: config.task_queue.max_concurrent_tasks,
             default_retry_count: config.task_queue.default_retry_count,
             task_timeout: config.task_queue.task_timeout,
+            pools: config
+                .task_queue
+                .pools
+                .iter()
+                .map(|(task_type, pool)| {
+                    (
+                        task_type.clone(),
+                        TaskPoolSettings {
+                            max_concurrent: pool.max_concurrent,
+                            per_library: pool.per_library,
+                        },
+                    )
+                })
+                .collect(),
         },
         logging: LoggingConfigResponse {
             level: config.logging.level.clone(),
@@ -1013,6 +1226,21 @@ pub async fn update_config(
             new_config.task_queue.task_timeout = task_timeout;
             updated_fields.push("task_queue.task_timeout".to_string());
         }
+        if let Some(pools) = task_queue_update.pools {
+            new_config.task_queue.pools = pools
+                .into_iter()
+                .map(|(task_type, pool)| {
+                    (
+                        task_type.trim().to_string(),
+                        TaskPoolConfig {
+                            max_concurrent: pool.max_concurrent,
+                            per_library: pool.per_library,
+                        },
+                    )
+                })
+                .collect();
+            updated_fields.push("task_queue.pools".to_string());
+        }
     }
 
     if let Some(logging_update) = req.logging {
@@ -1117,6 +1345,12 @@ pub async fn update_config(
         TingError::InvalidRequest(format!("Invalid configuration: {}", e))
     })?;
 
+    // Pool limits apply to the next tasks picked from the queue
+    state
+        .task_queue
+        .set_pool_limits(new_config.task_queue.pools.clone())
+        .await;
+
     let mut config = state.config.write().await;
     *config = new_config;
     drop(config);
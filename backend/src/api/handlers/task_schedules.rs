@@ -0,0 +1,267 @@
+use super::AppState;
+use crate::api::require_admin;
+use crate::core::error::{Result, TingError};
+use crate::core::task_queue::{CronSchedule, TaskPayload};
+use crate::db::models::{TaskSchedule, TaskScheduleRun};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const DEFAULT_RUN_HISTORY: u32 = 20;
+const MAX_RUN_HISTORY: u32 = 100;
+
+#[derive(Debug, Serialize)]
+pub struct TaskScheduleResponse {
+    pub id: String,
+    pub name: String,
+    pub cron: String,
+    pub payload: serde_json::Value,
+    pub enabled: bool,
+    pub last_run_at: Option<String>,
+    /// Next time the schedule fires, `None` when disabled or never due
+    pub next_run_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaskScheduleRequest {
+    pub name: String,
+    pub cron: String,
+    pub payload: serde_json::Value,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaskScheduleRunsQuery {
+    pub limit: Option<u32>,
+}
+
+impl TaskScheduleResponse {
+    fn new(schedule: TaskSchedule, time_zone: Tz) -> Self {
+        let next_run_at = if schedule.enabled {
+            CronSchedule::parse(&schedule.cron)
+                .ok()
+                .and_then(|cron| cron.next_after(Utc::now(), time_zone))
+                .map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true))
+        } else {
+            None
+        };
+        Self {
+            payload: serde_json::from_str(&schedule.payload).unwrap_or(serde_json::Value::Null),
+            id: schedule.id,
+            name: schedule.name,
+            cron: schedule.cron,
+            enabled: schedule.enabled,
+            last_run_at: schedule.last_run_at,
+            next_run_at,
+            created_at: schedule.created_at,
+            updated_at: schedule.updated_at,
+        }
+    }
+}
+
+pub async fn list_task_schedules(
+    State(state): State<AppState>,
+    user: crate::auth::middleware::AuthUser,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+
+    let time_zone = state.task_schedule_runner.time_zone().await?;
+    let schedules = state
+        .task_schedule_repo
+        .find_all()
+        .await?
+        .into_iter()
+        .map(|schedule| TaskScheduleResponse::new(schedule, time_zone))
+        .collect::<Vec<_>>();
+
+    Ok(Json(schedules))
+}
+
+pub async fn get_task_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+
+    let schedule = find_schedule(&state, &id).await?;
+    let time_zone = state.task_schedule_runner.time_zone().await?;
+    Ok(Json(TaskScheduleResponse::new(schedule, time_zone)))
+}
+
+pub async fn create_task_schedule(
+    State(state): State<AppState>,
+    user: crate::auth::middleware::AuthUser,
+    Json(req): Json<TaskScheduleRequest>,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+
+    let now = Utc::now().to_rfc3339();
+    let schedule = TaskSchedule {
+        id: Uuid::new_v4().to_string(),
+        name: normalize_name(&req.name)?,
+        cron: normalize_cron(&req.cron)?,
+        payload: normalize_payload(&state, req.payload).await?,
+        enabled: req.enabled,
+        last_run_at: None,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    state.task_schedule_repo.create(&schedule).await?;
+
+    tracing::info!(
+        target: "audit::task_schedule",
+        schedule_id = %schedule.id,
+        schedule_name = %schedule.name,
+        cron = %schedule.cron,
+        actor = %user.username,
+        "Task schedule created"
+    );
+
+    let schedule = find_schedule(&state, &schedule.id).await?;
+    let time_zone = state.task_schedule_runner.time_zone().await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(TaskScheduleResponse::new(schedule, time_zone)),
+    ))
+}
+
+pub async fn update_task_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+    Json(req): Json<TaskScheduleRequest>,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+
+    let mut schedule = find_schedule(&state, &id).await?;
+    schedule.name = normalize_name(&req.name)?;
+    schedule.cron = normalize_cron(&req.cron)?;
+    schedule.payload = normalize_payload(&state, req.payload).await?;
+    schedule.enabled = req.enabled;
+    state.task_schedule_repo.update(&schedule).await?;
+
+    tracing::info!(
+        target: "audit::task_schedule",
+        schedule_id = %schedule.id,
+        schedule_name = %schedule.name,
+        cron = %schedule.cron,
+        enabled = schedule.enabled,
+        actor = %user.username,
+        "Task schedule updated"
+    );
+
+    let schedule = find_schedule(&state, &id).await?;
+    let time_zone = state.task_schedule_runner.time_zone().await?;
+    Ok(Json(TaskScheduleResponse::new(schedule, time_zone)))
+}
+
+pub async fn delete_task_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+
+    let schedule = find_schedule(&state, &id).await?;
+    state.task_schedule_repo.delete(&id).await?;
+
+    tracing::info!(
+        target: "audit::task_schedule",
+        schedule_id = %id,
+        schedule_name = %schedule.name,
+        actor = %user.username,
+        "Task schedule deleted"
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Submit the schedule's task immediately, whether or not it is enabled
+pub async fn run_task_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+
+    let schedule = find_schedule(&state, &id).await?;
+    let run = state
+        .task_schedule_runner
+        .run_schedule(&schedule, "manual")
+        .await?;
+
+    let status = if run.task_id.is_some() {
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(run)))
+}
+
+pub async fn list_task_schedule_runs(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: crate::auth::middleware::AuthUser,
+    Query(query): Query<TaskScheduleRunsQuery>,
+) -> Result<impl IntoResponse> {
+    require_admin(&user)?;
+
+    find_schedule(&state, &id).await?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_RUN_HISTORY)
+        .clamp(1, MAX_RUN_HISTORY);
+    let runs: Vec<TaskScheduleRun> = state.task_schedule_repo.find_runs(&id, limit).await?;
+
+    Ok(Json(runs))
+}
+
+async fn find_schedule(state: &AppState, id: &str) -> Result<TaskSchedule> {
+    state
+        .task_schedule_repo
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| TingError::NotFound(format!("Task schedule {} not found", id)))
+}
+
+fn normalize_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(TingError::ValidationError(
+            "Schedule name cannot be empty".to_string(),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+fn normalize_cron(expression: &str) -> Result<String> {
+    let expression = expression.trim();
+    CronSchedule::parse(expression).map_err(TingError::ValidationError)?;
+    Ok(expression.to_string())
+}
+
+/// Parse the payload as a `TaskPayload` and store it in its canonical form
+async fn normalize_payload(state: &AppState, payload: serde_json::Value) -> Result<String> {
+    let payload: TaskPayload = serde_json::from_value(payload)
+        .map_err(|e| TingError::ValidationError(format!("Invalid task payload: {}", e)))?;
+    state
+        .task_schedule_runner
+        .validate_payload(&payload)
+        .await?;
+    serde_json::to_string(&payload).map_err(|e| TingError::SerializationError(e.to_string()))
+}
@@ -1,7 +1,8 @@
 use super::AppState;
 use crate::api::models::{
     CreateUserRequest, DeleteProgressHistoryRequest, DeleteProgressHistoryResponse,
-    FavoriteActionResponse, ProgressResponse, UpdateProgressRequest, UpdateUserRequest,
+    DevicesResponse, FavoriteActionResponse, ProgressResponse, SleepTimerMode, SleepTimerResponse,
+    SyncProgressRequest, SyncProgressResponse, UpdateProgressRequest, UpdateUserRequest,
     UpdateUserSettingsRequest, UserActionResponse, UserInfoResponse, UserSettingsResponse,
 };
 use crate::api::require_admin;
@@ -13,8 +14,12 @@ use axum::{
     response::IntoResponse,
     Json,
 };
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
+/// Most progress events accepted in one sync request
+const MAX_SYNC_EVENTS: usize = 1000;
+
 fn mask_restricted_settings(response: &mut UserSettingsResponse, is_admin: bool) {
     if is_admin {
         return;
@@ -316,6 +321,9 @@ pub async fn update_user_settings(
         }
     }
 
+    crate::api::handlers::media::stream::validate_hls_settings(&settings_obj)?;
+    crate::api::handlers::media::stream::validate_normalize_settings(&settings_obj)?;
+
     // Clean up any potential recursive nesting from previous bugs in the existing object
     if let Some(obj) = settings_obj.as_object_mut() {
         obj.remove("settings_json");
@@ -533,6 +541,8 @@ pub async fn update_progress(
         .await?
         .ok_or_else(|| TingError::NotFound(format!("Book {} not found", req.book_id)))?;
 
+    let mut position = req.position;
+    let mut duration = req.duration;
     if let Some(ref chapter_id) = req.chapter_id {
         let chapter = state
             .chapter_repo
@@ -545,6 +555,14 @@ pub async fn update_progress(
                 "Chapter does not belong to the specified book".to_string(),
             ));
         }
+
+        // Positions in a virtual chapter are relative to its slice of the file
+        if chapter.is_virtual() {
+            if let Some(length) = chapter.slice_length() {
+                position = position.clamp(0.0, length);
+                duration = Some(length);
+            }
+        }
     }
 
     let progress = crate::db::models::Progress {
@@ -552,12 +570,16 @@ pub async fn update_progress(
         user_id: user.id.clone(),
         book_id: req.book_id.clone(),
         chapter_id: req.chapter_id.clone(),
-        position: req.position,
-        duration: req.duration,
+        position,
+        duration,
         updated_at: chrono::Utc::now().to_rfc3339(),
     };
 
     state.progress_repo.upsert(&progress).await?;
+    state
+        .sleep_timers
+        .record_position(&user.id, &req.book_id, req.chapter_id.as_deref(), position)
+        .await;
 
     if let Some(playback_start) = req.playback_start {
         crate::api::playback_audit::record_playback_start(
@@ -572,3 +594,153 @@ pub async fn update_progress(
 
     Ok((StatusCode::OK, Json(ProgressResponse::from(progress))))
 }
+
+/// Handler for GET /api/me/devices - List the user's connected devices and the active player
+pub async fn list_devices(
+    State(state): State<AppState>,
+    user: crate::auth::middleware::AuthUser,
+) -> Result<impl IntoResponse> {
+    let (devices, active_device_id) = state.ws_manager.list_devices(&user.id).await;
+    Ok(Json(DevicesResponse {
+        devices,
+        active_device_id,
+    }))
+}
+
+/// Handler for GET /api/me/sleep-timer - Get the sleep timer shared by the user's devices
+pub async fn get_sleep_timer(
+    State(state): State<AppState>,
+    user: crate::auth::middleware::AuthUser,
+) -> Result<impl IntoResponse> {
+    Ok(Json(SleepTimerResponse {
+        timer: state.sleep_timers.get(&user.id).await,
+    }))
+}
+
+/// Handler for PUT /api/me/sleep-timer - Start a sleep timer on all of the user's devices
+pub async fn set_sleep_timer(
+    State(state): State<AppState>,
+    user: crate::auth::middleware::AuthUser,
+    Json(mode): Json<SleepTimerMode>,
+) -> Result<impl IntoResponse> {
+    let timer = state.sleep_timers.start(&user.id, mode).await?;
+    Ok(Json(SleepTimerResponse { timer: Some(timer) }))
+}
+
+/// Handler for DELETE /api/me/sleep-timer - Cancel the sleep timer without pausing
+pub async fn cancel_sleep_timer(
+    State(state): State<AppState>,
+    user: crate::auth::middleware::AuthUser,
+) -> Result<impl IntoResponse> {
+    state.sleep_timers.cancel(&user.id).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for POST /api/progress/sync - Merge progress recorded offline by a device
+///
+/// Events carry the time the client recorded them. A position only replaces the
+/// stored one when it is newer, and every new event is counted in the listening
+/// statistics on the day it was recorded.
+pub async fn sync_progress(
+    State(state): State<AppState>,
+    user: crate::auth::middleware::AuthUser,
+    Json(req): Json<SyncProgressRequest>,
+) -> Result<impl IntoResponse> {
+    let device_id = req.device_id.trim();
+    if device_id.is_empty() {
+        return Err(TingError::ValidationError(
+            "device_id must not be empty".to_string(),
+        ));
+    }
+    if req.events.len() > MAX_SYNC_EVENTS {
+        return Err(TingError::ValidationError(format!(
+            "At most {} events can be synced at once",
+            MAX_SYNC_EVENTS
+        )));
+    }
+
+    let now = chrono::Utc::now();
+    let mut known_books = HashSet::new();
+    let mut chapters = HashMap::new();
+    let mut events = Vec::with_capacity(req.events.len());
+    for event in req.events {
+        if !event.position.is_finite() || event.position < 0.0 {
+            return Err(TingError::ValidationError(
+                "position must be a non-negative number of seconds".to_string(),
+            ));
+        }
+        let recorded_at = chrono::DateTime::parse_from_rfc3339(&event.recorded_at)
+            .map_err(|_| {
+                TingError::ValidationError(format!(
+                    "Invalid recorded_at timestamp: {}",
+                    event.recorded_at
+                ))
+            })?
+            .with_timezone(&chrono::Utc)
+            // A fast device clock must not make its positions win forever
+            .min(now);
+
+        if !known_books.contains(&event.book_id) {
+            state
+                .book_repo
+                .find_by_id(&event.book_id)
+                .await?
+                .ok_or_else(|| TingError::NotFound(format!("Book {} not found", event.book_id)))?;
+            known_books.insert(event.book_id.clone());
+        }
+
+        let mut position = event.position;
+        let mut duration = event.duration;
+        if let Some(ref chapter_id) = event.chapter_id {
+            if !chapters.contains_key(chapter_id) {
+                let chapter = state
+                    .chapter_repo
+                    .find_by_id(chapter_id)
+                    .await?
+                    .ok_or_else(|| {
+                        TingError::NotFound(format!("Chapter {} not found", chapter_id))
+                    })?;
+                chapters.insert(chapter_id.clone(), chapter);
+            }
+            let chapter = &chapters[chapter_id];
+            if chapter.book_id != event.book_id {
+                return Err(TingError::ValidationError(
+                    "Chapter does not belong to the specified book".to_string(),
+                ));
+            }
+
+            // Positions in a virtual chapter are relative to its slice of the file
+            if chapter.is_virtual() {
+                if let Some(length) = chapter.slice_length() {
+                    position = position.clamp(0.0, length);
+                    duration = Some(length);
+                }
+            }
+        }
+
+        events.push(crate::db::models::ProgressEvent {
+            book_id: event.book_id,
+            chapter_id: event.chapter_id,
+            position,
+            duration,
+            recorded_at,
+        });
+    }
+
+    let total = events.len();
+    let (accepted, progress) = state
+        .progress_repo
+        .sync_events(&user.id, device_id, events)
+        .await?;
+
+    for entry in &progress {
+        crate::api::ws::handler::broadcast_progress_update(&state.ws_manager, &user.id, entry)
+            .await;
+    }
+
+    Ok(Json(SyncProgressResponse {
+        progress: progress.into_iter().map(ProgressResponse::from).collect(),
+        accepted,
+        ignored: total - accepted,
+    }))
+}
@@ -6,12 +6,14 @@
 //! - Rate limiting
 //! - Error handling and response formatting
 
+pub mod abs;
 pub mod handlers;
 pub mod middleware;
 pub mod models;
 pub mod playback_audit;
 pub mod routes;
 pub mod server;
+pub mod subsonic;
 pub mod utils;
 pub mod ws;
 
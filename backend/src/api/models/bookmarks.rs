@@ -0,0 +1,58 @@
+use super::common::deserialize_nullable;
+use crate::db::models::Bookmark;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct BookmarkQuery {
+    pub book_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateBookmarkRequest {
+    pub chapter_id: String,
+    /// Seconds inside the chapter
+    pub position: f64,
+    /// End of a clip in seconds inside the chapter
+    #[serde(default)]
+    pub end_position: Option<f64>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateBookmarkRequest {
+    pub position: Option<f64>,
+    /// `null` turns a clip back into a plain bookmark
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub end_position: Option<Option<f64>>,
+    /// `null` removes the note
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub note: Option<Option<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BookmarkResponse {
+    pub id: String,
+    pub book_id: String,
+    pub chapter_id: String,
+    pub position: f64,
+    pub end_position: Option<f64>,
+    pub note: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<Bookmark> for BookmarkResponse {
+    fn from(bookmark: Bookmark) -> Self {
+        Self {
+            id: bookmark.id,
+            book_id: bookmark.book_id,
+            chapter_id: bookmark.chapter_id,
+            position: bookmark.position,
+            end_position: bookmark.end_position,
+            note: bookmark.note,
+            created_at: bookmark.created_at,
+            updated_at: bookmark.updated_at,
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use super::common::deserialize_tags_or_string;
-use crate::db::models::Book;
+use super::common::{deserialize_nullable, deserialize_tags_or_string};
+use crate::db::models::{Book, SkipRange};
 use crate::plugin::scraper::{BookDetail, BookItem};
 use crate::plugin::types::{LocalizedText, ScraperSearchField};
 use serde::{Deserialize, Serialize};
@@ -43,6 +43,45 @@ pub struct SearchResponse {
     pub page_size: u32,
 }
 
+/// Query parameters for ranked library search
+#[derive(Debug, Deserialize)]
+pub struct LibrarySearchQuery {
+    /// Search keywords; every term must match
+    pub q: String,
+    /// Restrict results to one library
+    pub library_id: Option<String>,
+    /// Page number (1-indexed, default: 1)
+    #[serde(default = "default_page")]
+    pub page: u32,
+    /// Page size (default: 20)
+    #[serde(default = "default_page_size")]
+    pub page_size: u32,
+}
+
+/// Response for ranked library search
+#[derive(Debug, Serialize)]
+pub struct LibrarySearchResponse {
+    /// Matching books, best match first
+    pub items: Vec<LibrarySearchItem>,
+    /// Total number of results
+    pub total: u32,
+    /// Current page number
+    pub page: u32,
+    /// Number of items per page
+    pub page_size: u32,
+}
+
+/// One ranked book in a library search
+#[derive(Debug, Serialize)]
+pub struct LibrarySearchItem {
+    #[serde(flatten)]
+    pub book: BookResponse,
+    /// Relevance score (higher is better)
+    pub score: f64,
+    /// Chapters whose titles contain the query terms
+    pub matched_chapters: Vec<ChapterResponse>,
+}
+
 /// Response for scraper sources list
 #[derive(Debug, Serialize)]
 pub struct ScraperSourcesResponse {
@@ -218,10 +257,26 @@ pub struct ChapterResponse {
     pub created_at: String,
     pub progress_position: Option<f64>,
     pub progress_updated_at: Option<String>,
+    /// Start of the chapter inside `path` in seconds (virtual chapters only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_offset: Option<f64>,
+    /// End of the chapter inside `path` in seconds (virtual chapters only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_offset: Option<f64>,
+    /// Integrated loudness in LUFS (analysed chapters only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loudness_lufs: Option<f64>,
+    /// True peak in dBTP (analysed chapters only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub true_peak_dbtp: Option<f64>,
+    /// Ranges the player skips, in seconds from the chapter start
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skip_ranges: Vec<SkipRange>,
 }
 
 impl From<crate::db::models::Chapter> for ChapterResponse {
     fn from(chapter: crate::db::models::Chapter) -> Self {
+        let skip_ranges = chapter.parsed_skip_ranges();
         Self {
             id: chapter.id,
             book_id: chapter.book_id,
@@ -233,6 +288,11 @@ impl From<crate::db::models::Chapter> for ChapterResponse {
             created_at: chapter.created_at,
             progress_position: None,
             progress_updated_at: None,
+            start_offset: chapter.start_offset,
+            end_offset: chapter.end_offset,
+            loudness_lufs: chapter.loudness_lufs,
+            true_peak_dbtp: chapter.true_peak_dbtp,
+            skip_ranges,
         }
     }
 }
@@ -273,6 +333,70 @@ pub struct UpdateChapterRequest {
     pub duration: Option<i32>,
     pub chapter_index: Option<i32>,
     pub is_extra: Option<i32>,
+    /// Start of the chapter inside `path` in seconds; `null` makes it a whole-file chapter again
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub start_offset: Option<Option<f64>>,
+    /// End of the chapter inside `path` in seconds
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub end_offset: Option<Option<f64>>,
+    /// Ranges the player skips; an empty list clears them
+    pub skip_ranges: Option<Vec<SkipRange>>,
+}
+
+/// Request body for splitting a chapter into time slices
+#[derive(Debug, Deserialize)]
+pub struct SplitChapterRequest {
+    /// Split points in seconds, relative to the chapter start
+    pub offsets: Vec<f64>,
+    /// Optional titles for the resulting slices, in order
+    #[serde(default)]
+    pub titles: Vec<String>,
+}
+
+/// Request body for joining adjacent slices of one file
+#[derive(Debug, Deserialize)]
+pub struct JoinChaptersRequest {
+    pub chapter_ids: Vec<String>,
+    pub title: Option<String>,
+}
+
+/// Request body for queueing a loudness analysis of a book or library
+#[derive(Debug, Deserialize, Default)]
+pub struct LoudnessAnalysisRequest {
+    /// Measure chapters again even if they were analysed before
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Request body for applying the skip proposal of a book
+#[derive(Debug, Deserialize, Default)]
+pub struct ApplySkipProposalRequest {
+    /// Apply the proposed book-wide `skip_intro`/`skip_outro` (default true)
+    pub book: Option<bool>,
+    /// Chapters whose proposed ranges to apply; all of them when omitted
+    pub chapter_ids: Option<Vec<String>>,
+}
+
+/// Skip ranges proposed for one chapter, next to the ones it has
+#[derive(Debug, Serialize)]
+pub struct ChapterSkipProposalResponse {
+    pub chapter_id: String,
+    pub title: Option<String>,
+    pub duration: Option<i32>,
+    pub ranges: Vec<SkipRange>,
+    pub current_ranges: Vec<SkipRange>,
+}
+
+/// Pending skip proposal of a book, next to the book's current values
+#[derive(Debug, Serialize)]
+pub struct SkipProposalResponse {
+    pub book_id: String,
+    pub skip_intro: Option<i32>,
+    pub skip_outro: Option<i32>,
+    pub current_skip_intro: i32,
+    pub current_skip_outro: i32,
+    pub chapters: Vec<ChapterSkipProposalResponse>,
+    pub created_at: String,
 }
 
 // Tags API models
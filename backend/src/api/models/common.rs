@@ -1,4 +1,4 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
@@ -27,3 +27,15 @@ where
         _ => Ok(None),
     }
 }
+
+/// Tell an explicit `null` apart from a missing field.
+///
+/// Use with `#[serde(default)]`: a missing field stays `None`, `null` becomes
+/// `Some(None)` and a value becomes `Some(Some(value))`.
+pub fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// What a podcast feed is built from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedKind {
+    Book,
+    Series,
+    Playlist,
+    Library,
+}
+
+impl FeedKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Book => "book",
+            Self::Series => "series",
+            Self::Playlist => "playlist",
+            Self::Library => "library",
+        }
+    }
+}
+
+/// Request body for POST /api/feeds
+#[derive(Debug, Deserialize)]
+pub struct CreateFeedLinkRequest {
+    pub kind: FeedKind,
+    pub id: String,
+    /// Link lifetime; `0` never expires, defaults to one year
+    pub expires_in_seconds: Option<u64>,
+}
+
+/// Response for POST /api/feeds
+#[derive(Debug, Serialize)]
+pub struct FeedLinkResponse {
+    pub url: String,
+    /// Unix timestamp, `0` when the link never expires
+    pub expires: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignedFeedQuery {
+    pub expires: Option<i64>,
+    pub user: Option<String>,
+    pub signature: Option<String>,
+}
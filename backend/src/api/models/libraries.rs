@@ -171,6 +171,15 @@ pub struct CacheListResponse {
     pub caches: Vec<CacheInfoResponse>,
     pub total: usize,
     pub total_size: u64,
+    pub transcode: TranscodeCacheResponse,
+}
+
+/// HLS segments shared across sessions in the transcode cache
+#[derive(Debug, Serialize)]
+pub struct TranscodeCacheResponse {
+    pub segments: usize,
+    pub total_size: u64,
+    pub max_size: u64,
 }
 
 /// Response for cache operations
@@ -187,5 +196,6 @@ pub struct CacheOperationResponse {
 pub struct ClearCacheResponse {
     pub success: bool,
     pub deleted_count: usize,
+    pub transcode_segments_deleted: usize,
     pub message: String,
 }
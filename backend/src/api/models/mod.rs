@@ -1,18 +1,24 @@
+pub mod bookmarks;
 pub mod books;
 pub mod common;
+pub mod feeds;
 pub mod libraries;
 pub mod playlists;
 pub mod plugins;
+pub mod stats;
 pub mod system;
 pub mod tasks;
 pub mod tools;
 pub mod users;
 
+pub use bookmarks::*;
 pub use books::*;
 pub use common::*;
+pub use feeds::*;
 pub use libraries::*;
 pub use playlists::*;
 pub use plugins::*;
+pub use stats::*;
 pub use system::*;
 pub use tasks::*;
 pub use tools::*;
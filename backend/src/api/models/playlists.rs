@@ -1,5 +1,5 @@
 use crate::api::models::{BookResponse, SeriesResponse};
-use crate::db::models::Playlist;
+use crate::db::models::{Playlist, SmartPlaylistRules};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Deserialize)]
@@ -13,6 +13,12 @@ pub struct CreatePlaylistRequest {
     pub title: String,
     #[serde(default)]
     pub description: Option<String>,
+    /// `manual` (default) or `smart`
+    #[serde(default)]
+    pub playlist_type: Option<String>,
+    /// Required for smart playlists
+    #[serde(default)]
+    pub rules: Option<SmartPlaylistRules>,
     #[serde(default)]
     pub book_ids: Vec<String>,
     #[serde(default)]
@@ -28,6 +34,9 @@ pub struct UpdatePlaylistRequest {
     pub book_ids: Option<Vec<String>>,
     #[serde(default)]
     pub items: Option<Vec<PlaylistItemRequest>>,
+    /// Replaces the rules of a smart playlist
+    #[serde(default)]
+    pub rules: Option<SmartPlaylistRules>,
 }
 
 #[derive(Debug, Serialize)]
@@ -45,6 +54,8 @@ pub struct PlaylistResponse {
     pub user_id: String,
     pub title: String,
     pub description: Option<String>,
+    pub playlist_type: String,
+    pub rules: Option<SmartPlaylistRules>,
     pub created_at: String,
     pub updated_at: String,
     pub book_ids: Vec<String>,
@@ -59,6 +70,11 @@ impl From<Playlist> for PlaylistResponse {
             user_id: playlist.user_id,
             title: playlist.title,
             description: playlist.description,
+            playlist_type: playlist.playlist_type,
+            rules: playlist
+                .smart_rules
+                .as_deref()
+                .and_then(|rules| serde_json::from_str(rules).ok()),
             created_at: playlist.created_at,
             updated_at: playlist.updated_at,
             book_ids: Vec::new(),
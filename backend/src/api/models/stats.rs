@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+/// Bucket size for the listening timeline
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsGranularity {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct StatsTimelineQuery {
+    #[serde(default)]
+    pub granularity: StatsGranularity,
+    /// First local day, `YYYY-MM-DD`
+    pub from: Option<String>,
+    /// Last local day, `YYYY-MM-DD`
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct StatsTopQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Response for GET /api/me/stats/summary
+#[derive(Debug, Serialize)]
+pub struct ListeningSummaryResponse {
+    pub time_zone: String,
+    pub today: String,
+    pub today_seconds: f64,
+    pub week_seconds: f64,
+    pub month_seconds: f64,
+    pub total_seconds: f64,
+    pub current_streak: i64,
+    pub longest_streak: i64,
+    pub completed_chapters: i64,
+    pub completed_books: i64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ListeningTimelinePoint {
+    /// First local day of the bucket
+    pub start: String,
+    pub listen_seconds: f64,
+}
+
+/// Response for GET /api/me/stats/timeline
+#[derive(Debug, Serialize)]
+pub struct ListeningTimelineResponse {
+    pub time_zone: String,
+    pub granularity: StatsGranularity,
+    pub from: String,
+    pub to: String,
+    pub points: Vec<ListeningTimelinePoint>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopBookStatistics {
+    pub id: String,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub narrator: Option<String>,
+    pub cover_url: Option<String>,
+    pub listen_seconds: f64,
+}
+
+/// Listening time of an author or narrator
+#[derive(Debug, Serialize)]
+pub struct TopPersonStatistics {
+    pub name: String,
+    pub books: i64,
+    pub listen_seconds: f64,
+}
+
+/// Response for GET /api/me/stats/top
+#[derive(Debug, Serialize)]
+pub struct ListeningTopResponse {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub books: Vec<TopBookStatistics>,
+    pub authors: Vec<TopPersonStatistics>,
+    pub narrators: Vec<TopPersonStatistics>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WrappedMonth {
+    /// `YYYY-MM`
+    pub month: String,
+    pub listen_seconds: f64,
+}
+
+/// Response for GET /api/me/stats/wrapped/:year
+#[derive(Debug, Serialize)]
+pub struct ListeningWrappedResponse {
+    pub year: i32,
+    pub time_zone: String,
+    pub listen_seconds: f64,
+    pub active_days: i64,
+    pub longest_streak: i64,
+    pub books_started: i64,
+    pub completed_chapters: i64,
+    pub completed_books: i64,
+    pub top_month: Option<WrappedMonth>,
+    pub months: Vec<WrappedMonth>,
+    pub top_books: Vec<TopBookStatistics>,
+    pub top_authors: Vec<TopPersonStatistics>,
+    pub top_narrators: Vec<TopPersonStatistics>,
+}
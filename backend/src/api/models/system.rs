@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Serialize)]
 pub struct ApplicationTimeZoneResponse {
@@ -160,6 +161,22 @@ pub struct TaskQueueMetrics {
     pub avg_processing_time_ms: f64,
     /// Task failure rate (failed / total)
     pub failure_rate: f64,
+    /// Queued and running tasks per task type, with the pool limits
+    pub pools: Vec<TaskPoolMetrics>,
+}
+
+/// Tasks of one type currently in the queue
+#[derive(Debug, Serialize)]
+pub struct TaskPoolMetrics {
+    pub task_type: String,
+    /// Number of queued tasks
+    pub queued_tasks: usize,
+    /// Number of running tasks
+    pub running_tasks: usize,
+    /// Pool size, `None` when only the global limit applies
+    pub max_concurrent: Option<usize>,
+    /// Running tasks allowed per library
+    pub per_library: Option<usize>,
 }
 
 /// Database metrics
@@ -228,6 +245,16 @@ pub struct TaskQueueConfigResponse {
     pub max_concurrent_tasks: usize,
     pub default_retry_count: u32,
     pub task_timeout: u64,
+    pub pools: BTreeMap<String, TaskPoolSettings>,
+}
+
+/// Concurrency limits of one task type
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaskPoolSettings {
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+    #[serde(default)]
+    pub per_library: Option<usize>,
 }
 
 /// Logging configuration response
@@ -314,6 +341,8 @@ pub struct UpdateTaskQueueConfigRequest {
     pub max_concurrent_tasks: Option<usize>,
     pub default_retry_count: Option<u32>,
     pub task_timeout: Option<u64>,
+    /// Replaces all pools; an empty map removes them
+    pub pools: Option<HashMap<String, TaskPoolSettings>>,
 }
 
 /// Logging configuration update request
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 /// Query parameters for task list
 #[derive(Debug, Deserialize)]
 pub struct TasksQuery {
-    /// Filter by status (queued, running, completed, failed, cancelled)
+    /// Filter by status (waiting, queued, running, completed, failed, cancelled)
     pub status: Option<String>,
     /// Page number (1-indexed, default: 1)
     #[serde(default = "default_page")]
@@ -57,7 +57,7 @@ pub struct TaskInfoResponse {
     pub id: String,
     /// Task type
     pub task_type: String,
-    /// Task status (queued, running, completed, failed, cancelled)
+    /// Task status (waiting, queued, running, completed, failed, cancelled)
     pub status: String,
     /// Task payload (JSON string)
     pub payload: Option<String>,
@@ -79,6 +79,11 @@ pub struct TaskInfoResponse {
     pub started_at: Option<String>,
     /// Task completion timestamp (if finished)
     pub finished_at: Option<String>,
+    /// IDs of the tasks this task waits for
+    pub depends_on: Vec<String>,
+    /// Steps of a workflow, in submission order (workflows only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steps: Option<Vec<TaskInfoResponse>>,
 }
 
 /// Response for task detail
@@ -112,6 +117,45 @@ pub struct TaskDetailResponse {
     pub started_at: Option<String>,
     /// Task completion timestamp (if finished)
     pub finished_at: Option<String>,
+    /// Workflow the task is a step of
+    pub parent_id: Option<String>,
+    /// IDs of the tasks this task waits for
+    pub depends_on: Vec<String>,
+    /// Steps of a workflow, in submission order (workflows only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steps: Option<Vec<TaskInfoResponse>>,
+}
+
+/// Request for submitting a workflow
+#[derive(Debug, Deserialize)]
+pub struct CreateWorkflowRequest {
+    /// Workflow name
+    pub name: String,
+    /// Steps, each depending only on steps listed before it
+    pub steps: Vec<WorkflowStepRequest>,
+}
+
+/// One step of a workflow
+#[derive(Debug, Deserialize)]
+pub struct WorkflowStepRequest {
+    /// Key other steps use to depend on this one
+    pub key: String,
+    /// Task name (defaults to the key)
+    pub name: Option<String>,
+    /// Task payload, as accepted by task schedules
+    pub payload: serde_json::Value,
+    /// Keys of earlier steps, or IDs of existing tasks, to wait for
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+/// Response for workflow submission
+#[derive(Debug, Serialize)]
+pub struct CreateWorkflowResponse {
+    /// ID of the workflow task
+    pub id: String,
+    /// Task ID of each step, by step key
+    pub steps: std::collections::HashMap<String, String>,
 }
 
 /// Response for task cancellation
@@ -128,10 +172,17 @@ pub struct DeleteTaskResponse {
     pub message: String,
 }
 
+/// Query parameters for GET /api/v1/tasks/events
+#[derive(Debug, Deserialize)]
+pub struct TaskEventsQuery {
+    /// Only send events of this task
+    pub task_id: Option<String>,
+}
+
 /// Query parameters for clearing tasks
 #[derive(Debug, Deserialize)]
 pub struct ClearTasksQuery {
-    /// Filter by status (queued, running, completed, failed, cancelled)
+    /// Filter by status (waiting, queued, running, completed, failed, cancelled)
     pub status: Option<String>,
 }
 
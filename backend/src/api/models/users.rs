@@ -70,6 +70,73 @@ pub struct UpdateProgressRequest {
     pub playback_start: Option<f64>,
 }
 
+/// Request for POST /api/progress/sync
+#[derive(Debug, Deserialize)]
+pub struct SyncProgressRequest {
+    /// Stable id of the uploading device
+    pub device_id: String,
+    pub events: Vec<SyncProgressEvent>,
+}
+
+/// A position recorded by a client, possibly while offline
+#[derive(Debug, Deserialize)]
+pub struct SyncProgressEvent {
+    pub book_id: String,
+    pub chapter_id: Option<String>,
+    pub position: f64,
+    pub duration: Option<f64>,
+    /// RFC3339 time the client recorded the position
+    pub recorded_at: String,
+}
+
+/// Response for POST /api/progress/sync
+#[derive(Debug, Serialize)]
+pub struct SyncProgressResponse {
+    /// Current progress of every chapter in the batch
+    pub progress: Vec<ProgressResponse>,
+    /// Events that replaced the stored position
+    pub accepted: usize,
+    /// Events that were older than the stored position or already uploaded
+    pub ignored: usize,
+}
+
+/// What ends a sleep timer
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SleepTimerMode {
+    /// Pause after a number of seconds
+    Duration { seconds: u64 },
+    /// Pause when the current chapter ends
+    EndOfChapter,
+    /// Pause when `count` chapters, starting with the current one, have ended
+    Chapters { count: u32 },
+}
+
+/// A running sleep timer, shared by all devices of the user
+#[derive(Debug, Clone, Serialize)]
+pub struct SleepTimerState {
+    #[serde(flatten)]
+    pub mode: SleepTimerMode,
+    pub started_at: String,
+    /// When a duration timer runs out
+    pub expires_at: Option<String>,
+    /// Chapters left to finish for chapter timers, including the current one
+    pub chapters_remaining: Option<u32>,
+}
+
+/// Response for the sleep timer endpoints
+#[derive(Debug, Serialize)]
+pub struct SleepTimerResponse {
+    pub timer: Option<SleepTimerState>,
+}
+
+/// Response for GET /api/me/devices
+#[derive(Debug, Serialize)]
+pub struct DevicesResponse {
+    pub devices: Vec<crate::api::ws::manager::DeviceInfo>,
+    pub active_device_id: Option<String>,
+}
+
 // Favorites Management API models
 
 /// Response for favorite operations
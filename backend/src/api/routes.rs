@@ -1,17 +1,23 @@
 //! API routes
 
 use crate::api::handlers::media::stream::{
-    get_hls_playlist, get_hls_segment, seek_hls_stream, stream_signed_chapter,
+    get_hls_master_playlist, get_hls_playlist, get_hls_rendition_file, get_hls_segment,
+    seek_hls_stream, stream_signed_chapter,
 };
 use crate::api::handlers::{
     add_favorite,
+    analyze_book_loudness,
+    analyze_book_skips,
+    analyze_library_loudness,
     apply_scrape_result,
+    apply_skip_proposal,
     batch_delete_tasks,
     batch_update_chapters,
     // Cache management
     cache_chapter,
     call_plugin_route,
     call_public_plugin_route,
+    cancel_sleep_timer,
     cancel_task,
     check_update,
     clear_all_caches,
@@ -20,12 +26,17 @@ use crate::api::handlers::{
     clear_system_logs,
     clear_tasks,
     create_book,
+    create_bookmark,
+    create_feed_link,
     create_library,
     create_notification_webhook,
     create_playlist,
     create_series,
+    create_task_schedule,
     create_user,
+    create_workflow,
     delete_book,
+    delete_bookmark,
     delete_chapter_cache,
     delete_library,
     delete_notification_webhook,
@@ -33,7 +44,9 @@ use crate::api::handlers::{
     delete_progress_history,
     delete_series,
     delete_task,
+    delete_task_schedule,
     delete_user,
+    discard_skip_proposal,
     export_system_logs,
     find_content_processors,
     find_event_handlers,
@@ -49,6 +62,10 @@ use crate::api::handlers::{
     get_config,
     // Favorites management
     get_favorites,
+    get_listening_summary,
+    get_listening_timeline,
+    get_listening_top,
+    get_listening_wrapped,
     get_metrics,
     get_playlist,
     get_plugin_asset,
@@ -58,6 +75,9 @@ use crate::api::handlers::{
     get_recent_progress,
     get_scraper_sources,
     get_series,
+    get_signed_feed,
+    get_skip_proposal,
+    get_sleep_timer,
     get_stats,
     get_storage_folders,
     get_storage_roots,
@@ -65,6 +85,7 @@ use crate::api::handlers::{
     get_system_logs,
     get_tags,
     get_task,
+    get_task_schedule,
     // User settings
     get_user_settings,
     // System management endpoints
@@ -73,7 +94,10 @@ use crate::api::handlers::{
     install_store_plugin,
     invoke_plugin_capability,
     invoke_plugin_host,
+    join_chapters,
+    list_bookmarks,
     list_books,
+    list_devices,
     list_libraries,
     list_notification_events,
     list_notification_webhooks,
@@ -83,6 +107,8 @@ use crate::api::handlers::{
     list_plugins,
     // Series management
     list_series,
+    list_task_schedule_runs,
+    list_task_schedules,
     list_tasks,
     // User management (admin)
     list_users,
@@ -92,18 +118,26 @@ use crate::api::handlers::{
     proxy_cover,
     reload_plugin,
     remove_favorite,
+    revoke_feed_links,
+    run_task_schedule,
     scan_library,
     scrape_book_diff,
     scraper_search,
     search_books,
+    search_library,
+    set_sleep_timer,
     // Audio streaming
     sign_plugin_route,
+    split_chapter,
     stream_chapter,
+    sync_progress,
+    task_events,
     test_notification_webhook,
     test_webdav_connection,
     uninstall_plugin,
     update_application_time_zone,
     update_book,
+    update_bookmark,
     update_chapter,
     update_config,
     update_library,
@@ -112,12 +146,13 @@ use crate::api::handlers::{
     update_plugin_config,
     update_progress,
     update_series,
+    update_task_schedule,
     update_user,
     update_user_settings,
     write_book_metadata_to_files,
     AppState,
 };
-use crate::auth::handlers::{get_me, update_me};
+use crate::auth::handlers::{clear_subsonic_password, get_me, set_subsonic_password, update_me};
 use crate::auth::middleware::authenticate;
 use axum::{
     middleware,
@@ -162,7 +197,15 @@ pub fn build_api_routes(state: AppState) -> Router {
             "/api/stream/hls/:sessionId/playlist.m3u8",
             get(get_hls_playlist),
         )
+        .route(
+            "/api/stream/hls/:sessionId/master.m3u8",
+            get(get_hls_master_playlist),
+        )
         .route("/api/stream/hls/:sessionId/:filename", get(get_hls_segment))
+        .route(
+            "/api/stream/hls/:sessionId/:rendition/:filename",
+            get(get_hls_rendition_file),
+        )
         .route("/api/stream/hls/:sessionId/seek", post(seek_hls_stream))
         .route(
             "/api/v1/public/media/:chapterId",
@@ -171,18 +214,57 @@ pub fn build_api_routes(state: AppState) -> Router {
         .route(
             "/api/public/media/:chapterId",
             get(stream_signed_chapter).head(stream_signed_chapter),
-        );
+        )
+        .route("/api/v1/public/feeds/:kind/:id", get(get_signed_feed))
+        .route("/api/public/feeds/:kind/:id", get(get_signed_feed));
 
     // Protected routes (authentication required)
     let protected_routes = Router::new()
         // User endpoints
         .route("/api/me", get(get_me).patch(update_me))
+        .route(
+            "/api/me/subsonic-password",
+            put(set_subsonic_password).delete(clear_subsonic_password),
+        )
+        .route("/api/me/devices", get(list_devices))
+        .route("/api/v1/me/devices", get(list_devices))
+        .route(
+            "/api/me/sleep-timer",
+            get(get_sleep_timer)
+                .put(set_sleep_timer)
+                .delete(cancel_sleep_timer),
+        )
+        .route(
+            "/api/v1/me/sleep-timer",
+            get(get_sleep_timer)
+                .put(set_sleep_timer)
+                .delete(cancel_sleep_timer),
+        )
+        // Podcast feed links
+        .route(
+            "/api/me/feeds",
+            post(create_feed_link).delete(revoke_feed_links),
+        )
+        .route(
+            "/api/v1/me/feeds",
+            post(create_feed_link).delete(revoke_feed_links),
+        )
+        // Listening statistics endpoints
+        .route("/api/me/stats/summary", get(get_listening_summary))
+        .route("/api/me/stats/timeline", get(get_listening_timeline))
+        .route("/api/me/stats/top", get(get_listening_top))
+        .route("/api/me/stats/wrapped/:year", get(get_listening_wrapped))
+        .route("/api/v1/me/stats/summary", get(get_listening_summary))
+        .route("/api/v1/me/stats/timeline", get(get_listening_timeline))
+        .route("/api/v1/me/stats/top", get(get_listening_top))
+        .route("/api/v1/me/stats/wrapped/:year", get(get_listening_wrapped))
         // Progress management endpoints
         .route(
             "/api/progress/recent",
             get(get_recent_progress).delete(clear_recent_progress),
         )
         .route("/api/progress/recent/delete", post(delete_progress_history))
+        .route("/api/progress/sync", post(sync_progress))
         .route("/api/progress/:bookId", get(get_book_progress))
         .route("/api/progress", post(update_progress))
         // Favorites management endpoints
@@ -191,6 +273,20 @@ pub fn build_api_routes(state: AppState) -> Router {
             "/api/favorites/:bookId",
             post(add_favorite).delete(remove_favorite),
         )
+        // Bookmark endpoints
+        .route("/api/bookmarks", get(list_bookmarks).post(create_bookmark))
+        .route(
+            "/api/bookmarks/:id",
+            patch(update_bookmark).delete(delete_bookmark),
+        )
+        .route(
+            "/api/v1/bookmarks",
+            get(list_bookmarks).post(create_bookmark),
+        )
+        .route(
+            "/api/v1/bookmarks/:id",
+            patch(update_bookmark).delete(delete_bookmark),
+        )
         // Playlist endpoints
         .route("/api/playlists", get(list_playlists).post(create_playlist))
         .route(
@@ -227,6 +323,10 @@ pub fn build_api_routes(state: AppState) -> Router {
             patch(update_library).delete(delete_library),
         )
         .route("/api/libraries/:id/scan", post(scan_library))
+        .route(
+            "/api/libraries/:id/loudness-analysis",
+            post(analyze_library_loudness),
+        )
         .route(
             "/api/libraries/test-connection",
             post(test_webdav_connection),
@@ -251,6 +351,7 @@ pub fn build_api_routes(state: AppState) -> Router {
         .route("/api/v1/books/:id/scrape-diff", post(scrape_book_diff))
         .route("/api/v1/books/:id/scrape-apply", post(apply_scrape_result))
         .route("/api/v1/books/merge", post(merge_books))
+        .route("/api/v1/books/search", get(search_library))
         .route("/api/v1/books/chapters/move", post(move_chapters))
         .route("/api/v1/tools/regex/generate", post(generate_regex))
         .route("/api/v1/books/:id/chapters", get(get_book_chapters))
@@ -258,8 +359,18 @@ pub fn build_api_routes(state: AppState) -> Router {
             "/api/v1/books/:id/chapters/batch",
             put(batch_update_chapters).post(batch_update_chapters),
         )
+        .route("/api/v1/books/:id/chapters/join", post(join_chapters))
+        .route(
+            "/api/v1/books/:id/skip-proposal",
+            get(get_skip_proposal).delete(discard_skip_proposal),
+        )
+        .route(
+            "/api/v1/books/:id/skip-proposal/apply",
+            post(apply_skip_proposal),
+        )
         // Chapter endpoints
         .route("/api/v1/chapters/:id", patch(update_chapter))
+        .route("/api/v1/chapters/:id/split", post(split_chapter))
         // Tags endpoint
         .route("/api/v1/tags", get(get_tags))
         // Search and scraper endpoints
@@ -314,6 +425,23 @@ pub fn build_api_routes(state: AppState) -> Router {
         .route("/api/v1/tasks/:id", get(get_task).delete(delete_task))
         .route("/api/v1/tasks/:id/cancel", post(cancel_task))
         .route("/api/v1/tasks/batch-delete", post(batch_delete_tasks))
+        .route("/api/v1/tasks/workflows", post(create_workflow))
+        .route("/api/v1/tasks/events", get(task_events))
+        .route(
+            "/api/v1/tasks/schedules",
+            get(list_task_schedules).post(create_task_schedule),
+        )
+        .route(
+            "/api/v1/tasks/schedules/:id",
+            get(get_task_schedule)
+                .put(update_task_schedule)
+                .delete(delete_task_schedule),
+        )
+        .route("/api/v1/tasks/schedules/:id/run", post(run_task_schedule))
+        .route(
+            "/api/v1/tasks/schedules/:id/runs",
+            get(list_task_schedule_runs),
+        )
         // System management endpoints
         .route("/api/v1/system/statistics", get(get_admin_statistics))
         .route(
@@ -356,19 +484,35 @@ pub fn build_api_routes(state: AppState) -> Router {
         .route("/api/books/:id/scrape-diff", post(scrape_book_diff))
         .route("/api/books/:id/scrape-apply", post(apply_scrape_result))
         .route("/api/books/merge", post(merge_books))
+        .route("/api/books/search", get(search_library))
         .route("/api/books/chapters/move", post(move_chapters))
         .route(
             "/api/books/:id/write-metadata",
             post(write_book_metadata_to_files),
         )
+        .route(
+            "/api/books/:id/loudness-analysis",
+            post(analyze_book_loudness),
+        )
+        .route("/api/books/:id/skip-analysis", post(analyze_book_skips))
         .route("/api/tools/regex/generate", post(generate_regex))
         .route("/api/books/:id/chapters", get(get_book_chapters))
         .route(
             "/api/books/:id/chapters/batch",
             put(batch_update_chapters).post(batch_update_chapters),
         )
+        .route("/api/books/:id/chapters/join", post(join_chapters))
+        .route(
+            "/api/books/:id/skip-proposal",
+            get(get_skip_proposal).delete(discard_skip_proposal),
+        )
+        .route(
+            "/api/books/:id/skip-proposal/apply",
+            post(apply_skip_proposal),
+        )
         // Chapter endpoints (without /v1)
         .route("/api/chapters/:id", patch(update_chapter))
+        .route("/api/chapters/:id/split", post(split_chapter))
         // Tags endpoint (without /v1)
         .route("/api/tags", get(get_tags))
         // Search and scraper endpoints (without /v1)
@@ -420,6 +564,23 @@ pub fn build_api_routes(state: AppState) -> Router {
         .route("/api/tasks/:id", get(get_task).delete(delete_task))
         .route("/api/tasks/:id/cancel", post(cancel_task))
         .route("/api/tasks/batch-delete", post(batch_delete_tasks))
+        .route("/api/tasks/workflows", post(create_workflow))
+        .route("/api/tasks/events", get(task_events))
+        .route(
+            "/api/tasks/schedules",
+            get(list_task_schedules).post(create_task_schedule),
+        )
+        .route(
+            "/api/tasks/schedules/:id",
+            get(get_task_schedule)
+                .put(update_task_schedule)
+                .delete(delete_task_schedule),
+        )
+        .route("/api/tasks/schedules/:id/run", post(run_task_schedule))
+        .route(
+            "/api/tasks/schedules/:id/runs",
+            get(list_task_schedule_runs),
+        )
         // System management endpoints (without /v1)
         .route("/api/system/statistics", get(get_admin_statistics))
         .route(
@@ -101,6 +101,7 @@ impl ApiServer {
         let user_repo = Arc::new(crate::db::repository::UserRepository::new(db.clone()));
         let progress_repo = Arc::new(crate::db::repository::ProgressRepository::new(db.clone()));
         let favorite_repo = Arc::new(crate::db::repository::FavoriteRepository::new(db.clone()));
+        let bookmark_repo = Arc::new(crate::db::repository::BookmarkRepository::new(db.clone()));
         let settings_repo = Arc::new(crate::db::repository::UserSettingsRepository::new(
             db.clone(),
         ));
@@ -114,6 +115,9 @@ impl ApiServer {
         let notification_repo = Arc::new(
             crate::db::repository::NotificationWebhookRepository::new(db.clone()),
         );
+        let task_schedule_repo = Arc::new(crate::db::repository::TaskScheduleRepository::new(
+            db.clone(),
+        ));
 
         // Initialize JWT key manager (auto-generates and rotates keys)
         let jwt_key_manager = tokio::task::block_in_place(|| {
@@ -206,7 +210,8 @@ impl ApiServer {
             .with_storage_service(storage_service.clone())
             .with_merge_service(merge_service.clone())
             .with_notification_repo(notification_repo.clone())
-            .with_encryption_key(Arc::new(encryption_key)),
+            .with_encryption_key(Arc::new(encryption_key))
+            .with_episode_dir(config.storage.episode_dir()),
         );
 
         // Start task queue executor
@@ -226,11 +231,36 @@ impl ApiServer {
         // Wrap config in Arc<RwLock> for shared mutable access
         let config_arc = Arc::new(tokio::sync::RwLock::new(config.clone()));
 
+        // Refresh RSS libraries on their own schedules and submit tasks on the
+        // schedules administrators defined, both from one cron scheduler
+        let task_schedule_runner = Arc::new(crate::core::task_queue::TaskScheduleRunner::new(
+            task_schedule_repo.clone(),
+            library_repo.clone(),
+            system_settings_repo.clone(),
+            task_queue.clone(),
+            config_arc.clone(),
+        ));
+        let mut cron_scheduler = crate::core::task_queue::CronScheduler::new();
+        cron_scheduler.register(Arc::new(crate::core::task_queue::RssRefreshScheduler::new(
+            library_repo.clone(),
+            system_settings_repo.clone(),
+            task_queue.clone(),
+        )));
+        cron_scheduler.register(task_schedule_runner.clone());
+        tokio::spawn(cron_scheduler.run());
+
         // Create cache manager
         let cache_manager = Arc::new(
             crate::cache::CacheManager::new(config.storage.temp_dir.clone())
                 .map_err(|e| anyhow::anyhow!("Failed to create cache manager: {}", e))?,
         );
+        let transcode_cache = Arc::new(
+            crate::cache::transcode::TranscodeCache::new(
+                config.storage.transcode_cache_dir(),
+                config.storage.transcode_cache_size,
+            )
+            .map_err(|e| anyhow::anyhow!("Failed to create transcode cache: {}", e))?,
+        );
         let plugin_cache = Arc::new(
             crate::plugin::PluginCache::new(config.storage.data_dir.join("plugin-cache"))
                 .map_err(|e| anyhow::anyhow!("Failed to create plugin cache: {}", e))?,
@@ -273,6 +303,10 @@ impl ApiServer {
 
         // Create WebSocket session manager
         let ws_manager = crate::api::ws::manager::WsSessionManager::new();
+        let sleep_timers = crate::api::ws::sleep_timer::SleepTimerManager::new(
+            ws_manager.clone(),
+            progress_repo.clone(),
+        );
 
         // Create HLS session manager
         let hls_temp_dir = config.storage.temp_dir.join("ting_hls_sessions");
@@ -298,6 +332,7 @@ impl ApiServer {
             user_repo,
             progress_repo,
             favorite_repo,
+            bookmark_repo,
             settings_repo,
             system_settings_repo,
             library_repo,
@@ -305,6 +340,7 @@ impl ApiServer {
             series_repo,
             playlist_repo,
             notification_repo,
+            task_schedule_repo,
             book_service,
             scraper_service,
             plugin_manager,
@@ -312,10 +348,12 @@ impl ApiServer {
             plugin_host_gateway,
             config_manager,
             task_queue,
+            task_schedule_runner,
             config: config_arc,
             jwt_secret,
             jwt_key_manager, // 新增密钥管理器
             cache_manager,
+            transcode_cache,
             encryption_key: Arc::new(encryption_key),
             storage_service,
             preload_cache,
@@ -327,6 +365,7 @@ impl ApiServer {
             )),
             library_watcher,
             ws_manager,
+            sleep_timers,
             hls_session_manager,
         };
 
@@ -376,7 +415,21 @@ impl ApiServer {
         ));
 
         // Combine public and protected routes
-        let api_router = Router::new().merge(public_router).merge(protected_router);
+        let mut api_router = Router::new().merge(public_router).merge(protected_router);
+
+        // Audiobookshelf-compatible API, authenticated with the user's JWT
+        if config.server.enable_abs_api {
+            api_router =
+                api_router.nest("/abs", crate::api::abs::build_abs_routes(app_state.clone()));
+        }
+
+        // Subsonic-compatible API, authenticated with each user's Subsonic password
+        if config.server.enable_subsonic_api {
+            api_router = api_router.nest(
+                "/rest",
+                crate::api::subsonic::build_subsonic_routes(app_state.clone()),
+            );
+        }
 
         // Static file serving for SPA
         let static_dir = std::env::var("STATIC_DIR").unwrap_or_else(|_| "static".to_string());
@@ -0,0 +1,134 @@
+//! Subsonic authentication
+//!
+//! Clients send `u` plus either `t`/`s` (md5 of password and salt) or `p` (the
+//! password, optionally hex-encoded as `enc:...`) on every request. The
+//! password checked is the user's Subsonic password, which is stored encrypted
+//! because the token form needs it in plain text.
+
+use super::response::{SubsonicError, ERROR_WRONG_CREDENTIALS};
+use crate::api::handlers::AppState;
+use crate::auth::middleware::AuthUser;
+use crate::core::signing::constant_time_eq;
+use axum::{
+    extract::{Query, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use md5::{Digest, Md5};
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+struct AuthParams {
+    u: Option<String>,
+    p: Option<String>,
+    t: Option<String>,
+    s: Option<String>,
+}
+
+/// Middleware checking Subsonic credentials and inserting the [`AuthUser`]
+pub async fn authenticate(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let params = Query::<AuthParams>::try_from_uri(request.uri())
+        .map(|Query(params)| params)
+        .unwrap_or_default();
+
+    match check_credentials(&state, params).await {
+        Ok(user) => {
+            request.extensions_mut().insert(user);
+            next.run(request).await
+        }
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn check_credentials(
+    state: &AppState,
+    params: AuthParams,
+) -> std::result::Result<AuthUser, SubsonicError> {
+    let username = params.u.ok_or_else(|| SubsonicError::missing("u"))?;
+    let wrong_credentials =
+        || SubsonicError::new(ERROR_WRONG_CREDENTIALS, "Wrong username or password");
+
+    let (user, encrypted) = state
+        .user_repo
+        .find_subsonic_user(&username)
+        .await?
+        .ok_or_else(wrong_credentials)?;
+    let Some(encrypted) = encrypted else {
+        return Err(SubsonicError::new(
+            ERROR_WRONG_CREDENTIALS,
+            "Subsonic password has not been set for this user",
+        ));
+    };
+    let password = crate::core::crypto::decrypt(&encrypted, &state.encryption_key)?;
+
+    let valid = match (params.t, params.s, params.p) {
+        (Some(token), Some(salt), _) => constant_time_eq(
+            subsonic_token(&password, &salt).as_bytes(),
+            token.to_ascii_lowercase().as_bytes(),
+        ),
+        (_, _, Some(given)) => decode_password(&given).as_deref() == Some(password.as_str()),
+        _ => return Err(SubsonicError::missing("t")),
+    };
+    if !valid {
+        tracing::warn!(username = %username, "Subsonic authentication failed");
+        return Err(wrong_credentials());
+    }
+
+    Ok(AuthUser {
+        user_id: user.id.clone(),
+        id: user.id,
+        username: user.username,
+        role: user.role,
+    })
+}
+
+/// Password from the `p` parameter, which may be hex-encoded as `enc:...`
+fn decode_password(value: &str) -> Option<String> {
+    let Some(hex) = value.strip_prefix("enc:") else {
+        return Some(value.to_string());
+    };
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(hex.get(idx..idx + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Token a client sends for `password` and `salt`: their MD5 as lowercase hex
+fn subsonic_token(password: &str, salt: &str) -> String {
+    let mut hasher = Md5::new();
+    hasher.update(password.as_bytes());
+    hasher.update(salt.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_match_the_subsonic_example() {
+        // Example from the Subsonic API documentation
+        assert_eq!(
+            subsonic_token("sesame", "c19b2d"),
+            "26719a1196d2a940705a59634eb18eab"
+        );
+    }
+
+    #[test]
+    fn plain_and_hex_passwords_are_decoded() {
+        assert_eq!(decode_password("sesame").as_deref(), Some("sesame"));
+        assert_eq!(
+            decode_password("enc:736573616d65").as_deref(),
+            Some("sesame")
+        );
+        assert_eq!(decode_password("enc:7365f"), None);
+    }
+}
@@ -0,0 +1,897 @@
+//! Subsonic endpoint handlers
+//!
+//! Music folders are libraries, albums are books, songs are chapters and
+//! artists are book authors. Artist IDs are the author name prefixed with
+//! `ar-`; every other ID is the Ting Reader ID.
+
+use super::response::{Payload, SubsonicError, SubsonicResult, ERROR_GENERIC};
+use crate::api::handlers::media::{proxy_cover, stream_chapter, ProxyCoverQuery, StreamQuery};
+use crate::api::handlers::AppState;
+use crate::api::ws::handler::broadcast_progress_update;
+use crate::auth::middleware::AuthUser;
+use crate::db::models::{Book, Chapter, Library, Progress};
+use crate::db::repository::Repository;
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, Method},
+    response::{IntoResponse, Response},
+};
+use pinyin::ToPinyin;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+
+/// Largest page of albums, artists or songs a list call returns
+const MAX_LIST_SIZE: usize = 500;
+const ARTIST_ID_PREFIX: &str = "ar-";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct IdQuery {
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MusicFolderQuery {
+    pub music_folder_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumListQuery {
+    #[serde(rename = "type")]
+    pub list_type: Option<String>,
+    pub size: Option<usize>,
+    pub offset: Option<usize>,
+    pub from_year: Option<i32>,
+    pub to_year: Option<i32>,
+    pub genre: Option<String>,
+    pub music_folder_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchQuery {
+    pub query: Option<String>,
+    pub artist_count: Option<usize>,
+    pub artist_offset: Option<usize>,
+    pub album_count: Option<usize>,
+    pub album_offset: Option<usize>,
+    pub song_count: Option<usize>,
+    pub song_offset: Option<usize>,
+    pub music_folder_id: Option<String>,
+}
+
+/// Handler for ping.view
+pub async fn ping() -> SubsonicResult {
+    Ok(Payload::empty())
+}
+
+/// Handler for getLicense.view
+pub async fn get_license() -> SubsonicResult {
+    Ok(Payload::new("license", json!({ "valid": true })))
+}
+
+/// Handler for getOpenSubsonicExtensions.view
+pub async fn get_open_subsonic_extensions() -> SubsonicResult {
+    Ok(Payload::new("openSubsonicExtensions", json!([])))
+}
+
+/// Handler for getMusicFolders.view
+pub async fn get_music_folders(State(state): State<AppState>, user: AuthUser) -> SubsonicResult {
+    let folders: Vec<Value> = accessible_libraries(&state, &user)
+        .await?
+        .into_iter()
+        .map(|library| json!({ "id": library.id, "name": library.name }))
+        .collect();
+    Ok(Payload::new(
+        "musicFolders",
+        json!({ "musicFolder": folders }),
+    ))
+}
+
+/// Handler for getUser.view - Only the signed-in user can be looked up
+pub async fn get_user(State(state): State<AppState>, user: AuthUser) -> SubsonicResult {
+    let folders: Vec<String> = accessible_libraries(&state, &user)
+        .await?
+        .into_iter()
+        .map(|library| library.id)
+        .collect();
+    let admin = is_admin(&user);
+    Ok(Payload::new(
+        "user",
+        json!({
+            "username": user.username,
+            "scrobblingEnabled": true,
+            "adminRole": admin,
+            "settingsRole": false,
+            "downloadRole": true,
+            "uploadRole": false,
+            "playlistRole": false,
+            "coverArtRole": false,
+            "commentRole": false,
+            "podcastRole": false,
+            "streamRole": true,
+            "jukeboxRole": false,
+            "shareRole": false,
+            "folder": folders,
+        }),
+    ))
+}
+
+/// Handler for getIndexes.view - Books as top-level directories
+pub async fn get_indexes(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<MusicFolderQuery>,
+) -> SubsonicResult {
+    let books = list_books(&state, &user, query.music_folder_id).await?;
+    let mut indexes: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for book in &books {
+        let name = book_title(book);
+        indexes
+            .entry(index_letter(&name))
+            .or_default()
+            .push(json!({ "id": book.id, "name": name }));
+    }
+
+    Ok(Payload::new(
+        "indexes",
+        json!({
+            "lastModified": 0,
+            "ignoredArticles": "",
+            "index": index_entries(indexes, "artist"),
+        }),
+    ))
+}
+
+/// Handler for getMusicDirectory.view - A library lists its books, a book its chapters
+pub async fn get_music_directory(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<IdQuery>,
+) -> SubsonicResult {
+    let id = query.id.ok_or_else(|| SubsonicError::missing("id"))?;
+
+    if let Some(library) = accessible_libraries(&state, &user)
+        .await?
+        .into_iter()
+        .find(|library| library.id == id)
+    {
+        let books = list_books(&state, &user, Some(library.id.clone())).await?;
+        let children: Vec<Value> = books.iter().map(directory_value).collect();
+        return Ok(Payload::new(
+            "directory",
+            json!({ "id": library.id, "name": library.name, "child": children }),
+        ));
+    }
+
+    let book = load_book(&state, &user, &id).await?;
+    let chapters = state.chapter_repo.find_by_book(&book.id).await?;
+    Ok(Payload::new(
+        "directory",
+        json!({
+            "id": book.id,
+            "parent": book.library_id,
+            "name": book_title(&book),
+            "child": song_values(&book, &chapters),
+        }),
+    ))
+}
+
+/// Handler for getArtists.view - Book authors grouped by initial
+pub async fn get_artists(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<MusicFolderQuery>,
+) -> SubsonicResult {
+    let books = list_books(&state, &user, query.music_folder_id).await?;
+    let mut indexes: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for (name, albums) in group_by_artist(&books) {
+        indexes
+            .entry(index_letter(&name))
+            .or_default()
+            .push(artist_value(&name, albums.len()));
+    }
+
+    Ok(Payload::new(
+        "artists",
+        json!({ "ignoredArticles": "", "index": index_entries(indexes, "artist") }),
+    ))
+}
+
+/// Handler for getArtist.view
+pub async fn get_artist(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<IdQuery>,
+) -> SubsonicResult {
+    let id = query.id.ok_or_else(|| SubsonicError::missing("id"))?;
+    let name = artist_name(&id).ok_or_else(|| SubsonicError::from(not_found("Artist", &id)))?;
+    let books: Vec<Book> = list_books(&state, &user, None)
+        .await?
+        .into_iter()
+        .filter(|book| book_artist(book) == name)
+        .collect();
+    if books.is_empty() {
+        return Err(not_found("Artist", &id).into());
+    }
+
+    let mut artist = artist_value(&name, books.len());
+    artist["album"] = Value::Array(album_values(&state, &books).await?);
+    Ok(Payload::new("artist", artist))
+}
+
+/// Handler for getAlbum.view - A book and its chapters
+pub async fn get_album(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<IdQuery>,
+) -> SubsonicResult {
+    let id = query.id.ok_or_else(|| SubsonicError::missing("id"))?;
+    let book = load_book(&state, &user, &id).await?;
+    let chapters = state.chapter_repo.find_by_book(&book.id).await?;
+
+    let mut album = album_value(&book, &chapters);
+    album["song"] = Value::Array(song_values(&book, &chapters));
+    Ok(Payload::new("album", album))
+}
+
+/// Handler for getSong.view
+pub async fn get_song(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<IdQuery>,
+) -> SubsonicResult {
+    let id = query.id.ok_or_else(|| SubsonicError::missing("id"))?;
+    let (book, chapter) = load_chapter(&state, &user, &id).await?;
+    let chapters = state.chapter_repo.find_by_book(&book.id).await?;
+    let track = chapters
+        .iter()
+        .position(|candidate| candidate.id == chapter.id)
+        .unwrap_or(0);
+    Ok(Payload::new("song", song_value(&book, &chapter, track + 1)))
+}
+
+/// Handler for getAlbumList.view
+pub async fn get_album_list(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<AlbumListQuery>,
+) -> SubsonicResult {
+    let books = album_list(&state, &user, query).await?;
+    let albums: Vec<Value> = books.iter().map(directory_value).collect();
+    Ok(Payload::new("albumList", json!({ "album": albums })))
+}
+
+/// Handler for getAlbumList2.view
+pub async fn get_album_list2(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<AlbumListQuery>,
+) -> SubsonicResult {
+    let books = album_list(&state, &user, query).await?;
+    Ok(Payload::new(
+        "albumList2",
+        json!({ "album": album_values(&state, &books).await? }),
+    ))
+}
+
+/// Handler for getStarred2.view - Favorite books
+pub async fn get_starred2(State(state): State<AppState>, user: AuthUser) -> SubsonicResult {
+    let books = favorite_books(&state, &user).await?;
+    Ok(Payload::new(
+        "starred2",
+        json!({ "artist": [], "album": album_values(&state, &books).await?, "song": [] }),
+    ))
+}
+
+/// Handler for search3.view. An empty query lists everything, which some
+/// clients use to sync their whole library.
+pub async fn search3(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<SearchQuery>,
+) -> SubsonicResult {
+    let text = query
+        .query
+        .as_deref()
+        .unwrap_or_default()
+        .trim()
+        .trim_matches('"')
+        .trim()
+        .to_string();
+    let books = list_books(&state, &user, query.music_folder_id).await?;
+    let artist_count = page_size(query.artist_count, 20);
+    let album_count = page_size(query.album_count, 20);
+    let song_count = page_size(query.song_count, 20);
+
+    let artists: Vec<Value> = group_by_artist(&books)
+        .into_iter()
+        .filter(|(name, _)| contains_ignore_case(name, &text))
+        .skip(query.artist_offset.unwrap_or(0))
+        .take(artist_count)
+        .map(|(name, albums)| artist_value(&name, albums.len()))
+        .collect();
+
+    let matched: Vec<Book> = if text.is_empty() {
+        books.clone()
+    } else {
+        let (hits, _) = state
+            .book_repo
+            .search(
+                &user.id,
+                is_admin(&user),
+                &text,
+                None,
+                MAX_LIST_SIZE as u32,
+                0,
+            )
+            .await?;
+        let visible: HashSet<&str> = books.iter().map(|book| book.id.as_str()).collect();
+        hits.into_iter()
+            .map(|hit| hit.book)
+            .filter(|book| visible.contains(book.id.as_str()))
+            .collect()
+    };
+    let albums: Vec<Book> = matched
+        .into_iter()
+        .skip(query.album_offset.unwrap_or(0))
+        .take(album_count)
+        .collect();
+
+    let song_offset = query.song_offset.unwrap_or(0);
+    let mut songs = Vec::new();
+    let mut skipped = 0;
+    if song_count > 0 {
+        'books: for book in &books {
+            let chapters = state.chapter_repo.find_by_book(&book.id).await?;
+            for (idx, chapter) in chapters.iter().enumerate() {
+                if !text.is_empty()
+                    && !contains_ignore_case(chapter.title.as_deref().unwrap_or_default(), &text)
+                {
+                    continue;
+                }
+                if skipped < song_offset {
+                    skipped += 1;
+                    continue;
+                }
+                songs.push(song_value(book, chapter, idx + 1));
+                if songs.len() >= song_count {
+                    break 'books;
+                }
+            }
+        }
+    }
+
+    Ok(Payload::new(
+        "searchResult3",
+        json!({
+            "artist": artists,
+            "album": album_values(&state, &albums).await?,
+            "song": songs,
+        }),
+    ))
+}
+
+/// Handler for stream.view - Plays the original file; transcoding options are ignored
+pub async fn stream(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<IdQuery>,
+    method: Method,
+    headers: HeaderMap,
+) -> std::result::Result<Response, SubsonicError> {
+    stream_file(state, user, query, method, headers, false).await
+}
+
+/// Handler for download.view
+pub async fn download(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<IdQuery>,
+    method: Method,
+    headers: HeaderMap,
+) -> std::result::Result<Response, SubsonicError> {
+    stream_file(state, user, query, method, headers, true).await
+}
+
+/// Handler for getCoverArt.view - `id` is a book, or a chapter of the book
+pub async fn get_cover_art(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<IdQuery>,
+) -> std::result::Result<Response, SubsonicError> {
+    let id = query.id.ok_or_else(|| SubsonicError::missing("id"))?;
+    let book = match state.book_repo.find_by_id(&id).await? {
+        Some(_) => load_book(&state, &user, &id).await?,
+        None => load_chapter(&state, &user, &id).await?.0,
+    };
+    let path = book
+        .cover_url
+        .clone()
+        .filter(|path| !path.trim().is_empty())
+        .ok_or_else(|| SubsonicError::from(not_found("Cover art", &id)))?;
+
+    let params = ProxyCoverQuery {
+        path,
+        library_id: Some(book.library_id),
+        book_id: Some(book.id),
+    };
+    Ok(proxy_cover(State(state), Query(params))
+        .await?
+        .into_response())
+}
+
+/// Handler for scrobble.view - Record played chapters as progress.
+///
+/// A submission marks the chapter as listened to the end; a now-playing
+/// notification moves the book's progress to the start of the chapter unless
+/// it is already inside it.
+pub async fn scrobble(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(params): Query<Vec<(String, String)>>,
+) -> SubsonicResult {
+    let ids: Vec<&str> = params
+        .iter()
+        .filter(|(key, _)| key == "id")
+        .map(|(_, value)| value.as_str())
+        .collect();
+    if ids.is_empty() {
+        return Err(SubsonicError::missing("id"));
+    }
+    let submission = params
+        .iter()
+        .find(|(key, _)| key == "submission")
+        .map_or(true, |(_, value)| value != "false");
+
+    for id in ids {
+        let (book, chapter) = load_chapter(&state, &user, id).await?;
+        let position = if submission {
+            chapter.slice_length().unwrap_or(0.0)
+        } else {
+            let current = state.progress_repo.get_by_book(&user.id, &book.id).await?;
+            if current
+                .as_ref()
+                .is_some_and(|progress| progress.chapter_id.as_deref() == Some(chapter.id.as_str()))
+            {
+                continue;
+            }
+            0.0
+        };
+
+        let progress = Progress {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user.id.clone(),
+            book_id: book.id.clone(),
+            chapter_id: Some(chapter.id.clone()),
+            position,
+            duration: chapter.slice_length(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        };
+        state.progress_repo.upsert(&progress).await?;
+        state
+            .sleep_timers
+            .record_position(&user.id, &book.id, Some(&chapter.id), position)
+            .await;
+        broadcast_progress_update(&state.ws_manager, &user.id, &progress).await;
+    }
+
+    Ok(Payload::empty())
+}
+
+fn is_admin(user: &AuthUser) -> bool {
+    user.role == "admin"
+}
+
+fn not_found(kind: &str, id: &str) -> crate::core::error::TingError {
+    crate::core::error::TingError::NotFound(format!("{} {} not found", kind, id))
+}
+
+async fn stream_file(
+    state: AppState,
+    user: AuthUser,
+    query: IdQuery,
+    method: Method,
+    headers: HeaderMap,
+    download: bool,
+) -> std::result::Result<Response, SubsonicError> {
+    let id = query.id.ok_or_else(|| SubsonicError::missing("id"))?;
+    let (_, chapter) = load_chapter(&state, &user, &id).await?;
+    let params = StreamQuery {
+        token: None,
+        transcode: None,
+        seek: None,
+        download: download.then(|| "true".to_string()),
+        abr: None,
+    };
+    Ok(stream_chapter(
+        State(state),
+        Path(chapter.id),
+        Query(params),
+        method,
+        headers,
+        Some(user),
+    )
+    .await?)
+}
+
+async fn accessible_libraries(
+    state: &AppState,
+    user: &AuthUser,
+) -> crate::core::error::Result<Vec<Library>> {
+    if is_admin(user) {
+        state.library_repo.find_all().await
+    } else {
+        state.library_repo.find_by_user_access(&user.id).await
+    }
+}
+
+/// Books the user can see, newest first, optionally in one library
+async fn list_books(
+    state: &AppState,
+    user: &AuthUser,
+    library_id: Option<String>,
+) -> crate::core::error::Result<Vec<Book>> {
+    state
+        .book_repo
+        .find_with_filters(&user.id, is_admin(user), None, None, library_id)
+        .await
+}
+
+async fn load_book(
+    state: &AppState,
+    user: &AuthUser,
+    book_id: &str,
+) -> std::result::Result<Book, SubsonicError> {
+    let book = state
+        .book_repo
+        .find_by_id(book_id)
+        .await?
+        .ok_or_else(|| not_found("Album", book_id))?;
+    if !state
+        .book_repo
+        .check_access(&book.id, &user.id, is_admin(user))
+        .await?
+    {
+        return Err(not_found("Album", book_id).into());
+    }
+    Ok(book)
+}
+
+async fn load_chapter(
+    state: &AppState,
+    user: &AuthUser,
+    chapter_id: &str,
+) -> std::result::Result<(Book, Chapter), SubsonicError> {
+    let chapter = state
+        .chapter_repo
+        .find_by_id(chapter_id)
+        .await?
+        .ok_or_else(|| not_found("Song", chapter_id))?;
+    let book = load_book(state, user, &chapter.book_id)
+        .await
+        .map_err(|_| SubsonicError::from(not_found("Song", chapter_id)))?;
+    Ok((book, chapter))
+}
+
+async fn favorite_books(
+    state: &AppState,
+    user: &AuthUser,
+) -> crate::core::error::Result<Vec<Book>> {
+    let mut books = Vec::new();
+    for favorite in state.favorite_repo.get_by_user(&user.id).await? {
+        if let Some(book) = state.book_repo.find_by_id(&favorite.book_id).await? {
+            if state
+                .book_repo
+                .check_access(&book.id, &user.id, is_admin(user))
+                .await?
+            {
+                books.push(book);
+            }
+        }
+    }
+    Ok(books)
+}
+
+/// Books for getAlbumList and getAlbumList2
+async fn album_list(
+    state: &AppState,
+    user: &AuthUser,
+    query: AlbumListQuery,
+) -> std::result::Result<Vec<Book>, SubsonicError> {
+    let list_type = query
+        .list_type
+        .clone()
+        .ok_or_else(|| SubsonicError::missing("type"))?;
+    let mut books = list_books(state, user, query.music_folder_id.clone()).await?;
+
+    match list_type.as_str() {
+        "newest" => {}
+        "random" => books.shuffle(&mut rand::thread_rng()),
+        "alphabeticalByName" => books.sort_by_key(|book| book_title(book).to_lowercase()),
+        "alphabeticalByArtist" => books.sort_by_key(|book| {
+            (
+                book_artist(book).to_lowercase(),
+                book_title(book).to_lowercase(),
+            )
+        }),
+        "recent" | "frequent" => {
+            let visible: HashSet<String> = books.iter().map(|book| book.id.clone()).collect();
+            let mut seen = HashSet::new();
+            let recent_ids: Vec<String> = state
+                .progress_repo
+                .get_recent(&user.id, Some(MAX_LIST_SIZE as i32 * 4))
+                .await?
+                .into_iter()
+                .map(|progress| progress.book_id)
+                .filter(|book_id| visible.contains(book_id) && seen.insert(book_id.clone()))
+                .collect();
+            books = recent_ids
+                .iter()
+                .filter_map(|id| books.iter().find(|book| &book.id == id).cloned())
+                .collect();
+        }
+        "starred" => {
+            let visible: HashSet<String> = books.iter().map(|book| book.id.clone()).collect();
+            books = favorite_books(state, user)
+                .await?
+                .into_iter()
+                .filter(|book| visible.contains(&book.id))
+                .collect();
+        }
+        "byYear" => {
+            let from = query
+                .from_year
+                .ok_or_else(|| SubsonicError::missing("fromYear"))?;
+            let to = query
+                .to_year
+                .ok_or_else(|| SubsonicError::missing("toYear"))?;
+            let (low, high) = (from.min(to), from.max(to));
+            books.retain(|book| book.year.is_some_and(|year| year >= low && year <= high));
+            books.sort_by_key(|book| book.year);
+            if from > to {
+                books.reverse();
+            }
+        }
+        "byGenre" => {
+            let genre = query
+                .genre
+                .clone()
+                .ok_or_else(|| SubsonicError::missing("genre"))?;
+            books.retain(|book| {
+                split_names(book.genre.as_deref().unwrap_or_default())
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(&genre))
+            });
+        }
+        // Books have no ratings
+        "highest" => books.clear(),
+        other => {
+            return Err(SubsonicError::new(
+                ERROR_GENERIC,
+                format!("Unsupported album list type: {}", other),
+            ))
+        }
+    }
+
+    Ok(books
+        .into_iter()
+        .skip(query.offset.unwrap_or(0))
+        .take(page_size(query.size, 10))
+        .collect())
+}
+
+fn page_size(size: Option<usize>, default: usize) -> usize {
+    size.unwrap_or(default).min(MAX_LIST_SIZE)
+}
+
+fn contains_ignore_case(text: &str, term: &str) -> bool {
+    term.is_empty() || text.to_lowercase().contains(&term.to_lowercase())
+}
+
+fn book_title(book: &Book) -> String {
+    book.title
+        .clone()
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| book.id.clone())
+}
+
+fn book_artist(book: &Book) -> String {
+    book.author
+        .as_deref()
+        .map(str::trim)
+        .filter(|author| !author.is_empty())
+        .unwrap_or("Unknown")
+        .to_string()
+}
+
+fn artist_id(name: &str) -> String {
+    format!("{}{}", ARTIST_ID_PREFIX, urlencoding::encode(name))
+}
+
+fn artist_name(id: &str) -> Option<String> {
+    let encoded = id.strip_prefix(ARTIST_ID_PREFIX)?;
+    urlencoding::decode(encoded)
+        .ok()
+        .map(|name| name.into_owned())
+}
+
+fn artist_value(name: &str, album_count: usize) -> Value {
+    json!({ "id": artist_id(name), "name": name, "albumCount": album_count })
+}
+
+/// Books grouped by author, sorted by author name
+fn group_by_artist(books: &[Book]) -> BTreeMap<String, Vec<&Book>> {
+    let mut artists: BTreeMap<String, Vec<&Book>> = BTreeMap::new();
+    for book in books {
+        artists.entry(book_artist(book)).or_default().push(book);
+    }
+    artists
+}
+
+/// Index heading for a name: its first letter, using pinyin for Chinese
+/// characters, or `#`
+fn index_letter(name: &str) -> String {
+    let Some(first) = name.trim().chars().next() else {
+        return "#".to_string();
+    };
+    let letter = first
+        .to_pinyin()
+        .and_then(|pinyin| pinyin.first_letter().chars().next())
+        .unwrap_or(first)
+        .to_ascii_uppercase();
+    if letter.is_ascii_alphabetic() {
+        letter.to_string()
+    } else {
+        "#".to_string()
+    }
+}
+
+fn index_entries(indexes: BTreeMap<String, Vec<Value>>, entry_key: &str) -> Vec<Value> {
+    indexes
+        .into_iter()
+        .map(|(name, entries)| {
+            let mut index = json!({ "name": name });
+            index[entry_key] = Value::Array(entries);
+            index
+        })
+        .collect()
+}
+
+fn split_names(value: &str) -> Vec<String> {
+    value
+        .split([',', '，', '、'])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Subsonic wants ISO 8601; stored timestamps may be SQLite's `YYYY-MM-DD HH:MM:SS`
+fn iso_timestamp(value: &str) -> String {
+    chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .map(|at| {
+            at.and_utc()
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        })
+        .unwrap_or_else(|_| value.to_string())
+}
+
+fn chapter_seconds(chapter: &Chapter) -> i64 {
+    chapter.slice_length().unwrap_or(0.0).round() as i64
+}
+
+/// A book as a folder entry (getMusicDirectory, getAlbumList)
+fn directory_value(book: &Book) -> Value {
+    let artist = book_artist(book);
+    json!({
+        "id": book.id,
+        "parent": book.library_id,
+        "isDir": true,
+        "title": book_title(book),
+        "album": book_title(book),
+        "artist": artist,
+        "year": book.year,
+        "genre": book.genre,
+        "coverArt": book.id,
+        "created": iso_timestamp(&book.created_at),
+    })
+}
+
+/// A book as an ID3 album (getAlbum, getAlbumList2, search3)
+fn album_value(book: &Book, chapters: &[Chapter]) -> Value {
+    let artist = book_artist(book);
+    json!({
+        "id": book.id,
+        "name": book_title(book),
+        "artist": artist,
+        "artistId": artist_id(&artist),
+        "coverArt": book.id,
+        "songCount": chapters.len(),
+        "duration": chapters.iter().map(chapter_seconds).sum::<i64>(),
+        "created": iso_timestamp(&book.created_at),
+        "year": book.year,
+        "genre": book.genre,
+    })
+}
+
+async fn album_values(state: &AppState, books: &[Book]) -> crate::core::error::Result<Vec<Value>> {
+    let mut albums = Vec::with_capacity(books.len());
+    for book in books {
+        let chapters = state.chapter_repo.find_by_book(&book.id).await?;
+        albums.push(album_value(book, &chapters));
+    }
+    Ok(albums)
+}
+
+/// A chapter as a song; `track` is 1-based
+fn song_value(book: &Book, chapter: &Chapter, track: usize) -> Value {
+    let artist = book_artist(book);
+    let suffix = std::path::Path::new(&chapter.path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let content_type = mime_guess::from_path(&chapter.path)
+        .first()
+        .map(|mime| mime.to_string())
+        .filter(|mime| mime.starts_with("audio/"))
+        .unwrap_or_else(|| "audio/mpeg".to_string());
+    json!({
+        "id": chapter.id,
+        "parent": book.id,
+        "isDir": false,
+        "title": chapter
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Chapter {}", track)),
+        "album": book_title(book),
+        "artist": artist,
+        "track": track,
+        "year": book.year,
+        "genre": book.genre,
+        "coverArt": book.id,
+        "contentType": content_type,
+        "suffix": suffix,
+        "duration": chapter_seconds(chapter),
+        "albumId": book.id,
+        "artistId": artist_id(&artist),
+        "type": "audiobook",
+        "mediaType": "song",
+        "isVideo": false,
+        "created": iso_timestamp(&chapter.created_at),
+    })
+}
+
+fn song_values(book: &Book, chapters: &[Chapter]) -> Vec<Value> {
+    chapters
+        .iter()
+        .enumerate()
+        .map(|(idx, chapter)| song_value(book, chapter, idx + 1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn artist_ids_round_trip() {
+        let id = artist_id("南派三叔, 某人");
+        assert!(id.starts_with(ARTIST_ID_PREFIX));
+        assert_eq!(artist_name(&id).as_deref(), Some("南派三叔, 某人"));
+        assert_eq!(artist_name("book-1"), None);
+    }
+
+    #[test]
+    fn names_are_indexed_by_initial() {
+        assert_eq!(index_letter("盗墓笔记"), "D");
+        assert_eq!(index_letter("three body"), "T");
+        assert_eq!(index_letter("1984"), "#");
+        assert_eq!(index_letter(""), "#");
+        assert_eq!(iso_timestamp("2024-01-02 03:04:05"), "2024-01-02T03:04:05Z");
+    }
+}
@@ -0,0 +1,107 @@
+//! Subsonic / OpenSubsonic API
+//!
+//! An optional facade mounted under `/rest` when `server.enable_subsonic_api`
+//! is set, so Subsonic players can browse and play audiobooks. Libraries are
+//! music folders, books are albums and chapters are songs; scrobbles are
+//! stored as playback progress. Clients sign in with the Subsonic password
+//! each user sets for themselves.
+
+pub mod auth;
+pub mod handlers;
+pub mod response;
+
+use crate::api::handlers::AppState;
+use axum::{
+    middleware,
+    routing::{get, MethodRouter},
+    Router,
+};
+
+/// Subsonic API version the facade implements
+pub const SUBSONIC_API_VERSION: &str = "1.16.1";
+
+/// Register a method both as `/name` and `/name.view`
+fn view(router: Router<AppState>, name: &str, handler: MethodRouter<AppState>) -> Router<AppState> {
+    router
+        .route(&format!("/{}", name), handler.clone())
+        .route(&format!("/{}.view", name), handler)
+}
+
+/// Build the routes served under `/rest`
+pub fn build_subsonic_routes(state: AppState) -> Router {
+    let mut router = Router::new();
+    let methods: [(&str, MethodRouter<AppState>); 19] = [
+        ("ping", get(handlers::ping).post(handlers::ping)),
+        (
+            "getLicense",
+            get(handlers::get_license).post(handlers::get_license),
+        ),
+        (
+            "getOpenSubsonicExtensions",
+            get(handlers::get_open_subsonic_extensions)
+                .post(handlers::get_open_subsonic_extensions),
+        ),
+        (
+            "getMusicFolders",
+            get(handlers::get_music_folders).post(handlers::get_music_folders),
+        ),
+        (
+            "getIndexes",
+            get(handlers::get_indexes).post(handlers::get_indexes),
+        ),
+        (
+            "getMusicDirectory",
+            get(handlers::get_music_directory).post(handlers::get_music_directory),
+        ),
+        (
+            "getArtists",
+            get(handlers::get_artists).post(handlers::get_artists),
+        ),
+        (
+            "getArtist",
+            get(handlers::get_artist).post(handlers::get_artist),
+        ),
+        (
+            "getAlbum",
+            get(handlers::get_album).post(handlers::get_album),
+        ),
+        ("getSong", get(handlers::get_song).post(handlers::get_song)),
+        (
+            "getAlbumList",
+            get(handlers::get_album_list).post(handlers::get_album_list),
+        ),
+        (
+            "getAlbumList2",
+            get(handlers::get_album_list2).post(handlers::get_album_list2),
+        ),
+        (
+            "getStarred2",
+            get(handlers::get_starred2).post(handlers::get_starred2),
+        ),
+        ("search3", get(handlers::search3).post(handlers::search3)),
+        (
+            "stream",
+            get(handlers::stream)
+                .head(handlers::stream)
+                .post(handlers::stream),
+        ),
+        ("download", get(handlers::download).post(handlers::download)),
+        (
+            "getCoverArt",
+            get(handlers::get_cover_art).post(handlers::get_cover_art),
+        ),
+        ("scrobble", get(handlers::scrobble).post(handlers::scrobble)),
+        ("getUser", get(handlers::get_user).post(handlers::get_user)),
+    ];
+    for (name, handler) in methods {
+        router = view(router, name, handler);
+    }
+
+    router
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::authenticate,
+        ))
+        .layer(middleware::from_fn(response::render))
+        .with_state(state)
+}
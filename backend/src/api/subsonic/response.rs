@@ -0,0 +1,272 @@
+//! Subsonic response envelope
+//!
+//! Handlers return a [`Payload`] or a [`SubsonicError`]; [`render`] wraps either
+//! in `subsonic-response` and writes it as XML or JSON depending on the `f`
+//! parameter. Subsonic reports errors inside a `200 OK` body, so the HTTP
+//! status is always 200 for API calls. Binary responses (audio, covers) pass
+//! through untouched.
+
+use super::SUBSONIC_API_VERSION;
+use crate::core::error::TingError;
+use axum::{
+    extract::Request,
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use quick_xml::escape::escape;
+use serde_json::{Map, Value};
+
+const SUBSONIC_XMLNS: &str = "http://subsonic.org/restapi";
+
+/// Generic error
+pub const ERROR_GENERIC: u32 = 0;
+/// Required parameter is missing
+pub const ERROR_MISSING_PARAMETER: u32 = 10;
+/// Wrong username or password
+pub const ERROR_WRONG_CREDENTIALS: u32 = 40;
+/// User is not authorized for the given operation
+pub const ERROR_NOT_AUTHORIZED: u32 = 50;
+/// The requested data was not found
+pub const ERROR_NOT_FOUND: u32 = 70;
+
+/// Successful response body, merged into `subsonic-response`
+#[derive(Debug, Clone, Default)]
+pub struct Payload(pub Map<String, Value>);
+
+impl Payload {
+    /// A response with no content besides the status
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// A response with a single top-level element
+    pub fn new(key: &str, value: Value) -> Self {
+        let mut body = Map::new();
+        body.insert(key.to_string(), value);
+        Self(body)
+    }
+}
+
+/// Failed response carrying a Subsonic error code
+#[derive(Debug, Clone)]
+pub struct SubsonicError {
+    pub code: u32,
+    pub message: String,
+}
+
+impl SubsonicError {
+    pub fn new(code: u32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn missing(param: &str) -> Self {
+        Self::new(
+            ERROR_MISSING_PARAMETER,
+            format!("Required parameter is missing: {}", param),
+        )
+    }
+}
+
+impl From<TingError> for SubsonicError {
+    fn from(error: TingError) -> Self {
+        let code = match &error {
+            TingError::NotFound(_) => ERROR_NOT_FOUND,
+            TingError::PermissionDenied(_) => ERROR_NOT_AUTHORIZED,
+            TingError::AuthenticationError(_) => ERROR_WRONG_CREDENTIALS,
+            TingError::InvalidRequest(_) | TingError::ValidationError(_) => ERROR_MISSING_PARAMETER,
+            _ => ERROR_GENERIC,
+        };
+        Self::new(code, error.to_string())
+    }
+}
+
+pub type SubsonicResult = std::result::Result<Payload, SubsonicError>;
+
+// Both types render as an empty response tagged with the body; `render` reads
+// the tag back once the requested format is known.
+impl IntoResponse for Payload {
+    fn into_response(self) -> Response {
+        let mut response = StatusCode::OK.into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+impl IntoResponse for SubsonicError {
+    fn into_response(self) -> Response {
+        let mut response = StatusCode::OK.into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Xml,
+    Json,
+}
+
+fn request_format(query: Option<&str>) -> Format {
+    let format = query.and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "f")
+            .map(|(_, value)| value.into_owned())
+    });
+    match format.as_deref() {
+        Some("json") | Some("jsonp") => Format::Json,
+        _ => Format::Xml,
+    }
+}
+
+/// Middleware writing handler results in the format the client asked for
+pub async fn render(request: Request, next: Next) -> Response {
+    let format = request_format(request.uri().query());
+    let mut response = next.run(request).await;
+
+    let body = if let Some(payload) = response.extensions_mut().remove::<Payload>() {
+        envelope("ok", payload.0)
+    } else if let Some(error) = response.extensions_mut().remove::<SubsonicError>() {
+        let mut body = Map::new();
+        body.insert(
+            "error".to_string(),
+            serde_json::json!({ "code": error.code, "message": error.message }),
+        );
+        envelope("failed", body)
+    } else {
+        return response;
+    };
+
+    let (content_type, body) = match format {
+        Format::Json => (
+            "application/json",
+            serde_json::json!({ "subsonic-response": without_nulls(Value::Object(body)) })
+                .to_string(),
+        ),
+        Format::Xml => ("text/xml; charset=UTF-8", to_xml(&body)),
+    };
+    ([(header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+fn envelope(status: &str, body: Map<String, Value>) -> Map<String, Value> {
+    let mut envelope = Map::new();
+    envelope.insert("status".to_string(), Value::from(status));
+    envelope.insert("version".to_string(), Value::from(SUBSONIC_API_VERSION));
+    envelope.insert("type".to_string(), Value::from("ting-reader"));
+    envelope.insert(
+        "serverVersion".to_string(),
+        Value::from(env!("CARGO_PKG_VERSION")),
+    );
+    envelope.insert("openSubsonic".to_string(), Value::from(true));
+    envelope.extend(body);
+    envelope
+}
+
+/// Drop null fields, which Subsonic leaves out rather than sending empty
+fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, without_nulls(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(without_nulls).collect()),
+        other => other,
+    }
+}
+
+/// Write the JSON form of a response as Subsonic XML.
+///
+/// Scalars become attributes, objects become child elements and arrays become
+/// one child element per entry, all named after their key.
+fn to_xml(body: &Map<String, Value>) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let mut root = Map::new();
+    root.insert("xmlns".to_string(), Value::from(SUBSONIC_XMLNS));
+    root.extend(body.clone());
+    write_element(&mut xml, "subsonic-response", &root);
+    xml
+}
+
+fn write_element(xml: &mut String, name: &str, fields: &Map<String, Value>) {
+    xml.push('<');
+    xml.push_str(name);
+    for (key, value) in fields {
+        let text = match value {
+            Value::String(text) => text.clone(),
+            Value::Bool(_) | Value::Number(_) => value.to_string(),
+            _ => continue,
+        };
+        xml.push_str(&format!(r#" {}="{}""#, key, escape(&text)));
+    }
+
+    let mut children = String::new();
+    for (key, value) in fields {
+        match value {
+            Value::Object(child) => write_element(&mut children, key, child),
+            Value::Array(items) => {
+                for item in items {
+                    match item {
+                        Value::Object(child) => write_element(&mut children, key, child),
+                        Value::String(_) | Value::Bool(_) | Value::Number(_) => {
+                            let text = item
+                                .as_str()
+                                .map_or_else(|| item.to_string(), str::to_string);
+                            children.push_str(&format!("<{0}>{1}</{0}>", key, escape(&text)));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if children.is_empty() {
+        xml.push_str("/>");
+    } else {
+        xml.push('>');
+        xml.push_str(&children);
+        xml.push_str(&format!("</{}>", name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_body_maps_to_subsonic_xml() {
+        let payload = Payload::new(
+            "album",
+            serde_json::json!({
+                "id": "book-1",
+                "name": "A & B",
+                "songCount": 2,
+                "year": null,
+                "folder": ["lib-1"],
+                "song": [{ "id": "c1" }, { "id": "c2" }],
+            }),
+        );
+        let xml = to_xml(&envelope("ok", payload.0));
+        assert!(xml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?><subsonic-response"#));
+        assert!(xml.contains(r#"xmlns="http://subsonic.org/restapi""#));
+        assert!(xml.contains(r#"status="ok""#));
+        assert!(xml.contains(
+            r#"<album id="book-1" name="A &amp; B" songCount="2"><folder>lib-1</folder><song id="c1"/><song id="c2"/></album>"#
+        ));
+        assert!(xml.ends_with("</subsonic-response>"));
+    }
+
+    #[test]
+    fn format_follows_the_f_parameter() {
+        assert_eq!(request_format(Some("u=a&f=json")), Format::Json);
+        assert_eq!(request_format(Some("u=a&f=xml")), Format::Xml);
+        assert_eq!(request_format(None), Format::Xml);
+    }
+}
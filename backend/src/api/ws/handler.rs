@@ -1,7 +1,9 @@
-//! WebSocket handler for real-time progress sync
+//! WebSocket handler for real-time progress, bookmark and sleep timer sync, and
+//! for remote control between a user's devices
 
 use crate::api::handlers::AppState;
-use crate::api::ws::manager::WsSessionManager;
+use crate::api::models::{BookmarkResponse, SleepTimerState};
+use crate::api::ws::manager::{DeviceInfo, WsSessionManager};
 use crate::auth::jwt;
 use crate::core::error::TingError;
 use crate::db::models::Progress;
@@ -17,6 +19,7 @@ use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
 use tracing::{debug, warn};
 
 /// WebSocket query parameters (token auth)
@@ -36,10 +39,45 @@ enum ClientMessage {
         position: f64,
         playback_start: Option<f64>,
     },
+    /// Name this connection so other devices can control it
+    #[serde(rename = "register_device")]
+    RegisterDevice {
+        device_id: String,
+        name: String,
+        device_type: Option<String>,
+    },
+    #[serde(rename = "device_command")]
+    DeviceCommand {
+        target_device_id: String,
+        #[serde(flatten)]
+        command: DeviceCommand,
+    },
     #[serde(rename = "ping")]
     Ping,
 }
 
+/// Remote-control command sent from one device to another
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum DeviceCommand {
+    Play,
+    Pause,
+    Seek {
+        position: f64,
+    },
+    Next,
+    /// Continue the user's latest playback on the target device
+    Handoff,
+}
+
+/// Where a handed-off device should start playing
+#[derive(Serialize, Debug)]
+struct HandoffPlayback {
+    book_id: String,
+    chapter_id: Option<String>,
+    position: f64,
+}
+
 /// Server → Client message
 #[derive(Serialize, Debug)]
 #[serde(tag = "type")]
@@ -51,12 +89,122 @@ enum ServerMessage {
         position: f64,
         updated_at: String,
     },
+    #[serde(rename = "bookmark_changed")]
+    BookmarkChanged {
+        action: BookmarkAction,
+        bookmark: BookmarkResponse,
+    },
+    #[serde(rename = "sleep_timer_updated")]
+    SleepTimerUpdated { timer: Option<SleepTimerState> },
+    #[serde(rename = "pause")]
+    Pause {
+        reason: PauseReason,
+        book_id: String,
+        chapter_id: Option<String>,
+        position: f64,
+    },
+    #[serde(rename = "devices_updated")]
+    DevicesUpdated {
+        devices: Vec<DeviceInfo>,
+        active_device_id: Option<String>,
+    },
+    #[serde(rename = "device_command")]
+    DeviceCommand {
+        from_device_id: Option<String>,
+        #[serde(flatten)]
+        command: DeviceCommand,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        playback: Option<HandoffPlayback>,
+    },
     #[serde(rename = "pong")]
     Pong,
     #[serde(rename = "error")]
     Error { message: String },
 }
 
+/// What happened to a bookmark announced through `bookmark_changed`
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum BookmarkAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// Push a bookmark change to all sessions of the user (including other devices)
+pub async fn broadcast_bookmark_change(
+    ws_manager: &WsSessionManager,
+    user_id: &str,
+    action: BookmarkAction,
+    bookmark: BookmarkResponse,
+) {
+    let message = serde_json::to_string(&ServerMessage::BookmarkChanged { action, bookmark })
+        .unwrap_or_default();
+    ws_manager.broadcast(user_id, &message).await;
+}
+
+/// Why the server asked every device to pause
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseReason {
+    SleepTimer,
+}
+
+/// Push the current sleep timer, or `None` once it is gone, to all sessions of the user
+pub async fn broadcast_sleep_timer(
+    ws_manager: &WsSessionManager,
+    user_id: &str,
+    timer: Option<SleepTimerState>,
+) {
+    let message =
+        serde_json::to_string(&ServerMessage::SleepTimerUpdated { timer }).unwrap_or_default();
+    ws_manager.broadcast(user_id, &message).await;
+}
+
+/// Tell every session of the user to pause at the given position
+pub async fn broadcast_pause(
+    ws_manager: &WsSessionManager,
+    user_id: &str,
+    reason: PauseReason,
+    progress: &Progress,
+) {
+    let message = serde_json::to_string(&ServerMessage::Pause {
+        reason,
+        book_id: progress.book_id.clone(),
+        chapter_id: progress.chapter_id.clone(),
+        position: progress.position,
+    })
+    .unwrap_or_default();
+    ws_manager.broadcast(user_id, &message).await;
+}
+
+/// Push the user's connected devices and active player to all of their sessions
+async fn broadcast_devices(ws_manager: &WsSessionManager, user_id: &str) {
+    let (devices, active_device_id) = ws_manager.list_devices(user_id).await;
+    let message = serde_json::to_string(&ServerMessage::DevicesUpdated {
+        devices,
+        active_device_id,
+    })
+    .unwrap_or_default();
+    ws_manager.broadcast(user_id, &message).await;
+}
+
+/// Push a stored position to every session of the user
+pub async fn broadcast_progress_update(
+    ws_manager: &WsSessionManager,
+    user_id: &str,
+    progress: &Progress,
+) {
+    let message = serde_json::to_string(&ServerMessage::ProgressUpdated {
+        book_id: progress.book_id.clone(),
+        chapter_id: progress.chapter_id.clone(),
+        position: progress.position,
+        updated_at: progress.updated_at.clone(),
+    })
+    .unwrap_or_default();
+    ws_manager.broadcast(user_id, &message).await;
+}
+
 /// Handle WebSocket upgrade request
 pub async fn ws_handler(
     State(state): State<AppState>,
@@ -110,6 +258,21 @@ async fn authenticate_ws_token(
     }
 }
 
+/// One WebSocket connection of a user
+struct Connection {
+    id: u64,
+    /// Messages for this connection only
+    sender: mpsc::UnboundedSender<String>,
+}
+
+impl Connection {
+    fn reply(&self, message: &ServerMessage) {
+        let _ = self
+            .sender
+            .send(serde_json::to_string(message).unwrap_or_default());
+    }
+}
+
 /// Handle an established WebSocket connection
 async fn handle_ws_connection(socket: WebSocket, state: AppState, user_id: String) {
     debug!("WebSocket connection established: user={}", &user_id);
@@ -118,28 +281,39 @@ async fn handle_ws_connection(socket: WebSocket, state: AppState, user_id: Strin
 
     // Subscribe to broadcast channel for this user's progress updates
     let mut broadcast_rx = ws_manager.subscribe(&user_id).await;
+    let (direct_tx, mut direct_rx) = mpsc::unbounded_channel::<String>();
+    let connection = Connection {
+        id: ws_manager.next_connection_id(),
+        sender: direct_tx,
+    };
+    let connection_id = connection.id;
 
     let user_id_send = user_id.clone();
     let user_id_recv = user_id.clone();
+    let ws_manager_recv = ws_manager.clone();
 
-    // Task: forward broadcast messages to the WebSocket client
+    // Task: forward broadcast and direct messages to the WebSocket client
     let send_task: tokio::task::JoinHandle<()> = tokio::spawn(async move {
         loop {
-            match broadcast_rx.recv().await {
-                Ok(msg) => {
-                    if sender.send(Message::Text(msg.into())).await.is_err() {
-                        break;
+            let msg = tokio::select! {
+                msg = broadcast_rx.recv() => match msg {
+                    Ok(msg) => msg,
+                    Err(RecvError::Lagged(n)) => {
+                        debug!(
+                            "WS broadcast lagged by {} messages for user {}",
+                            n, &user_id_send
+                        );
+                        continue;
                     }
-                }
-                Err(RecvError::Lagged(n)) => {
-                    debug!(
-                        "WS broadcast lagged by {} messages for user {}",
-                        n, &user_id_send
-                    );
-                }
-                Err(RecvError::Closed) => {
-                    break;
-                }
+                    Err(RecvError::Closed) => break,
+                },
+                msg = direct_rx.recv() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+            };
+            if sender.send(Message::Text(msg)).await.is_err() {
+                break;
             }
         }
     });
@@ -149,7 +323,14 @@ async fn handle_ws_connection(socket: WebSocket, state: AppState, user_id: Strin
         while let Some(msg) = receiver.next().await {
             match msg {
                 Ok(Message::Text(text)) => {
-                    handle_client_message(&state, &ws_manager, &user_id_recv, &text).await;
+                    handle_client_message(
+                        &state,
+                        &ws_manager_recv,
+                        &user_id_recv,
+                        &connection,
+                        &text,
+                    )
+                    .await;
                 }
                 Ok(Message::Close(_)) => {
                     break;
@@ -169,6 +350,9 @@ async fn handle_ws_connection(socket: WebSocket, state: AppState, user_id: Strin
         _ = recv_task => {},
     }
 
+    if ws_manager.unregister_device(&user_id, connection_id).await {
+        broadcast_devices(&ws_manager, &user_id).await;
+    }
     debug!("WebSocket connection closed: user={}", &user_id);
 }
 
@@ -177,17 +361,16 @@ async fn handle_client_message(
     state: &AppState,
     ws_manager: &Arc<WsSessionManager>,
     user_id: &str,
+    connection: &Connection,
     text: &str,
 ) {
     let msg: ClientMessage = match serde_json::from_str(text) {
         Ok(m) => m,
         Err(e) => {
             warn!("Invalid WS message from user {}: {}", user_id, e);
-            let error = serde_json::to_string(&ServerMessage::Error {
+            connection.reply(&ServerMessage::Error {
                 message: format!("Invalid message: {}", e),
-            })
-            .unwrap_or_default();
-            ws_manager.broadcast(user_id, &error).await;
+            });
             return;
         }
     };
@@ -232,6 +415,11 @@ async fn handle_client_message(
                 return;
             }
 
+            state
+                .sleep_timers
+                .record_position(user_id, &book_id, chapter_id.as_deref(), position)
+                .await;
+
             if let Some(playback_start) = playback_start {
                 crate::api::playback_audit::record_playback_start(
                     state,
@@ -244,19 +432,181 @@ async fn handle_client_message(
             }
 
             // Broadcast to all sessions of this user (including other devices)
-            let broadcast_msg = serde_json::to_string(&ServerMessage::ProgressUpdated {
-                book_id,
-                chapter_id,
-                position,
-                updated_at: progress.updated_at,
-            })
-            .unwrap_or_default();
+            broadcast_progress_update(ws_manager, user_id, &progress).await;
 
-            ws_manager.broadcast(user_id, &broadcast_msg).await;
+            // A registered device reporting progress is the one playing
+            if let Some(device) = ws_manager.device_of(user_id, connection.id).await {
+                if ws_manager
+                    .set_active_device(user_id, &device.device_id)
+                    .await
+                {
+                    broadcast_devices(ws_manager, user_id).await;
+                }
+            }
+        }
+        ClientMessage::RegisterDevice {
+            device_id,
+            name,
+            device_type,
+        } => {
+            let device_id = device_id.trim();
+            let name = name.trim();
+            if device_id.is_empty() || name.is_empty() {
+                connection.reply(&ServerMessage::Error {
+                    message: "device_id and name must not be empty".to_string(),
+                });
+                return;
+            }
+            let info = DeviceInfo {
+                device_id: device_id.to_string(),
+                name: name.to_string(),
+                device_type: device_type
+                    .map(|device_type| device_type.trim().to_string())
+                    .filter(|device_type| !device_type.is_empty()),
+                connected_at: chrono::Utc::now().to_rfc3339(),
+            };
+            ws_manager
+                .register_device(user_id, connection.id, info, connection.sender.clone())
+                .await;
+            broadcast_devices(ws_manager, user_id).await;
+        }
+        ClientMessage::DeviceCommand {
+            target_device_id,
+            command,
+        } => {
+            if let Err(message) = send_device_command(
+                state,
+                ws_manager,
+                user_id,
+                connection,
+                &target_device_id,
+                command,
+            )
+            .await
+            {
+                connection.reply(&ServerMessage::Error { message });
+            }
         }
         ClientMessage::Ping => {
-            let pong = serde_json::to_string(&ServerMessage::Pong).unwrap_or_default();
-            ws_manager.broadcast(user_id, &pong).await;
+            connection.reply(&ServerMessage::Pong);
         }
     }
 }
+
+/// Deliver a remote-control command and keep track of the active player.
+///
+/// `play` and `handoff` make the target the active player; a handoff also
+/// pauses the device that was playing before.
+async fn send_device_command(
+    state: &AppState,
+    ws_manager: &WsSessionManager,
+    user_id: &str,
+    connection: &Connection,
+    target_device_id: &str,
+    command: DeviceCommand,
+) -> std::result::Result<(), String> {
+    let from_device_id = ws_manager
+        .device_of(user_id, connection.id)
+        .await
+        .map(|device| device.device_id);
+
+    let playback = if command == DeviceCommand::Handoff {
+        let latest = state
+            .progress_repo
+            .get_recent(user_id, Some(1))
+            .await
+            .map_err(|e| e.to_string())?;
+        let latest = latest
+            .into_iter()
+            .next()
+            .ok_or_else(|| "There is no playback to hand off".to_string())?;
+        Some(HandoffPlayback {
+            book_id: latest.book_id,
+            chapter_id: latest.chapter_id,
+            position: latest.position,
+        })
+    } else {
+        None
+    };
+    let previous_device_id = ws_manager.active_device(user_id).await;
+
+    let message = serde_json::to_string(&ServerMessage::DeviceCommand {
+        from_device_id: from_device_id.clone(),
+        command,
+        playback,
+    })
+    .unwrap_or_default();
+    if !ws_manager
+        .send_to_device(user_id, target_device_id, &message)
+        .await
+    {
+        return Err(format!("Device {} is not connected", target_device_id));
+    }
+
+    if matches!(command, DeviceCommand::Play | DeviceCommand::Handoff) {
+        if command == DeviceCommand::Handoff {
+            if let Some(previous) = previous_device_id.filter(|id| id != target_device_id) {
+                let pause = serde_json::to_string(&ServerMessage::DeviceCommand {
+                    from_device_id,
+                    command: DeviceCommand::Pause,
+                    playback: None,
+                })
+                .unwrap_or_default();
+                ws_manager.send_to_device(user_id, &previous, &pause).await;
+            }
+        }
+        if ws_manager
+            .set_active_device(user_id, target_device_id)
+            .await
+        {
+            broadcast_devices(ws_manager, user_id).await;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_device_commands() {
+        let message: ClientMessage = serde_json::from_str(
+            r#"{"type":"device_command","target_device_id":"speaker","command":"seek","position":42.5}"#,
+        )
+        .unwrap();
+        match message {
+            ClientMessage::DeviceCommand {
+                target_device_id,
+                command,
+            } => {
+                assert_eq!(target_device_id, "speaker");
+                assert_eq!(command, DeviceCommand::Seek { position: 42.5 });
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn serializes_handoff_with_playback() {
+        let message = serde_json::to_value(ServerMessage::DeviceCommand {
+            from_device_id: Some("phone".to_string()),
+            command: DeviceCommand::Handoff,
+            playback: Some(HandoffPlayback {
+                book_id: "book-1".to_string(),
+                chapter_id: Some("chapter-1".to_string()),
+                position: 12.0,
+            }),
+        })
+        .unwrap();
+        assert_eq!(
+            message,
+            serde_json::json!({
+                "type": "device_command",
+                "from_device_id": "phone",
+                "command": "handoff",
+                "playback": {"book_id": "book-1", "chapter_id": "chapter-1", "position": 12.0},
+            })
+        );
+    }
+}
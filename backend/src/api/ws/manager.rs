@@ -1,27 +1,170 @@
 //! WebSocket session manager
 //!
 //! Tracks connected WebSocket sessions per user and broadcasts progress updates.
+//! Connections that register as a named device can also be addressed one by one,
+//! and the manager remembers which of a user's devices is the active player.
 
+use serde::Serialize;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, mpsc, RwLock};
 
 /// Maximum number of queued messages per user's broadcast channel
 const BROADCAST_CAPACITY: usize = 64;
 
+/// A connection registered as a playback device
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DeviceInfo {
+    /// Stable id chosen by the client, e.g. per installation
+    pub device_id: String,
+    pub name: String,
+    /// Free-form hint for clients, e.g. `phone`, `web`, `speaker`
+    pub device_type: Option<String>,
+    pub connected_at: String,
+}
+
+struct ConnectedDevice {
+    info: DeviceInfo,
+    sender: mpsc::UnboundedSender<String>,
+}
+
+#[derive(Default)]
+struct UserDevices {
+    /// Map of connection id → registered device
+    connections: HashMap<u64, ConnectedDevice>,
+    active_device_id: Option<String>,
+}
+
+impl UserDevices {
+    fn find(&self, device_id: &str) -> Option<&ConnectedDevice> {
+        self.connections
+            .values()
+            .find(|device| device.info.device_id == device_id)
+    }
+}
+
 /// Manages WebSocket sessions grouped by user ID
 pub struct WsSessionManager {
     /// Map of user_id → broadcast sender for progress updates
     channels: RwLock<HashMap<String, broadcast::Sender<String>>>,
+    /// Map of user_id → devices registered by that user's connections
+    devices: RwLock<HashMap<String, UserDevices>>,
+    next_connection_id: AtomicU64,
 }
 
 impl WsSessionManager {
     pub fn new() -> Arc<Self> {
         Arc::new(Self {
             channels: RwLock::new(HashMap::new()),
+            devices: RwLock::new(HashMap::new()),
+            next_connection_id: AtomicU64::new(1),
         })
     }
 
+    /// Id for a new connection, unique for the lifetime of the process
+    pub fn next_connection_id(&self) -> u64 {
+        self.next_connection_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Register a connection as a named device.
+    ///
+    /// A device id registered by an older connection moves to this one.
+    pub async fn register_device(
+        &self,
+        user_id: &str,
+        connection_id: u64,
+        info: DeviceInfo,
+        sender: mpsc::UnboundedSender<String>,
+    ) {
+        let mut devices = self.devices.write().await;
+        let user_devices = devices.entry(user_id.to_string()).or_default();
+        user_devices
+            .connections
+            .retain(|id, device| *id == connection_id || device.info.device_id != info.device_id);
+        user_devices
+            .connections
+            .insert(connection_id, ConnectedDevice { info, sender });
+    }
+
+    /// Forget the device of a closed connection. Returns whether it was registered.
+    pub async fn unregister_device(&self, user_id: &str, connection_id: u64) -> bool {
+        let mut devices = self.devices.write().await;
+        let Some(user_devices) = devices.get_mut(user_id) else {
+            return false;
+        };
+        let Some(removed) = user_devices.connections.remove(&connection_id) else {
+            return false;
+        };
+        if user_devices.active_device_id.as_deref() == Some(removed.info.device_id.as_str())
+            && user_devices.find(&removed.info.device_id).is_none()
+        {
+            user_devices.active_device_id = None;
+        }
+        if user_devices.connections.is_empty() {
+            devices.remove(user_id);
+        }
+        true
+    }
+
+    /// Device registered by a connection
+    pub async fn device_of(&self, user_id: &str, connection_id: u64) -> Option<DeviceInfo> {
+        let devices = self.devices.read().await;
+        devices
+            .get(user_id)
+            .and_then(|user_devices| user_devices.connections.get(&connection_id))
+            .map(|device| device.info.clone())
+    }
+
+    /// Connected devices of a user, oldest connection first, and the active player
+    pub async fn list_devices(&self, user_id: &str) -> (Vec<DeviceInfo>, Option<String>) {
+        let devices = self.devices.read().await;
+        let Some(user_devices) = devices.get(user_id) else {
+            return (Vec::new(), None);
+        };
+        let mut connections = user_devices.connections.iter().collect::<Vec<_>>();
+        connections.sort_by_key(|(id, _)| **id);
+        (
+            connections
+                .into_iter()
+                .map(|(_, device)| device.info.clone())
+                .collect(),
+            user_devices.active_device_id.clone(),
+        )
+    }
+
+    /// The device currently playing for the user
+    pub async fn active_device(&self, user_id: &str) -> Option<String> {
+        let devices = self.devices.read().await;
+        devices
+            .get(user_id)
+            .and_then(|user_devices| user_devices.active_device_id.clone())
+    }
+
+    /// Mark a connected device as the active player. Returns whether anything changed.
+    pub async fn set_active_device(&self, user_id: &str, device_id: &str) -> bool {
+        let mut devices = self.devices.write().await;
+        let Some(user_devices) = devices.get_mut(user_id) else {
+            return false;
+        };
+        if user_devices.find(device_id).is_none()
+            || user_devices.active_device_id.as_deref() == Some(device_id)
+        {
+            return false;
+        }
+        user_devices.active_device_id = Some(device_id.to_string());
+        true
+    }
+
+    /// Send a message to one device of a user. Returns false when it is not connected.
+    pub async fn send_to_device(&self, user_id: &str, device_id: &str, message: &str) -> bool {
+        let devices = self.devices.read().await;
+        devices
+            .get(user_id)
+            .and_then(|user_devices| user_devices.find(device_id))
+            .is_some_and(|device| device.sender.send(message.to_string()).is_ok())
+    }
+
     /// Subscribe to progress updates for a user.
     /// Returns a receiver that will get all progress update messages for this user.
     pub async fn subscribe(&self, user_id: &str) -> broadcast::Receiver<String> {
@@ -69,3 +212,68 @@ impl WsSessionManager {
         (total_channels, active_receivers)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(device_id: &str) -> DeviceInfo {
+        DeviceInfo {
+            device_id: device_id.to_string(),
+            name: device_id.to_string(),
+            device_type: None,
+            connected_at: "2024-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_messages_to_registered_devices() {
+        let manager = WsSessionManager::new();
+        let (phone_tx, mut phone_rx) = mpsc::unbounded_channel();
+        let (speaker_tx, _speaker_rx) = mpsc::unbounded_channel();
+        manager
+            .register_device("user-1", 1, device("phone"), phone_tx)
+            .await;
+        manager
+            .register_device("user-1", 2, device("speaker"), speaker_tx)
+            .await;
+
+        assert!(manager.send_to_device("user-1", "phone", "hello").await);
+        assert_eq!(phone_rx.recv().await.as_deref(), Some("hello"));
+        assert!(!manager.send_to_device("user-1", "car", "hello").await);
+        assert!(!manager.send_to_device("user-2", "phone", "hello").await);
+
+        assert!(manager.set_active_device("user-1", "speaker").await);
+        assert!(!manager.set_active_device("user-1", "speaker").await);
+        assert!(!manager.set_active_device("user-1", "car").await);
+        let (devices, active) = manager.list_devices("user-1").await;
+        assert_eq!(devices, vec![device("phone"), device("speaker")]);
+        assert_eq!(active.as_deref(), Some("speaker"));
+
+        assert!(manager.unregister_device("user-1", 2).await);
+        assert!(!manager.unregister_device("user-1", 2).await);
+        assert_eq!(manager.active_device("user-1").await, None);
+    }
+
+    #[tokio::test]
+    async fn reconnecting_device_replaces_its_old_connection() {
+        let manager = WsSessionManager::new();
+        let (old_tx, _old_rx) = mpsc::unbounded_channel();
+        let (new_tx, _new_rx) = mpsc::unbounded_channel();
+        manager
+            .register_device("user-1", 1, device("phone"), old_tx)
+            .await;
+        manager.set_active_device("user-1", "phone").await;
+        manager
+            .register_device("user-1", 2, device("phone"), new_tx)
+            .await;
+
+        // The stale connection closing must not drop the device or its active state
+        assert!(!manager.unregister_device("user-1", 1).await);
+        assert_eq!(manager.list_devices("user-1").await.0.len(), 1);
+        assert_eq!(
+            manager.active_device("user-1").await.as_deref(),
+            Some("phone")
+        );
+    }
+}
@@ -1,2 +1,3 @@
 pub mod handler;
 pub mod manager;
+pub mod sleep_timer;
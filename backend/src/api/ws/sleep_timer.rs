@@ -0,0 +1,407 @@
+//! Server-side sleep timers
+//!
+//! Each user has at most one timer, shared by all of their devices. Progress
+//! reports from any device move chapter timers along; when a timer runs out
+//! every connected session is told to pause and the stop position is stored as
+//! progress. Timers live in memory and do not survive a restart.
+
+use crate::api::models::{SleepTimerMode, SleepTimerState};
+use crate::api::ws::handler::{broadcast_pause, broadcast_sleep_timer, PauseReason};
+use crate::api::ws::manager::WsSessionManager;
+use crate::core::error::{Result, TingError};
+use crate::db::models::Progress;
+use crate::db::repository::ProgressRepository;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::task::AbortHandle;
+use tracing::warn;
+
+/// Longest duration timer, in seconds
+pub const MAX_SLEEP_TIMER_SECS: u64 = 24 * 60 * 60;
+/// Most chapters a chapter timer can wait for
+pub const MAX_SLEEP_TIMER_CHAPTERS: u32 = 100;
+/// Reports older than this are taken to come from a paused player, whose
+/// position has not moved since
+const PLAYING_REPORT_GAP: Duration = Duration::from_secs(60);
+
+/// Last position a device reported for the user
+#[derive(Debug, Clone)]
+struct ReportedPosition {
+    book_id: String,
+    chapter_id: Option<String>,
+    position: f64,
+    reported_at: Instant,
+}
+
+impl ReportedPosition {
+    /// Where playback is at `now`, assuming the player kept playing since the
+    /// report unless the report is too old for that
+    fn position_at(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.reported_at);
+        if elapsed > PLAYING_REPORT_GAP {
+            self.position
+        } else {
+            self.position + elapsed.as_secs_f64()
+        }
+    }
+}
+
+struct ActiveTimer {
+    /// Tells a stale duration task apart from the timer that replaced it
+    generation: u64,
+    state: SleepTimerState,
+    /// Chapter being played when the count last moved
+    chapter_id: Option<String>,
+    task: Option<AbortHandle>,
+}
+
+#[derive(Default)]
+struct PlaybackSession {
+    position: Option<ReportedPosition>,
+    timer: Option<ActiveTimer>,
+}
+
+/// Holds sleep timers and the playback position they act on
+pub struct SleepTimerManager {
+    ws_manager: Arc<WsSessionManager>,
+    progress_repo: Arc<ProgressRepository>,
+    sessions: Mutex<HashMap<String, PlaybackSession>>,
+    generation: AtomicU64,
+}
+
+impl SleepTimerManager {
+    pub fn new(
+        ws_manager: Arc<WsSessionManager>,
+        progress_repo: Arc<ProgressRepository>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            ws_manager,
+            progress_repo,
+            sessions: Mutex::new(HashMap::new()),
+            generation: AtomicU64::new(0),
+        })
+    }
+
+    /// The user's running timer
+    pub async fn get(&self, user_id: &str) -> Option<SleepTimerState> {
+        let sessions = self.sessions.lock().await;
+        sessions
+            .get(user_id)
+            .and_then(|session| session.timer.as_ref())
+            .map(|timer| timer.state.clone())
+    }
+
+    /// Start a timer for the user, replacing any running one
+    pub async fn start(
+        self: &Arc<Self>,
+        user_id: &str,
+        mode: SleepTimerMode,
+    ) -> Result<SleepTimerState> {
+        let now = chrono::Utc::now();
+        let (expires_at, chapters_remaining) = match mode {
+            SleepTimerMode::Duration { seconds } => {
+                if seconds == 0 || seconds > MAX_SLEEP_TIMER_SECS {
+                    return Err(TingError::ValidationError(format!(
+                        "seconds must be between 1 and {}",
+                        MAX_SLEEP_TIMER_SECS
+                    )));
+                }
+                (Some(now + chrono::Duration::seconds(seconds as i64)), None)
+            }
+            SleepTimerMode::EndOfChapter => (None, Some(1)),
+            SleepTimerMode::Chapters { count } => {
+                if count == 0 || count > MAX_SLEEP_TIMER_CHAPTERS {
+                    return Err(TingError::ValidationError(format!(
+                        "count must be between 1 and {}",
+                        MAX_SLEEP_TIMER_CHAPTERS
+                    )));
+                }
+                (None, Some(count))
+            }
+        };
+        let state = SleepTimerState {
+            mode,
+            started_at: now.to_rfc3339(),
+            expires_at: expires_at.map(|at| at.to_rfc3339()),
+            chapters_remaining,
+        };
+
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        let task = if let SleepTimerMode::Duration { seconds } = mode {
+            let manager = self.clone();
+            let user_id = user_id.to_string();
+            let handle = tokio::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_secs(seconds)).await;
+                manager.expire(&user_id, generation, None).await;
+            });
+            Some(handle.abort_handle())
+        } else {
+            None
+        };
+
+        {
+            let mut sessions = self.sessions.lock().await;
+            let session = sessions.entry(user_id.to_string()).or_default();
+            let chapter_id = session
+                .position
+                .as_ref()
+                .and_then(|position| position.chapter_id.clone());
+            let replaced = session.timer.replace(ActiveTimer {
+                generation,
+                state: state.clone(),
+                chapter_id,
+                task,
+            });
+            if let Some(task) = replaced.and_then(|timer| timer.task) {
+                task.abort();
+            }
+        }
+
+        broadcast_sleep_timer(&self.ws_manager, user_id, Some(state.clone())).await;
+        Ok(state)
+    }
+
+    /// Stop the user's timer without pausing playback. Returns false when none was running.
+    pub async fn cancel(&self, user_id: &str) -> bool {
+        let timer = {
+            let mut sessions = self.sessions.lock().await;
+            sessions
+                .get_mut(user_id)
+                .and_then(|session| session.timer.take())
+        };
+        let Some(timer) = timer else {
+            return false;
+        };
+        if let Some(task) = timer.task {
+            task.abort();
+        }
+        broadcast_sleep_timer(&self.ws_manager, user_id, None).await;
+        true
+    }
+
+    /// Note a position reported by one of the user's devices.
+    ///
+    /// Moving to another chapter ends the chapter that was playing; once a
+    /// chapter timer has seen its last chapter end, playback stops at the start
+    /// of the next one.
+    pub async fn record_position(
+        &self,
+        user_id: &str,
+        book_id: &str,
+        chapter_id: Option<&str>,
+        position: f64,
+    ) {
+        let reported = ReportedPosition {
+            book_id: book_id.to_string(),
+            chapter_id: chapter_id.map(str::to_string),
+            position,
+            reported_at: Instant::now(),
+        };
+
+        let mut expired = None;
+        let mut changed = None;
+        {
+            let mut sessions = self.sessions.lock().await;
+            let session = sessions.entry(user_id.to_string()).or_default();
+            session.position = Some(reported.clone());
+
+            if let Some(timer) = session.timer.as_mut() {
+                if let Some(remaining) = timer.state.chapters_remaining {
+                    let chapter_ended = chapter_id.is_some()
+                        && timer.chapter_id.is_some()
+                        && timer.chapter_id.as_deref() != chapter_id;
+                    timer.chapter_id = chapter_id.map(str::to_string).or(timer.chapter_id.take());
+                    if chapter_ended {
+                        if remaining <= 1 {
+                            expired = Some(timer.generation);
+                        } else {
+                            timer.state.chapters_remaining = Some(remaining - 1);
+                            changed = Some(timer.state.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(generation) = expired {
+            let stop = ReportedPosition {
+                position: 0.0,
+                ..reported
+            };
+            self.expire(user_id, generation, Some(stop)).await;
+        } else if let Some(state) = changed {
+            broadcast_sleep_timer(&self.ws_manager, user_id, Some(state)).await;
+        }
+    }
+
+    /// End a timer: pause every device and store where playback stopped.
+    ///
+    /// `stop` defaults to the last reported position, moved on by the time
+    /// played since it was reported.
+    async fn expire(&self, user_id: &str, generation: u64, stop: Option<ReportedPosition>) {
+        let stop = {
+            let mut sessions = self.sessions.lock().await;
+            let Some(session) = sessions.get_mut(user_id) else {
+                return;
+            };
+            if session
+                .timer
+                .as_ref()
+                .map_or(true, |timer| timer.generation != generation)
+            {
+                return;
+            }
+            session.timer = None;
+            if stop.is_some() {
+                session.position = stop.clone();
+            }
+            stop.or_else(|| {
+                session.position.clone().map(|mut last| {
+                    last.position = last.position_at(Instant::now());
+                    last
+                })
+            })
+        };
+
+        broadcast_sleep_timer(&self.ws_manager, user_id, None).await;
+        let Some(stop) = stop else {
+            return;
+        };
+
+        let progress = Progress {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            book_id: stop.book_id,
+            chapter_id: stop.chapter_id,
+            position: stop.position,
+            duration: None,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        };
+        if let Err(e) = self.progress_repo.upsert(&progress).await {
+            warn!(
+                user_id = %user_id,
+                error = %e,
+                "Failed to store sleep timer stop position"
+            );
+        }
+        broadcast_pause(
+            &self.ws_manager,
+            user_id,
+            PauseReason::SleepTimer,
+            &progress,
+        )
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::manager::DatabaseManager;
+
+    async fn create_manager() -> (Arc<SleepTimerManager>, Arc<ProgressRepository>) {
+        let db = Arc::new(DatabaseManager::new_in_memory().expect("create test database"));
+        db.execute(|conn| {
+            conn.execute_batch(
+                "INSERT INTO users (id, username, password_hash, role) VALUES ('user-1', 'tester', 'hash', 'user'); \
+                 INSERT INTO libraries (id, name, type, url) VALUES ('library-1', 'Test', 'local', ''); \
+                 INSERT INTO books (id, library_id, title, path, hash) VALUES ('book-1', 'library-1', 'Book', '/book', 'h'); \
+                 INSERT INTO chapters (id, book_id, title, path) VALUES \
+                    ('chapter-1', 'book-1', 'One', '/1'), \
+                    ('chapter-2', 'book-1', 'Two', '/2'), \
+                    ('chapter-3', 'book-1', 'Three', '/3');",
+            )?;
+            Ok(())
+        })
+        .await
+        .expect("seed test database");
+
+        let progress_repo = Arc::new(ProgressRepository::new(db));
+        let manager = SleepTimerManager::new(WsSessionManager::new(), progress_repo.clone());
+        (manager, progress_repo)
+    }
+
+    #[tokio::test]
+    async fn chapter_timer_stops_at_the_start_of_the_next_chapter() {
+        let (manager, progress_repo) = create_manager().await;
+        manager
+            .record_position("user-1", "book-1", Some("chapter-1"), 120.0)
+            .await;
+        manager
+            .start("user-1", SleepTimerMode::Chapters { count: 2 })
+            .await
+            .unwrap();
+
+        manager
+            .record_position("user-1", "book-1", Some("chapter-1"), 130.0)
+            .await;
+        manager
+            .record_position("user-1", "book-1", Some("chapter-2"), 4.0)
+            .await;
+        let timer = manager.get("user-1").await.unwrap();
+        assert_eq!(timer.chapters_remaining, Some(1));
+
+        manager
+            .record_position("user-1", "book-1", Some("chapter-3"), 5.0)
+            .await;
+        assert!(manager.get("user-1").await.is_none());
+
+        let progress = progress_repo
+            .get_by_book("user-1", "book-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(progress.chapter_id.as_deref(), Some("chapter-3"));
+        assert_eq!(progress.position, 0.0);
+    }
+
+    #[test]
+    fn stop_position_moves_on_from_the_last_report() {
+        let reported_at = Instant::now();
+        let reported = ReportedPosition {
+            book_id: "book-1".to_string(),
+            chapter_id: Some("chapter-1".to_string()),
+            position: 120.0,
+            reported_at,
+        };
+        assert_eq!(reported.position_at(reported_at), 120.0);
+        assert_eq!(
+            reported.position_at(reported_at + Duration::from_secs(15)),
+            135.0
+        );
+        // A player that stopped reporting long ago is paused
+        assert_eq!(
+            reported.position_at(reported_at + PLAYING_REPORT_GAP * 2),
+            120.0
+        );
+    }
+
+    #[tokio::test]
+    async fn replaced_duration_timer_does_not_fire() {
+        let (manager, _) = create_manager().await;
+        let first = manager
+            .start("user-1", SleepTimerMode::Duration { seconds: 60 })
+            .await
+            .unwrap();
+        manager
+            .start("user-1", SleepTimerMode::EndOfChapter)
+            .await
+            .unwrap();
+
+        // The first timer's generation no longer matches, so expiring it is a no-op
+        manager.expire("user-1", 1, None).await;
+        let timer = manager.get("user-1").await.unwrap();
+        assert_eq!(timer.mode, SleepTimerMode::EndOfChapter);
+        assert!(first.expires_at.is_some());
+
+        assert!(manager.cancel("user-1").await);
+        assert!(!manager.cancel("user-1").await);
+        assert!(manager
+            .start("user-1", SleepTimerMode::Duration { seconds: 0 })
+            .await
+            .is_err());
+    }
+}
@@ -4,8 +4,8 @@ use crate::api::handlers::AppState;
 use crate::api::utils::{request_info_from_headers, RequestInfo};
 use crate::auth::jwt::{generate_token, validate_token, validate_token_with_secrets};
 use crate::auth::models::{
-    LoginRequest, LoginResponse, RegisterRequest, SessionRestoreRequest, SuccessResponse,
-    TokenLoginRequest, UpdateUserRequest, UserInfo,
+    LoginRequest, LoginResponse, RegisterRequest, SessionRestoreRequest, SubsonicPasswordRequest,
+    SuccessResponse, TokenLoginRequest, UpdateUserRequest, UserInfo,
 };
 use crate::auth::password::{hash_password, verify_password};
 use crate::core::error::{Result, TingError};
@@ -25,6 +25,8 @@ use std::{
 };
 use uuid::Uuid;
 
+/// Longest Subsonic password accepted
+const MAX_SUBSONIC_PASSWORD_CHARS: usize = 128;
 const SESSION_RESTORE_LOG_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
 static SESSION_RESTORE_LOGS: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
 
@@ -255,15 +257,14 @@ pub async fn register(
     }
 }
 
-/// Handler for POST /api/auth/login - User login
-pub async fn login(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    connect_info: Option<ConnectInfo<SocketAddr>>,
-    Json(req): Json<LoginRequest>,
-) -> Result<impl IntoResponse> {
-    let peer_addr = connect_info.map(|ConnectInfo(addr)| addr);
-    let request_info = request_info_from_headers(&headers, peer_addr);
+/// Check a username and password and issue a JWT token, recording the login
+/// under `login_method`.
+pub(crate) async fn login_with_password(
+    state: &AppState,
+    req: &LoginRequest,
+    request_info: &RequestInfo,
+    login_method: &str,
+) -> Result<(User, String)> {
     tracing::debug!(username = %req.username, "Attempting login");
 
     // Find user by username
@@ -323,7 +324,21 @@ pub async fn login(
         generate_token(&user.id, &state.jwt_secret)?
     };
 
-    record_login_success(&state, &user, &request_info, "password");
+    record_login_success(state, &user, request_info, login_method);
+
+    Ok((user, token))
+}
+
+/// Handler for POST /api/auth/login - User login
+pub async fn login(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(req): Json<LoginRequest>,
+) -> Result<impl IntoResponse> {
+    let peer_addr = connect_info.map(|ConnectInfo(addr)| addr);
+    let request_info = request_info_from_headers(&headers, peer_addr);
+    let (user, token) = login_with_password(&state, &req, &request_info, "password").await?;
 
     Ok(Json(LoginResponse {
         user: user_info_from_user(&user),
@@ -475,3 +490,40 @@ pub async fn update_me(
         role: db_user.role,
     }))
 }
+
+/// Handler for PUT /api/me/subsonic-password - Set the password used by Subsonic clients
+pub async fn set_subsonic_password(
+    State(state): State<AppState>,
+    user: crate::auth::middleware::AuthUser,
+    Json(req): Json<SubsonicPasswordRequest>,
+) -> Result<Json<SuccessResponse>> {
+    if req.password.is_empty() || req.password.chars().count() > MAX_SUBSONIC_PASSWORD_CHARS {
+        return Err(TingError::ValidationError(format!(
+            "password must be between 1 and {} characters",
+            MAX_SUBSONIC_PASSWORD_CHARS
+        )));
+    }
+
+    let encrypted = crate::core::crypto::encrypt(&req.password, &state.encryption_key)?;
+    state
+        .user_repo
+        .set_subsonic_password(&user.id, Some(&encrypted))
+        .await?;
+
+    tracing::info!(user_id = %user.id, "Subsonic password set");
+    Ok(Json(SuccessResponse { success: true }))
+}
+
+/// Handler for DELETE /api/me/subsonic-password - Turn off Subsonic access for the user
+pub async fn clear_subsonic_password(
+    State(state): State<AppState>,
+    user: crate::auth::middleware::AuthUser,
+) -> Result<Json<SuccessResponse>> {
+    state
+        .user_repo
+        .set_subsonic_password(&user.id, None)
+        .await?;
+
+    tracing::info!(user_id = %user.id, "Subsonic password cleared");
+    Ok(Json(SuccessResponse { success: true }))
+}
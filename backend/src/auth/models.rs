@@ -56,3 +56,9 @@ pub struct UpdateUserRequest {
 pub struct SuccessResponse {
     pub success: bool,
 }
+
+/// Set Subsonic password request
+#[derive(Debug, Deserialize)]
+pub struct SubsonicPasswordRequest {
+    pub password: String,
+}
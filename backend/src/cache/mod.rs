@@ -2,6 +2,8 @@
 //!
 //! Provides functionality for managing cached chapter files.
 
+pub mod transcode;
+
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
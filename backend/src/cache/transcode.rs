@@ -0,0 +1,206 @@
+//! Transcoded HLS segments shared across sessions
+//!
+//! Segments are stored as `{content_id}/{rendition}/segment_NNN.ts`, where the
+//! content id is derived from the chapter hash and the time window the chapter
+//! covers. Any session playing the same chapter in the same rendition reuses
+//! the files, and the total size is held under a limit by evicting the least
+//! recently served segments.
+
+use crate::core::LruCache;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+
+/// Size and contents of the transcode cache
+#[derive(Debug, Clone, Copy)]
+pub struct TranscodeCacheStats {
+    pub segments: usize,
+    pub total_size: u64,
+    pub max_size: u64,
+}
+
+/// Size-bounded store of transcoded segments, indexed by relative path
+pub struct TranscodeCache {
+    dir: PathBuf,
+    entries: Mutex<LruCache<String, ()>>,
+}
+
+impl TranscodeCache {
+    /// Open the cache in `dir`, indexing the segments an earlier run left there
+    pub fn new(dir: PathBuf, max_size: u64) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        let mut segments = Vec::new();
+        collect_segments(&dir, &dir, &mut segments)?;
+        // Oldest first, so that they are also the first to be evicted
+        segments.sort_by_key(|(_, _, modified)| *modified);
+
+        let mut entries = LruCache::new(max_size);
+        for (key, size, _) in segments {
+            for (evicted, _) in entries.insert(key, (), size) {
+                let _ = fs::remove_file(dir.join(&evicted));
+            }
+        }
+
+        Ok(Self {
+            dir,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Content id of a chapter covering `duration` seconds from `start` in
+    /// the file with hash `chapter_hash`
+    pub fn content_id(chapter_hash: &str, start: f64, duration: f64) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(chapter_hash.as_bytes());
+        hasher.update(format!("|{:.3}|{:.3}", start, duration).as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Key of one segment of a rendition
+    pub fn segment_key(content_id: &str, rendition: &str, file_name: &str) -> String {
+        format!("{}/{}/{}", content_id, rendition, file_name)
+    }
+
+    /// Path of a cached segment, refreshing its place in the eviction order
+    pub async fn get(&self, key: &str) -> Option<PathBuf> {
+        let mut entries = self.entries.lock().await;
+        let key = key.to_string();
+        entries.get(&key)?;
+        let path = self.dir.join(&key);
+        if path.exists() {
+            Some(path)
+        } else {
+            // Removed behind our back
+            entries.remove(&key);
+            None
+        }
+    }
+
+    /// Unique path to transcode a segment into before it is added with `insert`
+    pub async fn partial_path(&self, key: &str) -> io::Result<PathBuf> {
+        let path = self
+            .dir
+            .join(format!("{}.{}.part", key, uuid::Uuid::new_v4()));
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        Ok(path)
+    }
+
+    /// Move a finished segment into the cache, evicting older segments as
+    /// needed, and return its path
+    pub async fn insert(&self, key: &str, partial: &Path) -> io::Result<PathBuf> {
+        let path = self.dir.join(key);
+        tokio::fs::rename(partial, &path).await?;
+        let size = tokio::fs::metadata(&path).await?.len();
+
+        let evicted = self.entries.lock().await.insert(key.to_string(), (), size);
+        for (evicted, _) in evicted {
+            if evicted != key {
+                let _ = tokio::fs::remove_file(self.dir.join(&evicted)).await;
+            }
+        }
+        Ok(path)
+    }
+
+    pub async fn stats(&self) -> TranscodeCacheStats {
+        let entries = self.entries.lock().await;
+        TranscodeCacheStats {
+            segments: entries.len(),
+            total_size: entries.total_size(),
+            max_size: entries.max_size(),
+        }
+    }
+
+    /// Delete every cached segment, returning how many there were
+    pub async fn clear(&self) -> io::Result<usize> {
+        let mut entries = self.entries.lock().await;
+        let count = entries.len();
+        *entries = LruCache::new(entries.max_size());
+
+        let mut dirs = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = dirs.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                tokio::fs::remove_dir_all(entry.path()).await?;
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// Segments below `dir` as `(key, size, modified)`; leftover partial files
+/// from an interrupted transcode are deleted
+fn collect_segments(
+    root: &Path,
+    dir: &Path,
+    segments: &mut Vec<(String, u64, SystemTime)>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            collect_segments(root, &path, segments)?;
+            continue;
+        }
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ts") => {
+                let Ok(relative) = path.strip_prefix(root) else {
+                    continue;
+                };
+                let key = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                segments.push((key, metadata.len(), modified));
+            }
+            Some("part") => {
+                let _ = fs::remove_file(&path);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn segments_are_shared_and_evicted_by_size() {
+        let dir = std::env::temp_dir().join(format!("ting-transcode-{}", uuid::Uuid::new_v4()));
+        let cache = TranscodeCache::new(dir.clone(), 10).unwrap();
+        let content = TranscodeCache::content_id("hash@0", 0.0, 60.0);
+        assert_ne!(content, TranscodeCache::content_id("hash@0", 0.0, 61.0));
+
+        let first = TranscodeCache::segment_key(&content, "aac_64k", "segment_000.ts");
+        let second = TranscodeCache::segment_key(&content, "aac_64k", "segment_001.ts");
+        for key in [&first, &second] {
+            let partial = cache.partial_path(key).await.unwrap();
+            tokio::fs::write(&partial, b"123456").await.unwrap();
+            cache.insert(key, &partial).await.unwrap();
+        }
+
+        // Both segments do not fit, so the first one made way for the second
+        assert!(cache.get(&first).await.is_none());
+        assert!(!dir.join(&first).exists());
+        assert_eq!(cache.get(&second).await, Some(dir.join(&second)));
+        let stats = cache.stats().await;
+        assert_eq!((stats.segments, stats.total_size), (1, 6));
+
+        // A new instance picks up what is on disk
+        let reopened = TranscodeCache::new(dir.clone(), 10).unwrap();
+        assert!(reopened.get(&second).await.is_some());
+        assert_eq!(reopened.clear().await.unwrap(), 1);
+        assert!(!dir.join(&second).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
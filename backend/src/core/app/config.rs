@@ -3,6 +3,7 @@
 use clap::Parser;
 use config::{Config as ConfigBuilder, ConfigError as BuilderError, Environment, File};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
@@ -98,6 +99,7 @@ impl Config {
             .set_default("storage.local_storage_root", "./storage")?
             .set_default("storage.local_library_roots", Vec::<String>::new())?
             .set_default("storage.max_disk_usage", 10737418240u64)? // 10 GB
+            .set_default("storage.transcode_cache_size", 1073741824u64)? // 1 GB
             .set_default("audio.cache_enabled", true)?
             .set_default("audio.cache_size", 104857600)? // 100 MB
             .set_default("audio.buffer_size", 65536)?; // 64 KB
@@ -217,6 +219,7 @@ impl Config {
             .set_default("storage.local_storage_root", "./storage")?
             .set_default("storage.local_library_roots", Vec::<String>::new())?
             .set_default("storage.max_disk_usage", 10737418240u64)?
+            .set_default("storage.transcode_cache_size", 1073741824u64)?
             .set_default("audio.cache_enabled", true)?
             .set_default("audio.cache_size", 104857600)? // 100 MB
             .set_default("audio.buffer_size", 65536)?; // 64 KB
@@ -299,6 +302,16 @@ pub struct ServerConfig {
     pub request_timeout: u64, // seconds
     pub gateway_prefix: Option<String>,
     pub gateway_socket: Option<PathBuf>,
+    /// Serve the Audiobookshelf-compatible API under `/abs`
+    #[serde(default)]
+    pub enable_abs_api: bool,
+    /// Serve the Subsonic-compatible API under `/rest`
+    #[serde(default)]
+    pub enable_subsonic_api: bool,
+    /// Public base URL (scheme, host and path prefix) used for absolute links
+    /// handed to external apps, such as podcast feeds
+    #[serde(default)]
+    pub public_url: Option<String>,
 }
 
 impl ServerConfig {
@@ -345,8 +358,25 @@ impl ServerConfig {
             }
         }
 
+        if let Some(url) = &self.public_url {
+            let url = url.trim();
+            if !(url.starts_with("http://") || url.starts_with("https://")) || url.contains('?') {
+                return Err(ConfigError::InvalidServer(
+                    "public_url must be an http(s) URL without a query string".to_string(),
+                ));
+            }
+        }
+
         Ok(())
     }
+
+    /// Configured public base URL without a trailing slash
+    pub fn public_base_url(&self) -> Option<String> {
+        self.public_url
+            .as_deref()
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -418,6 +448,20 @@ pub struct TaskQueueConfig {
     pub max_concurrent_tasks: usize,
     pub default_retry_count: u32,
     pub task_timeout: u64, // seconds
+    /// Limits of individual task types, on top of `max_concurrent_tasks`
+    #[serde(default)]
+    pub pools: HashMap<String, TaskPoolConfig>,
+}
+
+/// Concurrency limits of one task type
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TaskPoolConfig {
+    /// Tasks of the type running at once
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+    /// Tasks of the type running at once for the same library
+    #[serde(default)]
+    pub per_library: Option<usize>,
 }
 
 impl TaskQueueConfig {
@@ -428,6 +472,20 @@ impl TaskQueueConfig {
             ));
         }
 
+        for (task_type, pool) in &self.pools {
+            if task_type.trim().is_empty() {
+                return Err(ConfigError::InvalidTaskQueue(
+                    "pool task type cannot be empty".to_string(),
+                ));
+            }
+            if pool.max_concurrent == Some(0) || pool.per_library == Some(0) {
+                return Err(ConfigError::InvalidTaskQueue(format!(
+                    "limits of pool {} must be greater than 0",
+                    task_type
+                )));
+            }
+        }
+
         if self.task_timeout == 0 {
             return Err(ConfigError::InvalidTaskQueue(
                 "task_timeout must be greater than 0".to_string(),
@@ -552,9 +610,20 @@ pub struct StorageConfig {
     pub local_storage_root: PathBuf, // Root directory for local libraries
     #[serde(default)]
     pub local_library_roots: Vec<PathBuf>, // Additional allowed roots for local libraries
+    pub transcode_cache_size: u64,   // bytes, HLS segments shared across sessions
 }
 
 impl StorageConfig {
+    /// Directory RSS libraries download episodes into, one folder per library
+    pub fn episode_dir(&self) -> PathBuf {
+        self.data_dir.join("episodes")
+    }
+
+    /// Directory of the HLS segments shared across sessions
+    pub fn transcode_cache_dir(&self) -> PathBuf {
+        self.temp_dir.join("ting_transcode_cache")
+    }
+
     pub fn validate(&self) -> Result<(), ConfigError> {
         if self.data_dir.as_os_str().is_empty() {
             return Err(ConfigError::InvalidStorage(
@@ -590,6 +659,12 @@ impl StorageConfig {
             ));
         }
 
+        if self.transcode_cache_size == 0 {
+            return Err(ConfigError::InvalidStorage(
+                "transcode_cache_size must be greater than 0".to_string(),
+            ));
+        }
+
         Ok(())
     }
 }
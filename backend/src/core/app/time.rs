@@ -3,7 +3,8 @@
 //! The backend always generates and stores timestamps in UTC. This module only
 //! validates a display preference and, for the first FPK installation, reads a
 //! host OS IANA time-zone name directly. It does not change the process clock,
-//! SQLite functions, or log timestamp generation.
+//! SQLite functions, or log timestamp generation. Listening statistics use it to
+//! decide which calendar day a moment of listening belongs to.
 
 use chrono_tz::Tz;
 
@@ -22,6 +23,16 @@ pub fn parse_time_zone(value: &str) -> Result<String, String> {
         .map_err(|_| format!("Unsupported IANA time zone: {value}"))
 }
 
+/// Time zone for a stored preference, falling back to UTC when it is invalid
+pub fn resolve_time_zone(value: &str) -> Tz {
+    value.trim().parse::<Tz>().unwrap_or(Tz::UTC)
+}
+
+/// Calendar day of an instant in the given time zone
+pub fn local_date(at: &chrono::DateTime<chrono::Utc>, time_zone: Tz) -> chrono::NaiveDate {
+    at.with_timezone(&time_zone).date_naive()
+}
+
 /// Read the host OS time zone only when the FPK launcher explicitly enables it.
 /// Docker FPK exposes `/etc/localtime` and `/etc/timezone` read-only so the
 /// `iana-time-zone` crate can resolve the host's IANA name without fnOS APIs.
@@ -49,7 +60,7 @@ pub fn initial_time_zone_from_os() -> Option<String> {
 
 #[cfg(test)]
 mod tests {
-    use super::{parse_time_zone, DEFAULT_TIME_ZONE};
+    use super::{local_date, parse_time_zone, resolve_time_zone, DEFAULT_TIME_ZONE};
 
     #[test]
     fn accepts_iana_time_zones() {
@@ -61,6 +72,22 @@ mod tests {
         assert!(parse_time_zone("Mars/Olympus").is_err());
     }
 
+    #[test]
+    fn local_date_follows_the_time_zone() {
+        let at = chrono::DateTime::parse_from_rfc3339("2024-03-01T20:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(
+            local_date(&at, resolve_time_zone("UTC")).to_string(),
+            "2024-03-01"
+        );
+        assert_eq!(
+            local_date(&at, resolve_time_zone("Asia/Shanghai")).to_string(),
+            "2024-03-02"
+        );
+        assert_eq!(resolve_time_zone("Mars/Olympus"), chrono_tz::Tz::UTC);
+    }
+
     #[test]
     fn default_is_utc() {
         assert_eq!(DEFAULT_TIME_ZONE, "UTC");
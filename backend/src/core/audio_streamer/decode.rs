@@ -0,0 +1,211 @@
+//! Decoding with symphonia on blocking threads
+//!
+//! Shared by the FFmpeg-free transcoder and the loudness analysis: opening the
+//! first audio track of a source, seeking to a position, and reading async
+//! sources from synchronous decoder code.
+
+use crate::core::error::{Result, TingError};
+use std::io::{self, Read};
+use std::sync::Mutex;
+use symphonia::core::audio::{SampleBuffer, SignalSpec};
+use symphonia::core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::{Time, TimeBase};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The first audio track of a probed source, with a decoder for it
+pub struct AudioTrack {
+    pub format: Box<dyn FormatReader>,
+    pub decoder: Box<dyn Decoder>,
+    pub track_id: u32,
+    pub params: CodecParameters,
+}
+
+impl AudioTrack {
+    pub fn open(
+        source: Box<dyn MediaSource>,
+        hint: &Hint,
+    ) -> std::result::Result<Self, SymphoniaError> {
+        let mss = MediaSourceStream::new(source, Default::default());
+        let probed = symphonia::default::get_probe().format(
+            hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?;
+        let format = probed.format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or(SymphoniaError::Unsupported("no audio track"))?;
+        let track_id = track.id;
+        let params = track.codec_params.clone();
+        let decoder = symphonia::default::get_codecs().make(&params, &DecoderOptions::default())?;
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            params,
+        })
+    }
+
+    /// Sample rate of the track, assuming CD quality when the container does
+    /// not say
+    pub fn sample_rate(&self) -> u32 {
+        self.params.sample_rate.unwrap_or(44100)
+    }
+
+    /// Frames of the track after `start` seconds, when the container says
+    pub fn frames_after(&self, start: f64) -> Option<u64> {
+        let start_frame = (start.max(0.0) * self.sample_rate() as f64).round() as u64;
+        self.params
+            .n_frames
+            .map(|frames| frames.saturating_sub(start_frame))
+    }
+
+    /// Move to `start` seconds and return the number of decoded frames still
+    /// to be dropped to land on it exactly. Sources that cannot seek are
+    /// decoded from the beginning.
+    pub fn seek(&mut self, start: f64) -> u64 {
+        let rate = self.sample_rate() as u64;
+        let start_frame = (start.max(0.0) * rate as f64).round() as u64;
+        if start_frame == 0 {
+            return 0;
+        }
+        let seeked = self.format.seek(
+            SeekMode::Accurate,
+            SeekTo::Time {
+                time: Time::from(start),
+                track_id: Some(self.track_id),
+            },
+        );
+        self.decoder.reset();
+        match seeked {
+            Ok(seeked) => frames_between(
+                seeked.actual_ts,
+                seeked.required_ts,
+                self.params.time_base,
+                rate,
+            ),
+            Err(_) => start_frame,
+        }
+    }
+}
+
+/// Decode `limit` seconds (or the rest) of the first audio track of `source`,
+/// starting `start` seconds in, and hand the interleaved samples to `sink`.
+/// Blocks until the audio is decoded, so call it from a blocking thread.
+pub fn decode_samples(
+    source: Box<dyn MediaSource>,
+    hint: &Hint,
+    start: f64,
+    limit: Option<f64>,
+    mut sink: impl FnMut(SignalSpec, &[f32]),
+) -> Result<()> {
+    let mut track = AudioTrack::open(source, hint)
+        .map_err(|e| TingError::InvalidRequest(format!("Format cannot be analysed: {}", e)))?;
+    let mut skip_frames = track.seek(start);
+    let mut remaining = limit.map(|limit| (limit * track.sample_rate() as f64).round() as u64);
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+
+    while remaining != Some(0) {
+        let packet = match track.format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(TingError::ExternalError(e.to_string())),
+        };
+        if packet.track_id() != track.track_id {
+            continue;
+        }
+        let decoded = match track.decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(TingError::ExternalError(e.to_string())),
+        };
+        let spec = *decoded.spec();
+        let frames = decoded.frames() as u64;
+        let skipped = skip_frames.min(frames);
+        skip_frames -= skipped;
+        if skipped == frames {
+            continue;
+        }
+
+        if buffer
+            .as_ref()
+            .map_or(true, |buffer| buffer.capacity() < decoded.capacity())
+        {
+            buffer = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+        }
+        let buffer = buffer.as_mut().expect("buffer was just allocated");
+        buffer.copy_interleaved_ref(decoded);
+
+        let channels = spec.channels.count().max(1);
+        let mut samples = &buffer.samples()[skipped as usize * channels..];
+        if let Some(remaining) = remaining.as_mut() {
+            let frames = (samples.len() / channels) as u64;
+            let taken = frames.min(*remaining);
+            samples = &samples[..taken as usize * channels];
+            *remaining -= taken;
+        }
+        sink(spec, samples);
+    }
+
+    Ok(())
+}
+
+/// Frames from `from` to `to`, two timestamps in the track's time base
+pub fn frames_between(from: u64, to: u64, time_base: Option<TimeBase>, rate: u64) -> u64 {
+    let ts = to.saturating_sub(from);
+    match time_base {
+        Some(time_base) => {
+            let time = time_base.calc_time(ts);
+            ((time.seconds as f64 + time.frac) * rate as f64).round() as u64
+        }
+        None => ts,
+    }
+}
+
+/// Blocking `Read` over an async reader, for symphonia running on a blocking
+/// thread
+pub struct BlockingReader {
+    inner: Mutex<Box<dyn AsyncRead + Send + Unpin>>,
+    runtime: tokio::runtime::Handle,
+}
+
+impl BlockingReader {
+    /// Wrap `inner`; must be called from within the Tokio runtime
+    pub fn new(inner: Box<dyn AsyncRead + Send + Unpin>) -> Self {
+        Self {
+            inner: Mutex::new(inner),
+            runtime: tokio::runtime::Handle::current(),
+        }
+    }
+}
+
+impl Read for BlockingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let inner = self
+            .inner
+            .get_mut()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "reader poisoned"))?;
+        self.runtime.block_on(inner.read(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_convert_to_frames() {
+        let time_base = Some(TimeBase::new(1, 1000));
+        assert_eq!(frames_between(1000, 1500, time_base, 44100), 22050);
+        assert_eq!(frames_between(10, 4, None, 44100), 0);
+    }
+}
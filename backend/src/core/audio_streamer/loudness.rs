@@ -0,0 +1,391 @@
+//! EBU R128 loudness measurement
+//!
+//! Integrated loudness follows ITU-R BS.1770-4: the signal is K-weighted,
+//! measured in 400 ms blocks overlapping by 75%, and gated at -70 LUFS and
+//! 10 LU below the level of the blocks that pass that first gate. True peak is
+//! the largest sample level after 4x oversampling (2x above 96 kHz).
+
+use super::decode_samples;
+use crate::core::error::{Result, TingError};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use symphonia::core::audio::Channels;
+use symphonia::core::io::MediaSource;
+use symphonia::core::probe::Hint;
+
+/// Reported for silence and anything quieter than the absolute gate
+pub const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Reported as the true peak of digital silence
+pub const SILENCE_PEAK_DBTP: f64 = -120.0;
+/// Loudness normalized playback aims for unless the user picks another one
+pub const DEFAULT_TARGET_LUFS: f64 = -16.0;
+/// Range of targets users may pick
+pub const TARGET_RANGE_LUFS: (f64, f64) = (-31.0, -5.0);
+/// Normalized playback never boosts a chapter's true peak above this
+pub const PEAK_CEILING_DBTP: f64 = -1.0;
+/// Largest boost, so that near-silent chapters do not turn into noise
+pub const MAX_GAIN_DB: f64 = 20.0;
+
+const RELATIVE_GATE_LU: f64 = -10.0;
+/// Gating blocks are made of four 100 ms steps
+const STEPS_PER_BLOCK: usize = 4;
+/// Input samples each interpolated true-peak sample is computed from
+const PEAK_FILTER_TAPS: usize = 12;
+
+/// Measured loudness of a chapter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessAnalysis {
+    pub integrated_lufs: f64,
+    pub true_peak_dbtp: f64,
+}
+
+/// Gain in dB that brings a chapter measured at `loudness_lufs` to
+/// `target_lufs`. Boosts stop where the true peak would pass
+/// [`PEAK_CEILING_DBTP`] and at [`MAX_GAIN_DB`]; chapters louder than the
+/// target are always turned down.
+pub fn normalization_gain_db(loudness_lufs: f64, true_peak_dbtp: f64, target_lufs: f64) -> f64 {
+    let gain = target_lufs - loudness_lufs;
+    if gain <= 0.0 {
+        return gain;
+    }
+    gain.min((PEAK_CEILING_DBTP - true_peak_dbtp).max(0.0))
+        .min(MAX_GAIN_DB)
+}
+
+/// Measure `limit` seconds (or the rest) of the first audio track of
+/// `source`, starting `start` seconds in. Blocks until the audio is decoded,
+/// so call it from a blocking thread.
+pub fn analyze(
+    source: Box<dyn MediaSource>,
+    hint: Hint,
+    start: f64,
+    limit: Option<f64>,
+) -> Result<LoudnessAnalysis> {
+    let mut meter: Option<LoudnessMeter> = None;
+    decode_samples(source, &hint, start, limit, |spec, samples| {
+        meter
+            .get_or_insert_with(|| LoudnessMeter::new(spec.rate, spec.channels))
+            .add_frames(samples)
+    })?;
+
+    meter
+        .map(|meter| meter.finish())
+        .ok_or_else(|| TingError::InvalidRequest("No audio to analyse".to_string()))
+}
+
+/// Biquad filter in transposed direct form II
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    pub(super) fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { b, a, z: [0.0; 2] }
+    }
+
+    pub(super) fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// The two stages of the K-weighting filter at `rate`: a high shelf modelling
+/// the head, then a high pass
+fn k_weighting(rate: f64) -> [Biquad; 2] {
+    let k = (PI * 1681.974450955533 / rate).tan();
+    let q = 0.7071752369554196;
+    let vh = 10f64.powf(3.999843853973347 / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let k = (PI * 38.13547087602444 / rate).tan();
+    let q = 0.5003270373238773;
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+    [shelf, high_pass]
+}
+
+/// Weight of a channel in the loudness sum
+fn channel_weight(channel: Channels) -> f64 {
+    if channel == Channels::LFE1 || channel == Channels::LFE2 {
+        0.0
+    } else if channel == Channels::SIDE_LEFT
+        || channel == Channels::SIDE_RIGHT
+        || channel == Channels::REAR_LEFT
+        || channel == Channels::REAR_RIGHT
+    {
+        1.41
+    } else {
+        1.0
+    }
+}
+
+/// Windowed-sinc coefficients for each interpolated phase of the true-peak
+/// oversampler; phase 0 is the input sample itself and is not included
+fn oversampling_phases(factor: usize) -> Vec<[f64; PEAK_FILTER_TAPS]> {
+    let half_width = (PEAK_FILTER_TAPS / 2) as f64;
+    (1..factor)
+        .map(|phase| {
+            let mut taps = [0.0; PEAK_FILTER_TAPS];
+            for (delay, tap) in taps.iter_mut().enumerate() {
+                let t = delay as f64 - half_width + phase as f64 / factor as f64;
+                let sinc = if t == 0.0 {
+                    1.0
+                } else {
+                    (PI * t).sin() / (PI * t)
+                };
+                let window = if t.abs() < half_width {
+                    0.5 * (1.0 + (PI * t / half_width).cos())
+                } else {
+                    0.0
+                };
+                *tap = sinc * window;
+            }
+            let sum: f64 = taps.iter().sum();
+            taps.iter_mut().for_each(|tap| *tap /= sum);
+            taps
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+struct ChannelState {
+    filters: [Biquad; 2],
+    weight: f64,
+    /// Latest input samples, newest at `position`
+    history: [f64; PEAK_FILTER_TAPS],
+    position: usize,
+}
+
+/// Running BS.1770 measurement of interleaved samples
+#[derive(Debug, Clone)]
+pub struct LoudnessMeter {
+    channels: Vec<ChannelState>,
+    phases: Vec<[f64; PEAK_FILTER_TAPS]>,
+    step_frames: usize,
+    /// Frames and weighted energy collected for the current 100 ms step
+    frames_in_step: usize,
+    step_energy: f64,
+    /// Mean energy of the latest steps, enough to form one block
+    recent_steps: VecDeque<f64>,
+    /// Mean energy of every gating block so far
+    blocks: Vec<f64>,
+    peak: f64,
+}
+
+impl LoudnessMeter {
+    pub fn new(rate: u32, channels: Channels) -> Self {
+        let filters = k_weighting(rate as f64);
+        let mut states: Vec<ChannelState> = channels
+            .iter()
+            .map(|channel| ChannelState {
+                filters,
+                weight: channel_weight(channel),
+                history: [0.0; PEAK_FILTER_TAPS],
+                position: 0,
+            })
+            .collect();
+        if states.is_empty() {
+            // Unknown layouts are measured as mono
+            states.push(ChannelState {
+                filters,
+                weight: 1.0,
+                history: [0.0; PEAK_FILTER_TAPS],
+                position: 0,
+            });
+        }
+        let factor = match rate {
+            0..=95_999 => 4,
+            96_000..=191_999 => 2,
+            _ => 1,
+        };
+        Self {
+            channels: states,
+            phases: oversampling_phases(factor),
+            step_frames: (rate as usize / 10).max(1),
+            frames_in_step: 0,
+            step_energy: 0.0,
+            recent_steps: VecDeque::with_capacity(STEPS_PER_BLOCK),
+            blocks: Vec::new(),
+            peak: 0.0,
+        }
+    }
+
+    /// Add interleaved samples in the layout given to [`LoudnessMeter::new`]
+    pub fn add_frames(&mut self, samples: &[f32]) {
+        let count = self.channels.len();
+        for frame in samples.chunks_exact(count) {
+            for (state, &sample) in self.channels.iter_mut().zip(frame) {
+                let sample = sample as f64;
+                self.peak = self.peak.max(sample.abs());
+
+                state.position = (state.position + 1) % PEAK_FILTER_TAPS;
+                state.history[state.position] = sample;
+                for taps in &self.phases {
+                    let mut interpolated = 0.0;
+                    for (delay, tap) in taps.iter().enumerate() {
+                        let index = (state.position + PEAK_FILTER_TAPS - delay) % PEAK_FILTER_TAPS;
+                        interpolated += tap * state.history[index];
+                    }
+                    self.peak = self.peak.max(interpolated.abs());
+                }
+
+                let [shelf, high_pass] = &mut state.filters;
+                let weighted = high_pass.process(shelf.process(sample));
+                self.step_energy += state.weight * weighted * weighted;
+            }
+
+            self.frames_in_step += 1;
+            if self.frames_in_step == self.step_frames {
+                self.finish_step();
+            }
+        }
+    }
+
+    fn finish_step(&mut self) {
+        if self.recent_steps.len() == STEPS_PER_BLOCK {
+            self.recent_steps.pop_front();
+        }
+        self.recent_steps
+            .push_back(self.step_energy / self.step_frames as f64);
+        if self.recent_steps.len() == STEPS_PER_BLOCK {
+            self.blocks
+                .push(self.recent_steps.iter().sum::<f64>() / STEPS_PER_BLOCK as f64);
+        }
+        self.frames_in_step = 0;
+        self.step_energy = 0.0;
+    }
+
+    /// Integrated loudness and true peak of everything added
+    pub fn finish(&self) -> LoudnessAnalysis {
+        let absolute_gate = energy(ABSOLUTE_GATE_LUFS);
+        let gated_mean = |threshold: f64| {
+            let (sum, count) = self
+                .blocks
+                .iter()
+                .filter(|block| **block > threshold)
+                .fold((0.0, 0usize), |(sum, count), block| {
+                    (sum + block, count + 1)
+                });
+            (count > 0).then(|| sum / count as f64)
+        };
+        let integrated_lufs = gated_mean(absolute_gate)
+            .map(|mean| energy(lufs(mean) + RELATIVE_GATE_LU).max(absolute_gate))
+            .and_then(gated_mean)
+            .map_or(ABSOLUTE_GATE_LUFS, |mean| {
+                lufs(mean).max(ABSOLUTE_GATE_LUFS)
+            });
+        let true_peak_dbtp = if self.peak > 0.0 {
+            (20.0 * self.peak.log10()).max(SILENCE_PEAK_DBTP)
+        } else {
+            SILENCE_PEAK_DBTP
+        };
+        LoudnessAnalysis {
+            integrated_lufs,
+            true_peak_dbtp,
+        }
+    }
+}
+
+fn lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+fn energy(lufs: f64) -> f64 {
+    10f64.powf((lufs + 0.691) / 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(rate: u32, seconds: f64, amplitude: f32, channels: usize) -> Vec<f32> {
+        let frames = (rate as f64 * seconds) as usize;
+        (0..frames)
+            .flat_map(|frame| {
+                let phase = 2.0 * PI * 997.0 * frame as f64 / rate as f64;
+                std::iter::repeat(amplitude * phase.sin() as f32).take(channels)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sine_reads_at_its_reference_loudness() {
+        let stereo = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+        let mut meter = LoudnessMeter::new(48000, stereo);
+        meter.add_frames(&sine(48000, 5.0, 0.1, 2));
+        let analysis = meter.finish();
+        assert!(
+            (analysis.integrated_lufs + 20.0).abs() < 0.1,
+            "{:?}",
+            analysis
+        );
+        assert!(
+            (analysis.true_peak_dbtp + 20.0).abs() < 0.1,
+            "{:?}",
+            analysis
+        );
+
+        // Silence is gated out instead of pulling the level down; only the
+        // few blocks straddling the onset count
+        let mut gated = LoudnessMeter::new(48000, stereo);
+        gated.add_frames(&vec![0.0; 48000 * 2 * 5]);
+        gated.add_frames(&sine(48000, 5.0, 0.1, 2));
+        let gated = gated.finish();
+        assert!((gated.integrated_lufs - analysis.integrated_lufs).abs() < 0.3);
+
+        let mut mono = LoudnessMeter::new(44100, Channels::FRONT_LEFT);
+        mono.add_frames(&sine(44100, 3.0, 0.1, 1));
+        assert!((mono.finish().integrated_lufs + 23.0).abs() < 0.1);
+
+        let silent = LoudnessMeter::new(44100, stereo).finish();
+        assert_eq!(silent.integrated_lufs, ABSOLUTE_GATE_LUFS);
+        assert_eq!(silent.true_peak_dbtp, SILENCE_PEAK_DBTP);
+    }
+
+    #[test]
+    fn true_peak_finds_peaks_between_samples() {
+        // A quarter-rate sine sampled at ±45° never hits its crest
+        let samples: Vec<f32> = (0..4000)
+            .map(|n| (PI / 2.0 * n as f64 + PI / 4.0).sin() as f32 * 0.5)
+            .collect();
+        let mut meter = LoudnessMeter::new(44100, Channels::FRONT_LEFT);
+        meter.add_frames(&samples);
+        let sample_peak = 20.0 * (0.5f64 * 0.5f64.sqrt()).log10();
+        let true_peak = meter.finish().true_peak_dbtp;
+        assert!(true_peak > sample_peak + 2.5, "{}", true_peak);
+        assert!(
+            (true_peak - 20.0 * 0.5f64.log10()).abs() < 0.3,
+            "{}",
+            true_peak
+        );
+    }
+
+    #[test]
+    fn gain_stays_under_the_peak_ceiling() {
+        assert_eq!(normalization_gain_db(-20.0, -10.0, -16.0), 4.0);
+        assert_eq!(normalization_gain_db(-20.0, -3.0, -16.0), 2.0);
+        assert_eq!(normalization_gain_db(-20.0, 0.5, -16.0), 0.0);
+        assert_eq!(normalization_gain_db(-10.0, 0.5, -16.0), -6.0);
+        assert_eq!(
+            normalization_gain_db(ABSOLUTE_GATE_LUFS, SILENCE_PEAK_DBTP, -16.0),
+            MAX_GAIN_DB
+        );
+    }
+}
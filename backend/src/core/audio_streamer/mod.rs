@@ -1,4 +1,8 @@
+mod decode;
+pub mod loudness;
+pub mod skip_detection;
 mod types;
+pub use decode::*;
 pub use types::*;
 
 use crate::core::error::{Result, TingError};
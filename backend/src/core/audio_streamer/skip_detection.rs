@@ -0,0 +1,617 @@
+//! Intro and outro detection
+//!
+//! Finds what the chapters of a book open and close with: jingles recurring
+//! in neighbouring chapters, and silence at either end. The first and last
+//! [`WINDOW_SECS`] of each chapter are fingerprinted every 25 ms, one bit per
+//! pair of adjacent bands of a 300-3000 Hz filter bank telling whether their
+//! energy difference grew, and the windows of neighbouring chapters are
+//! compared at every offset for the longest stretch they share.
+
+use super::decode_samples;
+use super::loudness::Biquad;
+use crate::core::error::{Result, TingError};
+use crate::db::models::{SkipKind, SkipRange};
+use std::f64::consts::PI;
+use symphonia::core::io::MediaSource;
+use symphonia::core::probe::Hint;
+
+/// Seconds at each end of a chapter searched for an intro or outro
+pub const WINDOW_SECS: f64 = 90.0;
+
+const HOP_SECS: f64 = 0.025;
+/// Hops a fingerprinted frame spans. Frames overlap so that a jingle
+/// fingerprints alike however it falls on the hop grid.
+const HOPS_PER_FRAME: usize = 8;
+const BANDS: usize = 17;
+const BITS: usize = BANDS - 1;
+const BAND_RANGE_HZ: (f64, f64) = (300.0, 3000.0);
+/// Hops quieter than this are silent
+const SILENCE_DBFS: f64 = -50.0;
+/// Shortest silence skipped at a chapter edge or next to a jingle
+const MIN_SILENCE_SECS: f64 = 0.5;
+/// Shortest stretch two chapters must share to count as a jingle
+const MIN_JINGLE_SECS: f64 = 3.0;
+/// Fingerprints are compared a second at a time
+const MATCH_FRAMES: usize = 40;
+/// Share of differing bits up to which a second of two chapters matches;
+/// unrelated audio differs in about half of them
+const MAX_BIT_ERROR_RATE: f64 = 0.3;
+/// Jingles starting or ending this close to the chapter edge are stretched to it
+const EDGE_SNAP_SECS: f64 = 5.0;
+/// A silence this close to a jingle is skipped along with it
+const GAP_SNAP_SECS: f64 = 0.5;
+/// Chapters each chapter is compared with, following it in play order
+const NEIGHBOURS: usize = 2;
+/// Chapter skips this close to each other agree on a book-wide skip
+const AGREEMENT_SECS: f64 = 2.0;
+
+/// Fingerprints of one end of a chapter
+#[derive(Debug, Clone)]
+struct Window {
+    /// Chapter position of the first hop in seconds
+    offset: f64,
+    /// Chapter position where the decoded audio ends
+    end: f64,
+    prints: Vec<u16>,
+    /// Whether each frame is loud enough for its fingerprint to mean anything
+    audible: Vec<bool>,
+    /// Silences as chapter positions
+    silences: Vec<(f64, f64)>,
+}
+
+impl Window {
+    fn time(&self, frame: usize) -> f64 {
+        self.offset + frame as f64 * HOP_SECS
+    }
+
+    /// Chapter positions covered by `len` frames from `start`
+    fn stretch(&self, start: usize, len: usize) -> (f64, f64) {
+        let end = self.time(start + len + HOPS_PER_FRAME).min(self.end);
+        (self.time(start), end)
+    }
+
+    /// Start of a silence around or just before `position`, or `position`
+    fn silence_before(&self, position: f64) -> f64 {
+        self.silences
+            .iter()
+            .find(|(start, end)| *start < position && *end >= position - GAP_SNAP_SECS)
+            .map_or(position, |&(start, _)| start)
+    }
+
+    /// End of a silence around or just after `position`, or `position`
+    fn silence_after(&self, position: f64) -> f64 {
+        self.silences
+            .iter()
+            .find(|(start, end)| *end > position && *start <= position + GAP_SNAP_SECS)
+            .map_or(position, |&(_, end)| end)
+    }
+}
+
+/// Band energies and power of each hop of a window
+struct Extractor {
+    bands: Vec<Biquad>,
+    hop_len: usize,
+    filled: usize,
+    energy: [f64; BANDS],
+    power: f64,
+    hops: Vec<([f64; BANDS], f64)>,
+}
+
+impl Extractor {
+    fn new(rate: u32) -> Self {
+        let rate = rate as f64;
+        let (low, high) = BAND_RANGE_HZ;
+        let ratio = (high / low).powf(1.0 / BANDS as f64);
+        let bands = (0..BANDS)
+            .map(|band| {
+                let lower = low * ratio.powi(band as i32);
+                let upper = lower * ratio;
+                let centre = (lower * upper).sqrt().min(rate * 0.45);
+                let q = centre / (upper - lower);
+                let w0 = 2.0 * PI * centre / rate;
+                let alpha = w0.sin() / (2.0 * q);
+                let a0 = 1.0 + alpha;
+                Biquad::new(
+                    [alpha / a0, 0.0, -alpha / a0],
+                    [-2.0 * w0.cos() / a0, (1.0 - alpha) / a0],
+                )
+            })
+            .collect();
+        Self {
+            bands,
+            hop_len: ((rate * HOP_SECS).round() as usize).max(1),
+            filled: 0,
+            energy: [0.0; BANDS],
+            power: 0.0,
+            hops: Vec::new(),
+        }
+    }
+
+    /// Add interleaved samples, downmixed to mono
+    fn add_frames(&mut self, samples: &[f32], channels: usize) {
+        for frame in samples.chunks_exact(channels) {
+            let x = frame.iter().map(|&s| s as f64).sum::<f64>() / channels as f64;
+            self.power += x * x;
+            for (filter, energy) in self.bands.iter_mut().zip(self.energy.iter_mut()) {
+                let y = filter.process(x);
+                *energy += y * y;
+            }
+            self.filled += 1;
+            if self.filled == self.hop_len {
+                self.hops
+                    .push((self.energy, self.power / self.hop_len as f64));
+                self.energy = [0.0; BANDS];
+                self.power = 0.0;
+                self.filled = 0;
+            }
+        }
+    }
+
+    fn finish(self, offset: f64) -> Window {
+        let silence = 10f64.powf(SILENCE_DBFS / 10.0);
+        let frames: Vec<([f64; BANDS], f64)> = self
+            .hops
+            .windows(HOPS_PER_FRAME)
+            .map(|hops| {
+                let mut energy = [0.0; BANDS];
+                let mut power = 0.0;
+                for (hop_energy, hop_power) in hops {
+                    for (sum, value) in energy.iter_mut().zip(hop_energy) {
+                        *sum += value;
+                    }
+                    power += hop_power;
+                }
+                (energy, power / HOPS_PER_FRAME as f64)
+            })
+            .collect();
+
+        let mut prints = Vec::with_capacity(frames.len());
+        let mut audible = Vec::with_capacity(frames.len());
+        for pair in frames.windows(2) {
+            let ((previous, _), (current, power)) = (&pair[0], &pair[1]);
+            let mut print = 0u16;
+            for bit in 0..BITS {
+                let difference =
+                    (current[bit] - current[bit + 1]) - (previous[bit] - previous[bit + 1]);
+                if difference > 0.0 {
+                    print |= 1 << bit;
+                }
+            }
+            prints.push(print);
+            audible.push(*power > silence);
+        }
+
+        let mut silences = Vec::new();
+        let mut silent_since = None;
+        for (hop, (_, power)) in self.hops.iter().enumerate() {
+            match (*power <= silence, silent_since) {
+                (true, None) => silent_since = Some(hop),
+                (false, Some(start)) => {
+                    silences.push((start, hop));
+                    silent_since = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = silent_since {
+            silences.push((start, self.hops.len()));
+        }
+        let time = |hop: usize| offset + hop as f64 * HOP_SECS;
+        let silences = silences
+            .into_iter()
+            .map(|(start, end)| (time(start), time(end)))
+            .filter(|(start, end)| end - start >= MIN_SILENCE_SECS)
+            .collect();
+
+        Window {
+            offset,
+            end: time(self.hops.len()),
+            prints,
+            audible,
+            silences,
+        }
+    }
+}
+
+/// Fingerprints of both ends of a chapter
+#[derive(Debug, Clone)]
+pub struct ChapterPrint {
+    /// Chapter length in seconds
+    length: f64,
+    head: Window,
+    tail: Option<Window>,
+}
+
+/// Fingerprint the chapter playing `length` seconds of `head` from `start`
+/// seconds in. The end of chapters longer than [`WINDOW_SECS`] is read from
+/// `tail`, a second copy of the source; without it only the intro is looked
+/// for. Blocks until the audio is decoded, so call it from a blocking thread.
+pub fn fingerprint(
+    head: Box<dyn MediaSource>,
+    tail: Option<Box<dyn MediaSource>>,
+    hint: Hint,
+    start: f64,
+    length: f64,
+) -> Result<ChapterPrint> {
+    let head = read_window(head, &hint, start, 0.0, length.min(WINDOW_SECS))?;
+    let tail = if length <= WINDOW_SECS {
+        Some(head.clone())
+    } else {
+        let offset = length - WINDOW_SECS;
+        tail.map(|tail| read_window(tail, &hint, start + offset, offset, WINDOW_SECS))
+            .transpose()?
+    };
+    Ok(ChapterPrint { length, head, tail })
+}
+
+fn read_window(
+    source: Box<dyn MediaSource>,
+    hint: &Hint,
+    start: f64,
+    offset: f64,
+    limit: f64,
+) -> Result<Window> {
+    let mut extractor: Option<Extractor> = None;
+    decode_samples(source, hint, start, Some(limit), |spec, samples| {
+        extractor
+            .get_or_insert_with(|| Extractor::new(spec.rate))
+            .add_frames(samples, spec.channels.count().max(1))
+    })?;
+
+    extractor
+        .map(|extractor| extractor.finish(offset))
+        .ok_or_else(|| TingError::InvalidRequest("No audio to analyse".to_string()))
+}
+
+/// Skips found across the chapters of a book
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SkipDetection {
+    /// Seconds to skip at the start of every chapter, when the intros agree
+    pub skip_intro: Option<i32>,
+    /// Seconds to skip at the end of every chapter, when the outros agree
+    pub skip_outro: Option<i32>,
+    /// Skip ranges of each chapter, in the order the chapters were given
+    pub ranges: Vec<Vec<SkipRange>>,
+}
+
+/// Find the intros and outros of a book's chapters, given in play order
+pub fn detect(chapters: &[ChapterPrint]) -> SkipDetection {
+    let heads: Vec<_> = chapters.iter().map(|chapter| Some(&chapter.head)).collect();
+    let tails: Vec<_> = chapters
+        .iter()
+        .map(|chapter| chapter.tail.as_ref())
+        .collect();
+    let intro_jingles = shared_stretches(&heads);
+    let outro_jingles = shared_stretches(&tails);
+
+    let mut intros = Vec::with_capacity(chapters.len());
+    let mut outros = Vec::with_capacity(chapters.len());
+    for (index, chapter) in chapters.iter().enumerate() {
+        let fits = |&(start, end): &(f64, f64)| end - start <= chapter.length / 2.0;
+        let intro = intro_range(&chapter.head, intro_jingles[index]).filter(fits);
+        // Both ends of a short chapter are the same audio, where one jingle
+        // would be found twice
+        let outro = chapter
+            .tail
+            .as_ref()
+            .and_then(|tail| outro_range(tail, chapter.length, outro_jingles[index]))
+            .filter(fits)
+            .filter(|(start, _)| intro.map_or(true, |(_, end)| *start >= end));
+        intros.push(intro);
+        outros.push(outro);
+    }
+
+    let opening: Vec<f64> = intros
+        .iter()
+        .flatten()
+        .filter(|(start, _)| *start <= 0.0)
+        .map(|(_, end)| *end)
+        .collect();
+    let closing: Vec<f64> = chapters
+        .iter()
+        .zip(&outros)
+        .filter_map(|(chapter, outro)| {
+            outro
+                .filter(|(_, end)| *end >= chapter.length)
+                .map(|(start, _)| chapter.length - start)
+        })
+        .collect();
+    let with_tails = chapters
+        .iter()
+        .filter(|chapter| chapter.tail.is_some())
+        .count();
+
+    let ranges = intros
+        .iter()
+        .zip(&outros)
+        .map(|(intro, outro)| {
+            [(SkipKind::Intro, intro), (SkipKind::Outro, outro)]
+                .into_iter()
+                .filter_map(|(kind, range)| {
+                    range.map(|(start, end)| SkipRange {
+                        kind,
+                        start: round_tenth(start),
+                        end: round_tenth(end),
+                    })
+                })
+                .collect()
+        })
+        .collect();
+
+    SkipDetection {
+        skip_intro: agreed(&opening, chapters.len()),
+        skip_outro: agreed(&closing, with_tails),
+        ranges,
+    }
+}
+
+/// The longest stretch each window shares with a neighbouring chapter's, as
+/// chapter positions
+fn shared_stretches(windows: &[Option<&Window>]) -> Vec<Option<(f64, f64)>> {
+    let mut found: Vec<Option<(f64, f64)>> = vec![None; windows.len()];
+    for (index, window) in windows.iter().enumerate() {
+        let Some(window) = *window else {
+            continue;
+        };
+        let neighbours = (index + 1)..windows.len().min(index + 1 + NEIGHBOURS);
+        for other_index in neighbours {
+            let Some(other) = windows[other_index] else {
+                continue;
+            };
+            let Some((start, other_start, len)) = longest_match(window, other) else {
+                continue;
+            };
+            for (slot, owner, first) in [(index, window, start), (other_index, other, other_start)]
+            {
+                let stretch = owner.stretch(first, len);
+                if found[slot].map_or(true, |(start, end)| end - start < stretch.1 - stretch.0) {
+                    found[slot] = Some(stretch);
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Longest stretch two windows share at any offset, as `(start in a,
+/// start in b, frames)`
+fn longest_match(a: &Window, b: &Window) -> Option<(usize, usize, usize)> {
+    let min_len = (MIN_JINGLE_SECS / HOP_SECS) as usize;
+    let (a_len, b_len) = (a.prints.len(), b.prints.len());
+    if a_len < min_len || b_len < min_len {
+        return None;
+    }
+    let max_errors = (MAX_BIT_ERROR_RATE * (BITS * MATCH_FRAMES) as f64) as u32;
+
+    let mut best: Option<(usize, usize, usize)> = None;
+    let mut errors = Vec::with_capacity(a_len.min(b_len));
+    // Every offset of the two windows, as the first frames compared
+    let offsets = (0..=b_len - min_len)
+        .rev()
+        .map(|b_start| (0, b_start))
+        .chain((1..=a_len - min_len).map(|a_start| (a_start, 0)));
+    for (a_start, b_start) in offsets {
+        let overlap = (a_len - a_start).min(b_len - b_start);
+        if best.is_some_and(|(_, _, len)| overlap <= len) {
+            continue;
+        }
+        errors.clear();
+        errors.extend((0..overlap).map(|k| {
+            let (i, j) = (a_start + k, b_start + k);
+            if a.audible[i] && b.audible[j] {
+                (a.prints[i] ^ b.prints[j]).count_ones()
+            } else {
+                BITS as u32
+            }
+        }));
+        if let Some((start, len)) = longest_run(&errors, max_errors) {
+            if len >= min_len && best.map_or(true, |(_, _, best)| len > best) {
+                best = Some((a_start + start, b_start + start, len));
+            }
+        }
+    }
+    best
+}
+
+/// Longest stretch of `errors` whose every second stays within `max_errors`,
+/// as `(start, length)`
+fn longest_run(errors: &[u32], max_errors: u32) -> Option<(usize, usize)> {
+    if errors.len() < MATCH_FRAMES {
+        return None;
+    }
+    let mut sum: u32 = errors[..MATCH_FRAMES].iter().sum();
+    let mut best: Option<(usize, usize)> = None;
+    let mut run_start = None;
+    for first in 0..=errors.len() - MATCH_FRAMES {
+        if first > 0 {
+            sum = sum + errors[first + MATCH_FRAMES - 1] - errors[first - 1];
+        }
+        if sum <= max_errors {
+            let start = *run_start.get_or_insert(first);
+            let len = first + MATCH_FRAMES - start;
+            if best.map_or(true, |(_, best)| len > best) {
+                best = Some((start, len));
+            }
+        } else {
+            run_start = None;
+        }
+    }
+    best
+}
+
+/// Intro of a chapter: its jingle, stretched to the chapter start when it
+/// opens the chapter and over the silences around it, or else the silence
+/// the chapter opens with
+fn intro_range(window: &Window, jingle: Option<(f64, f64)>) -> Option<(f64, f64)> {
+    match jingle {
+        Some((start, end)) => {
+            let start = if start <= window.offset + EDGE_SNAP_SECS {
+                window.offset
+            } else {
+                window.silence_before(start)
+            };
+            Some((start, window.silence_after(end)))
+        }
+        None => window
+            .silences
+            .first()
+            .filter(|(start, _)| *start <= window.offset)
+            .map(|&(_, end)| (window.offset, end)),
+    }
+}
+
+/// Outro of a chapter, found like its intro
+fn outro_range(window: &Window, length: f64, jingle: Option<(f64, f64)>) -> Option<(f64, f64)> {
+    match jingle {
+        Some((start, end)) => {
+            let end = if end >= length - EDGE_SNAP_SECS {
+                length
+            } else {
+                window.silence_after(end)
+            };
+            Some((window.silence_before(start), end))
+        }
+        None => window
+            .silences
+            .last()
+            .filter(|(_, end)| *end >= window.end)
+            .map(|&(start, _)| (start, length)),
+    }
+}
+
+/// A book-wide skip when most of `chapters` skip about as much at one end:
+/// the shortest such skip, so that no chapter loses more than its own
+fn agreed(skips: &[f64], chapters: usize) -> Option<i32> {
+    if skips.len() < 2 || skips.len() * 2 < chapters {
+        return None;
+    }
+    let shortest = skips.iter().copied().fold(f64::INFINITY, f64::min);
+    let longest = skips.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if longest - shortest > AGREEMENT_SECS {
+        return None;
+    }
+    let seconds = shortest.floor() as i32;
+    (seconds > 0).then_some(seconds)
+}
+
+fn round_tenth(seconds: f64) -> f64 {
+    (seconds * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 8000;
+
+    fn jingle(seconds: f64, notes: &[f64]) -> Vec<f32> {
+        let len = (seconds * RATE as f64) as usize;
+        (0..len)
+            .map(|n| {
+                let t = n as f64 / RATE as f64;
+                let note = notes[(t / 0.25) as usize % notes.len()];
+                (0.25 * (2.0 * PI * note * t).sin() + 0.15 * (2.0 * PI * note * 1.5 * t).sin())
+                    as f32
+            })
+            .collect()
+    }
+
+    fn noise(seconds: f64, seed: u32) -> Vec<f32> {
+        let mut state = seed.wrapping_mul(2654435761).wrapping_add(1);
+        (0..(seconds * RATE as f64) as usize)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                (state >> 8) as f32 / (1 << 24) as f32 * 0.6 - 0.3
+            })
+            .collect()
+    }
+
+    fn silence(seconds: f64) -> Vec<f32> {
+        vec![0.0; (seconds * RATE as f64) as usize]
+    }
+
+    /// Fingerprint the first and last `window` seconds of `samples`
+    fn print(samples: &[f32], window: f64) -> ChapterPrint {
+        let length = samples.len() as f64 / RATE as f64;
+        let split = samples.len() - (window * RATE as f64) as usize;
+        let mut head = Extractor::new(RATE);
+        head.add_frames(&samples[..(window * RATE as f64) as usize], 1);
+        let mut tail = Extractor::new(RATE);
+        tail.add_frames(&samples[split..], 1);
+        ChapterPrint {
+            length,
+            head: head.finish(0.0),
+            tail: Some(tail.finish(split as f64 / RATE as f64)),
+        }
+    }
+
+    #[test]
+    fn finds_jingles_shared_by_neighbouring_chapters() {
+        let intro = jingle(5.0, &[440.0, 660.0, 520.0, 880.0, 1240.0, 700.0]);
+        let outro = jingle(4.0, &[1500.0, 980.0, 600.0, 2100.0, 800.0]);
+        let chapters: Vec<ChapterPrint> = (0..4)
+            .map(|seed| {
+                let samples = [
+                    intro.clone(),
+                    silence(1.0),
+                    noise(20.0 + seed as f64, seed),
+                    silence(1.0),
+                    outro.clone(),
+                ]
+                .concat();
+                print(&samples, 12.0)
+            })
+            .collect();
+
+        let detection = detect(&chapters);
+        assert_eq!(detection.ranges.len(), 4);
+        for (chapter, ranges) in chapters.iter().zip(&detection.ranges) {
+            assert_eq!(ranges.len(), 2, "{:?}", ranges);
+            assert_eq!(ranges[0].kind, SkipKind::Intro);
+            assert_eq!(ranges[0].start, 0.0);
+            assert!((ranges[0].end - 6.0).abs() <= 0.3, "{:?}", ranges[0]);
+            assert_eq!(ranges[1].kind, SkipKind::Outro);
+            assert!(
+                (ranges[1].start - (chapter.length - 5.0)).abs() <= 0.3,
+                "{:?}",
+                ranges[1]
+            );
+            assert_eq!(ranges[1].end, round_tenth(chapter.length));
+        }
+        assert!(matches!(detection.skip_intro, Some(5..=6)));
+        assert!(matches!(detection.skip_outro, Some(4..=5)));
+    }
+
+    #[test]
+    fn skips_edge_silence_without_a_jingle() {
+        let chapters: Vec<ChapterPrint> = (0..3)
+            .map(|seed| {
+                let samples = [silence(2.0), noise(20.0, seed), silence(1.5)].concat();
+                print(&samples, 10.0)
+            })
+            .collect();
+
+        let detection = detect(&chapters);
+        for ranges in &detection.ranges {
+            assert_eq!(
+                ranges,
+                &vec![
+                    SkipRange {
+                        kind: SkipKind::Intro,
+                        start: 0.0,
+                        end: 2.0
+                    },
+                    SkipRange {
+                        kind: SkipKind::Outro,
+                        start: 22.0,
+                        end: 23.5
+                    },
+                ]
+            );
+        }
+        assert_eq!(detection.skip_intro, Some(2));
+        assert_eq!(detection.skip_outro, Some(1));
+        assert_eq!(agreed(&[3.0, 8.0, 3.5], 3), None);
+        assert_eq!(agreed(&[3.0], 1), None);
+    }
+}
@@ -1,9 +1,10 @@
 use crate::core::error::{Result, TingError};
-use crate::db::models::Book;
+use crate::db::models::{Book, Chapter, ChapterSegment};
 use crate::db::repository::{BookRepository, ChapterRepository, Repository};
 use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::{info, warn};
+use uuid::Uuid;
 
 /// Service for handling book merging and chapter moves.
 pub struct MergeService {
@@ -289,6 +290,237 @@ impl MergeService {
         Ok(())
     }
 
+    /// Split a chapter into consecutive slices at chapter-relative `offsets` (seconds).
+    ///
+    /// The first slice keeps the original chapter id and saved progress moves to
+    /// the slice it falls into; later chapters of the book are shifted to make
+    /// room. All slices are marked as manually corrected so rescans keep them.
+    pub async fn split_chapter(
+        &self,
+        chapter_id: &str,
+        offsets: Vec<f64>,
+        titles: Vec<String>,
+    ) -> Result<Vec<Chapter>> {
+        let chapter = self
+            .chapter_repo
+            .find_by_id(chapter_id)
+            .await?
+            .ok_or_else(|| TingError::NotFound("Chapter not found".to_string()))?;
+        let length = chapter
+            .slice_length()
+            .filter(|length| *length > 0.0)
+            .ok_or_else(|| {
+                TingError::ValidationError("Chapter duration is unknown; cannot split".to_string())
+            })?;
+        if !self
+            .chapter_repo
+            .find_segments(chapter_id)
+            .await?
+            .is_empty()
+        {
+            return Err(TingError::ValidationError(
+                "Chapters joined from several files cannot be split".to_string(),
+            ));
+        }
+        validate_split_offsets(&offsets, length)?;
+
+        let base_start = chapter.start_offset.unwrap_or(0.0);
+        let base_title = chapter.title.clone().unwrap_or_default();
+        let base_index = chapter.chapter_index.unwrap_or(0);
+
+        let mut bounds = vec![0.0];
+        bounds.extend(offsets.iter().copied());
+        bounds.push(length);
+
+        let mut slices = Vec::with_capacity(bounds.len() - 1);
+        for (position, window) in bounds.windows(2).enumerate() {
+            let start = base_start + window[0];
+            let end = base_start + window[1];
+            let title = titles
+                .get(position)
+                .map(|title| title.trim())
+                .filter(|title| !title.is_empty())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("{} ({})", base_title, position + 1));
+
+            let mut slice = if position == 0 {
+                chapter.clone()
+            } else {
+                Chapter {
+                    id: Uuid::new_v4().to_string(),
+                    created_at: chrono::Utc::now().to_rfc3339(),
+                    ..chapter.clone()
+                }
+            };
+            slice.title = Some(title);
+            slice.chapter_index = Some(base_index + position as i32);
+            slice.duration = Some((end - start).round() as i32);
+            slice.start_offset = Some(start);
+            slice.end_offset = Some(end);
+            slice.manual_corrected = 1;
+            slice.hash = chapter.hash.as_deref().map(|hash| {
+                let file_hash = hash.split('@').next().unwrap_or(hash);
+                format!("{}@{}", file_hash, (start * 1000.0).round() as i64)
+            });
+
+            slices.push(slice);
+        }
+        self.chapter_repo.apply_split(slices.clone()).await?;
+
+        info!("Split chapter {} into {} slices", chapter_id, slices.len());
+        Ok(slices)
+    }
+
+    /// Join chapters into one: adjacent slices of the same file, or
+    /// neighbouring chapters of different files.
+    ///
+    /// The earliest chapter survives and absorbs the others' time range and
+    /// saved progress. Chapters of several files keep their file ranges as
+    /// segments, which are played back to back.
+    pub async fn join_chapters(
+        &self,
+        book_id: &str,
+        chapter_ids: Vec<String>,
+        title: Option<String>,
+    ) -> Result<Chapter> {
+        if chapter_ids.len() < 2 {
+            return Err(TingError::ValidationError(
+                "At least two chapters are required to join".to_string(),
+            ));
+        }
+
+        let mut chapters = Vec::with_capacity(chapter_ids.len());
+        for chapter_id in &chapter_ids {
+            let chapter = self
+                .chapter_repo
+                .find_by_id(chapter_id)
+                .await?
+                .ok_or_else(|| TingError::NotFound(format!("Chapter {} not found", chapter_id)))?;
+            if chapter.book_id != book_id {
+                return Err(TingError::ValidationError(format!(
+                    "Chapter {} does not belong to book {}",
+                    chapter_id, book_id
+                )));
+            }
+            chapters.push(chapter);
+        }
+
+        if chapters
+            .iter()
+            .any(|chapter| chapter.is_extra != chapters[0].is_extra)
+        {
+            return Err(TingError::ValidationError(
+                "Main and extra chapters cannot be joined".to_string(),
+            ));
+        }
+
+        // Expand every chapter into the file ranges it plays
+        let mut parts = Vec::with_capacity(chapters.len());
+        for chapter in &chapters {
+            let length = chapter.slice_length().ok_or_else(|| {
+                TingError::ValidationError(format!("Chapter {} has no known duration", chapter.id))
+            })?;
+            let mut segments = self.chapter_repo.find_segments(&chapter.id).await?;
+            if segments.is_empty() {
+                segments.push(ChapterSegment {
+                    path: chapter.path.clone(),
+                    start_offset: chapter.start_offset,
+                    end_offset: chapter.end_offset,
+                });
+            }
+            parts.push((segments, length));
+        }
+
+        let single_file = parts
+            .iter()
+            .flat_map(|(segments, _)| segments)
+            .all(|segment| segment.path == chapters[0].path);
+        let mut order: Vec<usize> = (0..chapters.len()).collect();
+        if single_file {
+            // Slices of one file play in time order and must leave no gap
+            let windows: Vec<(f64, f64)> = chapters
+                .iter()
+                .zip(&parts)
+                .map(|(chapter, (_, length))| {
+                    let start = chapter.start_offset.unwrap_or(0.0);
+                    (start, start + length)
+                })
+                .collect();
+            order.sort_by(|a, b| windows[*a].0.total_cmp(&windows[*b].0));
+            if !slices_are_contiguous(order.iter().map(|index| windows[*index])) {
+                return Err(TingError::ValidationError(
+                    "Only adjacent slices can be joined".to_string(),
+                ));
+            }
+        } else {
+            // Different files play in book order and must be neighbours there
+            order.sort_by_key(|index| chapters[*index].chapter_index);
+            let mut neighbours: Vec<Chapter> = self
+                .chapter_repo
+                .find_by_book(book_id)
+                .await?
+                .into_iter()
+                .filter(|chapter| chapter.is_extra == chapters[0].is_extra)
+                .collect();
+            neighbours.sort_by_key(|chapter| chapter.chapter_index);
+            let positions: Vec<usize> = order
+                .iter()
+                .filter_map(|index| {
+                    neighbours
+                        .iter()
+                        .position(|chapter| chapter.id == chapters[*index].id)
+                })
+                .collect();
+            if positions.windows(2).any(|pair| pair[1] != pair[0] + 1) {
+                return Err(TingError::ValidationError(
+                    "Only neighbouring chapters can be joined".to_string(),
+                ));
+            }
+        }
+
+        let mut segments: Vec<ChapterSegment> = Vec::new();
+        let mut absorbed = Vec::with_capacity(order.len() - 1);
+        let mut total = 0.0;
+        for (position, index) in order.iter().enumerate() {
+            if position > 0 {
+                absorbed.push((chapters[*index].clone(), total));
+            }
+            let (chapter_segments, length) = &parts[*index];
+            total += length;
+            for segment in chapter_segments {
+                match segments.last_mut() {
+                    Some(last) if segment_continues(last, segment) => {
+                        last.end_offset = segment.end_offset;
+                    }
+                    _ => segments.push(segment.clone()),
+                }
+            }
+        }
+
+        let mut joined = chapters[order[0]].clone();
+        if let Some(title) = title.filter(|title| !title.trim().is_empty()) {
+            joined.title = Some(title);
+        }
+        joined.path = segments[0].path.clone();
+        if segments.len() == 1 {
+            joined.start_offset = segments[0].start_offset;
+            joined.end_offset = segments[0].end_offset;
+            segments.clear();
+        } else {
+            // The joined chapter spans files, so its range lives in the segments
+            joined.start_offset = None;
+            joined.end_offset = None;
+        }
+        joined.duration = Some(total.round() as i32);
+        joined.manual_corrected = 1;
+        self.chapter_repo
+            .apply_join(joined.clone(), absorbed, segments)
+            .await?;
+
+        info!("Joined {} chapters into {}", chapter_ids.len(), joined.id);
+        Ok(joined)
+    }
+
     /// Update manual correction status for a book
     pub async fn update_manual_correction(
         &self,
@@ -309,3 +541,72 @@ impl MergeService {
         Ok(())
     }
 }
+
+/// Split points must be strictly increasing and fall inside the chapter
+fn validate_split_offsets(offsets: &[f64], length: f64) -> Result<()> {
+    if offsets.is_empty() {
+        return Err(TingError::ValidationError(
+            "At least one split offset is required".to_string(),
+        ));
+    }
+    let mut previous = 0.0;
+    for offset in offsets {
+        if !offset.is_finite() || *offset <= previous || *offset >= length {
+            return Err(TingError::ValidationError(format!(
+                "Split offset {} must be increasing and within 0..{}",
+                offset, length
+            )));
+        }
+        previous = *offset;
+    }
+    Ok(())
+}
+
+/// Sorted `(start, end)` windows are contiguous when each starts where the last ended
+const CONTIGUITY_TOLERANCE_SECS: f64 = 0.5;
+
+fn slices_are_contiguous(mut windows: impl Iterator<Item = (f64, f64)>) -> bool {
+    let Some((_, mut end)) = windows.next() else {
+        return true;
+    };
+    for (start, next_end) in windows {
+        if (start - end).abs() > CONTIGUITY_TOLERANCE_SECS {
+            return false;
+        }
+        end = next_end;
+    }
+    true
+}
+
+/// Whether `next` picks up the same file where `last` stops.
+fn segment_continues(last: &ChapterSegment, next: &ChapterSegment) -> bool {
+    match (last.end_offset, next.start_offset) {
+        (Some(end), Some(start)) => {
+            last.path == next.path && (start - end).abs() <= CONTIGUITY_TOLERANCE_SECS
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slices_are_contiguous, validate_split_offsets};
+
+    #[test]
+    fn validates_split_offsets_inside_chapter() {
+        assert!(validate_split_offsets(&[60.0, 120.0], 300.0).is_ok());
+        assert!(validate_split_offsets(&[], 300.0).is_err());
+        assert!(validate_split_offsets(&[120.0, 60.0], 300.0).is_err());
+        assert!(validate_split_offsets(&[300.0], 300.0).is_err());
+    }
+
+    #[test]
+    fn detects_gaps_between_slices() {
+        assert!(slices_are_contiguous(
+            [(0.0, 60.0), (60.2, 120.0)].into_iter()
+        ));
+        assert!(!slices_are_contiguous(
+            [(0.0, 60.0), (90.0, 120.0)].into_iter()
+        ));
+    }
+}
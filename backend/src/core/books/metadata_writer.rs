@@ -215,6 +215,11 @@ mod tests {
             hash: None,
             manual_corrected: 0,
             created_at: String::new(),
+            start_offset: None,
+            end_offset: None,
+            loudness_lufs: None,
+            true_peak_dbtp: None,
+            skip_ranges: None,
         }
     }
 
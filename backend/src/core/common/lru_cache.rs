@@ -1,6 +1,6 @@
 //! Generic LRU cache with size-based eviction
 //!
-//! Used by AudioStreamer and DecryptionCacheService.
+//! Used by AudioStreamer, DecryptionCacheService and TranscodeCache.
 
 use std::collections::HashMap;
 use std::hash::Hash;
@@ -51,12 +51,18 @@ where
         })
     }
 
-    pub fn insert(&mut self, key: K, value: V, size: u64) {
+    /// Insert an entry, replacing any entry with the same key, and return
+    /// the entries evicted to make room for it
+    pub fn insert(&mut self, key: K, value: V, size: u64) -> Vec<(K, V)> {
+        self.remove(&key);
+
         // Evict oldest entries if adding this would exceed max_size
+        let mut evicted = Vec::new();
         while self.total_size + size > self.max_size && !self.entries.is_empty() {
             if let Some(oldest_key) = self.find_oldest_key() {
                 if let Some(removed) = self.entries.remove(&oldest_key) {
                     self.total_size = self.total_size.saturating_sub(removed.size);
+                    evicted.push((oldest_key, removed.value));
                 }
             } else {
                 break;
@@ -72,6 +78,7 @@ where
             },
         );
         self.total_size += size;
+        evicted
     }
 
     pub fn remove(&mut self, key: &K) -> Option<V> {
@@ -141,7 +148,8 @@ mod tests {
         cache.get(&"a".to_string());
 
         // Insert 40 more: 50 + 30 + 40 = 120 > 100, evicts oldest ("b")
-        cache.insert("c".into(), "value_c".into(), 40);
+        let evicted = cache.insert("c".into(), "value_c".into(), 40);
+        assert_eq!(evicted, vec![("b".to_string(), "value_b".to_string())]);
         assert!(!cache.contains_key(&"b".to_string()));
         assert!(cache.contains_key(&"a".to_string()));
         assert!(cache.contains_key(&"c".to_string()));
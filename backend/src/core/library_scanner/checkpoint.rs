@@ -0,0 +1,244 @@
+//! Checkpoints of interrupted scans
+//!
+//! A `library_scan` task keeps a checkpoint under `checkpoint` in its payload
+//! while it runs. When the task is retried, or recovered after a restart, the
+//! scanner reads the checkpoint back and skips the work an earlier attempt
+//! already did. A scan that completes removes its checkpoint again.
+
+use super::{LibraryScanner, NewEpisode, ScanResult};
+use crate::core::error::TingError;
+use crate::core::task_queue::TaskPayload;
+use crate::db::repository::Repository;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Minimum time between two checkpoint writes of the same scan
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Progress of a library scan, stored in its task payload
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+    /// Book directories (local paths or WebDAV URLs) already processed, with
+    /// the book each one became
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub processed: HashMap<String, String>,
+    /// Books counted by the processed directories
+    #[serde(default)]
+    pub total_books: usize,
+    #[serde(default)]
+    pub books_created: usize,
+    #[serde(default)]
+    pub books_updated: usize,
+    #[serde(default)]
+    pub books_skipped: usize,
+    /// WebDAV directory listing, partial until `complete` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listing: Option<WebDavListingCursor>,
+    /// RSS episodes already stored, by chapter hash
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub episodes: HashMap<String, String>,
+    /// RSS episodes reported as new so far
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub new_episodes: Vec<NewEpisode>,
+    #[serde(skip)]
+    last_saved: Option<Instant>,
+}
+
+/// Where a breadth-first WebDAV listing stands
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebDavListingCursor {
+    /// Directories still to be listed, in order
+    pub pending: Vec<String>,
+    /// Directories listed or queued so far
+    pub visited: Vec<String>,
+    /// Number of directories listed so far
+    pub listed: usize,
+    /// Files found so far, with their modification time
+    pub files: Vec<(String, Option<DateTime<Utc>>)>,
+    /// Whether every directory has been listed
+    pub complete: bool,
+}
+
+impl ScanCheckpoint {
+    /// Whether there is anything to resume from
+    pub fn is_empty(&self) -> bool {
+        self.processed.is_empty()
+            && self.listing.is_none()
+            && self.episodes.is_empty()
+            && self.new_episodes.is_empty()
+    }
+
+    /// Whether `CHECKPOINT_INTERVAL` has passed since the last write
+    pub fn save_due(&self) -> bool {
+        self.last_saved
+            .map_or(true, |at| at.elapsed() >= CHECKPOINT_INTERVAL)
+    }
+
+    /// Start a result with the books an earlier attempt already counted
+    pub fn resume_result(&self) -> ScanResult {
+        ScanResult {
+            total_books: self.total_books,
+            books_created: self.books_created,
+            books_updated: self.books_updated,
+            books_skipped: self.books_skipped,
+            new_episodes: self.new_episodes.clone(),
+            start_time: Some(Instant::now()),
+            ..Default::default()
+        }
+    }
+
+    /// Note a processed book directory along with the counters it changed
+    pub fn record(&mut self, dir: String, book_id: String, result: &ScanResult) {
+        self.processed.insert(dir, book_id);
+        self.total_books = result.total_books;
+        self.books_created = result.books_created;
+        self.books_updated = result.books_updated;
+        self.books_skipped = result.books_skipped;
+    }
+
+    /// Note a stored RSS episode along with the counters and new episodes so far
+    pub fn record_episode(
+        &mut self,
+        chapter_hash: String,
+        chapter_id: String,
+        result: &ScanResult,
+    ) {
+        self.episodes.insert(chapter_hash, chapter_id);
+        self.total_books = result.total_books;
+        self.books_created = result.books_created;
+        self.books_updated = result.books_updated;
+        self.books_skipped = result.books_skipped;
+        self.new_episodes = result.new_episodes.clone();
+    }
+}
+
+impl LibraryScanner {
+    /// Checkpoint an earlier attempt of the task left in its payload
+    pub(crate) async fn load_checkpoint(&self, task_id: Option<&str>) -> ScanCheckpoint {
+        let (Some(repo), Some(task_id)) = (&self.task_repo, task_id) else {
+            return ScanCheckpoint::default();
+        };
+        let record = match repo.find_by_id(task_id).await {
+            Ok(Some(record)) => record,
+            Ok(None) => return ScanCheckpoint::default(),
+            Err(e) => {
+                warn!(task_id = %task_id, error = %e, "Failed to read scan checkpoint");
+                return ScanCheckpoint::default();
+            }
+        };
+        match record
+            .payload
+            .as_deref()
+            .and_then(|payload| serde_json::from_str::<TaskPayload>(payload).ok())
+        {
+            Some(TaskPayload::Custom { data, .. }) => data
+                .get("checkpoint")
+                .cloned()
+                .and_then(|checkpoint| serde_json::from_value(checkpoint).ok())
+                .unwrap_or_default(),
+            _ => ScanCheckpoint::default(),
+        }
+    }
+
+    /// Store the checkpoint in the task payload. Unless `force` is set, a
+    /// checkpoint written less than `CHECKPOINT_INTERVAL` ago is left as is.
+    pub(crate) async fn save_checkpoint(
+        &self,
+        task_id: Option<&str>,
+        checkpoint: &mut ScanCheckpoint,
+        force: bool,
+    ) {
+        if !force && !checkpoint.save_due() {
+            return;
+        }
+        checkpoint.last_saved = Some(Instant::now());
+        match serde_json::to_value(&*checkpoint) {
+            Ok(value) => self.write_checkpoint(task_id, Some(value)).await,
+            Err(e) => warn!(error = %e, "Failed to serialize scan checkpoint"),
+        }
+    }
+
+    /// Remove the checkpoint once the scan has completed
+    pub(crate) async fn clear_checkpoint(&self, task_id: Option<&str>) {
+        self.write_checkpoint(task_id, None).await;
+    }
+
+    async fn write_checkpoint(&self, task_id: Option<&str>, checkpoint: Option<serde_json::Value>) {
+        let (Some(repo), Some(task_id)) = (&self.task_repo, task_id) else {
+            return;
+        };
+        let result = async {
+            let Some(record) = repo.find_by_id(task_id).await? else {
+                return Ok(());
+            };
+            let Some(payload) = record.payload.as_deref() else {
+                return Ok(());
+            };
+            let mut payload: TaskPayload = serde_json::from_str(payload)
+                .map_err(|e| TingError::SerializationError(e.to_string()))?;
+            let TaskPayload::Custom { data, .. } = &mut payload else {
+                return Ok(());
+            };
+            let Some(fields) = data.as_object_mut() else {
+                return Ok(());
+            };
+            match checkpoint {
+                Some(checkpoint) => {
+                    fields.insert("checkpoint".to_string(), checkpoint);
+                }
+                None => {
+                    if fields.remove("checkpoint").is_none() {
+                        return Ok(());
+                    }
+                }
+            }
+            let payload = serde_json::to_string(&payload)
+                .map_err(|e| TingError::SerializationError(e.to_string()))?;
+            repo.update_payload(task_id, &payload).await
+        }
+        .await;
+        if let Err(e) = result {
+            warn!(task_id = %task_id, error = %e, "Failed to store scan checkpoint");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoints_round_trip_through_the_payload() {
+        let result = ScanResult {
+            total_books: 2,
+            books_created: 1,
+            books_skipped: 1,
+            ..Default::default()
+        };
+        let mut checkpoint = ScanCheckpoint::default();
+        assert!(checkpoint.is_empty());
+        checkpoint.record("/books/a".to_string(), "book-a".to_string(), &result);
+        checkpoint.listing = Some(WebDavListingCursor {
+            pending: vec!["https://dav/b".to_string()],
+            visited: vec!["https://dav".to_string(), "https://dav/b".to_string()],
+            listed: 1,
+            files: vec![("https://dav/a/1.mp3".to_string(), None)],
+            complete: false,
+        });
+
+        let value = serde_json::to_value(&checkpoint).unwrap();
+        let restored: ScanCheckpoint = serde_json::from_value(value).unwrap();
+        assert!(!restored.is_empty());
+        assert_eq!(restored.processed["/books/a"], "book-a");
+        assert_eq!(restored.listing.as_ref().unwrap().pending.len(), 1);
+
+        let resumed = restored.resume_result();
+        assert_eq!(resumed.total_books, 2);
+        assert_eq!(resumed.books_created, 1);
+        assert_eq!(resumed.books_skipped, 1);
+        assert!(resumed.errors.is_empty());
+    }
+}
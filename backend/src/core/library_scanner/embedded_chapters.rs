@@ -0,0 +1,653 @@
+//! Embedded chapter extraction for single-file audiobooks
+//!
+//! M4B/MP4 files frequently carry their own chapter list, either as a Nero
+//! `chpl` box under `moov/udta` or as a QuickTime text track. Single-file MP3
+//! rips instead ship a `.cue` sidecar or ID3v2 `CHAP`/`CTOC` frames. Each entry
+//! is turned into a virtual chapter that points at a time slice of the file.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// A chapter marker read from inside an audio file, in seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddedChapter {
+    pub title: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl EmbeddedChapter {
+    pub fn duration(&self) -> f64 {
+        (self.end - self.start).max(0.0)
+    }
+}
+
+/// Container extensions that may carry MP4 chapter metadata
+pub const MP4_CHAPTER_EXTENSIONS: &[&str] = &["m4b", "m4a", "mp4"];
+
+/// Returns true when the file extension can hold MP4 chapter metadata
+pub fn supports_mp4_chapters(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            MP4_CHAPTER_EXTENSIONS
+                .iter()
+                .any(|candidate| ext.eq_ignore_ascii_case(candidate))
+        })
+        .unwrap_or(false)
+}
+
+/// Returns true when the file may carry chapter markers of any supported kind
+pub fn supports_embedded_chapters(path: &Path) -> bool {
+    supports_mp4_chapters(path) || supports_id3_chapters(path) || find_cue_sheet(path).is_some()
+}
+
+/// Returns true when the file can hold ID3v2 `CHAP` frames
+pub fn supports_id3_chapters(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("mp3"))
+        .unwrap_or(false)
+}
+
+/// Read chapter markers for a local file from every supported source.
+///
+/// A `.cue` sidecar wins because users add it deliberately; embedded MP4 or
+/// ID3 chapters are used otherwise. `total_duration` closes the last CUE
+/// track, which has no explicit end.
+pub fn read_embedded_chapters(path: &Path, total_duration: Option<f64>) -> Vec<EmbeddedChapter> {
+    if let Some(cue_path) = find_cue_sheet(path) {
+        if let Ok(bytes) = std::fs::read(&cue_path) {
+            let file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let chapters = cue_sheet_chapters(&bytes, &file_name, total_duration.unwrap_or(0.0));
+            if !chapters.is_empty() {
+                return chapters;
+            }
+        }
+    }
+
+    if supports_mp4_chapters(path) {
+        return read_mp4_chapters(path);
+    }
+    if supports_id3_chapters(path) {
+        return read_id3_chapters(path, total_duration.unwrap_or(0.0));
+    }
+    Vec::new()
+}
+
+/// Locate a `.cue` sidecar next to an audio file (`book.cue` or `book.mp3.cue`)
+pub fn find_cue_sheet(path: &Path) -> Option<PathBuf> {
+    if path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("cue"))
+        .unwrap_or(true)
+    {
+        return None;
+    }
+    let mut appended = path.as_os_str().to_os_string();
+    appended.push(".cue");
+    [path.with_extension("cue"), PathBuf::from(appended)]
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+}
+
+/// Sidecar names to try for a remote file path or URL, in preference order
+pub fn cue_sidecar_candidates(file_path: &str) -> Vec<String> {
+    let name_start = file_path.rfind('/').map(|idx| idx + 1).unwrap_or(0);
+    let mut candidates = Vec::new();
+    if let Some(dot) = file_path[name_start..].rfind('.') {
+        candidates.push(format!("{}.cue", &file_path[..name_start + dot]));
+    }
+    candidates.push(format!("{}.cue", file_path));
+    candidates
+}
+
+/// Decode a CUE sheet and return the chapters it defines for `file_name`
+pub fn cue_sheet_chapters(
+    bytes: &[u8],
+    file_name: &str,
+    total_duration: f64,
+) -> Vec<EmbeddedChapter> {
+    parse_cue_sheet(&decode_cue_text(bytes), file_name, total_duration)
+}
+
+/// Read embedded chapters from an M4B/MP4 file.
+///
+/// QuickTime chapter tracks are preferred because they are what Apple tools
+/// write; the Nero `chpl` list is used as a fallback. Returns an empty list
+/// when the file has no usable chapter metadata.
+pub fn read_mp4_chapters(path: &Path) -> Vec<EmbeddedChapter> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Vec::new(),
+    };
+    let size = match file.metadata() {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return Vec::new(),
+    };
+
+    let mut reader = std::io::BufReader::new(file);
+    let chapters = read_text_track_chapters(&mut reader, size);
+    if !chapters.is_empty() {
+        return chapters;
+    }
+
+    if reader.seek(SeekFrom::Start(0)).is_err() {
+        return Vec::new();
+    }
+    read_nero_chapters(&mut reader, size).unwrap_or_default()
+}
+
+fn read_text_track_chapters<R: Read + Seek>(reader: &mut R, size: u64) -> Vec<EmbeddedChapter> {
+    let mut mp4 = match mp4::Mp4Reader::read_header(reader, size) {
+        Ok(mp4) => mp4,
+        Err(_) => return Vec::new(),
+    };
+
+    let text_handler = mp4::FourCC::from(*b"text");
+    let track = mp4
+        .tracks()
+        .values()
+        .find(|track| track.trak.mdia.hdlr.handler_type == text_handler)
+        .map(|track| (track.track_id(), track.timescale(), track.sample_count()));
+    let (track_id, timescale, sample_count) = match track {
+        Some(track) if track.1 > 0 => track,
+        _ => return Vec::new(),
+    };
+    let total = mp4.duration().as_secs_f64();
+
+    let mut chapters = Vec::new();
+    for sample_id in 1..=sample_count {
+        let sample = match mp4.read_sample(track_id, sample_id) {
+            Ok(Some(sample)) => sample,
+            _ => break,
+        };
+        let start = sample.start_time as f64 / timescale as f64;
+        let end = (sample.start_time + sample.duration as u64) as f64 / timescale as f64;
+        chapters.push(EmbeddedChapter {
+            title: decode_text_sample(&sample.bytes),
+            start,
+            end: if total > 0.0 { end.min(total) } else { end },
+        });
+    }
+    normalize_chapters(chapters, total)
+}
+
+/// QuickTime text samples are a big-endian u16 length followed by the text
+fn decode_text_sample(bytes: &[u8]) -> String {
+    if bytes.len() < 2 {
+        return String::new();
+    }
+    let len = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+    let text = &bytes[2..bytes.len().min(2 + len)];
+    decode_title(text)
+}
+
+fn decode_title(bytes: &[u8]) -> String {
+    // UTF-16 text samples start with a BOM
+    if bytes.len() >= 2 && bytes[0] == 0xFE && bytes[1] == 0xFF {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        return String::from_utf16_lossy(&units).trim().to_string();
+    }
+    String::from_utf8_lossy(bytes)
+        .trim_matches(char::from(0))
+        .trim()
+        .to_string()
+}
+
+/// Walk the top-level boxes to find `moov/udta/chpl` and parse it.
+fn read_nero_chapters<R: Read + Seek>(reader: &mut R, size: u64) -> Option<Vec<EmbeddedChapter>> {
+    let (moov_start, moov_size) = find_box(reader, 0, size, b"moov")?;
+    let duration = read_mvhd_duration(reader, moov_start, moov_size);
+    let (udta_start, udta_size) = find_box(reader, moov_start, moov_size, b"udta")?;
+    let (chpl_start, chpl_size) = find_box(reader, udta_start, udta_size, b"chpl")?;
+
+    // chpl payloads are small; refuse anything unreasonable
+    if chpl_size > 4 * 1024 * 1024 {
+        return None;
+    }
+    reader.seek(SeekFrom::Start(chpl_start)).ok()?;
+    let mut payload = vec![0u8; chpl_size as usize];
+    reader.read_exact(&mut payload).ok()?;
+
+    Some(parse_chpl(&payload, duration.unwrap_or(0.0)))
+}
+
+/// Parse a Nero `chpl` payload (without the box header).
+///
+/// Start times are stored in 100ns units; each chapter ends where the next one
+/// begins and the last one ends at `total_duration`.
+pub(crate) fn parse_chpl(payload: &[u8], total_duration: f64) -> Vec<EmbeddedChapter> {
+    if payload.len() < 5 {
+        return Vec::new();
+    }
+    let version = payload[0];
+    let mut pos = 4;
+    if version != 0 {
+        pos += 4;
+    }
+    let count = match payload.get(pos) {
+        Some(count) => *count as usize,
+        None => return Vec::new(),
+    };
+    pos += 1;
+
+    let mut markers = Vec::with_capacity(count);
+    for _ in 0..count {
+        if pos + 9 > payload.len() {
+            break;
+        }
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(&payload[pos..pos + 8]);
+        let start = u64::from_be_bytes(raw) as f64 / 10_000_000.0;
+        let title_len = payload[pos + 8] as usize;
+        pos += 9;
+        let title_end = (pos + title_len).min(payload.len());
+        let title = decode_title(&payload[pos..title_end]);
+        pos = title_end;
+        markers.push(EmbeddedChapter {
+            title,
+            start,
+            end: 0.0,
+        });
+    }
+
+    for index in 0..markers.len() {
+        markers[index].end = markers
+            .get(index + 1)
+            .map(|next| next.start)
+            .unwrap_or(total_duration);
+    }
+    normalize_chapters(markers, total_duration)
+}
+
+/// Read ID3v2 `CHAP` frames from an MP3 file.
+pub fn read_id3_chapters(path: &Path, total_duration: f64) -> Vec<EmbeddedChapter> {
+    match id3::Tag::read_from_path(path) {
+        Ok(tag) => chapters_from_id3_tag(&tag, total_duration),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Convert the `CHAP` frames of a tag into chapters.
+///
+/// When a top-level `CTOC` frame exists it decides which chapters are used and
+/// chapters it does not reference are ignored. Chapters are always ordered by
+/// start time, since their slices play back in file order.
+pub fn chapters_from_id3_tag(tag: &id3::Tag, total_duration: f64) -> Vec<EmbeddedChapter> {
+    let frames: Vec<&id3::frame::Chapter> = tag.chapters().collect();
+    if frames.is_empty() {
+        return Vec::new();
+    }
+
+    let toc = tag
+        .tables_of_contents()
+        .find(|toc| toc.top_level)
+        .or_else(|| tag.tables_of_contents().next());
+    let ordered: Vec<&id3::frame::Chapter> = match toc {
+        Some(toc) if !toc.elements.is_empty() => toc
+            .elements
+            .iter()
+            .filter_map(|id| frames.iter().find(|chap| &chap.element_id == id).copied())
+            .collect(),
+        _ => frames,
+    };
+
+    let chapters = ordered
+        .into_iter()
+        .map(|chap| {
+            let title = chap
+                .frames
+                .iter()
+                .find(|frame| frame.id() == "TIT2")
+                .and_then(|frame| frame.content().text())
+                .map(|text| text.trim().to_string())
+                .unwrap_or_default();
+            EmbeddedChapter {
+                title,
+                start: chap.start_time as f64 / 1000.0,
+                end: chap.end_time as f64 / 1000.0,
+            }
+        })
+        .collect();
+    normalize_chapters(chapters, total_duration)
+}
+
+/// CUE sheets written on Windows are often not UTF-8; fall back to lossy decoding.
+fn decode_cue_text(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+    String::from_utf8_lossy(bytes).to_string()
+}
+
+/// Parse a CUE sheet into chapters for `file_name`.
+///
+/// Only tracks under a `FILE` entry naming the audio file are used, unless the
+/// sheet references a single file. `INDEX 01` positions are `MM:SS:FF` with 75
+/// frames per second; each track ends where the next one starts.
+pub(crate) fn parse_cue_sheet(
+    text: &str,
+    file_name: &str,
+    total_duration: f64,
+) -> Vec<EmbeddedChapter> {
+    struct CueTrack {
+        file: Option<String>,
+        title: String,
+        start: Option<f64>,
+    }
+
+    let mut files = Vec::new();
+    let mut tracks: Vec<CueTrack> = Vec::new();
+    let mut current_file: Option<String> = None;
+
+    for line in text.lines() {
+        let line = line.trim();
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match keyword.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                let name = cue_quoted_value(rest);
+                files.push(name.clone());
+                current_file = Some(name);
+            }
+            "TRACK" => tracks.push(CueTrack {
+                file: current_file.clone(),
+                title: String::new(),
+                start: None,
+            }),
+            "TITLE" => {
+                if let Some(track) = tracks.last_mut() {
+                    track.title = cue_quoted_value(rest);
+                }
+            }
+            "INDEX" => {
+                let mut parts = rest.split_whitespace();
+                if parts.next() == Some("01") {
+                    if let (Some(track), Some(start)) =
+                        (tracks.last_mut(), parts.next().and_then(parse_cue_time))
+                    {
+                        track.start = Some(start);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let single_file = files.len() <= 1;
+    let mut markers: Vec<EmbeddedChapter> = tracks
+        .into_iter()
+        .filter(|track| {
+            single_file
+                || track
+                    .file
+                    .as_deref()
+                    .map(|file| cue_file_matches(file, file_name))
+                    .unwrap_or(false)
+        })
+        .filter_map(|track| {
+            track.start.map(|start| EmbeddedChapter {
+                title: track.title,
+                start,
+                end: 0.0,
+            })
+        })
+        .collect();
+
+    markers.sort_by(|a, b| a.start.total_cmp(&b.start));
+    for index in 0..markers.len() {
+        markers[index].end = markers
+            .get(index + 1)
+            .map(|next| next.start)
+            .unwrap_or(total_duration);
+    }
+    normalize_chapters(markers, total_duration)
+}
+
+fn cue_quoted_value(value: &str) -> String {
+    let value = value.trim();
+    match value.strip_prefix('"') {
+        Some(rest) => rest.split('"').next().unwrap_or_default().to_string(),
+        None => value
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+fn cue_file_matches(cue_file: &str, file_name: &str) -> bool {
+    let cue_name = cue_file.rsplit(['/', '\\']).next().unwrap_or(cue_file);
+    cue_name.eq_ignore_ascii_case(file_name)
+}
+
+/// Parse a CUE `MM:SS:FF` timestamp into seconds
+fn parse_cue_time(value: &str) -> Option<f64> {
+    let mut parts = value.split(':');
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: u64 = parts.next()?.parse().ok()?;
+    let frames: u64 = parts.next()?.parse().ok()?;
+    Some(minutes as f64 * 60.0 + seconds as f64 + frames as f64 / 75.0)
+}
+
+/// Sort chapters, close gaps for the last entry and drop empty slices.
+fn normalize_chapters(mut chapters: Vec<EmbeddedChapter>, total: f64) -> Vec<EmbeddedChapter> {
+    chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+    if let Some(last) = chapters.last_mut() {
+        if last.end <= last.start && total > last.start {
+            last.end = total;
+        }
+    }
+    chapters.retain(|chapter| chapter.end > chapter.start);
+    chapters
+}
+
+fn read_mvhd_duration<R: Read + Seek>(reader: &mut R, start: u64, size: u64) -> Option<f64> {
+    let (mvhd_start, _) = find_box(reader, start, size, b"mvhd")?;
+    reader.seek(SeekFrom::Start(mvhd_start)).ok()?;
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version).ok()?;
+    if version[0] == 1 {
+        let mut buf = [0u8; 28];
+        reader.read_exact(&mut buf).ok()?;
+        let timescale = u32::from_be_bytes([buf[16], buf[17], buf[18], buf[19]]);
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(&buf[20..28]);
+        let duration = u64::from_be_bytes(raw);
+        (timescale > 0).then(|| duration as f64 / timescale as f64)
+    } else {
+        let mut buf = [0u8; 16];
+        reader.read_exact(&mut buf).ok()?;
+        let timescale = u32::from_be_bytes([buf[8], buf[9], buf[10], buf[11]]);
+        let duration = u32::from_be_bytes([buf[12], buf[13], buf[14], buf[15]]);
+        (timescale > 0).then(|| duration as f64 / timescale as f64)
+    }
+}
+
+/// Find a child box inside `[start, start + size)`; returns the payload range.
+fn find_box<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    size: u64,
+    name: &[u8; 4],
+) -> Option<(u64, u64)> {
+    let end = start.checked_add(size)?;
+    let mut pos = start;
+    while pos + 8 <= end {
+        reader.seek(SeekFrom::Start(pos)).ok()?;
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).ok()?;
+        let mut box_size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let mut header_len = 8;
+        if box_size == 1 {
+            let mut large = [0u8; 8];
+            reader.read_exact(&mut large).ok()?;
+            box_size = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if box_size == 0 {
+            box_size = end - pos;
+        }
+        if box_size < header_len || pos + box_size > end {
+            return None;
+        }
+        if &header[4..8] == name {
+            return Some((pos + header_len, box_size - header_len));
+        }
+        pos += box_size;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chpl_payload(entries: &[(u64, &str)]) -> Vec<u8> {
+        let mut payload = vec![1, 0, 0, 0, 0, 0, 0, 0, entries.len() as u8];
+        for (start, title) in entries {
+            payload.extend_from_slice(&(start * 10_000_000).to_be_bytes());
+            payload.push(title.len() as u8);
+            payload.extend_from_slice(title.as_bytes());
+        }
+        payload
+    }
+
+    fn mp4_box(name: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(name);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn parses_nero_chapter_list() {
+        let payload = chpl_payload(&[(0, "Opening"), (90, "第二章"), (300, "Ending")]);
+        let chapters = parse_chpl(&payload, 420.0);
+
+        assert_eq!(chapters.len(), 3);
+        assert_eq!(chapters[0].title, "Opening");
+        assert_eq!(chapters[0].end, 90.0);
+        assert_eq!(chapters[1].title, "第二章");
+        assert_eq!(chapters[2].start, 300.0);
+        assert_eq!(chapters[2].end, 420.0);
+    }
+
+    #[test]
+    fn finds_chpl_inside_moov_udta() {
+        let mut mvhd = vec![0u8; 4];
+        mvhd.extend_from_slice(&[0u8; 8]);
+        mvhd.extend_from_slice(&1000u32.to_be_bytes());
+        mvhd.extend_from_slice(&600_000u32.to_be_bytes());
+        let udta = mp4_box(
+            b"udta",
+            &mp4_box(b"chpl", &chpl_payload(&[(0, "A"), (120, "B")])),
+        );
+        let mut moov_payload = mp4_box(b"mvhd", &mvhd);
+        moov_payload.extend_from_slice(&udta);
+        let mut file = mp4_box(b"ftyp", b"M4B \0\0\0\0");
+        file.extend_from_slice(&mp4_box(b"moov", &moov_payload));
+
+        let size = file.len() as u64;
+        let chapters = read_nero_chapters(&mut std::io::Cursor::new(file), size).unwrap();
+
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[1].title, "B");
+        assert_eq!(chapters[1].start, 120.0);
+        assert_eq!(chapters[1].end, 600.0);
+    }
+
+    #[test]
+    fn decodes_text_track_sample() {
+        let mut sample = 5u16.to_be_bytes().to_vec();
+        sample.extend_from_slice(b"Intro");
+        sample.extend_from_slice(&[0, 0, 0, 12, b'e', b'n', b'c', b'd']);
+        assert_eq!(decode_text_sample(&sample), "Intro");
+    }
+
+    #[test]
+    fn parses_cue_sheet_tracks_for_matching_file() {
+        let cue = r#"PERFORMER "Narrator"
+TITLE "The Book"
+FILE "book.mp3" MP3
+  TRACK 01 AUDIO
+    TITLE "Prologue"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Chapter 1"
+    INDEX 00 04:59:00
+    INDEX 01 05:00:37
+"#;
+        let chapters = parse_cue_sheet(cue, "BOOK.mp3", 900.0);
+
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title, "Prologue");
+        assert_eq!(chapters[0].end, chapters[1].start);
+        assert!((chapters[1].start - 300.493).abs() < 0.001);
+        assert_eq!(chapters[1].end, 900.0);
+    }
+
+    #[test]
+    fn builds_cue_sidecar_candidates_for_remote_paths() {
+        assert_eq!(
+            cue_sidecar_candidates("/books/a.b/book.mp3"),
+            vec!["/books/a.b/book.cue", "/books/a.b/book.mp3.cue"]
+        );
+        assert_eq!(
+            cue_sidecar_candidates("/books/a.b/book"),
+            vec!["/books/a.b/book.cue"]
+        );
+    }
+
+    #[test]
+    fn ignores_cue_tracks_of_other_files() {
+        let cue = "FILE \"a.mp3\" MP3\n TRACK 01 AUDIO\n  INDEX 01 00:00:00\n\
+                   FILE \"b.mp3\" MP3\n TRACK 02 AUDIO\n  TITLE \"B\"\n  INDEX 01 00:00:00\n \
+                   TRACK 03 AUDIO\n  TITLE \"C\"\n  INDEX 01 01:00:00\n";
+        let chapters = parse_cue_sheet(cue, "b.mp3", 120.0);
+
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title, "B");
+        assert_eq!(chapters[1].start, 60.0);
+    }
+
+    #[test]
+    fn selects_id3_chapters_by_table_of_contents() {
+        use id3::TagLike;
+
+        let mut tag = id3::Tag::new();
+        for (id, start, end, title) in [
+            ("ch0", 0, 60_000, "One"),
+            ("ch1", 60_000, 120_000, "Two"),
+            ("sub", 10_000, 20_000, "Nested"),
+        ] {
+            tag.add_frame(id3::frame::Chapter {
+                element_id: id.to_string(),
+                start_time: start,
+                end_time: end,
+                start_offset: 0xFFFF_FFFF,
+                end_offset: 0xFFFF_FFFF,
+                frames: vec![id3::Frame::text("TIT2", title)],
+            });
+        }
+        tag.add_frame(id3::frame::TableOfContents {
+            element_id: "toc".to_string(),
+            top_level: true,
+            ordered: true,
+            elements: vec!["ch1".to_string(), "ch0".to_string()],
+            frames: Vec::new(),
+        });
+
+        let chapters = chapters_from_id3_tag(&tag, 0.0);
+
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title, "One");
+        assert_eq!(chapters[1].title, "Two");
+        assert_eq!(chapters[1].start, 60.0);
+        assert_eq!(chapters[1].end, 120.0);
+    }
+}
@@ -1,6 +1,9 @@
+use super::super::embedded_chapters::{
+    find_cue_sheet, read_embedded_chapters, supports_embedded_chapters,
+};
 use super::super::shared::{
     apply_chapter_title_template, chapter_title_template_preserves_raw,
-    clean_or_preserve_chapter_title,
+    clean_or_preserve_chapter_title, SliceSource,
 };
 use super::super::LibraryScanner;
 use crate::core::error::{Result, TingError};
@@ -93,9 +96,15 @@ impl LibraryScanner {
         // existing files so the old relative path matches the new absolute path.
         let existing_chapters = self.chapter_repo.find_by_book(book_id).await?;
         let mut chapter_map: HashMap<PathBuf, Chapter> = HashMap::new();
+        // Virtual chapters share one file, so they are tracked separately per path.
+        let mut virtual_map: HashMap<PathBuf, Vec<Chapter>> = HashMap::new();
         let mut duplicate_chapter_ids = Vec::new();
         for ch in existing_chapters {
             let path = canonical_existing_path(Path::new(&ch.path));
+            if ch.is_virtual() {
+                virtual_map.entry(path).or_default().push(ch);
+                continue;
+            }
             if let Some(existing) = chapter_map.get(&path) {
                 let chapter_is_relative = Path::new(&ch.path).is_relative();
                 let existing_is_relative = Path::new(&existing.path).is_relative();
@@ -117,6 +126,28 @@ impl LibraryScanner {
             self.chapter_repo.delete(&chapter_id).await?;
             has_changes = true;
         }
+        for slices in virtual_map.values_mut() {
+            slices.sort_by(|a, b| {
+                a.start_offset
+                    .unwrap_or(0.0)
+                    .total_cmp(&b.start_offset.unwrap_or(0.0))
+            });
+        }
+
+        // Files played by a joined chapter belong to that chapter, not their own
+        let segment_owners: HashMap<PathBuf, String> = self
+            .chapter_repo
+            .find_segment_owners(book_id)
+            .await?
+            .into_iter()
+            .map(|(path, chapter_id)| (canonical_existing_path(Path::new(&path)), chapter_id))
+            .collect();
+
+        // Books scanned before chapter markers were read get probed once
+        let pending_probes = self
+            .chapter_repo
+            .find_pending_marker_probes(book_id)
+            .await?;
 
         let mut main_counter = 0;
         let mut extra_counter = 0;
@@ -142,23 +173,29 @@ impl LibraryScanner {
             // Incremental Scan Logic
             // Check if file exists in DB
             let canonical_file_path = canonical_existing_path(file_path);
-            let mut existing_chapter = chapter_map.get(&canonical_file_path).cloned();
-
-            // Check if file has changed
-            let is_modified = if let Some(last_scan) = last_scanned {
-                if let Ok(metadata) = std::fs::metadata(file_path) {
-                    if let Ok(mtime) = metadata.modified() {
-                        let mtime_utc: chrono::DateTime<chrono::Utc> = mtime.into();
-                        mtime_utc > last_scan
+            if let Some(owner_id) = segment_owners.get(&canonical_file_path) {
+                if processed_chapter_ids.insert(owner_id.clone()) {
+                    let is_extra = chapter_map
+                        .values()
+                        .any(|ch| &ch.id == owner_id && ch.is_extra == 1);
+                    if is_extra {
+                        extra_counter += 1;
                     } else {
-                        true // Can't read mtime, force check
+                        main_counter += 1;
                     }
-                } else {
-                    true // Can't read metadata, force check
                 }
-            } else {
-                true // No last scan, force check
-            };
+                continue;
+            }
+            let mut existing_chapter = chapter_map.get(&canonical_file_path).cloned();
+
+            // Check if file has changed
+            let is_modified = modified_since(file_path, last_scanned);
+            // A CUE sheet can be added or edited without touching the audio file.
+            let cue_sheet = find_cue_sheet(file_path);
+            let cue_modified = cue_sheet
+                .as_deref()
+                .map(|cue| modified_since(cue, last_scanned))
+                .unwrap_or(false);
 
             // Common Logic: Calculate Regex/Filename properties
             let filename_str = file_path
@@ -186,6 +223,92 @@ impl LibraryScanner {
                 }
             }
 
+            // Single-file books with embedded chapter markers are stored as virtual chapters.
+            let existing_slices = virtual_map.remove(&canonical_file_path).unwrap_or_default();
+            // Slices cut by hand are kept even when the file changes.
+            let manual_slices = existing_slices
+                .iter()
+                .any(|slice| slice.manual_corrected == 1);
+            // Read the markers again when their source changed, when a sidecar sits
+            // next to a file that is not split yet, or when the sidecar is gone.
+            let pending_probe = existing_chapter
+                .as_ref()
+                .filter(|ch| pending_probes.contains(&ch.id))
+                .map(|ch| ch.id.clone());
+            let markers_changed = is_modified
+                || pending_probe.is_some()
+                || cue_modified
+                || (cue_sheet.is_some() && existing_slices.is_empty())
+                || (!existing_slices.is_empty() && !supports_embedded_chapters(file_path));
+            if !existing_slices.is_empty() && (!markers_changed || manual_slices) {
+                let scanned_path = file_path.to_string_lossy().to_string();
+                has_changes |= self
+                    .keep_chapter_slices(
+                        &scanned_path,
+                        existing_slices,
+                        &mut main_counter,
+                        &mut extra_counter,
+                        &mut processed_chapter_ids,
+                    )
+                    .await?;
+                continue;
+            }
+
+            let embedded =
+                if markers_changed && !cloud_mode && supports_embedded_chapters(file_path) {
+                    // CUE tracks have no explicit end, so the last one needs the file duration
+                    let total_duration = if cue_sheet.is_some() {
+                        let (_, _, _, _, _, d) =
+                            self.extract_chapter_metadata(file_path, cloud_mode).await;
+                        Some(d as f64)
+                    } else {
+                        None
+                    };
+                    read_embedded_chapters(file_path, total_duration)
+                } else {
+                    Vec::new()
+                };
+            if let Some(chapter_id) = pending_probe {
+                self.chapter_repo.clear_marker_probe(&chapter_id).await?;
+            }
+            let had_slices = !existing_slices.is_empty();
+            let file_hash = if embedded.len() > 1 {
+                info!(
+                    "Found {} embedded chapters in {:?}",
+                    embedded.len(),
+                    file_path
+                );
+                self.calculate_file_hash(file_path)?
+            } else {
+                String::new()
+            };
+            let scanned_path = file_path.to_string_lossy().to_string();
+            let source = SliceSource {
+                book_id,
+                book_title: book.title.as_deref(),
+                path: &scanned_path,
+                file_hash: &file_hash,
+                file_name: &filename_str,
+                preserve_raw_titles: preserve_raw_chapter_titles,
+                extract_extra_chapters,
+                title_template: chapter_title_template,
+            };
+            let split = self
+                .reconcile_chapter_slices(
+                    &source,
+                    &embedded,
+                    &mut existing_chapter,
+                    existing_slices,
+                    &mut main_counter,
+                    &mut extra_counter,
+                    &mut processed_chapter_ids,
+                )
+                .await?;
+            has_changes |= split || had_slices;
+            if split {
+                continue;
+            }
+
             // Optimization: If chapter exists and file is not modified, skip processing!
             if let Some(ref ch) = existing_chapter {
                 if !is_modified {
@@ -483,6 +606,11 @@ impl LibraryScanner {
                     hash: Some(file_hash),
                     created_at: chrono::Utc::now().to_rfc3339(),
                     manual_corrected: 0,
+                    start_offset: None,
+                    end_offset: None,
+                    loudness_lufs: None,
+                    true_peak_dbtp: None,
+                    skip_ranges: None,
                 };
 
                 match self.chapter_repo.create(&chapter).await {
@@ -510,6 +638,20 @@ impl LibraryScanner {
             }
         }
 
+        for (path, slices) in virtual_map {
+            if path.exists() {
+                continue;
+            }
+            info!("Removing missing chapter file from DB: {:?}", path);
+            for ch in slices {
+                if let Err(e) = self.chapter_repo.delete(&ch.id).await {
+                    warn!("Failed to delete missing chapter {}: {}", ch.id, e);
+                } else {
+                    has_changes = true;
+                }
+            }
+        }
+
         Ok(has_changes)
     }
 
@@ -532,6 +674,17 @@ impl LibraryScanner {
         Ok(format!("{:x}", hasher.finalize()))
     }
 }
+/// True when `path` changed after the last scan, or when that cannot be told
+fn modified_since(path: &Path, last_scanned: Option<chrono::DateTime<chrono::Utc>>) -> bool {
+    let Some(last_scan) = last_scanned else {
+        return true; // No last scan, force check
+    };
+    match std::fs::metadata(path).and_then(|metadata| metadata.modified()) {
+        Ok(mtime) => chrono::DateTime::<chrono::Utc>::from(mtime) > last_scan,
+        Err(_) => true, // Can't read mtime, force check
+    }
+}
+
 fn canonical_existing_path(path: &Path) -> PathBuf {
     std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
 }
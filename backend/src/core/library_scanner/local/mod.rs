@@ -1,11 +1,12 @@
 mod chapters;
 mod metadata;
 
+use super::checkpoint::ScanCheckpoint;
 use super::{LibraryScanner, MetadataSource, ScanResult, ScanStatus};
 use crate::core::error::Result;
 use crate::core::library_scanner::shared::{
-    infer_series_directories, parse_chapter_range_dir_name, select_mergeable_range_groups,
-    ChapterRangeDir, CoalescedRangeDirs, SeriesDirectoryCandidate,
+    infer_series_directories, note_absorbed_range_books, parse_chapter_range_dir_name,
+    select_mergeable_range_groups, ChapterRangeDir, CoalescedRangeDirs, SeriesDirectoryCandidate,
 };
 use crate::core::nfo_manager::BookMetadata;
 use crate::db::repository::Repository;
@@ -25,9 +26,9 @@ impl LibraryScanner {
         task_id: Option<&str>,
         last_scanned: Option<chrono::DateTime<chrono::Utc>>,
         scraper_config: &crate::db::models::ScraperConfig,
+        checkpoint: &mut ScanCheckpoint,
     ) -> Result<ScanResult> {
-        let mut scan_result = ScanResult::default();
-        scan_result.start_time = Some(std::time::Instant::now());
+        let mut scan_result = checkpoint.resume_result();
 
         self.update_progress_key(task_id, "scan.local.scanning", serde_json::json!({}))
             .await;
@@ -130,6 +131,19 @@ impl LibraryScanner {
             self.check_cancellation(task_id).await?;
 
             processed_count += 1;
+            let dir_key = dir.to_string_lossy().to_string();
+            if let Some(book_id) = checkpoint.processed.get(&dir_key) {
+                // Processed by an earlier attempt and counted in its result
+                found_book_ids.insert(book_id.clone());
+                note_absorbed_range_books(
+                    &coalesced_range_dirs,
+                    &book_path_map,
+                    &dir,
+                    book_id,
+                    &mut absorbed_range_book_ids,
+                );
+                continue;
+            }
             let dir_name = dir
                 .file_name()
                 .and_then(|n| n.to_str())
@@ -208,19 +222,16 @@ impl LibraryScanner {
                             );
                         }
                     }
-                    if let Some(child_dirs) = coalesced_range_dirs.get(&dir) {
-                        for child_dir in &child_dirs.child_dirs {
-                            if let Some((child_book_id, manual_corrected, _)) =
-                                book_path_map.get(child_dir)
-                            {
-                                if child_book_id != &book_id && *manual_corrected == 0 {
-                                    absorbed_range_book_ids
-                                        .insert(child_book_id.clone(), book_id.clone());
-                                }
-                            }
-                        }
-                    }
+                    note_absorbed_range_books(
+                        &coalesced_range_dirs,
+                        &book_path_map,
+                        &dir,
+                        &book_id,
+                        &mut absorbed_range_book_ids,
+                    );
                     debug!(book_id = %book_id, path = ?dir, status = ?status, "Processed book directory");
+                    checkpoint.record(dir_key, book_id, &scan_result);
+                    self.save_checkpoint(task_id, checkpoint, false).await;
                 }
                 Err(e) => {
                     scan_result.failed_count += 1;
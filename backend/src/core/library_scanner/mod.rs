@@ -19,6 +19,8 @@ use std::path::Path;
 use std::sync::Arc;
 use tracing::{info, warn};
 
+pub mod checkpoint;
+pub mod embedded_chapters;
 pub mod local;
 pub mod rss;
 pub mod shared;
@@ -87,6 +89,20 @@ pub struct ScanResult {
     pub errors: Vec<String>,
     pub start_time: Option<std::time::Instant>,
     pub end_time: Option<std::time::Instant>,
+    /// Episodes an RSS scan added to a book that already existed
+    pub new_episodes: Vec<NewEpisode>,
+    /// Chapter IDs of every episode in the RSS feed, newest first; empty when
+    /// the feed was not read
+    pub rss_episode_ids: Vec<String>,
+}
+
+/// Episode discovered by re-reading an RSS feed
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NewEpisode {
+    pub book_id: String,
+    pub chapter_id: String,
+    pub title: String,
+    pub url: String,
 }
 
 impl ScanResult {
@@ -285,12 +301,29 @@ impl LibraryScanner {
             None
         };
 
+        // Pick up where an interrupted attempt of this task stopped
+        let mut checkpoint = self.load_checkpoint(task_id).await;
+        if !checkpoint.is_empty() {
+            info!(
+                library_id = %library_id,
+                processed = checkpoint.processed.len(),
+                "Resuming library scan from checkpoint"
+            );
+            self.update_progress_key(
+                task_id,
+                "scan.resuming",
+                serde_json::json!({ "count": checkpoint.processed.len() }),
+            )
+            .await;
+        }
+
         // Dispatch based on library type
         let scan_result = if library.library_type == "webdav" {
-            self.scan_webdav_library(&library, task_id, &scraper_config, mode)
+            self.scan_webdav_library(&library, task_id, &scraper_config, mode, &mut checkpoint)
                 .await?
         } else if library.library_type == "rss" {
-            self.scan_rss_library(&library, task_id, mode).await?
+            self.scan_rss_library(&library, task_id, mode, &mut checkpoint)
+                .await?
         } else {
             // Local library scan
             let path = Path::new(library_path);
@@ -308,9 +341,17 @@ impl LibraryScanner {
                 )));
             }
 
-            self.scan_local_library(library_id, path, task_id, last_scanned, &scraper_config)
-                .await?
+            self.scan_local_library(
+                library_id,
+                path,
+                task_id,
+                last_scanned,
+                &scraper_config,
+                &mut checkpoint,
+            )
+            .await?
         };
+        self.clear_checkpoint(task_id).await;
 
         // Update library last_scanned_at
         if let Err(e) = self.library_repo.update_last_scanned(library_id).await {
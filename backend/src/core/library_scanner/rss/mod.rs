@@ -1,6 +1,7 @@
-use super::{LibraryScanner, ScanMode, ScanResult, ScanStatus};
+use super::checkpoint::ScanCheckpoint;
+use super::{LibraryScanner, NewEpisode, ScanMode, ScanResult, ScanStatus};
 use crate::core::error::{Result, TingError};
-use crate::db::models::{Book, Chapter, Library};
+use crate::db::models::{Book, Chapter, Library, RssFeedValidators};
 use crate::db::repository::Repository;
 use chrono::{DateTime, Utc};
 use quick_xml::events::{BytesStart, Event};
@@ -43,9 +44,11 @@ impl LibraryScanner {
         library: &Library,
         task_id: Option<&str>,
         mode: ScanMode,
+        checkpoint: &mut ScanCheckpoint,
     ) -> Result<ScanResult> {
         let mut result = ScanResult {
             start_time: Some(std::time::Instant::now()),
+            new_episodes: checkpoint.new_episodes.clone(),
             ..Default::default()
         };
 
@@ -56,7 +59,15 @@ impl LibraryScanner {
         )
         .await;
 
-        let response = self
+        // A full scan always re-reads the feed; otherwise let the server tell
+        // us when nothing changed since the last fetch.
+        let previous_validators = if mode.is_full() {
+            None
+        } else {
+            self.library_repo.find_rss_validators(&library.id).await?
+        };
+
+        let mut request = self
             .http_client
             .get(&library.url)
             .header(
@@ -66,11 +77,34 @@ impl LibraryScanner {
             .header(
                 "User-Agent",
                 "TingReader/1.0 (+https://github.com/ting-reader)",
-            )
+            );
+        if let Some(validators) = &previous_validators {
+            if let Some(etag) = &validators.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = request
             .send()
             .await
             .map_err(|e| TingError::NetworkError(format!("Failed to fetch RSS feed: {}", e)))?;
 
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            self.update_progress_key(task_id, "scan.rss.not_modified", serde_json::json!({}))
+                .await;
+            info!(
+                target: "audit::scan",
+                library_id = %library.id,
+                "RSS feed not modified since last scan"
+            );
+            result.total_books = 1;
+            result.books_skipped = 1;
+            result.end_time = Some(std::time::Instant::now());
+            return Ok(result);
+        }
+
         if !response.status().is_success() {
             return Err(TingError::NetworkError(format!(
                 "RSS feed returned status {}",
@@ -78,6 +112,18 @@ impl LibraryScanner {
             )));
         }
 
+        let header_text = |name: reqwest::header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let validators = RssFeedValidators {
+            etag: header_text(reqwest::header::ETAG),
+            last_modified: header_text(reqwest::header::LAST_MODIFIED),
+        };
+
         let feed_bytes = response
             .bytes()
             .await
@@ -161,11 +207,33 @@ impl LibraryScanner {
         let book_id = self
             .upsert_rss_book(library, &feed, &book_hash, &mut result)
             .await?;
+        if checkpoint.books_created > 0 {
+            // Created by the interrupted attempt, which already found it new
+            result.books_created = 1;
+            result.books_updated = 0;
+            result.books_skipped = 0;
+        }
 
         let chapters_changed = self
-            .upsert_rss_chapters(&book_id, &feed.episodes, mode)
+            .upsert_rss_chapters(
+                &book_id,
+                &feed.episodes,
+                mode,
+                &mut result,
+                task_id,
+                checkpoint,
+            )
             .await?;
 
+        // Saved only once the feed is stored, so a failed scan retries in full
+        if let Err(e) = self
+            .library_repo
+            .save_rss_validators(&library.id, &validators)
+            .await
+        {
+            warn!(library_id = %library.id, error = %e, "Failed to save RSS feed validators");
+        }
+
         if chapters_changed && result.books_updated == 0 && result.books_created == 0 {
             result.books_skipped = result.books_skipped.saturating_sub(1);
             result.books_updated = 1;
@@ -299,7 +367,12 @@ impl LibraryScanner {
         book_id: &str,
         episodes: &[RssEpisode],
         mode: ScanMode,
+        result: &mut ScanResult,
+        task_id: Option<&str>,
+        checkpoint: &mut ScanCheckpoint,
     ) -> Result<bool> {
+        // Everything is new on the first scan; only report later additions
+        let report_new_episodes = result.books_created == 0;
         let existing_chapters = self.chapter_repo.find_by_book(book_id).await?;
         let mut existing_by_hash: HashMap<String, Chapter> = existing_chapters
             .iter()
@@ -313,6 +386,7 @@ impl LibraryScanner {
 
         let mut processed_ids = HashSet::new();
         let mut changed = false;
+        let mut feed_order = Vec::new();
 
         for (index, episode) in episodes.iter().enumerate() {
             let Some(enclosure_url) = episode.enclosure_url.as_ref() else {
@@ -324,6 +398,14 @@ impl LibraryScanner {
                 .filter(|guid| !guid.trim().is_empty())
                 .unwrap_or(enclosure_url);
             let chapter_hash = hash_string(&format!("rss:{}:{}", book_id, stable_key));
+            if let Some(chapter_id) = checkpoint.episodes.get(&chapter_hash) {
+                // Stored by the interrupted attempt
+                existing_by_hash.remove(&chapter_hash);
+                feed_order.push((episode.published_at, index, chapter_id.clone()));
+                processed_ids.insert(chapter_id.clone());
+                changed = true;
+                continue;
+            }
             let title = episode
                 .title
                 .clone()
@@ -341,8 +423,10 @@ impl LibraryScanner {
                 chapter.path = enclosure_url.clone();
                 chapter.duration = episode.duration;
                 chapter.book_id = book_id.to_string();
-                chapter.hash = Some(chapter_hash);
+                chapter.hash = Some(chapter_hash.clone());
                 self.chapter_repo.update(&chapter).await?;
+                feed_order.push((episode.published_at, index, chapter.id.clone()));
+                checkpoint.record_episode(chapter_hash, chapter.id.clone(), result);
                 processed_ids.insert(chapter.id);
                 changed = true;
             } else {
@@ -354,16 +438,34 @@ impl LibraryScanner {
                     duration: episode.duration,
                     chapter_index: Some(chapter_index),
                     is_extra: 0,
-                    hash: Some(chapter_hash),
+                    hash: Some(chapter_hash.clone()),
                     manual_corrected: 0,
                     created_at: chrono::Utc::now().to_rfc3339(),
+                    start_offset: None,
+                    end_offset: None,
+                    loudness_lufs: None,
+                    true_peak_dbtp: None,
+                    skip_ranges: None,
                 };
                 self.chapter_repo.create(&chapter).await?;
+                if report_new_episodes {
+                    result.new_episodes.push(NewEpisode {
+                        book_id: book_id.to_string(),
+                        chapter_id: chapter.id.clone(),
+                        title: chapter.title.clone().unwrap_or_default(),
+                        url: chapter.path.clone(),
+                    });
+                }
+                feed_order.push((episode.published_at, index, chapter.id.clone()));
+                checkpoint.record_episode(chapter_hash, chapter.id.clone(), result);
                 processed_ids.insert(chapter.id);
                 changed = true;
             }
+            self.save_checkpoint(task_id, checkpoint, false).await;
         }
 
+        result.rss_episode_ids = newest_first(feed_order);
+
         if mode.is_full() {
             for chapter in existing_chapters {
                 if !processed_ids.contains(&chapter.id) {
@@ -384,6 +486,20 @@ impl LibraryScanner {
     }
 }
 
+/// Order episodes by publication date, newest first. Episodes without a date
+/// keep their feed position after the dated ones.
+fn newest_first(mut episodes: Vec<(Option<DateTime<Utc>>, usize, String)>) -> Vec<String> {
+    episodes.sort_by(
+        |(a_date, a_index, _), (b_date, b_index, _)| match (a_date, b_date) {
+            (Some(a), Some(b)) => b.cmp(a).then(a_index.cmp(b_index)),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => a_index.cmp(b_index),
+        },
+    );
+    episodes.into_iter().map(|(_, _, id)| id).collect()
+}
+
 async fn fill_rss_theme_color(
     book: &mut Book,
     previous_cover_url: Option<&str>,
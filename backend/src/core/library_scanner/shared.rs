@@ -5,9 +5,13 @@ use std::hash::Hash;
 use std::sync::OnceLock;
 use tracing::{info, warn};
 
+use super::embedded_chapters::EmbeddedChapter;
 use super::{LibraryScanner, ScanResult};
+use crate::core::error::Result;
+use crate::db::models::Chapter;
 use crate::db::repository::Repository;
 use regex::Regex;
+use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct ChapterRangeDir {
@@ -60,6 +64,33 @@ pub struct PrefetchedBooks {
     pub all_books: Vec<(String, String, String, i32, Option<String>)>,
 }
 
+/// The file whose chapter markers are turned into virtual chapters, and how
+/// their titles are built
+pub(crate) struct SliceSource<'a> {
+    pub book_id: &'a str,
+    pub book_title: Option<&'a str>,
+    /// Path stored on the chapters
+    pub path: &'a str,
+    /// Hash of the whole file; each slice appends its start time
+    pub file_hash: &'a str,
+    /// Base of the titles of untitled markers
+    pub file_name: &'a str,
+    pub preserve_raw_titles: bool,
+    pub extract_extra_chapters: bool,
+    pub title_template: Option<&'a str>,
+}
+
+/// Next index among the main or the extra chapters of a book
+fn next_chapter_index(is_extra: bool, main_counter: &mut i32, extra_counter: &mut i32) -> i32 {
+    let counter = if is_extra {
+        extra_counter
+    } else {
+        main_counter
+    };
+    *counter += 1;
+    *counter
+}
+
 impl LibraryScanner {
     /// Pre-fetch all existing books for a library and build lookup maps
     pub(crate) async fn prefetch_books(&self, library_id: &str) -> PrefetchedBooks {
@@ -176,6 +207,173 @@ impl LibraryScanner {
 
         Ok(())
     }
+
+    /// Keep the stored slices of a file whose markers did not change,
+    /// renumbering them in scan order. Returns whether a row was updated.
+    pub(crate) async fn keep_chapter_slices(
+        &self,
+        path: &str,
+        slices: Vec<Chapter>,
+        main_counter: &mut i32,
+        extra_counter: &mut i32,
+        processed_chapter_ids: &mut HashSet<String>,
+    ) -> Result<bool> {
+        let mut changed = false;
+        for slice in slices {
+            let idx = next_chapter_index(slice.is_extra == 1, main_counter, extra_counter);
+            let reindex = slice.manual_corrected == 0 && slice.chapter_index != Some(idx);
+            if reindex || slice.path != path {
+                let mut updated = slice.clone();
+                updated.path = path.to_string();
+                if reindex {
+                    updated.chapter_index = Some(idx);
+                }
+                self.chapter_repo.update(&updated).await?;
+                changed = true;
+            }
+            processed_chapter_ids.insert(slice.id);
+        }
+        Ok(changed)
+    }
+
+    /// Store the chapter markers of a file as virtual chapters, or fold its
+    /// slices back into one chapter when it has fewer than two markers.
+    /// Returns whether the file was split.
+    ///
+    /// `whole_file` and `slices` are the rows stored for the file so far.
+    /// They are reused by position so progress stays attached; rows left over
+    /// are deleted. A file that stays whole keeps its row in `whole_file`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn reconcile_chapter_slices(
+        &self,
+        source: &SliceSource<'_>,
+        markers: &[EmbeddedChapter],
+        whole_file: &mut Option<Chapter>,
+        slices: Vec<Chapter>,
+        main_counter: &mut i32,
+        extra_counter: &mut i32,
+        processed_chapter_ids: &mut HashSet<String>,
+    ) -> Result<bool> {
+        if markers.len() <= 1 {
+            // The file lost its chapter markers: fold the slices back into one chapter.
+            let mut slices = slices.into_iter();
+            if whole_file.is_none() {
+                *whole_file = slices.next().map(|mut first| {
+                    first.start_offset = None;
+                    first.end_offset = None;
+                    first
+                });
+            }
+            for stale in slices {
+                self.chapter_repo.delete(&stale.id).await?;
+            }
+            return Ok(false);
+        }
+
+        let mut reusable = slices;
+        if let Some(chapter) = whole_file.take() {
+            reusable.insert(0, chapter);
+        }
+        let mut reusable = reusable.into_iter();
+
+        for (position, marker) in markers.iter().enumerate() {
+            let raw_title = if marker.title.is_empty() {
+                format!("{} {}", source.file_name, position + 1)
+            } else {
+                marker.title.clone()
+            };
+            let (title, detected_as_extra) = clean_or_preserve_chapter_title(
+                self.text_cleaner.as_ref(),
+                &raw_title,
+                source.book_title,
+                source.preserve_raw_titles,
+            );
+            let is_extra = source.extract_extra_chapters && detected_as_extra;
+            let chapter_idx = next_chapter_index(is_extra, main_counter, extra_counter);
+            let title = apply_chapter_title_template(
+                source.title_template,
+                source.book_title,
+                chapter_idx,
+                &title,
+            );
+
+            let (mut chapter, is_new) = match reusable.next() {
+                Some(existing) => (existing, false),
+                None => (
+                    Chapter {
+                        id: Uuid::new_v4().to_string(),
+                        book_id: source.book_id.to_string(),
+                        title: None,
+                        path: String::new(),
+                        duration: None,
+                        chapter_index: None,
+                        is_extra: 0,
+                        hash: None,
+                        created_at: chrono::Utc::now().to_rfc3339(),
+                        manual_corrected: 0,
+                        start_offset: None,
+                        end_offset: None,
+                        loudness_lufs: None,
+                        true_peak_dbtp: None,
+                        skip_ranges: None,
+                    },
+                    true,
+                ),
+            };
+            if is_new || chapter.manual_corrected == 0 {
+                chapter.title = Some(title);
+                chapter.chapter_index = Some(chapter_idx);
+                chapter.is_extra = if is_extra { 1 } else { 0 };
+            }
+            chapter.book_id = source.book_id.to_string();
+            chapter.path = source.path.to_string();
+            chapter.duration = Some(marker.duration().round() as i32);
+            chapter.hash = Some(format!(
+                "{}@{}",
+                source.file_hash,
+                (marker.start * 1000.0).round() as i64
+            ));
+            chapter.start_offset = Some(marker.start);
+            chapter.end_offset = Some(marker.end);
+
+            if is_new {
+                self.chapter_repo.create(&chapter).await?;
+            } else {
+                self.chapter_repo.update(&chapter).await?;
+            }
+            processed_chapter_ids.insert(chapter.id);
+        }
+
+        for stale in reusable {
+            if let Err(e) = self.chapter_repo.delete(&stale.id).await {
+                warn!("Failed to delete stale chapter {}: {}", stale.id, e);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Remember the books of range directories merged into `book_id`, so they
+/// can be absorbed once the scan is done
+pub(crate) fn note_absorbed_range_books<K>(
+    coalesced_range_dirs: &HashMap<K, CoalescedRangeDirs<K>>,
+    book_path_map: &HashMap<K, (String, i32, Option<String>)>,
+    dir: &K,
+    book_id: &str,
+    absorbed_range_book_ids: &mut HashMap<String, String>,
+) where
+    K: Eq + Hash,
+{
+    let Some(child_dirs) = coalesced_range_dirs.get(dir) else {
+        return;
+    };
+    for child_dir in &child_dirs.child_dirs {
+        if let Some((child_book_id, manual_corrected, _)) = book_path_map.get(child_dir) {
+            if child_book_id != book_id && *manual_corrected == 0 {
+                absorbed_range_book_ids.insert(child_book_id.clone(), book_id.to_string());
+            }
+        }
+    }
 }
 
 pub(crate) fn infer_series_directories<K>(
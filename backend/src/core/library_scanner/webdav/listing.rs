@@ -1,3 +1,4 @@
+use super::super::checkpoint::{ScanCheckpoint, WebDavListingCursor};
 use super::super::LibraryScanner;
 use crate::core::error::{Result, TingError};
 use quick_xml::events::Event;
@@ -6,12 +7,20 @@ use std::collections::{HashMap, HashSet};
 use tracing::{debug, warn};
 
 impl LibraryScanner {
-    /// List all files in a WebDAV library recursively
+    /// List all files in a WebDAV library recursively.
+    ///
+    /// The listing is kept in the checkpoint as it goes, so an interrupted
+    /// scan continues with the directories it had not listed yet.
     pub(super) async fn list_webdav_files(
         &self,
         library: &crate::db::models::Library,
         task_id: Option<&str>,
+        checkpoint: &mut ScanCheckpoint,
     ) -> Result<Vec<(String, Option<chrono::DateTime<chrono::Utc>>)>> {
+        if let Some(cursor) = checkpoint.listing.as_ref().filter(|cursor| cursor.complete) {
+            return Ok(cursor.files.clone());
+        }
+
         // Simple BFS or recursive traversal
         // Start from root
         let root_url = if library.root_path.starts_with('/') {
@@ -30,9 +39,23 @@ impl LibraryScanner {
         let mut files = HashMap::new(); // Use HashMap to store URL -> LastModified
         let mut queue = std::collections::VecDeque::new();
         let mut visited_dirs = HashSet::new(); // Track visited directories to prevent cycles/re-visits
+                                               // Limit depth/count to prevent infinite loops
+        let mut processed_dirs = 0;
 
-        queue.push_back(root_url.clone());
-        visited_dirs.insert(root_url);
+        if let Some(cursor) = checkpoint.listing.take() {
+            debug!(
+                pending = cursor.pending.len(),
+                files = cursor.files.len(),
+                "Resuming WebDAV listing from checkpoint"
+            );
+            files.extend(cursor.files);
+            queue.extend(cursor.pending);
+            visited_dirs.extend(cursor.visited);
+            processed_dirs = cursor.listed;
+        } else {
+            queue.push_back(root_url.clone());
+            visited_dirs.insert(root_url);
+        }
 
         let client = reqwest::Client::builder()
             .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
@@ -56,8 +79,6 @@ impl LibraryScanner {
             None
         };
 
-        // Limit depth/count to prevent infinite loops
-        let mut processed_dirs = 0;
         let max_dirs = 1000;
         let mut last_request_time = std::time::Instant::now();
         let min_request_interval = std::time::Duration::from_millis(200); // 200ms between requests
@@ -160,8 +181,29 @@ impl LibraryScanner {
                     warn!("WebDAV request failed for {}: {}", current_url, e);
                 }
             }
+
+            if checkpoint.save_due() {
+                checkpoint.listing = Some(listing_cursor(
+                    &queue,
+                    &visited_dirs,
+                    processed_dirs,
+                    &files,
+                    false,
+                ));
+                self.save_checkpoint(task_id, checkpoint, true).await;
+            }
         }
 
+        // Keep the finished listing, so resuming skips straight to the books
+        checkpoint.listing = Some(listing_cursor(
+            &queue,
+            &visited_dirs,
+            processed_dirs,
+            &files,
+            true,
+        ));
+        self.save_checkpoint(task_id, checkpoint, true).await;
+
         Ok(files.into_iter().collect())
     }
 
@@ -294,3 +336,22 @@ impl LibraryScanner {
         }
     }
 }
+
+fn listing_cursor(
+    queue: &std::collections::VecDeque<String>,
+    visited_dirs: &HashSet<String>,
+    listed: usize,
+    files: &HashMap<String, Option<chrono::DateTime<chrono::Utc>>>,
+    complete: bool,
+) -> WebDavListingCursor {
+    WebDavListingCursor {
+        pending: queue.iter().cloned().collect(),
+        visited: visited_dirs.iter().cloned().collect(),
+        listed,
+        files: files
+            .iter()
+            .map(|(url, modified)| (url.clone(), *modified))
+            .collect(),
+        complete,
+    }
+}
@@ -1,3 +1,6 @@
+use super::super::embedded_chapters::{
+    cue_sheet_chapters, read_id3_chapters, supports_id3_chapters, EmbeddedChapter,
+};
 use super::super::LibraryScanner;
 use crate::plugin::manager::FormatMethod;
 use base64::Engine;
@@ -8,6 +11,13 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tracing::debug;
 use uuid::Uuid;
 
+/// CUE sheets are plain text; anything larger is not a sidecar we want to parse
+const MAX_CUE_SHEET_SIZE: u64 = 1024 * 1024;
+
+/// Largest ID3v2 tag downloaded to look for chapter frames. Bigger tags are
+/// almost all cover art and are skipped rather than fetched on every scan.
+const MAX_ID3_TAG_SIZE: u64 = 4 * 1024 * 1024;
+
 impl LibraryScanner {
     pub(crate) async fn extract_webdav_metadata(
         &self,
@@ -645,4 +655,87 @@ impl LibraryScanner {
 
         (String::new(), String::new(), None, None, None, 0)
     }
+
+    /// Read CUE sidecar or ID3v2 `CHAP` chapter markers for a WebDAV file.
+    ///
+    /// Only the sidecar and the ID3 header are downloaded, never the audio.
+    /// `cue_url` comes from the directory listing, and the ID3 tag is only read
+    /// when `read_id3` is set, so unchanged files cost no extra requests.
+    pub(crate) async fn extract_webdav_embedded_chapters(
+        &self,
+        library: &crate::db::models::Library,
+        file_url: &str,
+        cue_url: Option<&str>,
+        read_id3: bool,
+        total_duration: f64,
+    ) -> Vec<EmbeddedChapter> {
+        let Some(storage) = &self.storage_service else {
+            return Vec::new();
+        };
+        let key = self.encryption_key.as_deref().unwrap_or(&[0u8; 32]);
+        let decoded_url = self.decode_url_path(file_url);
+        let file_name = decoded_url.split('/').next_back().unwrap_or_default();
+
+        if let Some(cue_url) = cue_url {
+            if let Ok((mut reader, size)) =
+                storage.get_webdav_reader(library, cue_url, None, key).await
+            {
+                let mut bytes = Vec::new();
+                if size <= MAX_CUE_SHEET_SIZE && reader.read_to_end(&mut bytes).await.is_ok() {
+                    let chapters = cue_sheet_chapters(&bytes, file_name, total_duration);
+                    if !chapters.is_empty() {
+                        debug!("Using CUE sidecar {} for WebDAV file", cue_url);
+                        return chapters;
+                    }
+                }
+            }
+        }
+
+        if !read_id3 || !supports_id3_chapters(Path::new(file_name)) {
+            return Vec::new();
+        }
+
+        // The ID3v2 tag sits at the start of the file; its size is in the header
+        let mut header = [0u8; 10];
+        match storage
+            .get_webdav_reader(library, file_url, Some((0, header.len() as u64)), key)
+            .await
+        {
+            Ok((mut reader, _)) => {
+                if reader.read_exact(&mut header).await.is_err() || &header[0..3] != b"ID3" {
+                    return Vec::new();
+                }
+            }
+            Err(_) => return Vec::new(),
+        }
+        let tag_size = ((header[6] as u64) << 21)
+            | ((header[7] as u64) << 14)
+            | ((header[8] as u64) << 7)
+            | (header[9] as u64);
+        let total_id3_size = 10 + tag_size;
+        if total_id3_size > MAX_ID3_TAG_SIZE {
+            debug!(
+                "Skipping {} byte ID3 tag of WebDAV file {} when looking for chapters",
+                total_id3_size, file_name
+            );
+            return Vec::new();
+        }
+
+        let temp_path =
+            std::env::temp_dir().join(format!("ting_scan_chapters_{}.mp3", Uuid::new_v4()));
+        let mut chapters = Vec::new();
+        if let Ok((mut reader, _)) = storage
+            .get_webdav_reader(library, file_url, Some((0, total_id3_size)), key)
+            .await
+        {
+            if let Ok(mut file) = tokio::fs::File::create(&temp_path).await {
+                if tokio::io::copy(&mut reader, &mut file).await.is_ok() {
+                    let _ = file.flush().await;
+                    chapters = read_id3_chapters(&temp_path, total_duration);
+                }
+            }
+        }
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        chapters
+    }
 }
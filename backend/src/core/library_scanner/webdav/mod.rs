@@ -2,11 +2,12 @@ mod listing;
 mod metadata;
 mod processing;
 
+use super::checkpoint::ScanCheckpoint;
 use super::{LibraryScanner, ScanMode, ScanResult, ScanStatus};
 use crate::core::error::{Result, TingError};
 use crate::core::library_scanner::shared::{
-    infer_series_directories, parse_chapter_range_dir_name, select_mergeable_range_groups,
-    ChapterRangeDir, CoalescedRangeDirs, SeriesDirectoryCandidate,
+    infer_series_directories, note_absorbed_range_books, parse_chapter_range_dir_name,
+    select_mergeable_range_groups, ChapterRangeDir, CoalescedRangeDirs, SeriesDirectoryCandidate,
 };
 use crate::db::repository::Repository;
 use sha2::{Digest, Sha256};
@@ -23,6 +24,7 @@ impl LibraryScanner {
         task_id: Option<&str>,
         scraper_config: &crate::db::models::ScraperConfig,
         mode: ScanMode,
+        checkpoint: &mut ScanCheckpoint,
     ) -> Result<ScanResult> {
         if self.storage_service.is_none() {
             return Err(TingError::ConfigError(
@@ -30,13 +32,12 @@ impl LibraryScanner {
             ));
         }
 
-        let mut scan_result = ScanResult::default();
-        scan_result.start_time = Some(std::time::Instant::now());
+        let mut scan_result = checkpoint.resume_result();
         self.update_progress_key(task_id, "scan.webdav.scanning", serde_json::json!({}))
             .await;
 
         // 1. List files recursively
-        let files = self.list_webdav_files(library, task_id).await?;
+        let files = self.list_webdav_files(library, task_id, checkpoint).await?;
 
         let supported_extensions = self.get_supported_extensions().await;
 
@@ -45,8 +46,8 @@ impl LibraryScanner {
         let mut dir_groups: HashMap<String, Vec<WebDavFileEntry>> = HashMap::new();
 
         // Metadata/sidecar file extensions that should be grouped alongside audio files
-        // so that cover images, metadata.json, book.nfo etc. are available during processing.
-        const METADATA_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "json", "nfo"];
+        // so that cover images, metadata.json, book.nfo, CUE sheets etc. are available during processing.
+        const METADATA_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "json", "nfo", "cue"];
 
         for (file_url, last_mod) in files {
             // Check extension
@@ -113,6 +114,18 @@ impl LibraryScanner {
             self.check_cancellation(task_id).await?;
 
             processed_count += 1;
+            if let Some(book_id) = checkpoint.processed.get(&dir_url) {
+                // Processed by an earlier attempt and counted in its result
+                found_book_ids.insert(book_id.clone());
+                note_absorbed_range_books(
+                    &coalesced_range_dirs,
+                    &book_path_map,
+                    &dir_url,
+                    book_id,
+                    &mut absorbed_range_book_ids,
+                );
+                continue;
+            }
             // Extract directory name from URL for logging
             let dir_name = self.webdav_url_name(&dir_url);
 
@@ -136,7 +149,7 @@ impl LibraryScanner {
 
             for (url, _) in file_entries.iter() {
                 let ext = url.split('.').last().unwrap_or_default().to_lowercase();
-                if ["json", "nfo", "jpg", "png", "jpeg", "webp"].contains(&ext.as_str()) {
+                if METADATA_EXTENSIONS.contains(&ext.as_str()) {
                     metadata_files.push(url.clone());
                 } else {
                     file_urls.push(url.clone());
@@ -175,7 +188,19 @@ impl LibraryScanner {
                 let current_file_count = file_urls.len();
                 let existing_chapters =
                     self.chapter_repo.find_by_book(id).await.unwrap_or_default();
-                let existing_chapter_count = existing_chapters.len();
+                // Chapters split from one file share its path and joined chapters
+                // span several, so count files instead
+                let segment_paths = self
+                    .chapter_repo
+                    .find_segment_owners(id)
+                    .await
+                    .unwrap_or_default();
+                let existing_chapter_count = existing_chapters
+                    .iter()
+                    .map(|chapter| chapter.path.as_str())
+                    .chain(segment_paths.keys().map(String::as_str))
+                    .collect::<HashSet<_>>()
+                    .len();
 
                 // Determine latest modification time in this directory
                 let max_mtime = file_entries.iter().filter_map(|(_, mtime)| *mtime).max();
@@ -197,10 +222,25 @@ impl LibraryScanner {
                     "Checking if WebDAV book needs update"
                 );
 
+                // Markers of books scanned before they were read, or of a CUE sheet
+                // that has not split its file yet, are read even when nothing changed.
+                let needs_marker_probe = !self
+                    .chapter_repo
+                    .find_pending_marker_probes(id)
+                    .await
+                    .unwrap_or_default()
+                    .is_empty()
+                    || (metadata_files
+                        .iter()
+                        .any(|url| url.to_lowercase().ends_with(".cue"))
+                        && !existing_chapters.iter().any(|ch| ch.is_virtual()));
+
                 // Skip only if:
                 // 1. File count hasn't changed AND
                 // 2. No files have been modified since last scan
-                if current_file_count == existing_chapter_count {
+                if needs_marker_probe {
+                    info!(book_id = %id, url = %dir_url, "WebDAV book has unread chapter markers, will process book");
+                } else if current_file_count == existing_chapter_count {
                     if let Some(latest) = max_mtime {
                         if latest <= last_scan_time {
                             let should_reprocess_chapter_titles = self
@@ -243,6 +283,8 @@ impl LibraryScanner {
                                     }
                                 }
                                 info!(book_id = %id, url = %dir_url, "Skipping up-to-date WebDAV book");
+                                checkpoint.record(dir_url, id.clone(), &scan_result);
+                                self.save_checkpoint(task_id, checkpoint, false).await;
                                 continue;
                             }
                         } else {
@@ -269,6 +311,8 @@ impl LibraryScanner {
                     &dir_url,
                     &file_urls,
                     &metadata_files,
+                    &file_entries,
+                    last_scanned,
                     task_id,
                     scraper_config,
                     existing_info,
@@ -299,19 +343,16 @@ impl LibraryScanner {
                             );
                         }
                     }
-                    if let Some(child_dirs) = coalesced_range_dirs.get(&dir_url) {
-                        for child_dir in &child_dirs.child_dirs {
-                            if let Some((child_book_id, manual_corrected, _)) =
-                                book_path_map.get(child_dir)
-                            {
-                                if child_book_id != &book_id && *manual_corrected == 0 {
-                                    absorbed_range_book_ids
-                                        .insert(child_book_id.clone(), book_id.clone());
-                                }
-                            }
-                        }
-                    }
+                    note_absorbed_range_books(
+                        &coalesced_range_dirs,
+                        &book_path_map,
+                        &dir_url,
+                        &book_id,
+                        &mut absorbed_range_book_ids,
+                    );
                     debug!(book_id = %book_id, url = %dir_url, status = ?status, "Processed WebDAV book directory");
+                    checkpoint.record(dir_url, book_id, &scan_result);
+                    self.save_checkpoint(task_id, checkpoint, false).await;
                 }
                 Err(e) => {
                     scan_result.failed_count += 1;
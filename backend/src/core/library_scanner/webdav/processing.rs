@@ -1,13 +1,14 @@
+use super::super::embedded_chapters::{cue_sidecar_candidates, supports_id3_chapters};
 use super::super::shared::{
     apply_chapter_title_template, chapter_title_template_preserves_raw,
-    clean_or_preserve_chapter_title,
+    clean_or_preserve_chapter_title, SliceSource,
 };
 use super::super::{LibraryScanner, MetadataSource, ScanStatus};
 use crate::core::error::Result;
 use crate::core::nfo_manager::BookMetadata;
 use crate::db::repository::Repository;
 use sha2::{Digest, Sha256};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use tracing::{info, warn};
 use uuid::Uuid;
@@ -19,6 +20,8 @@ impl LibraryScanner {
         dir_url: &str,
         file_urls: &[String],
         metadata_files: &[String],
+        file_entries: &[super::WebDavFileEntry],
+        last_scanned: Option<chrono::DateTime<chrono::Utc>>,
         _task_id: Option<&str>,
         scraper_config: &crate::db::models::ScraperConfig,
         existing_info: Option<(String, i32, Option<String>)>,
@@ -574,6 +577,48 @@ impl LibraryScanner {
         let mut processed_chapter_ids = HashSet::new();
         let mut chapters_changed = false;
 
+        // Slices cut by hand are kept as-is; group them by file for the loop below.
+        // Slices read from chapter markers are reused by position on a rescan.
+        let mut manual_slices: HashMap<String, Vec<crate::db::models::Chapter>> = HashMap::new();
+        let mut marker_slices: HashMap<String, Vec<crate::db::models::Chapter>> = HashMap::new();
+        // Files played by a joined chapter belong to that chapter, not their own
+        let segment_owners = self.chapter_repo.find_segment_owners(&book_id).await?;
+        let mut joined_is_extra: HashMap<String, bool> = HashMap::new();
+        for ch in self.chapter_repo.find_by_book(&book_id).await? {
+            if segment_owners.values().any(|owner_id| owner_id == &ch.id) {
+                joined_is_extra.insert(ch.id.clone(), ch.is_extra == 1);
+            }
+            if ch.is_virtual() && ch.manual_corrected == 1 {
+                manual_slices.entry(ch.path.clone()).or_default().push(ch);
+            } else if ch.is_virtual() {
+                marker_slices.entry(ch.path.clone()).or_default().push(ch);
+            }
+        }
+        for slices in marker_slices.values_mut() {
+            slices.sort_by(|a, b| {
+                a.start_offset
+                    .unwrap_or(0.0)
+                    .total_cmp(&b.start_offset.unwrap_or(0.0))
+            });
+        }
+
+        // Books scanned before chapter markers were read get probed once
+        let pending_probes = self
+            .chapter_repo
+            .find_pending_marker_probes(&book_id)
+            .await?;
+
+        // A file counts as changed when it is newer than the last scan or has no mtime
+        let mtimes: HashMap<&str, Option<chrono::DateTime<chrono::Utc>>> = file_entries
+            .iter()
+            .map(|(url, mtime)| (url.as_str(), *mtime))
+            .collect();
+        let changed_since_scan =
+            |url: &str| match (last_scanned, mtimes.get(url).copied().flatten()) {
+                (Some(last_scan), Some(mtime)) => mtime > last_scan,
+                _ => true,
+            };
+
         // Check if we can use JSON chapters
         let use_json_chapters = if let Some(ref chapters) = json_chapters {
             if chapters.len() == file_urls.len() {
@@ -624,6 +669,17 @@ impl LibraryScanner {
             chapter_title_template_preserves_raw(chapter_title_template.as_deref());
 
         for (index, file_url) in file_urls.iter().enumerate() {
+            if let Some(owner_id) = segment_owners.get(file_url) {
+                if processed_chapter_ids.insert(owner_id.clone()) {
+                    if joined_is_extra.get(owner_id).copied().unwrap_or(false) {
+                        extra_counter += 1;
+                    } else {
+                        main_counter += 1;
+                    }
+                }
+                continue;
+            }
+
             // Decode filename for title
             let decoded_file_url = self.decode_url_path(file_url);
             let filename = decoded_file_url
@@ -658,6 +714,58 @@ impl LibraryScanner {
             ch_hasher.update(file_url.as_bytes());
             let ch_hash = format!("{:x}", ch_hasher.finalize());
 
+            if let Some(slices) = manual_slices.remove(file_url) {
+                for slice in slices {
+                    if slice.is_extra == 1 {
+                        extra_counter += 1;
+                    } else {
+                        main_counter += 1;
+                    }
+                    processed_chapter_ids.insert(slice.id);
+                }
+                continue;
+            }
+
+            let existing_slices = marker_slices.remove(file_url).unwrap_or_default();
+            let mut whole_file = self
+                .chapter_repo
+                .find_by_hash(&ch_hash)
+                .await
+                .ok()
+                .flatten();
+            // CUE sheets are taken from the listing instead of probing for them
+            let cue_url = cue_sidecar_candidates(file_url)
+                .into_iter()
+                .find(|candidate| metadata_files.contains(candidate));
+            let pending_probe = whole_file
+                .as_ref()
+                .filter(|ch| pending_probes.contains(&ch.id))
+                .map(|ch| ch.id.clone());
+            let file_changed = changed_since_scan(file_url) || pending_probe.is_some();
+            let is_new_file = existing_slices.is_empty() && whole_file.is_none();
+            // Read the markers again when their source changed, when a sidecar sits
+            // next to a file that is not split yet, or when the sidecar is gone.
+            let markers_changed = file_changed
+                || is_new_file
+                || cue_url.as_deref().map(changed_since_scan).unwrap_or(false)
+                || (cue_url.is_some() && existing_slices.is_empty())
+                || (cue_url.is_none()
+                    && !existing_slices.is_empty()
+                    && !supports_id3_chapters(std::path::Path::new(&filename)));
+
+            if !existing_slices.is_empty() && (!markers_changed || is_cloud_mode) {
+                chapters_changed |= self
+                    .keep_chapter_slices(
+                        file_url,
+                        existing_slices,
+                        &mut main_counter,
+                        &mut extra_counter,
+                        &mut processed_chapter_ids,
+                    )
+                    .await?;
+                continue;
+            }
+
             // Extract metadata from WebDAV file (download header chunk)
             // In cloud mode we avoid probing WebDAV audio files and rely solely on scraped/sidecar metadata
             let (meta_title, meta_duration) = if use_json_chapters {
@@ -711,6 +819,55 @@ impl LibraryScanner {
                 (t, d)
             };
 
+            // Single-file rips with CUE or ID3 chapter markers become virtual chapters
+            let embedded = if markers_changed && !is_cloud_mode && !use_json_chapters {
+                self.extract_webdav_embedded_chapters(
+                    library,
+                    file_url,
+                    cue_url.as_deref(),
+                    file_changed || is_new_file,
+                    meta_duration as f64,
+                )
+                .await
+            } else {
+                Vec::new()
+            };
+            if let Some(chapter_id) = pending_probe {
+                self.chapter_repo.clear_marker_probe(&chapter_id).await?;
+            }
+            if embedded.len() > 1 {
+                info!(
+                    "Found {} embedded chapters in WebDAV file {}",
+                    embedded.len(),
+                    filename
+                );
+            }
+            let source = SliceSource {
+                book_id: &book_id,
+                book_title: book.title.as_deref(),
+                path: file_url,
+                file_hash: &ch_hash,
+                file_name: &filename,
+                preserve_raw_titles: preserve_raw_chapter_titles,
+                extract_extra_chapters: scraper_config.extract_extra_chapters,
+                title_template: chapter_title_template.as_deref(),
+            };
+            if self
+                .reconcile_chapter_slices(
+                    &source,
+                    &embedded,
+                    &mut whole_file,
+                    existing_slices,
+                    &mut main_counter,
+                    &mut extra_counter,
+                    &mut processed_chapter_ids,
+                )
+                .await?
+            {
+                chapters_changed = true;
+                continue;
+            }
+
             // metadata.json is a fallback title source. Explicit chapter regex
             // and filename-based titles still override it and go through cleaner.
             let (raw_title, should_clean_title) = if let Some(rt) = regex_title {
@@ -774,10 +931,15 @@ impl LibraryScanner {
                 hash: Some(ch_hash.clone()),
                 created_at: chrono::Utc::now().to_rfc3339(),
                 manual_corrected: 0,
+                start_offset: None,
+                end_offset: None,
+                loudness_lufs: None,
+                true_peak_dbtp: None,
+                skip_ranges: None,
             };
 
             // Check if chapter exists by hash (Deduplication)
-            if let Ok(Some(mut existing)) = self.chapter_repo.find_by_hash(&ch_hash).await {
+            if let Some(mut existing) = whole_file {
                 // Update existing chapter
                 // Check Lock
                 if existing.manual_corrected == 0 {
@@ -786,6 +948,9 @@ impl LibraryScanner {
                     existing.is_extra = chapter.is_extra;
                 }
                 existing.duration = chapter.duration;
+                existing.hash = chapter.hash;
+                existing.start_offset = None;
+                existing.end_offset = None;
                 existing.book_id = book_id.clone(); // Ensure it belongs to this book
                 self.chapter_repo.update(&existing).await?;
                 processed_chapter_ids.insert(existing.id.clone());
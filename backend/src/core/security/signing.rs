@@ -107,6 +107,31 @@ pub fn media_stream_signature_payload(
     )
 }
 
+pub fn sign_podcast_feed_request(
+    signing_key: &[u8; 32],
+    kind: &str,
+    id: &str,
+    expires: i64,
+    user_id: &str,
+    token_version: i64,
+) -> String {
+    let payload = podcast_feed_signature_payload(kind, id, expires, user_id, token_version);
+    hmac_sha256_base64_url(signing_key, payload.as_bytes())
+}
+
+pub fn podcast_feed_signature_payload(
+    kind: &str,
+    id: &str,
+    expires: i64,
+    user_id: &str,
+    token_version: i64,
+) -> String {
+    format!(
+        "podcast-feed\nkind:{}\nid:{}\nexpires:{}\nuser:{}\nversion:{}",
+        kind, id, expires, user_id, token_version
+    )
+}
+
 pub fn hmac_sha256_base64_url(key: &[u8], payload: &[u8]) -> String {
     let signature = hmac_sha256(key, payload);
     base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(signature)
@@ -169,4 +194,26 @@ mod tests {
     fn past_positive_signature_expiry_expires() {
         assert!(signature_has_expired(chrono::Utc::now().timestamp() - 60));
     }
+
+    #[test]
+    fn podcast_feed_signature_covers_target_user_and_version() {
+        let key = [7_u8; 32];
+        let signature = sign_podcast_feed_request(&key, "series", "s1", 0, "u1", 0);
+        assert_eq!(
+            signature,
+            sign_podcast_feed_request(&key, "series", "s1", 0, "u1", 0)
+        );
+        assert_ne!(
+            signature,
+            sign_podcast_feed_request(&key, "playlist", "s1", 0, "u1", 0)
+        );
+        assert_ne!(
+            signature,
+            sign_podcast_feed_request(&key, "series", "s1", 0, "u2", 0)
+        );
+        assert_ne!(
+            signature,
+            sign_podcast_feed_request(&key, "series", "s1", 0, "u1", 1)
+        );
+    }
 }
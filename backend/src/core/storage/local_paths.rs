@@ -310,6 +310,9 @@ mod tests {
                 request_timeout: 30,
                 gateway_prefix: None,
                 gateway_socket: None,
+                enable_abs_api: false,
+                enable_subsonic_api: false,
+                public_url: None,
             },
             database: DatabaseConfig {
                 path: PathBuf::from("test.db"),
@@ -327,6 +330,7 @@ mod tests {
                 max_concurrent_tasks: 1,
                 default_retry_count: 1,
                 task_timeout: 30,
+                pools: Default::default(),
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
@@ -352,6 +356,7 @@ mod tests {
                 max_disk_usage: 1024,
                 local_storage_root: storage_root,
                 local_library_roots: local_roots,
+                transcode_cache_size: 1024,
             },
             audio: AudioConfig {
                 cache_enabled: false,
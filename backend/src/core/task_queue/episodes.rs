@@ -0,0 +1,365 @@
+//! Local copies of RSS episodes
+//!
+//! Libraries in download mode fetch their feed's enclosures into
+//! `<data_dir>/episodes/<library_id>` after every scan. The same task applies
+//! the library's retention rules, so a feed that rotates old episodes out keeps
+//! playing from the copies that are still wanted.
+
+use super::{Priority, Task, TaskPayload, TaskQueue};
+use crate::core::error::{Result, TingError};
+use crate::db::models::{Chapter, EpisodeDownload, ScraperConfig};
+use crate::db::repository::Repository;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Downloads run after the scan and may take a while on a long feed
+const EPISODE_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(86400);
+
+impl TaskQueue {
+    /// Queue downloading and pruning the episodes of an RSS library.
+    ///
+    /// `chapter_ids` lists the feed's episodes newest first. Without it, as
+    /// after an unchanged feed, the task only removes played episodes.
+    pub async fn enqueue_episode_download(
+        &self,
+        library_id: &str,
+        chapter_ids: Option<&[String]>,
+    ) -> Result<String> {
+        let mut data = serde_json::json!({ "library_id": library_id });
+        if let Some(chapter_ids) = chapter_ids {
+            data["chapter_ids"] = serde_json::json!(chapter_ids);
+        }
+        let task = Task::new(
+            format!("episode_download_{}", library_id),
+            Priority::Low,
+            TaskPayload::Custom {
+                task_type: "episode_download".to_string(),
+                data,
+            },
+        )
+        .with_timeout(EPISODE_DOWNLOAD_TIMEOUT);
+
+        self.submit(task).await
+    }
+
+    /// Download wanted episodes and delete the ones retention lets go
+    pub(super) async fn handle_episode_download(
+        &self,
+        data: &serde_json::Value,
+        task_id: &str,
+    ) -> Result<()> {
+        let library_id = data["library_id"]
+            .as_str()
+            .ok_or_else(|| TingError::TaskError("Missing library_id".to_string()))?;
+        let library_repo = self
+            .library_repo
+            .as_ref()
+            .ok_or_else(|| TingError::TaskError("Library repository not configured".to_string()))?;
+        let chapter_repo = self
+            .chapter_repo
+            .as_ref()
+            .ok_or_else(|| TingError::TaskError("Chapter repository not configured".to_string()))?;
+        let episode_dir = self
+            .episode_dir
+            .as_ref()
+            .ok_or_else(|| TingError::TaskError("Episode directory not configured".to_string()))?
+            .join(library_id);
+        let library = library_repo
+            .find_by_id(library_id)
+            .await?
+            .ok_or_else(|| TingError::NotFound(format!("Library {} not found", library_id)))?;
+        let config = library
+            .scraper_config
+            .as_deref()
+            .and_then(|value| serde_json::from_str::<ScraperConfig>(value).ok())
+            .unwrap_or_default();
+
+        let played: HashSet<String> = if config.rss_delete_played_episodes {
+            library_repo
+                .find_episodes_played_by_all(library_id)
+                .await?
+                .into_iter()
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+        let mut downloaded = 0;
+        let mut failed = 0;
+        let kept = if !config.rss_download_episodes {
+            // Download mode was switched off: drop every local copy
+            HashSet::new()
+        } else if let Some(chapter_ids) = data["chapter_ids"].as_array() {
+            let mut newest_first: Vec<String> = chapter_ids
+                .iter()
+                .filter_map(|id| id.as_str().map(str::to_string))
+                .collect();
+            // Episodes the feed no longer lists are older than any it does;
+            // their copies stay under the same rules
+            let in_feed: HashSet<String> = newest_first.iter().cloned().collect();
+            let mut rotated_out: Vec<String> = library_repo
+                .find_episode_downloads(library_id)
+                .await?
+                .into_iter()
+                .map(|download| download.chapter_id)
+                .filter(|chapter_id| !in_feed.contains(chapter_id))
+                .collect();
+            rotated_out.reverse();
+            newest_first.extend(rotated_out);
+
+            let wanted = wanted_episodes(&newest_first, config.rss_keep_latest_episodes, &played);
+            let max_bytes = config
+                .rss_max_download_mb
+                .map(|mb| mb.saturating_mul(1024 * 1024));
+
+            let mut kept = HashSet::new();
+            let mut total_bytes = 0u64;
+            for (index, chapter_id) in wanted.iter().enumerate() {
+                let Some(chapter) = chapter_repo.find_by_id(chapter_id).await? else {
+                    continue;
+                };
+                let existing = library_repo
+                    .find_episode_download(chapter_id)
+                    .await?
+                    // Hosts often rotate tracking parameters in enclosure
+                    // URLs, so a copy stays valid while its file exists
+                    .filter(|download| Path::new(&download.file_path).exists());
+                let size = match existing {
+                    Some(download) => download.file_size.max(0) as u64,
+                    None => {
+                        let _ = self
+                            .task_repo
+                            .update_progress_key(
+                                task_id,
+                                "episodes.download.progress",
+                                serde_json::json!({
+                                    "current": index + 1,
+                                    "total": wanted.len(),
+                                    "title": chapter.title.as_deref().unwrap_or(""),
+                                }),
+                            )
+                            .await;
+                        match self
+                            .download_episode(&episode_dir, &library.id, &chapter)
+                            .await
+                        {
+                            Ok(download) => {
+                                library_repo.save_episode_download(&download).await?;
+                                downloaded += 1;
+                                download.file_size.max(0) as u64
+                            }
+                            Err(e) => {
+                                warn!(
+                                    chapter_id = %chapter.id,
+                                    url = %chapter.path,
+                                    error = %e,
+                                    "Failed to download RSS episode"
+                                );
+                                failed += 1;
+                                continue;
+                            }
+                        }
+                    }
+                };
+
+                // Older episodes give way once the size limit is reached
+                if max_bytes.is_some_and(|max| total_bytes + size > max) {
+                    break;
+                }
+                total_bytes += size;
+                kept.insert(chapter_id.clone());
+            }
+            kept
+        } else {
+            // The feed was not read, so only played episodes can go
+            library_repo
+                .find_episode_downloads(library_id)
+                .await?
+                .into_iter()
+                .map(|download| download.chapter_id)
+                .filter(|chapter_id| !played.contains(chapter_id))
+                .collect()
+        };
+
+        let mut deleted = 0;
+        let mut remaining = HashSet::new();
+        for download in library_repo.find_episode_downloads(library_id).await? {
+            if kept.contains(&download.chapter_id) {
+                remaining.insert(PathBuf::from(&download.file_path));
+                continue;
+            }
+            remove_episode_file(Path::new(&download.file_path)).await;
+            library_repo
+                .delete_episode_download(&download.chapter_id)
+                .await?;
+            deleted += 1;
+        }
+        // Rows of chapters dropped from the feed go with the chapter; their
+        // files are only found on disk
+        deleted += remove_untracked_files(&episode_dir, &remaining).await;
+
+        info!(
+            library_id = %library_id,
+            downloaded,
+            deleted,
+            failed,
+            "RSS episode downloads updated"
+        );
+        if let Err(e) = self
+            .task_repo
+            .update_progress_key(
+                task_id,
+                "episodes.download.completed",
+                serde_json::json!({
+                    "library_name": library.name,
+                    "downloaded": downloaded,
+                    "deleted": deleted,
+                    "failed": failed,
+                }),
+            )
+            .await
+        {
+            warn!(task_id = %task_id, error = %e, "Failed to update task progress message");
+        }
+
+        Ok(())
+    }
+
+    /// Fetch one enclosure, writing to a temporary file first so a broken
+    /// download never replaces a playable copy
+    async fn download_episode(
+        &self,
+        episode_dir: &Path,
+        library_id: &str,
+        chapter: &Chapter,
+    ) -> Result<EpisodeDownload> {
+        let storage_service = self
+            .storage_service
+            .as_ref()
+            .ok_or_else(|| TingError::TaskError("Storage service not configured".to_string()))?;
+        let (mut reader, _) = storage_service.get_http_reader(&chapter.path, None).await?;
+
+        tokio::fs::create_dir_all(episode_dir).await?;
+        let file_path = episode_dir.join(format!(
+            "{}.{}",
+            chapter.id,
+            enclosure_extension(&chapter.path)
+        ));
+        let temp_path = file_path.with_extension("part");
+        let mut file = tokio::fs::File::create(&temp_path).await?;
+        let copied = tokio::io::copy(&mut reader, &mut file).await;
+        drop(file);
+        let file_size = match copied {
+            Ok(size) => size,
+            Err(e) => {
+                remove_episode_file(&temp_path).await;
+                return Err(e.into());
+            }
+        };
+        tokio::fs::rename(&temp_path, &file_path).await?;
+
+        Ok(EpisodeDownload {
+            chapter_id: chapter.id.clone(),
+            library_id: library_id.to_string(),
+            file_path: file_path.to_string_lossy().to_string(),
+            file_size: file_size as i64,
+            source_url: chapter.path.clone(),
+            downloaded_at: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+}
+
+/// Episodes to keep locally: the newest `keep_latest` of the feed, minus the
+/// ones everybody finished. Played episodes are not replaced by older ones.
+fn wanted_episodes(
+    newest_first: &[String],
+    keep_latest: Option<u32>,
+    played: &HashSet<String>,
+) -> Vec<String> {
+    newest_first
+        .iter()
+        .take(keep_latest.map_or(usize::MAX, |count| count as usize))
+        .filter(|chapter_id| !played.contains(*chapter_id))
+        .cloned()
+        .collect()
+}
+
+/// File extension for a downloaded enclosure, taken from its URL path
+fn enclosure_extension(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            Path::new(url.path())
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(str::to_ascii_lowercase)
+        })
+        .filter(|ext| ext.len() <= 5 && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        // Most podcast enclosures without an extension are MP3
+        .unwrap_or_else(|| "mp3".to_string())
+}
+
+async fn remove_episode_file(path: &Path) {
+    if let Err(e) = tokio::fs::remove_file(path).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!(path = %path.display(), error = %e, "Failed to delete RSS episode file");
+        }
+    }
+}
+
+/// Delete files in the library's episode directory that no download refers to
+async fn remove_untracked_files(episode_dir: &Path, tracked: &HashSet<PathBuf>) -> usize {
+    let Ok(mut entries) = tokio::fs::read_dir(episode_dir).await else {
+        return 0;
+    };
+    let mut removed = 0;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.is_file() && !tracked.contains(&path) {
+            remove_episode_file(&path).await;
+            removed += 1;
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn keeps_the_newest_unplayed_episodes() {
+        let feed = ids(&["e5", "e4", "e3", "e2", "e1"]);
+        let played: HashSet<String> = ids(&["e4", "e1"]).into_iter().collect();
+
+        assert_eq!(
+            wanted_episodes(&feed, None, &HashSet::new()),
+            ids(&["e5", "e4", "e3", "e2", "e1"])
+        );
+        assert_eq!(
+            wanted_episodes(&feed, None, &played),
+            ids(&["e5", "e3", "e2"])
+        );
+        // Finishing an episode does not pull in an older one
+        assert_eq!(wanted_episodes(&feed, Some(3), &played), ids(&["e5", "e3"]));
+    }
+
+    #[test]
+    fn enclosure_extension_comes_from_the_url_path() {
+        assert_eq!(
+            enclosure_extension("https://cdn.example.com/show/ep1.M4A?token=x.mp3"),
+            "m4a"
+        );
+        assert_eq!(enclosure_extension("https://example.com/media/123"), "mp3");
+        assert_eq!(
+            enclosure_extension("https://example.com/download.php%3Fid=1"),
+            "mp3"
+        );
+    }
+}
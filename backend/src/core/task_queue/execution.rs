@@ -3,6 +3,7 @@ use crate::core::error::Result;
 use crate::db::repository::Repository;
 use id3::frame::{Picture, PictureType as Id3PictureType};
 use id3::{Tag, TagLike, Version};
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
@@ -60,6 +61,15 @@ impl TaskQueue {
                 "write_metadata" => {
                     self.handle_write_metadata(data, &task.id).await?;
                 }
+                "episode_download" => {
+                    self.handle_episode_download(data, &task.id).await?;
+                }
+                "loudness_analysis" => {
+                    self.handle_loudness_analysis(data, &task.id).await?;
+                }
+                "skip_analysis" => {
+                    self.handle_skip_analysis(data, &task.id).await?;
+                }
                 _ => {
                     self.handle_plugin_task(task_type, data, &task.id).await?;
                 }
@@ -122,6 +132,25 @@ impl TaskQueue {
         Ok(())
     }
 
+    /// Send an application event to webhooks and, when available, plugins
+    fn dispatch_event(&self, payload: crate::core::notifications::NotificationEventPayload) {
+        let Some(notification_repo) = &self.notification_repo else {
+            return;
+        };
+        if let Some(plugin_manager) = &self.plugin_manager {
+            crate::core::notifications::dispatch_application_event(
+                notification_repo.clone(),
+                plugin_manager.clone(),
+                payload,
+            );
+        } else {
+            crate::core::notifications::dispatch_notification_event(
+                notification_repo.clone(),
+                payload,
+            );
+        }
+    }
+
     /// Handle library scan task
     async fn handle_library_scan(&self, data: &serde_json::Value, task_id: &str) -> Result<()> {
         let library_id = data["library_id"].as_str().ok_or_else(|| {
@@ -246,39 +275,59 @@ impl TaskQueue {
             warn!(task_id = %task_id, error = %e, "Failed to update task progress message");
         }
 
-        if let Some(notification_repo) = &self.notification_repo {
-            let payload = crate::core::notifications::NotificationEventPayload::new(
-                "library.scan_completed",
-                "Library scan completed",
+        self.dispatch_event(crate::core::notifications::NotificationEventPayload::new(
+            "library.scan_completed",
+            "Library scan completed",
+            format!(
+                "Library {} scan completed: created {}, updated {}, deleted {}",
+                library.name, result.books_created, result.books_updated, result.books_deleted
+            ),
+            serde_json::json!({
+                "library_id": library.id,
+                "library_name": library.name,
+                "library_type": library.library_type,
+                "path": library_path,
+                "mode": scan_mode.as_str(),
+                "task_id": task_id,
+                "books_created": result.books_created,
+                "books_updated": result.books_updated,
+                "books_deleted": result.books_deleted,
+                "errors": result.errors.len(),
+            }),
+        ));
+
+        if !result.new_episodes.is_empty() {
+            info!(
+                library_id = %library_id,
+                count = result.new_episodes.len(),
+                "RSS library has new episodes"
+            );
+            self.dispatch_event(crate::core::notifications::NotificationEventPayload::new(
+                "library.new_episodes",
+                "New episodes",
                 format!(
-                    "Library {} scan completed: created {}, updated {}, deleted {}",
-                    library.name, result.books_created, result.books_updated, result.books_deleted
+                    "Library {} has {} new episodes: {}",
+                    library.name,
+                    result.new_episodes.len(),
+                    result
+                        .new_episodes
+                        .iter()
+                        .map(|episode| episode.title.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
                 ),
                 serde_json::json!({
                     "library_id": library.id,
                     "library_name": library.name,
-                    "library_type": library.library_type,
-                    "path": library_path,
-                    "mode": scan_mode.as_str(),
                     "task_id": task_id,
-                    "books_created": result.books_created,
-                    "books_updated": result.books_updated,
-                    "books_deleted": result.books_deleted,
-                    "errors": result.errors.len(),
+                    "count": result.new_episodes.len(),
+                    "episodes": result.new_episodes,
                 }),
-            );
-            if let Some(plugin_manager) = &self.plugin_manager {
-                crate::core::notifications::dispatch_application_event(
-                    notification_repo.clone(),
-                    plugin_manager.clone(),
-                    payload,
-                );
-            } else {
-                crate::core::notifications::dispatch_notification_event(
-                    notification_repo.clone(),
-                    payload,
-                );
-            }
+            ));
+        }
+
+        if library.library_type == "rss" {
+            self.queue_episode_download(&library, &result).await;
         }
 
         if !result.errors.is_empty() {
@@ -288,6 +337,49 @@ impl TaskQueue {
         Ok(())
     }
 
+    /// Follow an RSS scan with downloading its episodes when the library is in
+    /// download mode, or with removing the copies left from an earlier one
+    async fn queue_episode_download(
+        &self,
+        library: &crate::db::models::Library,
+        result: &crate::core::library_scanner::ScanResult,
+    ) {
+        let download_mode = library
+            .scraper_config
+            .as_deref()
+            .and_then(|value| serde_json::from_str::<crate::db::models::ScraperConfig>(value).ok())
+            .is_some_and(|config| config.rss_download_episodes);
+        if !download_mode {
+            let has_downloads = match &self.library_repo {
+                Some(repo) => repo
+                    .find_episode_downloads(&library.id)
+                    .await
+                    .is_ok_and(|downloads| !downloads.is_empty()),
+                None => false,
+            };
+            if !has_downloads {
+                return;
+            }
+        }
+        // One download run per library at a time; the next scan catches up
+        if self
+            .has_active_library_task("episode_download", &library.id)
+            .await
+            .unwrap_or(false)
+        {
+            return;
+        }
+
+        let chapter_ids =
+            (!result.rss_episode_ids.is_empty()).then_some(result.rss_episode_ids.as_slice());
+        if let Err(e) = self
+            .enqueue_episode_download(&library.id, chapter_ids)
+            .await
+        {
+            warn!(library_id = %library.id, error = %e, "Failed to queue RSS episode download");
+        }
+    }
+
     /// Handle write metadata task
     async fn handle_write_metadata(&self, data: &serde_json::Value, task_id: &str) -> Result<()> {
         let book_id = data["book_id"].as_str().ok_or_else(|| {
@@ -403,8 +495,21 @@ impl TaskQueue {
             }
         }
 
-        // Get chapters
-        let chapters = chapter_repo.find_by_book(book_id).await?;
+        // Get chapters. Chapters split from one file share it, so each file is
+        // written once and titled by the book instead of by one of its chapters.
+        let mut written_paths = HashSet::new();
+        let chapters: Vec<_> = chapter_repo
+            .find_by_book(book_id)
+            .await?
+            .into_iter()
+            .filter(|chapter| written_paths.insert(chapter.path.clone()))
+            .map(|mut chapter| {
+                if chapter.is_virtual() {
+                    chapter.title = book.title.clone();
+                }
+                chapter
+            })
+            .collect();
 
         let mut success_count = 0;
         let mut error_count = 0;
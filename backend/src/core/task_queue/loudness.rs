@@ -0,0 +1,244 @@
+//! Per-chapter loudness analysis
+//!
+//! Measures the integrated loudness and true peak of every chapter of a book
+//! or library and stores them on the chapter, where streams pick them up to
+//! normalize playback. Chapters are decoded with symphonia, so only its
+//! formats are measured; joined chapters, `.strm` links and plugin-backed
+//! formats are skipped.
+
+use super::{Priority, Task, TaskPayload, TaskQueue};
+use crate::core::audio_streamer::loudness::{self, LoudnessAnalysis};
+use crate::core::audio_streamer::BlockingReader;
+use crate::core::error::{Result, TingError};
+use crate::db::models::{Chapter, Library};
+use crate::db::repository::Repository;
+use std::path::Path;
+use std::time::Duration;
+use symphonia::core::io::{MediaSource, ReadOnlySource};
+use symphonia::core::probe::Hint;
+use tracing::{info, warn};
+
+/// A library of long books takes a while to decode end to end
+const LOUDNESS_ANALYSIS_TIMEOUT: Duration = Duration::from_secs(86400);
+
+impl TaskQueue {
+    /// Queue measuring the chapters of one book, or of the whole library
+    /// without `book_id`. Chapters measured before are skipped unless `force`
+    /// is set.
+    pub async fn enqueue_loudness_analysis(
+        &self,
+        name: String,
+        library_id: &str,
+        book_id: Option<&str>,
+        force: bool,
+    ) -> Result<String> {
+        let mut data = serde_json::json!({ "library_id": library_id, "force": force });
+        if let Some(book_id) = book_id {
+            data["book_id"] = serde_json::json!(book_id);
+        }
+        let task = Task::new(
+            name,
+            Priority::Low,
+            TaskPayload::Custom {
+                task_type: "loudness_analysis".to_string(),
+                data,
+            },
+        )
+        .with_timeout(LOUDNESS_ANALYSIS_TIMEOUT);
+
+        self.submit(task).await
+    }
+
+    /// Measure the chapters named by the task and store the results
+    pub(super) async fn handle_loudness_analysis(
+        &self,
+        data: &serde_json::Value,
+        task_id: &str,
+    ) -> Result<()> {
+        let library_id = data["library_id"]
+            .as_str()
+            .ok_or_else(|| TingError::TaskError("Missing library_id".to_string()))?;
+        let force = data["force"].as_bool().unwrap_or(false);
+        let book_repo = self
+            .book_repo
+            .as_ref()
+            .ok_or_else(|| TingError::TaskError("Book repository not configured".to_string()))?;
+        let library_repo = self
+            .library_repo
+            .as_ref()
+            .ok_or_else(|| TingError::TaskError("Library repository not configured".to_string()))?;
+        let chapter_repo = self
+            .chapter_repo
+            .as_ref()
+            .ok_or_else(|| TingError::TaskError("Chapter repository not configured".to_string()))?;
+        let library = library_repo
+            .find_by_id(library_id)
+            .await?
+            .ok_or_else(|| TingError::NotFound(format!("Library {} not found", library_id)))?;
+
+        let books = match data["book_id"].as_str() {
+            Some(book_id) => {
+                let book = book_repo
+                    .find_by_id(book_id)
+                    .await?
+                    .ok_or_else(|| TingError::NotFound(format!("Book {} not found", book_id)))?;
+                vec![book]
+            }
+            None => book_repo.find_by_library(library_id).await?,
+        };
+
+        let mut chapters = Vec::new();
+        let mut skipped = 0;
+        for book in &books {
+            for chapter in chapter_repo.find_by_book(&book.id).await? {
+                if !force && chapter.loudness_lufs.is_some() {
+                    continue;
+                }
+                if !self.can_analyse(&chapter).await
+                    || !chapter_repo.find_segments(&chapter.id).await?.is_empty()
+                {
+                    skipped += 1;
+                    continue;
+                }
+                chapters.push(chapter);
+            }
+        }
+
+        let mut analysed = 0;
+        let mut failed = 0;
+        for (index, chapter) in chapters.iter().enumerate() {
+            let _ = self
+                .task_repo
+                .update_progress_key(
+                    task_id,
+                    "loudness.analysis.progress",
+                    serde_json::json!({
+                        "current": index + 1,
+                        "total": chapters.len(),
+                        "title": chapter.title.as_deref().unwrap_or(""),
+                    }),
+                )
+                .await;
+            match self.analyse_chapter(&library, chapter).await {
+                Ok(analysis) => {
+                    chapter_repo
+                        .set_loudness(
+                            &chapter.id,
+                            analysis.integrated_lufs,
+                            analysis.true_peak_dbtp,
+                        )
+                        .await?;
+                    analysed += 1;
+                }
+                Err(e) => {
+                    warn!(
+                        chapter_id = %chapter.id,
+                        path = %chapter.path,
+                        error = %e,
+                        "Failed to analyse chapter loudness"
+                    );
+                    failed += 1;
+                }
+            }
+        }
+
+        info!(
+            library_id = %library_id,
+            analysed,
+            skipped,
+            failed,
+            "Chapter loudness analysed"
+        );
+        if let Err(e) = self
+            .task_repo
+            .update_progress_key(
+                task_id,
+                "loudness.analysis.completed",
+                serde_json::json!({
+                    "analysed": analysed,
+                    "skipped": skipped,
+                    "failed": failed,
+                }),
+            )
+            .await
+        {
+            warn!(task_id = %task_id, error = %e, "Failed to update task progress message");
+        }
+
+        Ok(())
+    }
+
+    /// Whether symphonia could be handed the chapter's file
+    pub(super) async fn can_analyse(&self, chapter: &Chapter) -> bool {
+        let path = Path::new(&chapter.path);
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("strm"))
+        {
+            return false;
+        }
+        match &self.plugin_manager {
+            Some(plugin_manager) => plugin_manager.find_plugin_for_format(path).await.is_none(),
+            None => true,
+        }
+    }
+
+    async fn analyse_chapter(
+        &self,
+        library: &Library,
+        chapter: &Chapter,
+    ) -> Result<LoudnessAnalysis> {
+        let source = self.open_chapter(library, chapter).await?;
+        let mut hint = Hint::new();
+        if let Some(ext) = Path::new(&chapter.path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+        {
+            hint.with_extension(ext);
+        }
+        let start = chapter.start_offset.unwrap_or(0.0);
+        let limit = chapter.end_offset.map(|end| (end - start).max(0.0));
+
+        tokio::task::spawn_blocking(move || loudness::analyze(source, hint, start, limit))
+            .await
+            .map_err(|e| TingError::TaskError(format!("Loudness analysis failed: {}", e)))?
+    }
+
+    /// The chapter's file: a downloaded RSS episode or local file when there
+    /// is one, otherwise read from the library's storage
+    pub(super) async fn open_chapter(
+        &self,
+        library: &Library,
+        chapter: &Chapter,
+    ) -> Result<Box<dyn MediaSource>> {
+        if library.library_type == "rss" {
+            if let Some(library_repo) = &self.library_repo {
+                if let Some(download) = library_repo.find_episode_download(&chapter.id).await? {
+                    if Path::new(&download.file_path).exists() {
+                        return Ok(Box::new(std::fs::File::open(&download.file_path)?));
+                    }
+                }
+            }
+        }
+        if library.library_type == "local" {
+            return Ok(Box::new(std::fs::File::open(&chapter.path)?));
+        }
+
+        let storage_service = self
+            .storage_service
+            .as_ref()
+            .ok_or_else(|| TingError::TaskError("Storage service not configured".to_string()))?;
+        let (reader, _) = if library.library_type == "webdav" {
+            let key = self
+                .encryption_key
+                .as_ref()
+                .ok_or_else(|| TingError::TaskError("Encryption key not configured".to_string()))?;
+            storage_service
+                .get_webdav_reader(library, &chapter.path, None, key)
+                .await?
+        } else {
+            storage_service.get_http_reader(&chapter.path, None).await?
+        };
+        Ok(Box::new(ReadOnlySource::new(BlockingReader::new(reader))))
+    }
+}
@@ -14,29 +14,50 @@ use crate::core::StorageService;
 use crate::db::manager::DatabaseManager;
 use crate::db::models::TaskRecord;
 use crate::db::repository::{
-    LibraryRepository, NotificationWebhookRepository, Repository, TaskRepository,
+    LibraryRepository, NotificationWebhookRepository, Repository, TaskEvent, TaskRepository,
 };
 use crate::plugin::manager::PluginManager;
 
 use chrono::SecondsFormat;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{mpsc, RwLock, Semaphore};
+use tokio::sync::{mpsc, Mutex, RwLock, Semaphore};
 use tokio::time::timeout;
 use tracing::{debug, error, info, warn};
 
+mod episodes;
 mod execution;
+mod loudness;
+mod pools;
+mod scheduler;
+mod schedules;
+mod skip_detection;
 mod types;
+mod workflows;
 
+pub use pools::TaskPoolStats;
+pub use scheduler::{
+    validate_rss_refresh_config, CronSchedule, CronScheduler, RssRefreshScheduler, ScheduledJob,
+    MIN_RSS_REFRESH_INTERVAL_MINUTES,
+};
+pub use schedules::TaskScheduleRunner;
 pub(crate) use types::PriorityTask;
 pub use types::{BackoffStrategy, Priority, RetryPolicy, Task, TaskPayload, TaskStatus};
+pub use workflows::WORKFLOW_TASK_TYPE;
+
+/// Library scans get a very long timeout (24 hours) to avoid timeouts on large libraries
+const LIBRARY_SCAN_TIMEOUT: Duration = Duration::from_secs(86400);
 
 /// Task queue for managing asynchronous tasks
 pub struct TaskQueue {
     config: TaskQueueConfig,
     queue: Arc<RwLock<BinaryHeap<PriorityTask>>>,
+    /// Tasks held back until their dependencies complete
+    waiting: Arc<Mutex<HashMap<String, Task>>>,
+    /// Running tasks per type, counted against `config.pools`
+    pools: Mutex<pools::TaskPools>,
     task_repo: TaskRepository,
     semaphore: Arc<Semaphore>,
     shutdown_tx: mpsc::Sender<()>,
@@ -55,6 +76,7 @@ pub struct TaskQueue {
     notification_repo: Option<Arc<NotificationWebhookRepository>>,
     encryption_key: Option<Arc<[u8; 32]>>,
     temp_dir: std::path::PathBuf,
+    episode_dir: Option<PathBuf>,
 }
 
 impl TaskQueue {
@@ -64,8 +86,10 @@ impl TaskQueue {
 
         Self {
             semaphore: Arc::new(Semaphore::new(config.max_concurrent_tasks)),
+            pools: Mutex::new(pools::TaskPools::new(config.pools.clone())),
             config,
             queue: Arc::new(RwLock::new(BinaryHeap::new())),
+            waiting: Arc::new(Mutex::new(HashMap::new())),
             task_repo: TaskRepository::new(db),
             shutdown_tx,
             shutdown_rx: Arc::new(RwLock::new(shutdown_rx)),
@@ -83,6 +107,7 @@ impl TaskQueue {
             notification_repo: None,
             encryption_key: None,
             temp_dir,
+            episode_dir: None,
         }
     }
 
@@ -161,6 +186,12 @@ impl TaskQueue {
         self
     }
 
+    /// Set the directory RSS episodes are downloaded into
+    pub fn with_episode_dir(mut self, episode_dir: PathBuf) -> Self {
+        self.episode_dir = Some(episode_dir);
+        self
+    }
+
     /// Recover incomplete tasks from database after system restart
     pub async fn recover_tasks(&self) -> Result<usize> {
         info!(
@@ -176,6 +207,10 @@ impl TaskQueue {
         debug!("Found {} queued tasks to recover", queued_tasks.len());
 
         for task_record in queued_tasks {
+            // Workflows are not run themselves; their steps are recovered below
+            if task_record.task_type == WORKFLOW_TASK_TYPE {
+                continue;
+            }
             match self.record_to_task(&task_record) {
                 Ok(task) => {
                     let mut queue = self.queue.write().await;
@@ -197,6 +232,9 @@ impl TaskQueue {
         debug!("Found {} running tasks to recover", running_tasks.len());
 
         for mut task_record in running_tasks {
+            if task_record.task_type == WORKFLOW_TASK_TYPE {
+                continue;
+            }
             // Mark as queued so they will be retried
             task_record.status = "queued".to_string();
             task_record.updated_at =
@@ -227,6 +265,31 @@ impl TaskQueue {
             }
         }
 
+        // Recover tasks waiting for dependencies; some may have become ready
+        // or impossible while the system was down
+        let waiting_tasks = self.task_repo.find_by_status("waiting").await?;
+        for task_record in waiting_tasks {
+            match self.record_to_task(&task_record) {
+                Ok(task) => {
+                    self.waiting.lock().await.insert(task.id.clone(), task);
+                    recovered_count += 1;
+                }
+                Err(e) => {
+                    warn!(
+                        task_id = %task_record.id,
+                        error = %e,
+                        "Failed to deserialize waiting task, skipping"
+                    );
+                }
+            }
+        }
+        self.settle_waiting().await;
+        for workflow in self.task_repo.find_by_type(WORKFLOW_TASK_TYPE).await? {
+            if matches!(workflow.status.as_str(), "queued" | "running") {
+                self.refresh_workflow(&workflow.id).await?;
+            }
+        }
+
         info!(
             message_key = "task.recovery.completed",
             message_params = %serde_json::json!({ "count": recovered_count }),
@@ -378,8 +441,7 @@ impl TaskQueue {
         } = task.payload
         {
             if task_type == "library_scan" {
-                // Set a very long timeout for library scans (24 hours) to avoid timeouts on large libraries
-                task.timeout = Duration::from_secs(86400);
+                task.timeout = LIBRARY_SCAN_TIMEOUT;
 
                 if let Some(library_id) = data.get("library_id").and_then(|v| v.as_str()) {
                     let library_id = library_id.to_string();
@@ -455,14 +517,30 @@ impl TaskQueue {
             task.timeout = Duration::from_secs(self.config.task_timeout);
         }
 
+        // Tasks with unfinished dependencies wait outside the queue; holding
+        // the waiting list keeps a dependency from completing unnoticed
+        let mut waiting = self.waiting.lock().await;
+        if !task.depends_on.is_empty() {
+            self.check_dependencies(&mut task).await?;
+        }
+
         // Persist task to database
         let task_record = self.task_to_record(&task)?;
         self.task_repo.create(&task_record).await?;
 
-        // Add to priority queue
-        {
-            let mut queue = self.queue.write().await;
-            queue.push(PriorityTask { task: task.clone() });
+        match task.status {
+            TaskStatus::Waiting => {
+                waiting.insert(task_id.clone(), task.clone());
+            }
+            TaskStatus::Queued => {
+                let mut queue = self.queue.write().await;
+                queue.push(PriorityTask { task: task.clone() });
+            }
+            _ => {}
+        }
+        drop(waiting);
+        if let Some(parent_id) = &task.parent_id {
+            self.refresh_workflow(parent_id).await?;
         }
 
         info!(
@@ -500,6 +578,10 @@ impl TaskQueue {
             .await?
             .ok_or_else(|| TingError::NotFound(format!("Task not found: {}", task_id)))?;
 
+        if task_record.task_type == WORKFLOW_TASK_TYPE {
+            return self.cancel_workflow(task_id).await;
+        }
+
         if task_record.status == TaskStatus::Running.as_str() {
             // Allow cancelling running tasks - the executor will pick this up
             info!(task_id = %task_id, "Marking running task as cancelled");
@@ -509,15 +591,14 @@ impl TaskQueue {
         task_record.updated_at = chrono::Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
         self.task_repo.update(&task_record).await?;
 
-        // Remove from queue if present
-        {
-            let mut queue = self.queue.write().await;
-            let tasks: Vec<_> = queue.drain().collect();
-            *queue = tasks
-                .into_iter()
-                .filter(|pt| pt.task.id != task_id)
-                .collect();
+        // Remove from queue or waiting list if present
+        self.forget_tasks(&HashSet::from([task_id.to_string()]))
+            .await;
+        if let Some(parent_id) = &task_record.parent_id {
+            self.refresh_workflow(parent_id).await?;
         }
+        // Tasks depending on this one will not run either
+        self.settle_waiting().await;
 
         info!(task_id = %task_id, "Task cancelled");
         Ok(())
@@ -575,6 +656,12 @@ impl TaskQueue {
         }
 
         self.task_repo.delete(task_id).await?;
+        // Steps of a workflow are deleted with it
+        self.forget_deleted_tasks().await?;
+        if let Some(parent_id) = &task_record.parent_id {
+            self.refresh_workflow(parent_id).await?;
+        }
+        self.settle_waiting().await;
 
         info!(task_id = %task_id, "Task deleted");
         Ok(())
@@ -583,6 +670,8 @@ impl TaskQueue {
     /// Batch delete tasks
     pub async fn delete_tasks(&self, ids: Vec<String>) -> Result<usize> {
         let count = self.task_repo.delete_batch(ids).await?;
+        self.forget_deleted_tasks().await?;
+        self.settle_waiting().await;
         info!(count = count, "Batch deleted tasks");
         Ok(count)
     }
@@ -599,6 +688,8 @@ impl TaskQueue {
             let tasks = self.task_repo.find_by_status(&s).await?;
             let count = tasks.len();
             self.task_repo.delete_by_status(&s).await?;
+            self.forget_deleted_tasks().await?;
+            self.settle_waiting().await;
             info!(status = %s, count = count, "Cleared tasks by status");
             Ok(count)
         } else {
@@ -606,16 +697,16 @@ impl TaskQueue {
             // We can't use delete_all because it would delete running tasks too
             // So we delete by status for each non-running status
             let mut total = 0;
-            for s in ["queued", "completed", "failed", "cancelled"] {
+            for s in ["waiting", "queued", "completed", "failed", "cancelled"] {
                 let tasks = self.task_repo.find_by_status(s).await?;
                 let count = tasks.len();
                 self.task_repo.delete_by_status(s).await?;
                 total += count;
             }
 
-            // Clear queued tasks from memory queue as well
-            let mut queue = self.queue.write().await;
-            queue.clear();
+            // Clear deleted tasks from memory as well; steps of running
+            // workflows are kept
+            self.forget_deleted_tasks().await?;
 
             info!(count = total, "Cleared all non-running tasks");
             Ok(total)
@@ -653,36 +744,68 @@ impl TaskQueue {
 
     /// Process the next task in the queue
     async fn process_next_task(self: &Arc<Self>) {
-        // Get next task from queue
-        let task = {
-            let mut queue = self.queue.write().await;
-            queue.pop().map(|pt| pt.task)
+        // Acquire semaphore permit before picking, so a task is only taken
+        // off the queue once it can run
+        let permit = match self.semaphore.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            Err(e) => {
+                error!(error = %e, "Failed to acquire semaphore permit");
+                return;
+            }
         };
 
-        if let Some(task) = task {
-            // Acquire semaphore permit
-            let permit = self.semaphore.clone().acquire_owned().await;
+        // Get next task whose pool has room
+        if let Some((task, slot)) = self.next_runnable_task().await {
+            let self_clone = Arc::clone(self);
 
-            match permit {
-                Ok(permit) => {
-                    let self_clone = Arc::clone(self);
-
-                    // Spawn task execution
-                    tokio::spawn(async move {
-                        self_clone.execute_task(task).await;
-                        drop(permit);
-                    });
-                }
-                Err(e) => {
-                    error!(error = %e, "Failed to acquire semaphore permit");
-                }
-            }
+            // Spawn task execution
+            tokio::spawn(async move {
+                self_clone.execute_task(task).await;
+                self_clone.pools.lock().await.finish(&slot);
+                drop(permit);
+            });
         } else {
             // No tasks available, wait a bit
+            drop(permit);
             tokio::time::sleep(Duration::from_millis(100)).await;
         }
     }
 
+    /// Follow status and progress changes of all tasks as they are written
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<TaskEvent> {
+        self.task_repo.subscribe()
+    }
+
+    /// Whether a scan of the library is queued or running
+    pub async fn has_active_library_scan(&self, library_id: &str) -> Result<bool> {
+        self.has_active_library_task("library_scan", library_id)
+            .await
+    }
+
+    /// Whether a task of this type is queued or running for the library
+    pub async fn has_active_library_task(&self, task_type: &str, library_id: &str) -> Result<bool> {
+        for status in [TaskStatus::Queued, TaskStatus::Running] {
+            let records = self.task_repo.find_by_status(status.as_str()).await?;
+            let active = records.iter().any(|record| {
+                record.task_type == task_type
+                    && record
+                        .payload
+                        .as_deref()
+                        .and_then(|payload| serde_json::from_str::<TaskPayload>(payload).ok())
+                        .is_some_and(|payload| match payload {
+                            TaskPayload::Custom { data, .. } => {
+                                data.get("library_id").and_then(|v| v.as_str()) == Some(library_id)
+                            }
+                            _ => false,
+                        })
+            });
+            if active {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     pub async fn enqueue_scan_library(
         &self,
         library_id: &str,
@@ -747,6 +870,7 @@ impl TaskQueue {
                     task_id = %task_id,
                     "Task completed successfully"
                 );
+                self.settle_waiting().await;
             }
             Ok(Err(e)) => {
                 // Task failed
@@ -811,6 +935,7 @@ impl TaskQueue {
                         retries = task.retries,
                         "Task failed after max retries"
                     );
+                    self.settle_waiting().await;
                 }
             }
             Err(_) => {
@@ -835,6 +960,7 @@ impl TaskQueue {
                 if let Err(e) = self.update_task_status(&task).await {
                     error!(task_id = %task_id, error = %e, "Failed to update task status");
                 }
+                self.settle_waiting().await;
             }
         }
     }
@@ -848,7 +974,11 @@ impl TaskQueue {
                 task.error.as_deref(),
                 task.retries as i32,
             )
-            .await
+            .await?;
+        if let Some(parent_id) = &task.parent_id {
+            self.refresh_workflow(parent_id).await?;
+        }
+        Ok(())
     }
 
     /// Convert Task to TaskRecord
@@ -858,12 +988,7 @@ impl TaskQueue {
 
         Ok(TaskRecord {
             id: task.id.clone(),
-            task_type: match &task.payload {
-                TaskPayload::ScraperSearch { .. } => "scraper_search".to_string(),
-                TaskPayload::FormatConvert { .. } => "format_convert".to_string(),
-                TaskPayload::PluginInvoke { .. } => "plugin_invoke".to_string(),
-                TaskPayload::Custom { task_type, .. } => task_type.clone(),
-            },
+            task_type: task.task_type().to_string(),
             status: task.status.as_str().to_string(),
             payload: Some(payload_json),
             message: None,
@@ -875,12 +1000,22 @@ impl TaskQueue {
             // Format time with 3 decimal places for milliseconds (SQL friendly)
             created_at: task.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
             updated_at: chrono::Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            parent_id: task.parent_id.clone(),
+            depends_on: if task.depends_on.is_empty() {
+                None
+            } else {
+                Some(
+                    serde_json::to_string(&task.depends_on)
+                        .map_err(|e| TingError::SerializationError(e.to_string()))?,
+                )
+            },
         })
     }
 
     /// Convert status string to TaskStatus
     fn status_from_str(&self, status: &str) -> TaskStatus {
         match status {
+            "waiting" => TaskStatus::Waiting,
             "queued" => TaskStatus::Queued,
             "running" => TaskStatus::Running,
             "completed" => TaskStatus::Completed,
@@ -931,6 +1066,14 @@ impl TaskQueue {
             "Successfully deserialized task from database"
         );
 
+        // Recovered scans resume from their checkpoint and keep their long timeout
+        let task_timeout = match &payload {
+            TaskPayload::Custom { task_type, .. } if task_type == "library_scan" => {
+                LIBRARY_SCAN_TIMEOUT
+            }
+            _ => Duration::from_secs(600), // Default timeout
+        };
+
         Ok(Task {
             id: record.id.clone(),
             name: format!("{} task", record.task_type),
@@ -943,11 +1086,17 @@ impl TaskQueue {
                     max: Duration::from_secs(60),
                 },
             },
-            timeout: Duration::from_secs(600), // Default timeout
+            timeout: task_timeout,
             status: self.status_from_str(&record.status),
             retries: record.retries as u32,
             error: record.error.clone(),
             created_at,
+            parent_id: record.parent_id.clone(),
+            depends_on: record
+                .depends_on
+                .as_deref()
+                .and_then(|value| serde_json::from_str(value).ok())
+                .unwrap_or_default(),
         })
     }
 }
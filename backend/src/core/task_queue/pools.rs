@@ -0,0 +1,282 @@
+//! Per-type worker pools and fair scheduling across priorities
+//!
+//! Every task runs under the global `max_concurrent_tasks` limit. A task type
+//! listed under `task_queue.pools` is further held to its pool's size and, with
+//! `per_library`, to that many tasks per library. Tasks whose pool is full stay
+//! queued without blocking tasks of other types behind them.
+//!
+//! Priority levels take turns in proportion to their weight (High 4, Normal 2,
+//! Low 1) instead of strictly by priority, so a steady stream of high-priority
+//! work slows lower-priority tasks down but cannot starve them.
+
+use super::{Priority, PriorityTask, Task, TaskQueue};
+use crate::core::config::TaskPoolConfig;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Pass added to a priority level each time it runs a task: High, Normal, Low
+const STRIDES: [u64; 3] = [1, 2, 4];
+
+/// Queued and running tasks of one type, with the limits of its pool
+#[derive(Debug, Clone)]
+pub struct TaskPoolStats {
+    pub task_type: String,
+    pub queued: usize,
+    pub running: usize,
+    pub max_concurrent: Option<usize>,
+    pub per_library: Option<usize>,
+}
+
+/// Running tasks counted against the pools, and the turn of each priority level
+#[derive(Debug, Default)]
+pub(crate) struct TaskPools {
+    limits: HashMap<String, TaskPoolConfig>,
+    running: HashMap<String, usize>,
+    running_per_library: HashMap<(String, String), usize>,
+    passes: [u64; 3],
+}
+
+/// Pool slot a running task holds until it finishes
+#[derive(Debug, Clone)]
+pub(crate) struct PoolSlot {
+    task_type: String,
+    library_id: Option<String>,
+}
+
+fn level(priority: Priority) -> usize {
+    match priority {
+        Priority::High => 0,
+        Priority::Normal => 1,
+        Priority::Low => 2,
+    }
+}
+
+impl TaskPools {
+    pub(crate) fn new(limits: HashMap<String, TaskPoolConfig>) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the task's pool has room for one more task
+    fn has_capacity(&self, task: &Task) -> bool {
+        let task_type = task.task_type();
+        let Some(limits) = self.limits.get(task_type) else {
+            return true;
+        };
+        if let Some(max) = limits.max_concurrent {
+            if self.running.get(task_type).copied().unwrap_or(0) >= max {
+                return false;
+            }
+        }
+        if let (Some(max), Some(library_id)) = (limits.per_library, task.library_id()) {
+            let key = (task_type.to_string(), library_id.to_string());
+            if self.running_per_library.get(&key).copied().unwrap_or(0) >= max {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Index of the task to run next among `tasks`, sorted in ascending
+    /// order as `BinaryHeap::into_sorted_vec` returns them
+    pub(crate) fn pick(&mut self, tasks: &[PriorityTask]) -> Option<usize> {
+        // Oldest runnable task of each priority level
+        let mut candidates = [None; 3];
+        for (index, queued) in tasks.iter().enumerate().rev() {
+            let level = level(queued.task.priority);
+            if candidates[level].is_none() && self.has_capacity(&queued.task) {
+                candidates[level] = Some(index);
+            }
+        }
+
+        let chosen = (0..candidates.len())
+            .filter(|&level| candidates[level].is_some())
+            .min_by_key(|&level| (self.passes[level], level))?;
+
+        // A level with nothing to run does not save up turns for later
+        let floor = self.passes[chosen];
+        for (level, candidate) in candidates.iter().enumerate() {
+            if candidate.is_none() {
+                self.passes[level] = self.passes[level].max(floor);
+            }
+        }
+        self.passes[chosen] += STRIDES[chosen];
+        candidates[chosen]
+    }
+
+    /// Count a task against its pool as it starts running
+    pub(crate) fn start(&mut self, task: &Task) -> PoolSlot {
+        let slot = PoolSlot {
+            task_type: task.task_type().to_string(),
+            library_id: task.library_id().map(str::to_string),
+        };
+        *self.running.entry(slot.task_type.clone()).or_default() += 1;
+        if let Some(library_id) = &slot.library_id {
+            *self
+                .running_per_library
+                .entry((slot.task_type.clone(), library_id.clone()))
+                .or_default() += 1;
+        }
+        slot
+    }
+
+    /// Release the slot of a task that has finished running
+    pub(crate) fn finish(&mut self, slot: &PoolSlot) {
+        if let Some(count) = self.running.get_mut(&slot.task_type) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                self.running.remove(&slot.task_type);
+            }
+        }
+        if let Some(library_id) = &slot.library_id {
+            let key = (slot.task_type.clone(), library_id.clone());
+            if let Some(count) = self.running_per_library.get_mut(&key) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    self.running_per_library.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+impl TaskQueue {
+    /// Take the next task to run off the queue and count it against its pool
+    pub(crate) async fn next_runnable_task(&self) -> Option<(Task, PoolSlot)> {
+        let mut queue = self.queue.write().await;
+        if queue.is_empty() {
+            return None;
+        }
+        let mut pools = self.pools.lock().await;
+        let mut tasks = std::mem::take(&mut *queue).into_sorted_vec();
+        let picked = pools.pick(&tasks).map(|index| tasks.remove(index).task);
+        *queue = BinaryHeap::from(tasks);
+        picked.map(|task| {
+            let slot = pools.start(&task);
+            (task, slot)
+        })
+    }
+
+    /// Replace the pool limits; running tasks keep their slots
+    pub async fn set_pool_limits(&self, limits: HashMap<String, TaskPoolConfig>) {
+        self.pools.lock().await.limits = limits;
+    }
+
+    /// Queued and running tasks per type, including configured pools with
+    /// neither, sorted by task type
+    pub async fn pool_stats(&self) -> Vec<TaskPoolStats> {
+        let mut queued: HashMap<String, usize> = HashMap::new();
+        for queued_task in self.queue.read().await.iter() {
+            *queued
+                .entry(queued_task.task.task_type().to_string())
+                .or_default() += 1;
+        }
+        let pools = self.pools.lock().await;
+
+        let mut task_types: Vec<&String> = queued
+            .keys()
+            .chain(pools.running.keys())
+            .chain(pools.limits.keys())
+            .collect();
+        task_types.sort();
+        task_types.dedup();
+
+        task_types
+            .into_iter()
+            .map(|task_type| {
+                let limits = pools.limits.get(task_type);
+                TaskPoolStats {
+                    task_type: task_type.clone(),
+                    queued: queued.get(task_type).copied().unwrap_or(0),
+                    running: pools.running.get(task_type).copied().unwrap_or(0),
+                    max_concurrent: limits.and_then(|limits| limits.max_concurrent),
+                    per_library: limits.and_then(|limits| limits.per_library),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::task_queue::TaskPayload;
+
+    fn task(task_type: &str, library_id: &str, priority: Priority) -> Task {
+        Task::new(
+            task_type.to_string(),
+            priority,
+            TaskPayload::Custom {
+                task_type: task_type.to_string(),
+                data: serde_json::json!({ "library_id": library_id }),
+            },
+        )
+    }
+
+    fn sorted(tasks: Vec<Task>) -> Vec<PriorityTask> {
+        tasks
+            .into_iter()
+            .map(|task| PriorityTask { task })
+            .collect::<BinaryHeap<_>>()
+            .into_sorted_vec()
+    }
+
+    #[test]
+    fn full_pools_let_other_tasks_through() {
+        let mut pools = TaskPools::new(HashMap::from([
+            (
+                "library_scan".to_string(),
+                TaskPoolConfig {
+                    max_concurrent: None,
+                    per_library: Some(1),
+                },
+            ),
+            (
+                "write_metadata".to_string(),
+                TaskPoolConfig {
+                    max_concurrent: Some(1),
+                    per_library: None,
+                },
+            ),
+        ]));
+        pools.start(&task("library_scan", "lib-1", Priority::Normal));
+        let writing = pools.start(&task("write_metadata", "lib-2", Priority::Normal));
+
+        let queued = sorted(vec![
+            task("write_metadata", "lib-1", Priority::High),
+            task("library_scan", "lib-1", Priority::High),
+            task("library_scan", "lib-2", Priority::Normal),
+        ]);
+        let index = pools.pick(&queued).unwrap();
+        assert_eq!(queued[index].task.library_id(), Some("lib-2"));
+        assert_eq!(queued[index].task.task_type(), "library_scan");
+
+        pools.finish(&writing);
+        let index = pools.pick(&queued).unwrap();
+        assert_eq!(queued[index].task.task_type(), "write_metadata");
+    }
+
+    #[test]
+    fn priority_levels_take_weighted_turns() {
+        let mut pools = TaskPools::default();
+        let mut queued = sorted(
+            (0..20)
+                .flat_map(|_| {
+                    [
+                        task("a", "lib", Priority::High),
+                        task("b", "lib", Priority::Normal),
+                        task("c", "lib", Priority::Low),
+                    ]
+                })
+                .collect(),
+        );
+
+        let mut runs = [0; 3];
+        for _ in 0..14 {
+            let index = pools.pick(&queued).unwrap();
+            runs[level(queued.remove(index).task.priority)] += 1;
+        }
+        assert_eq!(runs, [8, 4, 2]);
+    }
+}
@@ -0,0 +1,450 @@
+//! Cron scheduler and periodic refresh of RSS libraries
+//!
+//! One `CronScheduler` ticks once a minute and runs every job registered with
+//! it: the RSS refresh and the task schedules administrators define.
+//!
+//! The RSS refresh checks every RSS library's `ScraperConfig` and enqueues an
+//! incremental `library_scan` when its refresh interval has passed or its cron
+//! expression matches the current minute. Libraries without either setting
+//! are only scanned on demand.
+
+use super::TaskQueue;
+use crate::core::error::Result;
+use crate::core::library_scanner::ScanMode;
+use crate::core::time::resolve_time_zone;
+use crate::db::models::ScraperConfig;
+use crate::db::repository::{LibraryRepository, SystemSettingsRepository};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+/// Shortest refresh interval a library may ask for
+pub const MIN_RSS_REFRESH_INTERVAL_MINUTES: u32 = 5;
+
+/// Five-field cron expression: minute, hour, day of month, month, day of week.
+///
+/// Fields accept `*`, values, ranges (`1-5`), lists (`1,15`) and steps
+/// (`*/10`, `0-30/5`). Day of week runs from 0 (Sunday) to 7 (Sunday again).
+/// `@hourly`, `@daily`, `@weekly` and `@monthly` are accepted as shorthands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> std::result::Result<Self, String> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "Cron expression must have 5 fields: {}",
+                expression.trim()
+            ));
+        }
+
+        let days_of_week = parse_field(fields[4], 0, 7)?;
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days_of_month: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            // 7 is another name for Sunday
+            days_of_week: (days_of_week | days_of_week >> 7) & 0x7f,
+            any_day_of_month: fields[2].starts_with('*'),
+            any_day_of_week: fields[4].starts_with('*'),
+        })
+    }
+
+    /// Whether the schedule fires in the minute containing `at`
+    pub fn matches<T: Datelike + Timelike>(&self, at: &T) -> bool {
+        self.matches_hour(at) && self.minutes & (1 << at.minute()) != 0
+    }
+
+    /// First minute after `after` at which the schedule fires in `time_zone`,
+    /// looking at most a year ahead
+    pub fn next_after(&self, after: DateTime<Utc>, time_zone: Tz) -> Option<DateTime<Utc>> {
+        let limit = after + chrono::Duration::days(366);
+        let mut candidate =
+            after.with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        while candidate <= limit {
+            let local = candidate.with_timezone(&time_zone);
+            if !self.matches_hour(&local) {
+                // Skip to the start of the next local hour
+                candidate += chrono::Duration::minutes(60 - local.minute() as i64);
+            } else if self.matches(&local) {
+                return Some(candidate);
+            } else {
+                candidate += chrono::Duration::minutes(1);
+            }
+        }
+        None
+    }
+
+    /// Whether the day, month and hour of `at` match, ignoring the minute
+    fn matches_hour<T: Datelike + Timelike>(&self, at: &T) -> bool {
+        let has = |mask: u64, value: u32| mask & (1 << value) != 0;
+        let day_of_month = has(self.days_of_month, at.day());
+        let day_of_week = has(self.days_of_week, at.weekday().num_days_from_sunday());
+        // As in cron, when both day fields are restricted either may match
+        let day = match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (true, false) => day_of_week,
+            (false, true) => day_of_month,
+            (false, false) => day_of_month || day_of_week,
+        };
+
+        day && has(self.hours, at.hour()) && has(self.months, at.month())
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> std::result::Result<u64, String> {
+    let invalid = || format!("Invalid cron field: {}", field);
+    let number = |value: &str| value.parse::<u32>().map_err(|_| invalid());
+
+    let mut mask = 0_u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, number(step)?),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (number(start)?, number(end)?)
+        } else {
+            let start = number(range)?;
+            // `5/15` runs from 5 to the end of the range
+            (start, if step > 1 { max } else { start })
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+/// Check the refresh settings an RSS library may carry
+pub fn validate_rss_refresh_config(config: &ScraperConfig) -> std::result::Result<(), String> {
+    if let Some(minutes) = config.rss_refresh_interval_minutes {
+        if minutes != 0 && minutes < MIN_RSS_REFRESH_INTERVAL_MINUTES {
+            return Err(format!(
+                "RSS refresh interval must be at least {} minutes",
+                MIN_RSS_REFRESH_INTERVAL_MINUTES
+            ));
+        }
+    }
+    if let Some(expression) = config.rss_refresh_cron.as_deref() {
+        CronSchedule::parse(expression)?;
+    }
+    Ok(())
+}
+
+/// Whether an RSS library should be refreshed at `now`, given when it was
+/// last scanned or enqueued
+fn refresh_due(
+    config: &ScraperConfig,
+    last_run: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    time_zone: Tz,
+) -> bool {
+    if let Some(expression) = config
+        .rss_refresh_cron
+        .as_deref()
+        .filter(|value| !value.trim().is_empty())
+    {
+        let Ok(schedule) = CronSchedule::parse(expression) else {
+            return false;
+        };
+        let minute = now
+            .with_second(0)
+            .and_then(|at| at.with_nanosecond(0))
+            .unwrap_or(now);
+        return schedule.matches(&minute.with_timezone(&time_zone))
+            && last_run.map_or(true, |at| at < minute);
+    }
+
+    match config.rss_refresh_interval_minutes {
+        Some(minutes) if minutes > 0 => {
+            let interval =
+                chrono::Duration::minutes(minutes.max(MIN_RSS_REFRESH_INTERVAL_MINUTES) as i64);
+            last_run.map_or(true, |at| now - at >= interval)
+        }
+        _ => false,
+    }
+}
+
+/// Work the cron scheduler checks every minute
+#[async_trait]
+pub trait ScheduledJob: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &'static str;
+
+    /// Start whatever is due at `now`
+    async fn run_due(&self, now: DateTime<Utc>) -> Result<()>;
+}
+
+/// Runs the registered jobs once a minute
+#[derive(Default)]
+pub struct CronScheduler {
+    jobs: Vec<Arc<dyn ScheduledJob>>,
+}
+
+impl CronScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, job: Arc<dyn ScheduledJob>) {
+        self.jobs.push(job);
+    }
+
+    /// Check the jobs once a minute, forever
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(Duration::from_secs(60));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            let now = Utc::now();
+            for job in &self.jobs {
+                if let Err(e) = job.run_due(now).await {
+                    warn!(job = job.name(), error = %e, "Scheduled job failed");
+                }
+            }
+        }
+    }
+}
+
+/// Enqueues scans of RSS libraries on their configured schedule
+pub struct RssRefreshScheduler {
+    library_repo: Arc<LibraryRepository>,
+    settings_repo: Arc<SystemSettingsRepository>,
+    task_queue: Arc<TaskQueue>,
+    /// When each library was last enqueued, so a failing feed is not retried
+    /// every minute
+    last_enqueued: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl RssRefreshScheduler {
+    pub fn new(
+        library_repo: Arc<LibraryRepository>,
+        settings_repo: Arc<SystemSettingsRepository>,
+        task_queue: Arc<TaskQueue>,
+    ) -> Self {
+        Self {
+            library_repo,
+            settings_repo,
+            task_queue,
+            last_enqueued: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl ScheduledJob for RssRefreshScheduler {
+    fn name(&self) -> &'static str {
+        "rss_refresh"
+    }
+
+    async fn run_due(&self, now: DateTime<Utc>) -> Result<()> {
+        let time_zone = resolve_time_zone(
+            &self
+                .settings_repo
+                .application_time_zone_or_default()
+                .await?,
+        );
+        let libraries = self.library_repo.find_all().await?;
+
+        let mut last_enqueued = self.last_enqueued.lock().await;
+        last_enqueued.retain(|id, _| libraries.iter().any(|library| &library.id == id));
+
+        for library in libraries
+            .into_iter()
+            .filter(|library| library.library_type == "rss")
+        {
+            let config: ScraperConfig = library
+                .scraper_config
+                .as_ref()
+                .and_then(|json| serde_json::from_str(json).ok())
+                .unwrap_or_default();
+            let last_scanned = library
+                .last_scanned_at
+                .as_deref()
+                .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+                .map(|at| at.with_timezone(&Utc));
+            let last_run = last_scanned.max(last_enqueued.get(&library.id).copied());
+            if !refresh_due(&config, last_run, now, time_zone) {
+                continue;
+            }
+            // Submitting a scan cancels one in progress, so leave it be
+            if self.task_queue.has_active_library_scan(&library.id).await? {
+                continue;
+            }
+
+            last_enqueued.insert(library.id.clone(), now);
+            match self
+                .task_queue
+                .enqueue_scan_library(&library.id, &library.url, ScanMode::Incremental)
+                .await
+            {
+                Ok(task_id) => info!(
+                    library_id = %library.id,
+                    task_id = %task_id,
+                    "Scheduled RSS library refresh"
+                ),
+                Err(e) => warn!(
+                    library_id = %library.id,
+                    error = %e,
+                    "Failed to schedule RSS library refresh"
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parses_cron_fields() {
+        let schedule = CronSchedule::parse("*/15 6-8 * * 1-5").unwrap();
+        // Monday
+        assert!(schedule.matches(&Utc.with_ymd_and_hms(2026, 10, 12, 7, 45, 0).unwrap()));
+        assert!(!schedule.matches(&Utc.with_ymd_and_hms(2026, 10, 12, 7, 50, 0).unwrap()));
+        assert!(!schedule.matches(&Utc.with_ymd_and_hms(2026, 10, 12, 9, 0, 0).unwrap()));
+        // Sunday
+        assert!(!schedule.matches(&Utc.with_ymd_and_hms(2026, 10, 11, 7, 45, 0).unwrap()));
+
+        let sunday = CronSchedule::parse("0 0 * * 7").unwrap();
+        assert!(sunday.matches(&Utc.with_ymd_and_hms(2026, 10, 11, 0, 0, 0).unwrap()));
+        assert_eq!(
+            CronSchedule::parse("@daily").unwrap(),
+            CronSchedule::parse("0 0 * * *").unwrap()
+        );
+
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // The 1st of the month or any Friday
+        let schedule = CronSchedule::parse("0 12 1 * 5").unwrap();
+        assert!(schedule.matches(&Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap()));
+        assert!(schedule.matches(&Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap()));
+        assert!(!schedule.matches(&Utc.with_ymd_and_hms(2026, 10, 15, 12, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn next_run_is_found_in_the_app_time_zone() {
+        let shanghai: Tz = "Asia/Shanghai".parse().unwrap();
+        let daily = CronSchedule::parse("30 3 * * *").unwrap();
+        assert_eq!(
+            daily.next_after(at("2026-10-17T12:00:00Z"), shanghai),
+            Some(at("2026-10-17T19:30:00Z"))
+        );
+        // A schedule never fires in the minute it is asked from
+        assert_eq!(
+            daily.next_after(at("2026-10-17T19:30:10Z"), shanghai),
+            Some(at("2026-10-18T19:30:00Z"))
+        );
+        let leap_day = CronSchedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(
+            leap_day.next_after(at("2026-10-17T12:00:00Z"), Tz::UTC),
+            None
+        );
+        assert_eq!(
+            leap_day.next_after(at("2027-10-17T12:00:00Z"), Tz::UTC),
+            Some(at("2028-02-29T00:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn interval_refresh_waits_for_the_interval() {
+        let config = ScraperConfig {
+            rss_refresh_interval_minutes: Some(60),
+            ..Default::default()
+        };
+        let now = at("2026-10-17T12:00:00Z");
+        assert!(refresh_due(&config, None, now, Tz::UTC));
+        assert!(refresh_due(
+            &config,
+            Some(at("2026-10-17T11:00:00Z")),
+            now,
+            Tz::UTC
+        ));
+        assert!(!refresh_due(
+            &config,
+            Some(at("2026-10-17T11:30:00Z")),
+            now,
+            Tz::UTC
+        ));
+        assert!(!refresh_due(&ScraperConfig::default(), None, now, Tz::UTC));
+    }
+
+    #[test]
+    fn cron_refresh_fires_once_per_matching_minute_in_the_app_time_zone() {
+        let config = ScraperConfig {
+            rss_refresh_interval_minutes: Some(5),
+            rss_refresh_cron: Some("30 8 * * *".to_string()),
+            ..Default::default()
+        };
+        let shanghai: Tz = "Asia/Shanghai".parse().unwrap();
+        let now = at("2026-10-17T00:30:20Z");
+        assert!(refresh_due(&config, None, now, shanghai));
+        assert!(!refresh_due(&config, None, now, Tz::UTC));
+        assert!(!refresh_due(
+            &config,
+            Some(at("2026-10-17T00:30:05Z")),
+            now,
+            shanghai
+        ));
+    }
+
+    #[test]
+    fn validates_refresh_settings() {
+        let too_often = ScraperConfig {
+            rss_refresh_interval_minutes: Some(1),
+            ..Default::default()
+        };
+        assert!(validate_rss_refresh_config(&too_often).is_err());
+        let bad_cron = ScraperConfig {
+            rss_refresh_cron: Some("every hour".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_rss_refresh_config(&bad_cron).is_err());
+        assert!(validate_rss_refresh_config(&ScraperConfig::default()).is_ok());
+    }
+}
@@ -0,0 +1,271 @@
+//! Recurring tasks defined by administrators
+//!
+//! Each schedule stores a serialized `TaskPayload` and a cron expression
+//! evaluated in the application time zone. The runner is a job of the
+//! `CronScheduler`: every minute it submits the payload of each enabled
+//! schedule that matches, and records the run together with the id of the
+//! task it created.
+
+use super::{CronSchedule, ScheduledJob, Task, TaskPayload, TaskQueue};
+use crate::core::config::Config;
+use crate::core::error::{Result, TingError};
+use crate::core::local_paths::{path_to_display_string, resolve_existing_local_library_root};
+use crate::core::time::resolve_time_zone;
+use crate::db::models::{TaskSchedule, TaskScheduleRun};
+use crate::db::repository::{LibraryRepository, SystemSettingsRepository, TaskScheduleRepository};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Timelike, Utc};
+use chrono_tz::Tz;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Submits the payloads of task schedules when their cron expression is due
+pub struct TaskScheduleRunner {
+    schedule_repo: Arc<TaskScheduleRepository>,
+    library_repo: Arc<LibraryRepository>,
+    settings_repo: Arc<SystemSettingsRepository>,
+    task_queue: Arc<TaskQueue>,
+    config: Arc<RwLock<Config>>,
+}
+
+impl TaskScheduleRunner {
+    pub fn new(
+        schedule_repo: Arc<TaskScheduleRepository>,
+        library_repo: Arc<LibraryRepository>,
+        settings_repo: Arc<SystemSettingsRepository>,
+        task_queue: Arc<TaskQueue>,
+        config: Arc<RwLock<Config>>,
+    ) -> Self {
+        Self {
+            schedule_repo,
+            library_repo,
+            settings_repo,
+            task_queue,
+            config,
+        }
+    }
+
+    /// Time zone the cron expressions are evaluated in
+    pub async fn time_zone(&self) -> Result<Tz> {
+        Ok(resolve_time_zone(
+            &self
+                .settings_repo
+                .application_time_zone_or_default()
+                .await?,
+        ))
+    }
+
+    /// Submit the schedule's payload now and record the run.
+    ///
+    /// A payload that cannot be submitted is recorded as a run with an error
+    /// rather than returned, so it shows up in the schedule's history.
+    pub async fn run_schedule(
+        &self,
+        schedule: &TaskSchedule,
+        triggered_by: &str,
+    ) -> Result<TaskScheduleRun> {
+        let (task_id, error) = match self.submit(schedule, triggered_by).await {
+            Ok(task_id) => {
+                info!(
+                    schedule_id = %schedule.id,
+                    task_id = %task_id,
+                    triggered_by = %triggered_by,
+                    "Submitted scheduled task"
+                );
+                (Some(task_id), None)
+            }
+            Err(e) => {
+                warn!(
+                    schedule_id = %schedule.id,
+                    triggered_by = %triggered_by,
+                    error = %e,
+                    "Skipped scheduled task"
+                );
+                (None, Some(e.to_string()))
+            }
+        };
+
+        let run = TaskScheduleRun {
+            id: Uuid::new_v4().to_string(),
+            schedule_id: schedule.id.clone(),
+            triggered_by: triggered_by.to_string(),
+            triggered_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            task_status: task_id.as_ref().map(|_| "queued".to_string()),
+            task_id,
+            error,
+            task_error: None,
+        };
+        self.schedule_repo.record_run(&run).await?;
+        Ok(run)
+    }
+
+    /// Check a payload before it is stored in a schedule
+    pub async fn validate_payload(&self, payload: &TaskPayload) -> Result<()> {
+        if let Some(library_id) = scan_library_id(payload)? {
+            self.library_repo
+                .find_by_id(library_id)
+                .await?
+                .ok_or_else(|| TingError::NotFound(format!("Library {} not found", library_id)))?;
+        }
+        Ok(())
+    }
+
+    async fn submit(&self, schedule: &TaskSchedule, triggered_by: &str) -> Result<String> {
+        let mut payload: TaskPayload = serde_json::from_str(&schedule.payload)
+            .map_err(|e| TingError::InvalidRequest(format!("Invalid task payload: {}", e)))?;
+
+        if let Some(library_id) = scan_library_id(&payload)? {
+            // Submitting a scan cancels one in progress; a manual run does
+            // that on purpose, a scheduled one leaves it be
+            if triggered_by == "schedule"
+                && self.task_queue.has_active_library_scan(library_id).await?
+            {
+                return Err(TingError::TaskError(format!(
+                    "A scan of library {} is already queued or running",
+                    library_id
+                )));
+            }
+        }
+        self.prepare_payload(&mut payload).await?;
+
+        let task = Task::new(schedule.name.clone(), super::Priority::Normal, payload);
+        self.task_queue.submit(task).await
+    }
+
+    /// Fill in what a stored payload leaves out, such as the path of the
+    /// library a `library_scan` payload refers to
+    pub async fn prepare_payload(&self, payload: &mut TaskPayload) -> Result<()> {
+        if let Some(library_id) = scan_library_id(payload)?.map(str::to_string) {
+            let library = self
+                .library_repo
+                .find_by_id(&library_id)
+                .await?
+                .ok_or_else(|| TingError::NotFound(format!("Library {} not found", library_id)))?;
+            if let TaskPayload::Custom { data, .. } = payload {
+                if data.get("library_path").and_then(|v| v.as_str()).is_none() {
+                    let library_path = if library.library_type == "local" {
+                        let config = self.config.read().await;
+                        path_to_display_string(&resolve_existing_local_library_root(
+                            &library, &config,
+                        )?)
+                    } else {
+                        library.url.clone()
+                    };
+                    data["library_path"] = serde_json::json!(library_path);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ScheduledJob for TaskScheduleRunner {
+    fn name(&self) -> &'static str {
+        "task_schedules"
+    }
+
+    async fn run_due(&self, now: DateTime<Utc>) -> Result<()> {
+        let time_zone = self.time_zone().await?;
+        for schedule in self.schedule_repo.find_enabled().await? {
+            let last_run = schedule
+                .last_run_at
+                .as_deref()
+                .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+                .map(|at| at.with_timezone(&Utc));
+            if !schedule_due(&schedule.cron, last_run, now, time_zone) {
+                continue;
+            }
+            if let Err(e) = self.run_schedule(&schedule, "schedule").await {
+                warn!(schedule_id = %schedule.id, error = %e, "Failed to record task schedule run");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Library a `library_scan` payload refers to, `None` for other payloads
+fn scan_library_id(payload: &TaskPayload) -> Result<Option<&str>> {
+    match payload {
+        TaskPayload::Custom { task_type, data } if task_type == "library_scan" => data
+            .get("library_id")
+            .and_then(|v| v.as_str())
+            .map(Some)
+            .ok_or_else(|| {
+                TingError::InvalidRequest("library_scan payload requires library_id".to_string())
+            }),
+        _ => Ok(None),
+    }
+}
+
+/// Whether a schedule fires at `now`: its cron expression matches the current
+/// minute in the application time zone and it has not run in that minute yet
+fn schedule_due(
+    expression: &str,
+    last_run: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    time_zone: Tz,
+) -> bool {
+    let Ok(schedule) = CronSchedule::parse(expression) else {
+        return false;
+    };
+    let minute = now
+        .with_second(0)
+        .and_then(|at| at.with_nanosecond(0))
+        .unwrap_or(now);
+    schedule.matches(&minute.with_timezone(&time_zone)) && last_run.map_or(true, |at| at < minute)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn schedules_fire_once_in_a_matching_minute() {
+        let shanghai: Tz = "Asia/Shanghai".parse().unwrap();
+        let now = at("2026-10-16T19:00:30Z");
+        assert!(schedule_due("0 3 * * *", None, now, shanghai));
+        assert!(!schedule_due("0 3 * * *", None, now, Tz::UTC));
+        assert!(!schedule_due(
+            "0 3 * * *",
+            Some(at("2026-10-16T19:00:02Z")),
+            now,
+            shanghai
+        ));
+        assert!(schedule_due(
+            "0 3 * * *",
+            Some(at("2026-10-15T19:00:02Z")),
+            now,
+            shanghai
+        ));
+        assert!(!schedule_due("not a cron", None, now, shanghai));
+    }
+
+    #[test]
+    fn library_scan_payloads_must_name_a_library() {
+        let scan = TaskPayload::Custom {
+            task_type: "library_scan".to_string(),
+            data: serde_json::json!({ "library_id": "lib-1" }),
+        };
+        assert_eq!(scan_library_id(&scan).unwrap(), Some("lib-1"));
+        let missing = TaskPayload::Custom {
+            task_type: "library_scan".to_string(),
+            data: serde_json::json!({}),
+        };
+        assert!(scan_library_id(&missing).is_err());
+        let invoke = TaskPayload::PluginInvoke {
+            plugin_id: "plugin".to_string(),
+            method: "refresh".to_string(),
+            params: serde_json::json!({}),
+        };
+        assert_eq!(scan_library_id(&invoke).unwrap(), None);
+    }
+}
@@ -0,0 +1,193 @@
+//! Intro and outro detection for a book
+//!
+//! Fingerprints both ends of every chapter of a book and stores what they
+//! open and close with as a skip proposal, for an admin to review and apply.
+//! Chapters are read the same way as for the loudness analysis, so the same
+//! chapters are left out.
+
+use super::{Priority, Task, TaskPayload, TaskQueue};
+use crate::core::audio_streamer::skip_detection::{self, ChapterPrint, WINDOW_SECS};
+use crate::core::error::{Result, TingError};
+use crate::db::models::{Chapter, ChapterSkipProposal, Library, SkipKind, SkipProposal};
+use crate::db::repository::Repository;
+use std::path::Path;
+use std::time::Duration;
+use symphonia::core::probe::Hint;
+use tracing::{info, warn};
+
+/// Only the ends of chapters are decoded, but a book may have hundreds
+const SKIP_ANALYSIS_TIMEOUT: Duration = Duration::from_secs(6 * 3600);
+
+impl TaskQueue {
+    /// Queue looking for the intros and outros of a book
+    pub async fn enqueue_skip_analysis(&self, name: String, book_id: &str) -> Result<String> {
+        let task = Task::new(
+            name,
+            Priority::Low,
+            TaskPayload::Custom {
+                task_type: "skip_analysis".to_string(),
+                data: serde_json::json!({ "book_id": book_id }),
+            },
+        )
+        .with_timeout(SKIP_ANALYSIS_TIMEOUT);
+
+        self.submit(task).await
+    }
+
+    /// Fingerprint the chapters of the book named by the task and store the
+    /// skips found as its proposal
+    pub(super) async fn handle_skip_analysis(
+        &self,
+        data: &serde_json::Value,
+        task_id: &str,
+    ) -> Result<()> {
+        let book_id = data["book_id"]
+            .as_str()
+            .ok_or_else(|| TingError::TaskError("Missing book_id".to_string()))?;
+        let book_repo = self
+            .book_repo
+            .as_ref()
+            .ok_or_else(|| TingError::TaskError("Book repository not configured".to_string()))?;
+        let library_repo = self
+            .library_repo
+            .as_ref()
+            .ok_or_else(|| TingError::TaskError("Library repository not configured".to_string()))?;
+        let chapter_repo = self
+            .chapter_repo
+            .as_ref()
+            .ok_or_else(|| TingError::TaskError("Chapter repository not configured".to_string()))?;
+        let book = book_repo
+            .find_by_id(book_id)
+            .await?
+            .ok_or_else(|| TingError::NotFound(format!("Book {} not found", book_id)))?;
+        let library = library_repo
+            .find_by_id(&book.library_id)
+            .await?
+            .ok_or_else(|| TingError::NotFound(format!("Library {} not found", book.library_id)))?;
+
+        let mut chapters = Vec::new();
+        for chapter in chapter_repo.find_by_book(book_id).await? {
+            if self.can_analyse(&chapter).await
+                && chapter_repo.find_segments(&chapter.id).await?.is_empty()
+            {
+                chapters.push(chapter);
+            }
+        }
+
+        let mut analysed = Vec::new();
+        let mut prints = Vec::new();
+        for (index, chapter) in chapters.iter().enumerate() {
+            let _ = self
+                .task_repo
+                .update_progress_key(
+                    task_id,
+                    "skip.analysis.progress",
+                    serde_json::json!({
+                        "current": index + 1,
+                        "total": chapters.len(),
+                        "title": chapter.title.as_deref().unwrap_or(""),
+                    }),
+                )
+                .await;
+            match self.fingerprint_chapter(&library, chapter).await {
+                Ok(print) => {
+                    analysed.push(chapter);
+                    prints.push(print);
+                }
+                Err(e) => {
+                    warn!(
+                        chapter_id = %chapter.id,
+                        path = %chapter.path,
+                        error = %e,
+                        "Failed to fingerprint chapter"
+                    );
+                }
+            }
+        }
+
+        let detection = tokio::task::spawn_blocking(move || skip_detection::detect(&prints))
+            .await
+            .map_err(|e| TingError::TaskError(format!("Skip analysis failed: {}", e)))?;
+        let count = |kind: SkipKind| {
+            detection
+                .ranges
+                .iter()
+                .filter(|ranges| ranges.iter().any(|range| range.kind == kind))
+                .count()
+        };
+        let (intros, outros) = (count(SkipKind::Intro), count(SkipKind::Outro));
+
+        let proposal = SkipProposal {
+            book_id: book.id.clone(),
+            skip_intro: detection.skip_intro,
+            skip_outro: detection.skip_outro,
+            chapters: analysed
+                .iter()
+                .zip(detection.ranges)
+                .filter(|(_, ranges)| !ranges.is_empty())
+                .map(|(chapter, ranges)| ChapterSkipProposal {
+                    chapter_id: chapter.id.clone(),
+                    ranges,
+                })
+                .collect(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        chapter_repo.save_skip_proposal(&proposal).await?;
+
+        info!(
+            book_id = %book_id,
+            chapters = analysed.len(),
+            intros,
+            outros,
+            "Chapter intros and outros analysed"
+        );
+        if let Err(e) = self
+            .task_repo
+            .update_progress_key(
+                task_id,
+                "skip.analysis.completed",
+                serde_json::json!({
+                    "chapters": analysed.len(),
+                    "intros": intros,
+                    "outros": outros,
+                }),
+            )
+            .await
+        {
+            warn!(task_id = %task_id, error = %e, "Failed to update task progress message");
+        }
+
+        Ok(())
+    }
+
+    async fn fingerprint_chapter(
+        &self,
+        library: &Library,
+        chapter: &Chapter,
+    ) -> Result<ChapterPrint> {
+        let length = chapter
+            .slice_length()
+            .filter(|length| *length > 0.0)
+            .ok_or_else(|| TingError::InvalidRequest("Chapter length unknown".to_string()))?;
+        let head = self.open_chapter(library, chapter).await?;
+        let tail = if length > WINDOW_SECS {
+            Some(self.open_chapter(library, chapter).await?)
+        } else {
+            None
+        };
+        let mut hint = Hint::new();
+        if let Some(ext) = Path::new(&chapter.path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+        {
+            hint.with_extension(ext);
+        }
+        let start = chapter.start_offset.unwrap_or(0.0);
+
+        tokio::task::spawn_blocking(move || {
+            skip_detection::fingerprint(head, tail, hint, start, length)
+        })
+        .await
+        .map_err(|e| TingError::TaskError(format!("Skip analysis failed: {}", e)))?
+    }
+}
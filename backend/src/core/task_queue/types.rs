@@ -14,6 +14,8 @@ pub enum Priority {
 /// Task status
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TaskStatus {
+    /// Submitted, but a task it depends on has not completed yet
+    Waiting,
     Queued,
     Running,
     Completed,
@@ -24,6 +26,7 @@ pub enum TaskStatus {
 impl TaskStatus {
     pub fn as_str(&self) -> &str {
         match self {
+            TaskStatus::Waiting => "waiting",
             TaskStatus::Queued => "queued",
             TaskStatus::Running => "running",
             TaskStatus::Completed => "completed",
@@ -116,6 +119,12 @@ pub struct Task {
     pub retries: u32,
     pub error: Option<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Workflow this task is a step of
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// Tasks that must complete before this one is queued
+    #[serde(default)]
+    pub depends_on: Vec<String>,
 }
 
 impl Task {
@@ -131,6 +140,8 @@ impl Task {
             retries: 0,
             error: None,
             created_at: chrono::Utc::now(),
+            parent_id: None,
+            depends_on: Vec::new(),
         }
     }
 
@@ -143,6 +154,31 @@ impl Task {
         self.timeout = timeout;
         self
     }
+
+    /// Run only after these tasks completed; if one of them fails or is
+    /// cancelled, this task is cancelled too
+    pub fn with_dependencies(mut self, task_ids: Vec<String>) -> Self {
+        self.depends_on = task_ids;
+        self
+    }
+
+    /// Type the task is stored and pooled under
+    pub fn task_type(&self) -> &str {
+        match &self.payload {
+            TaskPayload::ScraperSearch { .. } => "scraper_search",
+            TaskPayload::FormatConvert { .. } => "format_convert",
+            TaskPayload::PluginInvoke { .. } => "plugin_invoke",
+            TaskPayload::Custom { task_type, .. } => task_type,
+        }
+    }
+
+    /// Library a custom task works on, if its payload names one
+    pub fn library_id(&self) -> Option<&str> {
+        match &self.payload {
+            TaskPayload::Custom { data, .. } => data.get("library_id").and_then(|v| v.as_str()),
+            _ => None,
+        }
+    }
 }
 
 /// Wrapper for priority queue ordering
@@ -0,0 +1,411 @@
+//! Task dependencies and workflows
+//!
+//! A task submitted with `depends_on` is stored as `waiting` and kept out of
+//! the priority queue until all of its prerequisites completed. If one of them
+//! fails or is cancelled instead, the waiting task is cancelled too, and the
+//! cancellation travels on down the chain.
+//!
+//! A workflow groups such tasks: it is a `workflow` row whose steps point at it
+//! through `parent_id`. The workflow itself never runs; its status and
+//! progress summarise those of its steps.
+
+use super::{Priority, PriorityTask, Task, TaskPayload, TaskQueue, TaskStatus};
+use crate::core::error::{Result, TingError};
+use crate::db::models::TaskRecord;
+use crate::db::repository::Repository;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// Task type of the rows that group the steps of a workflow
+pub const WORKFLOW_TASK_TYPE: &str = "workflow";
+
+/// Where a waiting task stands with respect to its prerequisites
+enum DependencyState {
+    Ready,
+    Pending,
+    /// A prerequisite failed, was cancelled or deleted
+    Broken(String),
+}
+
+impl TaskQueue {
+    /// Submit the steps of a workflow, grouped under one workflow task.
+    ///
+    /// A step may depend on steps listed before it and on existing tasks.
+    /// Returns the id of the workflow task.
+    pub async fn submit_workflow(&self, name: &str, steps: Vec<Task>) -> Result<String> {
+        if steps.is_empty() {
+            return Err(TingError::InvalidRequest(
+                "A workflow needs at least one step".to_string(),
+            ));
+        }
+        let mut known = HashSet::new();
+        for step in &steps {
+            for dependency in &step.depends_on {
+                if !known.contains(dependency)
+                    && self.task_repo.find_by_id(dependency).await?.is_none()
+                {
+                    return Err(TingError::InvalidRequest(format!(
+                        "Step {} depends on unknown task {}",
+                        step.name, dependency
+                    )));
+                }
+            }
+            known.insert(step.id.clone());
+        }
+
+        let workflow = Task::new(
+            name.to_string(),
+            Priority::Normal,
+            TaskPayload::Custom {
+                task_type: WORKFLOW_TASK_TYPE.to_string(),
+                data: serde_json::json!({ "name": name, "steps": steps.len() }),
+            },
+        );
+        let workflow_id = workflow.id.clone();
+        self.task_repo
+            .create(&self.task_to_record(&workflow)?)
+            .await?;
+
+        for mut step in steps {
+            step.parent_id = Some(workflow_id.clone());
+            if let Err(e) = self.submit(step).await {
+                // Steps already submitted must not run on their own
+                let _ = self.cancel(&workflow_id).await;
+                return Err(e);
+            }
+        }
+
+        info!(
+            workflow_id = %workflow_id,
+            workflow_name = %name,
+            "Workflow submitted"
+        );
+        Ok(workflow_id)
+    }
+
+    /// Steps of a workflow in the order they were submitted
+    pub async fn list_workflow_steps(&self, workflow_id: &str) -> Result<Vec<TaskRecord>> {
+        self.task_repo.find_children(workflow_id).await
+    }
+
+    /// Decide whether a new task can be queued, has to wait, or can never
+    /// run. Called by `submit` while holding the waiting list.
+    pub(super) async fn check_dependencies(&self, task: &mut Task) -> Result<()> {
+        for dependency in &task.depends_on {
+            if self.task_repo.find_by_id(dependency).await?.is_none() {
+                return Err(TingError::NotFound(format!(
+                    "Dependency task not found: {}",
+                    dependency
+                )));
+            }
+        }
+        match self.dependency_state(&task.depends_on).await? {
+            DependencyState::Ready => {}
+            DependencyState::Pending => task.status = TaskStatus::Waiting,
+            DependencyState::Broken(reason) => {
+                task.status = TaskStatus::Cancelled;
+                task.error = Some(reason);
+            }
+        }
+        Ok(())
+    }
+
+    async fn dependency_state(&self, depends_on: &[String]) -> Result<DependencyState> {
+        let mut pending = false;
+        for dependency in depends_on {
+            let Some(record) = self.task_repo.find_by_id(dependency).await? else {
+                return Ok(DependencyState::Broken(format!(
+                    "Dependency {} was deleted",
+                    dependency
+                )));
+            };
+            match record.status.as_str() {
+                "completed" => {}
+                "failed" => {
+                    return Ok(DependencyState::Broken(format!(
+                        "Dependency {} failed",
+                        dependency
+                    )))
+                }
+                "cancelled" => {
+                    return Ok(DependencyState::Broken(format!(
+                        "Dependency {} was cancelled",
+                        dependency
+                    )))
+                }
+                _ => pending = true,
+            }
+        }
+        Ok(if pending {
+            DependencyState::Pending
+        } else {
+            DependencyState::Ready
+        })
+    }
+
+    /// Queue the waiting tasks whose prerequisites all completed and cancel
+    /// the ones that can no longer run, until nothing changes
+    pub(super) async fn settle_waiting(&self) {
+        let mut waiting = self.waiting.lock().await;
+        loop {
+            let mut changed = false;
+            let ids: Vec<String> = waiting.keys().cloned().collect();
+            for id in ids {
+                let state = match self.dependency_state(&waiting[&id].depends_on).await {
+                    Ok(DependencyState::Pending) => continue,
+                    Ok(state) => state,
+                    Err(e) => {
+                        warn!(task_id = %id, error = %e, "Failed to check task dependencies");
+                        continue;
+                    }
+                };
+                let Some(mut task) = waiting.remove(&id) else {
+                    continue;
+                };
+                changed = true;
+
+                if let DependencyState::Broken(reason) = state {
+                    info!(task_id = %id, reason = %reason, "Cancelling task with broken dependency");
+                    task.status = TaskStatus::Cancelled;
+                    task.error = Some(reason);
+                    if let Err(e) = self.update_task_status(&task).await {
+                        warn!(task_id = %id, error = %e, "Failed to update task status");
+                    }
+                } else {
+                    info!(task_id = %id, "Dependencies completed, queueing task");
+                    task.status = TaskStatus::Queued;
+                    if let Err(e) = self.update_task_status(&task).await {
+                        warn!(task_id = %id, error = %e, "Failed to update task status");
+                    }
+                    self.queue.write().await.push(PriorityTask { task });
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Cancel every unfinished step of a workflow
+    pub(super) async fn cancel_workflow(&self, workflow_id: &str) -> Result<()> {
+        let steps = self.task_repo.find_children(workflow_id).await?;
+        let mut cancelled = HashSet::new();
+        for step in steps {
+            if !matches!(step.status.as_str(), "waiting" | "queued" | "running") {
+                continue;
+            }
+            self.task_repo
+                .update_status(
+                    &step.id,
+                    TaskStatus::Cancelled.as_str(),
+                    Some("Workflow cancelled"),
+                    step.retries,
+                )
+                .await?;
+            cancelled.insert(step.id);
+        }
+        self.forget_tasks(&cancelled).await;
+        self.refresh_workflow(workflow_id).await?;
+        // Tasks outside the workflow may depend on its steps
+        self.settle_waiting().await;
+
+        info!(workflow_id = %workflow_id, steps = cancelled.len(), "Workflow cancelled");
+        Ok(())
+    }
+
+    /// Drop tasks from the in-memory queue and waiting list
+    pub(super) async fn forget_tasks(&self, task_ids: &HashSet<String>) {
+        if task_ids.is_empty() {
+            return;
+        }
+        self.waiting
+            .lock()
+            .await
+            .retain(|id, _| !task_ids.contains(id));
+        let mut queue = self.queue.write().await;
+        let tasks: Vec<_> = queue.drain().collect();
+        *queue = tasks
+            .into_iter()
+            .filter(|pt| !task_ids.contains(&pt.task.id))
+            .collect();
+    }
+
+    /// Drop in-memory tasks whose rows were deleted
+    pub(super) async fn forget_deleted_tasks(&self) -> Result<()> {
+        let mut ids: Vec<String> = self.waiting.lock().await.keys().cloned().collect();
+        ids.extend(self.queue.read().await.iter().map(|pt| pt.task.id.clone()));
+        let mut deleted = HashSet::new();
+        for id in ids {
+            if self.task_repo.find_by_id(&id).await?.is_none() {
+                deleted.insert(id);
+            }
+        }
+        self.forget_tasks(&deleted).await;
+        Ok(())
+    }
+
+    /// Recompute a workflow's status and progress from its steps
+    pub(super) async fn refresh_workflow(&self, workflow_id: &str) -> Result<()> {
+        let steps = self.task_repo.find_children(workflow_id).await?;
+        let status = workflow_status(steps.iter().map(|step| step.status.as_str()));
+        let error = steps
+            .iter()
+            .find(|step| step.status == "failed")
+            .map(|step| {
+                format!(
+                    "Step {} failed: {}",
+                    step.id,
+                    step.error.as_deref().unwrap_or("unknown error")
+                )
+            });
+        self.task_repo
+            .update_status(workflow_id, status.as_str(), error.as_deref(), 0)
+            .await?;
+        self.task_repo
+            .update_progress_key(
+                workflow_id,
+                "workflow.progress",
+                serde_json::json!({
+                    "completed": steps.iter().filter(|step| step.status == "completed").count(),
+                    "total": steps.len(),
+                }),
+            )
+            .await
+    }
+}
+
+/// Status of a workflow given the statuses of its steps: running while any
+/// step is active or still to come after others finished, then failed,
+/// cancelled or completed
+fn workflow_status<'a>(steps: impl Iterator<Item = &'a str>) -> TaskStatus {
+    let statuses: Vec<&str> = steps.collect();
+    let any = |status: &str| statuses.contains(&status);
+    let pending = any("queued") || any("waiting");
+    if any("running") || (pending && statuses.iter().any(|s| !matches!(*s, "queued" | "waiting"))) {
+        TaskStatus::Running
+    } else if pending || statuses.is_empty() {
+        TaskStatus::Queued
+    } else if any("failed") {
+        TaskStatus::Failed
+    } else if any("cancelled") {
+        TaskStatus::Cancelled
+    } else {
+        TaskStatus::Completed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::TaskQueueConfig;
+    use crate::db::manager::DatabaseManager;
+    use std::sync::Arc;
+
+    fn step(name: &str) -> Task {
+        Task::new(
+            name.to_string(),
+            Priority::Normal,
+            TaskPayload::Custom {
+                task_type: "test".to_string(),
+                data: serde_json::json!({}),
+            },
+        )
+    }
+
+    fn task_queue() -> TaskQueue {
+        let db = Arc::new(DatabaseManager::new_in_memory().unwrap());
+        let config = TaskQueueConfig {
+            max_concurrent_tasks: 1,
+            default_retry_count: 3,
+            task_timeout: 600,
+            pools: Default::default(),
+        };
+        TaskQueue::new(config, db, std::env::temp_dir())
+    }
+
+    async fn status(queue: &TaskQueue, task_id: &str) -> String {
+        queue.get_task(task_id).await.unwrap().status
+    }
+
+    #[test]
+    fn workflow_status_summarises_steps() {
+        let status = |steps: &[&str]| workflow_status(steps.iter().copied());
+        assert_eq!(status(&["waiting", "queued"]), TaskStatus::Queued);
+        assert_eq!(status(&["completed", "waiting"]), TaskStatus::Running);
+        assert_eq!(status(&["failed", "running"]), TaskStatus::Running);
+        assert_eq!(status(&["failed", "cancelled"]), TaskStatus::Failed);
+        assert_eq!(status(&["completed", "cancelled"]), TaskStatus::Cancelled);
+        assert_eq!(status(&["completed", "completed"]), TaskStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn steps_wait_for_their_dependencies_and_failures_propagate() {
+        let queue = task_queue();
+
+        let scan = step("scan");
+        let scrape = step("scrape").with_dependencies(vec![scan.id.clone()]);
+        let write = step("write").with_dependencies(vec![scrape.id.clone()]);
+        let notify = step("notify").with_dependencies(vec![scan.id.clone()]);
+        let ids: Vec<String> = [&scan, &scrape, &write, &notify]
+            .iter()
+            .map(|task| task.id.clone())
+            .collect();
+        let workflow_id = queue
+            .submit_workflow("nightly", vec![scan, scrape, write, notify])
+            .await
+            .unwrap();
+
+        assert_eq!(status(&queue, &ids[0]).await, "queued");
+        assert_eq!(status(&queue, &ids[1]).await, "waiting");
+        assert_eq!(status(&queue, &workflow_id).await, "queued");
+        assert_eq!(queue.queue.read().await.len(), 1);
+
+        // The scan completes: both direct dependents are queued
+        queue
+            .task_repo
+            .update_status(&ids[0], "completed", None, 0)
+            .await
+            .unwrap();
+        queue.settle_waiting().await;
+        assert_eq!(status(&queue, &ids[1]).await, "queued");
+        assert_eq!(status(&queue, &ids[3]).await, "queued");
+        assert_eq!(status(&queue, &ids[2]).await, "waiting");
+
+        // The scrape fails: the write is cancelled, the notification is not
+        let mut scrape = queue
+            .record_to_task(&queue.get_task(&ids[1]).await.unwrap())
+            .unwrap();
+        scrape.status = TaskStatus::Failed;
+        queue.update_task_status(&scrape).await.unwrap();
+        queue.settle_waiting().await;
+        assert_eq!(status(&queue, &ids[2]).await, "cancelled");
+        assert_eq!(status(&queue, &ids[3]).await, "queued");
+        assert_eq!(status(&queue, &workflow_id).await, "running");
+
+        let steps = queue.list_workflow_steps(&workflow_id).await.unwrap();
+        assert_eq!(steps.iter().map(|s| s.id.clone()).collect::<Vec<_>>(), ids);
+
+        queue.cancel(&workflow_id).await.unwrap();
+        assert_eq!(status(&queue, &ids[3]).await, "cancelled");
+        assert_eq!(status(&queue, &workflow_id).await, "failed");
+        assert!(!queue
+            .queue
+            .read()
+            .await
+            .iter()
+            .any(|pt| pt.task.id == ids[3]));
+    }
+
+    #[tokio::test]
+    async fn workflow_steps_may_only_depend_on_earlier_steps() {
+        let queue = task_queue();
+
+        let later = step("later");
+        let first = step("first").with_dependencies(vec![later.id.clone()]);
+        assert!(queue
+            .submit_workflow("cycle", vec![first, later])
+            .await
+            .is_err());
+        assert!(queue.list_tasks().await.unwrap().is_empty());
+    }
+}
@@ -469,6 +469,299 @@ BEGIN
 END;
 "#;
 
+/// Twenty-sixth schema migration (version 26)
+const MIGRATION_V26: &str = r#"
+-- Virtual chapters: a chapter may cover only a time range of its file (seconds).
+ALTER TABLE chapters ADD COLUMN start_offset REAL;
+ALTER TABLE chapters ADD COLUMN end_offset REAL;
+"#;
+
+/// Twenty-seventh schema migration (version 27)
+const MIGRATION_V27: &str = r#"
+-- Single-file books scanned before chapter markers were read. The next scan
+-- probes each of these chapters once, even when the file has not changed.
+CREATE TABLE IF NOT EXISTS chapter_marker_probes (
+    chapter_id TEXT PRIMARY KEY,
+    FOREIGN KEY (chapter_id) REFERENCES chapters(id) ON DELETE CASCADE
+);
+
+INSERT OR IGNORE INTO chapter_marker_probes (chapter_id)
+SELECT c.id FROM chapters c
+WHERE c.start_offset IS NULL
+  AND (lower(c.path) LIKE '%.m4b' OR lower(c.path) LIKE '%.m4a'
+       OR lower(c.path) LIKE '%.mp4' OR lower(c.path) LIKE '%.mp3')
+  AND (SELECT COUNT(*) FROM chapters o WHERE o.book_id = c.book_id) = 1;
+"#;
+
+/// Twenty-eighth schema migration (version 28)
+const MIGRATION_V28: &str = r#"
+-- Chapters joined from several files. The chapter keeps the first file as its
+-- own path; every file it plays is listed here in order, with the time range
+-- used from it in seconds (NULL for the whole file).
+CREATE TABLE IF NOT EXISTS chapter_segments (
+    chapter_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    start_offset REAL,
+    end_offset REAL,
+    PRIMARY KEY (chapter_id, position),
+    FOREIGN KEY (chapter_id) REFERENCES chapters(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_chapter_segments_path ON chapter_segments(path);
+"#;
+
+/// Twenty-ninth schema migration (version 29)
+const MIGRATION_V29: &str = r#"
+-- Full-text search over books, their series, chapter titles and pinyin keys.
+-- Each book owns one document; doc_id is a stable INTEGER PRIMARY KEY because
+-- the implicit rowid of `books` may be renumbered by VACUUM.
+CREATE TABLE IF NOT EXISTS book_search_docs (
+    doc_id INTEGER PRIMARY KEY,
+    book_id TEXT NOT NULL UNIQUE
+);
+
+-- Substring matching for terms of three or more characters.
+CREATE VIRTUAL TABLE IF NOT EXISTS book_search USING fts5(
+    title, author, narrator, series, tags, chapters, description, pinyin,
+    tokenize = 'trigram'
+);
+
+-- Word tokens plus CJK unigrams/bigrams, so one- and two-character terms
+-- (most Chinese queries) are still answered from an index.
+CREATE VIRTUAL TABLE IF NOT EXISTS book_search_terms USING fts5(
+    terms,
+    tokenize = 'unicode61'
+);
+
+-- Books whose documents are stale. Triggers only queue the book id; the
+-- documents are rebuilt in batches before searching, so importing a book
+-- with thousands of chapters re-indexes it once instead of per chapter.
+CREATE TABLE IF NOT EXISTS book_search_dirty (
+    book_id TEXT PRIMARY KEY
+);
+
+CREATE TRIGGER IF NOT EXISTS book_search_books_insert AFTER INSERT ON books
+BEGIN
+    INSERT OR IGNORE INTO book_search_dirty (book_id) VALUES (NEW.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS book_search_books_update
+AFTER UPDATE OF title, author, narrator, tags, genre, description ON books
+BEGIN
+    INSERT OR IGNORE INTO book_search_dirty (book_id) VALUES (NEW.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS book_search_books_delete AFTER DELETE ON books
+BEGIN
+    DELETE FROM book_search
+        WHERE rowid IN (SELECT doc_id FROM book_search_docs WHERE book_id = OLD.id);
+    DELETE FROM book_search_terms
+        WHERE rowid IN (SELECT doc_id FROM book_search_docs WHERE book_id = OLD.id);
+    DELETE FROM book_search_docs WHERE book_id = OLD.id;
+    DELETE FROM book_search_dirty WHERE book_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS book_search_chapters_insert AFTER INSERT ON chapters
+BEGIN
+    INSERT OR IGNORE INTO book_search_dirty (book_id) VALUES (NEW.book_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS book_search_chapters_update
+AFTER UPDATE OF title, book_id ON chapters
+BEGIN
+    INSERT OR IGNORE INTO book_search_dirty (book_id) VALUES (NEW.book_id);
+    INSERT OR IGNORE INTO book_search_dirty (book_id) VALUES (OLD.book_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS book_search_chapters_delete AFTER DELETE ON chapters
+BEGIN
+    INSERT OR IGNORE INTO book_search_dirty (book_id) VALUES (OLD.book_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS book_search_series_books_insert AFTER INSERT ON series_books
+BEGIN
+    INSERT OR IGNORE INTO book_search_dirty (book_id) VALUES (NEW.book_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS book_search_series_books_delete AFTER DELETE ON series_books
+BEGIN
+    INSERT OR IGNORE INTO book_search_dirty (book_id) VALUES (OLD.book_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS book_search_series_update AFTER UPDATE OF title ON series
+BEGIN
+    INSERT OR IGNORE INTO book_search_dirty (book_id)
+        SELECT book_id FROM series_books WHERE series_id = NEW.id;
+END;
+
+INSERT OR IGNORE INTO book_search_dirty (book_id) SELECT id FROM books;
+"#;
+
+/// Thirtieth schema migration (version 30)
+const MIGRATION_V30: &str = r#"
+-- Bookmarks and clips saved at a chapter position. Positions are seconds
+-- inside the chapter; a clip also has an end position.
+CREATE TABLE IF NOT EXISTS bookmarks (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    book_id TEXT NOT NULL,
+    chapter_id TEXT NOT NULL,
+    position REAL NOT NULL,
+    end_position REAL,
+    note TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE,
+    FOREIGN KEY (chapter_id) REFERENCES chapters(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_bookmarks_user_book ON bookmarks(user_id, book_id);
+CREATE INDEX IF NOT EXISTS idx_bookmarks_chapter ON bookmarks(chapter_id);
+"#;
+
+/// Thirty-first schema migration (version 31)
+const MIGRATION_V31: &str = r#"
+-- Offline progress sync. Progress rows remember the device that wrote them
+-- (NULL for live updates) and each device's upload high-water mark lets a
+-- retried batch be recognised.
+ALTER TABLE progress ADD COLUMN device_id TEXT;
+
+CREATE TABLE IF NOT EXISTS progress_sync_devices (
+    user_id TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    last_event_at TEXT NOT NULL,
+    PRIMARY KEY (user_id, device_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+"#;
+
+/// Thirty-second schema migration (version 32)
+const MIGRATION_V32: &str = r#"
+-- Per-user listening statistics read one user's daily rows by date.
+CREATE INDEX IF NOT EXISTS idx_listening_events_user_activity_date
+ON listening_events(user_id, activity_date);
+"#;
+
+/// Thirty-third schema migration (version 33)
+const MIGRATION_V33: &str = r#"
+-- Smart playlists resolve their books from a JSON rule set when read instead
+-- of storing items.
+ALTER TABLE playlists ADD COLUMN playlist_type TEXT NOT NULL DEFAULT 'manual'
+    CHECK(playlist_type IN ('manual', 'smart'));
+ALTER TABLE playlists ADD COLUMN smart_rules TEXT;
+"#;
+
+/// Thirty-fourth schema migration (version 34)
+const MIGRATION_V34: &str = r#"
+-- Separate password for Subsonic clients. Their token auth needs the plain
+-- password, so it is kept encrypted instead of hashed.
+ALTER TABLE users ADD COLUMN subsonic_password TEXT;
+"#;
+
+/// Thirty-fifth schema migration (version 35)
+const MIGRATION_V35: &str = r#"
+-- Part of every podcast feed signature. Bumping it revokes all feed links the
+-- user has handed out.
+ALTER TABLE users ADD COLUMN feed_token_version INTEGER NOT NULL DEFAULT 0;
+"#;
+
+/// Thirty-sixth schema migration (version 36)
+const MIGRATION_V36: &str = r#"
+-- HTTP validators of the last RSS feed response, sent back on the next fetch
+-- so an unchanged feed can be skipped.
+CREATE TABLE IF NOT EXISTS rss_feed_validators (
+    library_id TEXT PRIMARY KEY,
+    etag TEXT,
+    last_modified TEXT,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (library_id) REFERENCES libraries(id) ON DELETE CASCADE
+);
+"#;
+
+/// Thirty-seventh schema migration (version 37)
+const MIGRATION_V37: &str = r#"
+-- Episodes of RSS libraries downloaded into local storage.
+CREATE TABLE IF NOT EXISTS episode_downloads (
+    chapter_id TEXT PRIMARY KEY,
+    library_id TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    file_size INTEGER NOT NULL,
+    source_url TEXT NOT NULL,
+    downloaded_at TEXT NOT NULL,
+    FOREIGN KEY (chapter_id) REFERENCES chapters(id) ON DELETE CASCADE,
+    FOREIGN KEY (library_id) REFERENCES libraries(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_episode_downloads_library ON episode_downloads(library_id);
+"#;
+
+/// Thirty-eighth schema migration (version 38)
+const MIGRATION_V38: &str = r#"
+-- Recurring tasks submitted to the task queue on cron expressions.
+CREATE TABLE IF NOT EXISTS task_schedules (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    cron TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    last_run_at TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- Run history; task_id points at the tasks row the run submitted.
+CREATE TABLE IF NOT EXISTS task_schedule_runs (
+    id TEXT PRIMARY KEY,
+    schedule_id TEXT NOT NULL,
+    triggered_by TEXT NOT NULL,
+    triggered_at TEXT NOT NULL,
+    task_id TEXT,
+    error TEXT,
+    FOREIGN KEY (schedule_id) REFERENCES task_schedules(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_schedule_runs_schedule ON task_schedule_runs(schedule_id, triggered_at);
+"#;
+
+/// Thirty-ninth schema migration (version 39)
+const MIGRATION_V39: &str = r#"
+-- Task dependencies. parent_id groups the steps of a workflow under the
+-- workflow's own row; depends_on is a JSON array of prerequisite task ids.
+ALTER TABLE tasks ADD COLUMN parent_id TEXT REFERENCES tasks(id) ON DELETE CASCADE;
+ALTER TABLE tasks ADD COLUMN depends_on TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_tasks_parent_id ON tasks(parent_id);
+"#;
+
+/// Fortieth schema migration (version 40)
+const MIGRATION_V40: &str = r#"
+-- EBU R128 loudness analysis of each chapter. Both stay NULL until the
+-- chapter has been analysed and are reset when its audio changes.
+ALTER TABLE chapters ADD COLUMN loudness_lufs REAL;
+ALTER TABLE chapters ADD COLUMN true_peak_dbtp REAL;
+"#;
+
+/// Forty-first schema migration (version 41)
+const MIGRATION_V41: &str = r#"
+-- Ranges of a chapter the player skips, a JSON array of {"kind", "start",
+-- "end"} in seconds from the chapter start. Reset when its audio changes.
+ALTER TABLE chapters ADD COLUMN skip_ranges TEXT;
+
+-- Skip intro/outro values proposed by the last silence and jingle analysis
+-- of a book, kept until an admin applies or discards them. chapters is a
+-- JSON array of {"chapter_id", "ranges"}.
+CREATE TABLE IF NOT EXISTS skip_proposals (
+    book_id TEXT PRIMARY KEY,
+    skip_intro INTEGER,
+    skip_outro INTEGER,
+    chapters TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE
+);
+"#;
+
 /// Seventeenth schema migration (version 17)
 const MIGRATION_V17: &str = r#"
 -- Admin-configured webhook notification listeners.
@@ -727,6 +1020,88 @@ pub fn run_migrations(conn: &mut Connection) -> Result<()> {
         apply_migration(conn, 25, MIGRATION_V25)?;
     }
 
+    if current_version < 26 {
+        info!("Applying migration v26: Virtual chapter offsets");
+        apply_migration(conn, 26, MIGRATION_V26)?;
+    }
+
+    if current_version < 27 {
+        info!("Applying migration v27: Chapter marker probes for existing books");
+        apply_migration(conn, 27, MIGRATION_V27)?;
+    }
+
+    if current_version < 28 {
+        info!("Applying migration v28: Chapters joined from several files");
+        apply_migration(conn, 28, MIGRATION_V28)?;
+    }
+
+    if current_version < 29 {
+        info!("Applying migration v29: Full-text search index");
+        apply_migration(conn, 29, MIGRATION_V29)?;
+        let indexed = crate::db::search::refresh_index(conn)?;
+        info!("Indexed {} books for full-text search", indexed);
+    }
+
+    if current_version < 30 {
+        info!("Applying migration v30: Bookmarks and clips");
+        apply_migration(conn, 30, MIGRATION_V30)?;
+    }
+
+    if current_version < 31 {
+        info!("Applying migration v31: Offline progress sync");
+        apply_migration(conn, 31, MIGRATION_V31)?;
+    }
+
+    if current_version < 32 {
+        info!("Applying migration v32: Listening statistics index");
+        apply_migration(conn, 32, MIGRATION_V32)?;
+    }
+
+    if current_version < 33 {
+        info!("Applying migration v33: Smart playlists");
+        apply_migration(conn, 33, MIGRATION_V33)?;
+    }
+
+    if current_version < 34 {
+        info!("Applying migration v34: Subsonic passwords");
+        apply_migration(conn, 34, MIGRATION_V34)?;
+    }
+
+    if current_version < 35 {
+        info!("Applying migration v35: Podcast feed token versions");
+        apply_migration(conn, 35, MIGRATION_V35)?;
+    }
+
+    if current_version < 36 {
+        info!("Applying migration v36: RSS feed validators");
+        apply_migration(conn, 36, MIGRATION_V36)?;
+    }
+
+    if current_version < 37 {
+        info!("Applying migration v37: RSS episode downloads");
+        apply_migration(conn, 37, MIGRATION_V37)?;
+    }
+
+    if current_version < 38 {
+        info!("Applying migration v38: Task schedules");
+        apply_migration(conn, 38, MIGRATION_V38)?;
+    }
+
+    if current_version < 39 {
+        info!("Applying migration v39: Task dependencies");
+        apply_migration(conn, 39, MIGRATION_V39)?;
+    }
+
+    if current_version < 40 {
+        info!("Applying migration v40: Chapter loudness");
+        apply_migration(conn, 40, MIGRATION_V40)?;
+    }
+
+    if current_version < 41 {
+        info!("Applying migration v41: Chapter skip ranges");
+        apply_migration(conn, 41, MIGRATION_V41)?;
+    }
+
     info!("Database migrations completed successfully");
     Ok(())
 }
@@ -967,4 +1342,32 @@ CREATE TABLE listening_events (
         assert_eq!(updates, 10_000);
         assert_eq!(seconds, 20_000.0);
     }
+
+    #[test]
+    fn migration_v27_queues_only_single_file_books_with_marker_formats() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATION_TABLE).unwrap();
+        conn.execute_batch(
+            r#"
+CREATE TABLE chapters (id TEXT PRIMARY KEY, book_id TEXT, path TEXT, start_offset REAL);
+INSERT INTO chapters VALUES ('m4b', 'book-1', '/a/Book.M4B', NULL);
+INSERT INTO chapters VALUES ('flac', 'book-2', '/b/book.flac', NULL);
+INSERT INTO chapters VALUES ('part-1', 'book-3', '/c/01.mp3', NULL);
+INSERT INTO chapters VALUES ('part-2', 'book-3', '/c/02.mp3', NULL);
+INSERT INTO chapters VALUES ('slice', 'book-4', '/d/book.m4b', 0.0);
+"#,
+        )
+        .unwrap();
+
+        apply_migration(&mut conn, 27, MIGRATION_V27).unwrap();
+
+        let queued: Vec<String> = conn
+            .prepare("SELECT chapter_id FROM chapter_marker_probes")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(queued, vec!["m4b".to_string()]);
+    }
 }
@@ -5,11 +5,13 @@
 //! - Repository pattern implementations
 //! - Database migrations
 //! - Data models and schemas
+//! - Full-text search indexing
 
 pub mod manager;
 pub mod migrations;
 pub mod models;
 pub mod repository;
+pub mod search;
 
 pub use manager::DatabaseManager;
 pub use models::{Book, Chapter, Series, SeriesBook, TaskRecord, User};
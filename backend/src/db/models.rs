@@ -64,6 +64,102 @@ pub struct Chapter {
     #[serde(default)]
     pub manual_corrected: i32, // 0 or 1
     pub created_at: String,
+    // V26: virtual chapters are time slices of `path`, in seconds
+    #[serde(default)]
+    pub start_offset: Option<f64>,
+    #[serde(default)]
+    pub end_offset: Option<f64>,
+    // V40: EBU R128 analysis, NULL until the chapter has been measured
+    #[serde(default)]
+    pub loudness_lufs: Option<f64>,
+    #[serde(default)]
+    pub true_peak_dbtp: Option<f64>,
+    // V41: JSON array of `SkipRange`, NULL when nothing is skipped
+    #[serde(default)]
+    pub skip_ranges: Option<String>,
+}
+
+impl Chapter {
+    /// True when the chapter is a slice of a larger file rather than the whole file
+    pub fn is_virtual(&self) -> bool {
+        self.start_offset.is_some()
+    }
+
+    /// Length of the slice in seconds, falling back to the stored duration
+    pub fn slice_length(&self) -> Option<f64> {
+        match (self.start_offset, self.end_offset) {
+            (Some(start), Some(end)) => Some((end - start).max(0.0)),
+            _ => self.duration.map(|duration| duration as f64),
+        }
+    }
+
+    /// Map a position inside the chapter to `(file position, remaining length)`.
+    ///
+    /// Whole-file chapters have no upper bound, so the length is `None`.
+    pub fn file_window(&self, position: f64) -> (f64, Option<f64>) {
+        let position = position.max(0.0);
+        let Some(start) = self.start_offset else {
+            return (position, None);
+        };
+        match self.slice_length() {
+            Some(length) => {
+                let position = position.min(length);
+                (start + position, Some(length - position))
+            }
+            None => (start + position, None),
+        }
+    }
+
+    /// Ranges the player skips; empty when none are set
+    pub fn parsed_skip_ranges(&self) -> Vec<SkipRange> {
+        self.skip_ranges
+            .as_deref()
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// One file range played by a chapter joined from several files (V28)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChapterSegment {
+    pub path: String,
+    pub start_offset: Option<f64>,
+    pub end_offset: Option<f64>,
+}
+
+/// What a skip range of a chapter covers (V41)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkipKind {
+    Intro,
+    Outro,
+}
+
+/// Part of a chapter the player skips, in seconds from the chapter start (V41)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SkipRange {
+    pub kind: SkipKind,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Skip ranges proposed for one chapter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChapterSkipProposal {
+    pub chapter_id: String,
+    pub ranges: Vec<SkipRange>,
+}
+
+/// Skip values proposed by the silence and jingle analysis of a book (V41)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkipProposal {
+    pub book_id: String,
+    /// Seconds to skip at the start of every chapter, when the intros agree
+    pub skip_intro: Option<i32>,
+    /// Seconds to skip at the end of every chapter, when the outros agree
+    pub skip_outro: Option<i32>,
+    pub chapters: Vec<ChapterSkipProposal>,
+    pub created_at: String,
 }
 
 /// Task record in the database
@@ -81,6 +177,42 @@ pub struct TaskRecord {
     pub max_retries: i32,
     pub created_at: String,
     pub updated_at: String,
+    /// Workflow the task is a step of
+    pub parent_id: Option<String>,
+    /// JSON array of task ids that must complete before this one runs
+    pub depends_on: Option<String>,
+}
+
+/// Recurring task submitted on a cron schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskSchedule {
+    pub id: String,
+    pub name: String,
+    /// Five-field cron expression in the application time zone
+    pub cron: String,
+    /// Serialized `TaskPayload` submitted on every run
+    pub payload: String,
+    pub enabled: bool,
+    pub last_run_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// One run of a task schedule, with the state of the task it submitted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskScheduleRun {
+    pub id: String,
+    pub schedule_id: String,
+    /// `schedule` or `manual`
+    pub triggered_by: String,
+    pub triggered_at: String,
+    /// Task submitted by the run; `None` when it was skipped or failed to submit
+    pub task_id: Option<String>,
+    /// Why no task was submitted
+    pub error: Option<String>,
+    /// Status of the submitted task, `None` once the task was deleted
+    pub task_status: Option<String>,
+    pub task_error: Option<String>,
 }
 
 /// User record in the database
@@ -105,6 +237,17 @@ pub struct Progress {
     pub updated_at: String,
 }
 
+/// Progress a client recorded while offline, replayed by the sync endpoint
+#[derive(Debug, Clone)]
+pub struct ProgressEvent {
+    pub book_id: String,
+    pub chapter_id: Option<String>,
+    pub position: f64,
+    pub duration: Option<f64>,
+    /// When the client played this position, never later than server time
+    pub recorded_at: chrono::DateTime<chrono::Utc>,
+}
+
 /// Favorite record in the database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Favorite {
@@ -114,6 +257,22 @@ pub struct Favorite {
     pub created_at: String,
 }
 
+/// Bookmark or clip at a chapter position (V30)
+///
+/// Positions are seconds inside the chapter; `end_position` makes it a clip.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub id: String,
+    pub user_id: String,
+    pub book_id: String,
+    pub chapter_id: String,
+    pub position: f64,
+    pub end_position: Option<f64>,
+    pub note: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
 /// User settings record in the database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserSettings {
@@ -142,6 +301,25 @@ pub struct Library {
     pub scraper_config: Option<String>,
 }
 
+/// `ETag` / `Last-Modified` of the last RSS feed response for a library
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RssFeedValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Local copy of an RSS episode kept by a library's download mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpisodeDownload {
+    pub chapter_id: String,
+    pub library_id: String,
+    pub file_path: String,
+    pub file_size: i64,
+    /// Enclosure URL the file was fetched from
+    pub source_url: String,
+    pub downloaded_at: String,
+}
+
 /// Scraper configuration stored in Library
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScraperConfig {
@@ -183,6 +361,25 @@ pub struct ScraperConfig {
     /// Cloud drive mode: when enabled, adjust scanning behavior for WebDAV/local libraries
     #[serde(default)]
     pub cloud_mode: bool,
+    /// RSS libraries: re-read the feed every this many minutes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rss_refresh_interval_minutes: Option<u32>,
+    /// RSS libraries: five-field cron expression in the application time zone,
+    /// used instead of the interval when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rss_refresh_cron: Option<String>,
+    /// RSS libraries: fetch episodes into local storage and play them from there
+    #[serde(default)]
+    pub rss_download_episodes: bool,
+    /// RSS libraries: only keep downloads of this many newest episodes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rss_keep_latest_episodes: Option<u32>,
+    /// RSS libraries: delete a download once every user who can see it finished it
+    #[serde(default)]
+    pub rss_delete_played_episodes: bool,
+    /// RSS libraries: total size of the downloads, older episodes go first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rss_max_download_mb: Option<u64>,
 }
 
 impl Default for ScraperConfig {
@@ -202,6 +399,12 @@ impl Default for ScraperConfig {
             extract_extra_chapters: default_extract_extra_chapters(),
             disable_watcher: false,
             cloud_mode: false,
+            rss_refresh_interval_minutes: None,
+            rss_refresh_cron: None,
+            rss_download_episodes: false,
+            rss_keep_latest_episodes: None,
+            rss_delete_played_episodes: false,
+            rss_max_download_mb: None,
         }
     }
 }
@@ -226,6 +429,41 @@ fn default_metadata_priority() -> Vec<String> {
     ]
 }
 
+#[cfg(test)]
+mod chapter_tests {
+    use super::Chapter;
+
+    fn chapter(start: Option<f64>, end: Option<f64>) -> Chapter {
+        Chapter {
+            id: "chapter".to_string(),
+            book_id: "book".to_string(),
+            title: None,
+            path: "/book.m4b".to_string(),
+            duration: Some(600),
+            chapter_index: Some(1),
+            is_extra: 0,
+            hash: None,
+            manual_corrected: 0,
+            created_at: String::new(),
+            start_offset: start,
+            end_offset: end,
+            loudness_lufs: None,
+            true_peak_dbtp: None,
+            skip_ranges: None,
+        }
+    }
+
+    #[test]
+    fn maps_positions_into_virtual_chapter_window() {
+        let slice = chapter(Some(120.0), Some(300.0));
+        assert_eq!(slice.file_window(30.0), (150.0, Some(150.0)));
+        assert_eq!(slice.file_window(500.0), (300.0, Some(0.0)));
+
+        let whole = chapter(None, None);
+        assert_eq!(whole.file_window(30.0), (30.0, None));
+    }
+}
+
 #[cfg(test)]
 mod scraper_config_tests {
     use super::ScraperConfig;
@@ -264,16 +502,86 @@ pub struct SeriesBook {
 }
 
 /// User playlist record
+///
+/// `playlist_type` is `manual` or `smart`. Smart playlists keep a JSON
+/// [`SmartPlaylistRules`] in `smart_rules` and have no stored items.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Playlist {
     pub id: String,
     pub user_id: String,
     pub title: String,
     pub description: Option<String>,
+    pub playlist_type: String,
+    pub smart_rules: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+impl Playlist {
+    pub fn is_smart(&self) -> bool {
+        self.playlist_type == "smart"
+    }
+}
+
+/// Saved query of a smart playlist.
+///
+/// Values listed for one field match if any of them does; every field that is
+/// set must match.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SmartPlaylistRules {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub library_ids: Vec<String>,
+    /// Substrings of the author
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    /// Substrings of the narrator
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub narrators: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub genres: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub year_from: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub year_to: Option<i32>,
+    /// Leave out books the user has finished
+    #[serde(default)]
+    pub unfinished: bool,
+    /// Only books added within this many days
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_within_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listening_status: Option<ListeningStatus>,
+    #[serde(default)]
+    pub sort: SmartPlaylistSort,
+    /// Defaults to newest first for `added` and `last_played`, otherwise ascending
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descending: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// Where a user is with a book, judged from their progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListeningStatus {
+    NotStarted,
+    InProgress,
+    Finished,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartPlaylistSort {
+    #[default]
+    Added,
+    Title,
+    Author,
+    Year,
+    LastPlayed,
+}
+
 /// Playlist book link record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlaylistBook {
@@ -2,6 +2,7 @@ use crate::core::error::{Result, TingError};
 use crate::db::manager::DatabaseManager;
 use crate::db::models::Book;
 use crate::db::repository::base::Repository;
+use crate::db::search::{refresh_index, search_filter};
 use async_trait::async_trait;
 use rusqlite::{OptionalExtension, Row};
 use std::sync::Arc;
@@ -30,6 +31,15 @@ pub(crate) fn map_book_row(row: &Row<'_>) -> rusqlite::Result<Book> {
     })
 }
 
+/// One ranked result of [`BookRepository::search`]
+#[derive(Debug, Clone)]
+pub struct BookSearchHit {
+    pub book: Book,
+    pub library_type: Option<String>,
+    /// Relevance score; higher is better
+    pub score: f64,
+}
+
 /// Repository for Book entities
 pub struct BookRepository {
     db: Arc<DatabaseManager>,
@@ -187,15 +197,15 @@ impl BookRepository {
             // We store params as String to make them easy to handle and Send
             let mut params: Vec<String> = Vec::new();
             let mut conditions: Vec<String> = Vec::new();
+            let mut order = "b.created_at DESC".to_string();
 
             // Filters
-            if let Some(s) = search {
-                conditions.push("(b.title LIKE ? OR b.author LIKE ? OR b.description LIKE ? OR b.narrator LIKE ?)".to_string());
-                let pattern = format!("%{}%", s);
-                params.push(pattern.clone());
-                params.push(pattern.clone());
-                params.push(pattern.clone());
-                params.push(pattern.clone());
+            if let Some(filter) = search.as_deref().and_then(search_filter) {
+                refresh_index(conn)?;
+                query += &filter.joins;
+                conditions.push(filter.condition);
+                params.extend(filter.params);
+                order = format!("{} DESC, {}", filter.score, order);
             }
 
             if let Some(t) = tag {
@@ -224,7 +234,8 @@ impl BookRepository {
                 query += &conditions.join(" AND ");
             }
 
-            query += " ORDER BY b.created_at DESC";
+            query += " ORDER BY ";
+            query += &order;
 
             let mut stmt = conn.prepare(&query).map_err(TingError::DatabaseError)?;
 
@@ -237,6 +248,79 @@ impl BookRepository {
         }).await
     }
 
+    /// Ranked full-text search over titles, people, series, tags and chapter
+    /// titles, including pinyin keys. Returns one page of hits (best first)
+    /// and the total number of matches.
+    pub async fn search(
+        &self,
+        user_id: &str,
+        is_admin: bool,
+        query: &str,
+        library_id: Option<String>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<(Vec<BookSearchHit>, u32)> {
+        let Some(filter) = search_filter(query) else {
+            return Ok((Vec::new(), 0));
+        };
+        let user_id = user_id.to_string();
+        self.db.execute(move |conn| {
+            refresh_index(conn)?;
+
+            let mut from = format!(
+                "FROM books b{} LEFT JOIN libraries l ON l.id = b.library_id WHERE {}",
+                filter.joins, filter.condition
+            );
+            let mut params = filter.params;
+
+            if let Some(lid) = library_id {
+                from += " AND b.library_id = ?";
+                params.push(lid);
+            }
+
+            if !is_admin {
+                from += " AND (
+                    b.library_id IN (SELECT library_id FROM user_library_access WHERE user_id = ?)
+                    OR
+                    b.id IN (SELECT book_id FROM user_book_access WHERE user_id = ?)
+                )";
+                params.push(user_id.clone());
+                params.push(user_id);
+            }
+
+            let total: u32 = conn
+                .query_row(
+                    &format!("SELECT COUNT(*) {}", from),
+                    rusqlite::params_from_iter(params.iter()),
+                    |row| row.get(0),
+                )
+                .map_err(TingError::DatabaseError)?;
+
+            let query = format!(
+                "SELECT b.id, b.library_id, b.title, b.author, b.narrator, b.cover_url, b.theme_color, \
+                 b.description, b.skip_intro, b.skip_outro, b.path, b.hash, b.tags, b.genre, b.year, b.created_at, \
+                 b.manual_corrected, b.match_pattern, b.chapter_regex, l.type, {} AS score \
+                 {} ORDER BY score DESC, b.created_at DESC LIMIT {} OFFSET {}",
+                filter.score, from, limit, offset
+            );
+
+            let mut stmt = conn.prepare(&query).map_err(TingError::DatabaseError)?;
+            let hits = stmt
+                .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+                    Ok(BookSearchHit {
+                        book: map_book_row(row)?,
+                        library_type: row.get(19)?,
+                        score: row.get(20)?,
+                    })
+                })
+                .map_err(TingError::DatabaseError)?
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(TingError::DatabaseError)?;
+
+            Ok((hits, total))
+        }).await
+    }
+
     pub async fn check_access(&self, book_id: &str, user_id: &str, is_admin: bool) -> Result<bool> {
         if is_admin {
             return Ok(true);
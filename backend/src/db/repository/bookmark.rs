@@ -0,0 +1,200 @@
+use crate::core::error::{Result, TingError};
+use crate::db::manager::DatabaseManager;
+use crate::db::models::Bookmark;
+use crate::db::repository::base::Repository;
+use async_trait::async_trait;
+use rusqlite::OptionalExtension;
+use std::sync::Arc;
+
+const BOOKMARK_COLUMNS: &str = "bm.id, bm.user_id, bm.book_id, bm.chapter_id, bm.position, \
+     bm.end_position, bm.note, bm.created_at, bm.updated_at";
+
+/// Repository for user bookmarks and clips
+pub struct BookmarkRepository {
+    db: Arc<DatabaseManager>,
+}
+
+impl BookmarkRepository {
+    pub fn new(db: Arc<DatabaseManager>) -> Self {
+        Self { db }
+    }
+
+    /// Bookmarks of a user, optionally limited to one book.
+    ///
+    /// A single book is listed in playback order; otherwise the newest come first.
+    pub async fn find_by_user(
+        &self,
+        user_id: &str,
+        book_id: Option<&str>,
+    ) -> Result<Vec<Bookmark>> {
+        let user_id = user_id.to_string();
+        let book_id = book_id.map(str::to_string);
+        self.db
+            .execute(move |conn| {
+                let mut params = vec![user_id];
+                let query = if let Some(book_id) = book_id {
+                    params.push(book_id);
+                    format!(
+                        "SELECT {} FROM bookmarks bm JOIN chapters c ON c.id = bm.chapter_id \
+                         WHERE bm.user_id = ? AND bm.book_id = ? \
+                         ORDER BY c.is_extra, c.chapter_index, bm.position",
+                        BOOKMARK_COLUMNS
+                    )
+                } else {
+                    format!(
+                        "SELECT {} FROM bookmarks bm WHERE bm.user_id = ? \
+                         ORDER BY bm.created_at DESC",
+                        BOOKMARK_COLUMNS
+                    )
+                };
+
+                let mut stmt = conn.prepare(&query).map_err(TingError::DatabaseError)?;
+                let bookmarks = stmt
+                    .query_map(rusqlite::params_from_iter(params.iter()), map_bookmark_row)
+                    .map_err(TingError::DatabaseError)?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(TingError::DatabaseError)?;
+
+                Ok(bookmarks)
+            })
+            .await
+    }
+
+    pub async fn find_by_user_and_id(
+        &self,
+        bookmark_id: &str,
+        user_id: &str,
+    ) -> Result<Option<Bookmark>> {
+        let bookmark_id = bookmark_id.to_string();
+        let user_id = user_id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.query_row(
+                    &format!(
+                        "SELECT {} FROM bookmarks bm WHERE bm.id = ? AND bm.user_id = ?",
+                        BOOKMARK_COLUMNS
+                    ),
+                    [&bookmark_id, &user_id],
+                    map_bookmark_row,
+                )
+                .optional()
+                .map_err(TingError::DatabaseError)
+            })
+            .await
+    }
+}
+
+fn map_bookmark_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Bookmark> {
+    Ok(Bookmark {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        book_id: row.get(2)?,
+        chapter_id: row.get(3)?,
+        position: row.get(4)?,
+        end_position: row.get(5)?,
+        note: row.get(6)?,
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
+    })
+}
+
+#[async_trait]
+impl Repository<Bookmark> for BookmarkRepository {
+    async fn find_by_id(&self, id: &str) -> Result<Option<Bookmark>> {
+        let id = id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.query_row(
+                    &format!(
+                        "SELECT {} FROM bookmarks bm WHERE bm.id = ?",
+                        BOOKMARK_COLUMNS
+                    ),
+                    [&id],
+                    map_bookmark_row,
+                )
+                .optional()
+                .map_err(TingError::DatabaseError)
+            })
+            .await
+    }
+
+    async fn find_all(&self) -> Result<Vec<Bookmark>> {
+        self.db
+            .execute(|conn| {
+                let mut stmt = conn
+                    .prepare(&format!(
+                        "SELECT {} FROM bookmarks bm ORDER BY bm.created_at DESC",
+                        BOOKMARK_COLUMNS
+                    ))
+                    .map_err(TingError::DatabaseError)?;
+
+                let bookmarks = stmt
+                    .query_map([], map_bookmark_row)
+                    .map_err(TingError::DatabaseError)?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(TingError::DatabaseError)?;
+
+                Ok(bookmarks)
+            })
+            .await
+    }
+
+    async fn create(&self, bookmark: &Bookmark) -> Result<()> {
+        let bookmark = bookmark.clone();
+        self.db
+            .execute(move |conn| {
+                conn.execute(
+                    "INSERT INTO bookmarks (id, user_id, book_id, chapter_id, position, end_position, \
+                     note, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    rusqlite::params![
+                        &bookmark.id,
+                        &bookmark.user_id,
+                        &bookmark.book_id,
+                        &bookmark.chapter_id,
+                        bookmark.position,
+                        bookmark.end_position,
+                        &bookmark.note,
+                        &bookmark.created_at,
+                        &bookmark.updated_at,
+                    ],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    async fn update(&self, bookmark: &Bookmark) -> Result<()> {
+        let bookmark = bookmark.clone();
+        self.db
+            .execute(move |conn| {
+                conn.execute(
+                    "UPDATE bookmarks SET chapter_id = ?, position = ?, end_position = ?, note = ?, \
+                     updated_at = ? WHERE id = ? AND user_id = ?",
+                    rusqlite::params![
+                        &bookmark.chapter_id,
+                        bookmark.position,
+                        bookmark.end_position,
+                        &bookmark.note,
+                        &bookmark.updated_at,
+                        &bookmark.id,
+                        &bookmark.user_id,
+                    ],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        let id = id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.execute("DELETE FROM bookmarks WHERE id = ?", [&id])
+                    .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+}
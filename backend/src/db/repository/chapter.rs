@@ -1,9 +1,10 @@
 use crate::core::error::{Result, TingError};
 use crate::db::manager::DatabaseManager;
-use crate::db::models::Chapter;
+use crate::db::models::{Chapter, ChapterSegment, SkipProposal, SkipRange};
 use crate::db::repository::base::Repository;
 use async_trait::async_trait;
-use rusqlite::{OptionalExtension, Row};
+use rusqlite::{Connection, OptionalExtension, Row};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 fn map_chapter_row(row: &Row<'_>) -> rusqlite::Result<Chapter> {
@@ -18,6 +19,75 @@ fn map_chapter_row(row: &Row<'_>) -> rusqlite::Result<Chapter> {
         hash: row.get(7)?,
         created_at: row.get(8)?,
         manual_corrected: row.get(9).unwrap_or(0),
+        start_offset: row.get(10)?,
+        end_offset: row.get(11)?,
+        loudness_lufs: row.get(12)?,
+        true_peak_dbtp: row.get(13)?,
+        skip_ranges: row.get(14)?,
+    })
+}
+
+fn insert_chapter(conn: &Connection, chapter: &Chapter) -> Result<()> {
+    conn.execute(
+        "INSERT INTO chapters (id, book_id, title, path, duration, chapter_index, is_extra, hash, manual_corrected, start_offset, end_offset) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        rusqlite::params![
+            &chapter.id,
+            &chapter.book_id,
+            &chapter.title,
+            &chapter.path,
+            chapter.duration,
+            chapter.chapter_index,
+            chapter.is_extra,
+            &chapter.hash,
+            chapter.manual_corrected,
+            chapter.start_offset,
+            chapter.end_offset,
+        ],
+    )
+    .map_err(TingError::DatabaseError)?;
+    Ok(())
+}
+
+/// Loudness measurements and skip ranges are kept as long as the chapter
+/// still plays the same audio, and dropped once the file or its range changes
+fn update_chapter(conn: &Connection, chapter: &Chapter) -> Result<()> {
+    conn.execute(
+        "UPDATE chapters SET book_id = ?1, title = ?2, path = ?3, duration = ?4, \
+         chapter_index = ?5, is_extra = ?6, hash = ?7, manual_corrected = ?8, \
+         start_offset = ?9, end_offset = ?10, \
+         loudness_lufs = CASE WHEN hash IS ?7 AND start_offset IS ?9 AND end_offset IS ?10 \
+                              THEN loudness_lufs END, \
+         true_peak_dbtp = CASE WHEN hash IS ?7 AND start_offset IS ?9 AND end_offset IS ?10 \
+                               THEN true_peak_dbtp END, \
+         skip_ranges = CASE WHEN hash IS ?7 AND start_offset IS ?9 AND end_offset IS ?10 \
+                            THEN skip_ranges END \
+         WHERE id = ?11",
+        rusqlite::params![
+            &chapter.book_id,
+            &chapter.title,
+            &chapter.path,
+            chapter.duration,
+            chapter.chapter_index,
+            chapter.is_extra,
+            &chapter.hash,
+            chapter.manual_corrected,
+            chapter.start_offset,
+            chapter.end_offset,
+            &chapter.id,
+        ],
+    )
+    .map_err(TingError::DatabaseError)?;
+    Ok(())
+}
+
+/// Skip ranges as stored in `chapters.skip_ranges`, `None` when empty
+fn skip_ranges_json(ranges: &[SkipRange]) -> Result<Option<String>> {
+    if ranges.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(ranges).map(Some).map_err(|e| {
+        TingError::SerializationError(format!("Failed to serialize skip ranges: {}", e))
     })
 }
 
@@ -39,12 +109,57 @@ impl ChapterRepository {
         Self { db }
     }
 
+    /// Find up to `per_book` chapters of each book whose titles contain every
+    /// term of a search query
+    pub async fn find_by_title_terms(
+        &self,
+        book_ids: Vec<String>,
+        query: &str,
+        per_book: u32,
+    ) -> Result<Vec<Chapter>> {
+        let patterns: Vec<String> = query
+            .split_whitespace()
+            .map(|term| {
+                let escaped = term
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                format!("%{}%", escaped)
+            })
+            .collect();
+        if patterns.is_empty() || book_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.db.execute(move |conn| {
+            let query = format!(
+                "SELECT id, book_id, title, path, duration, chapter_index, is_extra, hash, created_at, manual_corrected, start_offset, end_offset, \
+                 loudness_lufs, true_peak_dbtp, skip_ranges \
+                 FROM (SELECT *, ROW_NUMBER() OVER (PARTITION BY book_id ORDER BY is_extra ASC, chapter_index ASC) AS rank \
+                       FROM chapters WHERE book_id IN ({}){}) \
+                 WHERE rank <= {} ORDER BY book_id, rank",
+                vec!["?"; book_ids.len()].join(", "),
+                " AND title LIKE ? ESCAPE '\\'".repeat(patterns.len()),
+                per_book
+            );
+            let mut stmt = conn.prepare(&query).map_err(TingError::DatabaseError)?;
+
+            let params = book_ids.iter().chain(patterns.iter());
+            let chapters = stmt.query_map(rusqlite::params_from_iter(params), map_chapter_row)
+            .map_err(TingError::DatabaseError)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(TingError::DatabaseError)?;
+
+            Ok(chapters)
+        }).await
+    }
+
     /// Find chapters by book ID
     pub async fn find_by_book(&self, book_id: &str) -> Result<Vec<Chapter>> {
         let book_id = book_id.to_string();
         self.db.execute(move |conn| {
             let mut stmt = conn.prepare(
-                "SELECT id, book_id, title, path, duration, chapter_index, is_extra, hash, created_at, manual_corrected \
+                "SELECT id, book_id, title, path, duration, chapter_index, is_extra, hash, created_at, manual_corrected, start_offset, end_offset, \
+                 loudness_lufs, true_peak_dbtp, skip_ranges \
                  FROM chapters WHERE book_id = ? ORDER BY is_extra ASC, chapter_index ASC"
             ).map_err(TingError::DatabaseError)?;
 
@@ -62,7 +177,8 @@ impl ChapterRepository {
         let hash = hash.to_string();
         self.db.execute(move |conn| {
             conn.query_row(
-                "SELECT id, book_id, title, path, duration, chapter_index, is_extra, hash, created_at, manual_corrected \
+                "SELECT id, book_id, title, path, duration, chapter_index, is_extra, hash, created_at, manual_corrected, start_offset, end_offset, \
+                 loudness_lufs, true_peak_dbtp, skip_ranges \
                  FROM chapters WHERE hash = ?",
                 [&hash],
                 map_chapter_row
@@ -71,6 +187,427 @@ impl ChapterRepository {
         }).await
     }
 
+    /// Chapters of a book whose markers still need a one-time probe
+    pub async fn find_pending_marker_probes(&self, book_id: &str) -> Result<HashSet<String>> {
+        let book_id = book_id.to_string();
+        self.db
+            .execute(move |conn| {
+                let mut stmt = conn
+                    .prepare(
+                        "SELECT p.chapter_id FROM chapter_marker_probes p \
+                         JOIN chapters c ON c.id = p.chapter_id WHERE c.book_id = ?",
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                let ids = stmt
+                    .query_map([&book_id], |row| row.get(0))
+                    .map_err(TingError::DatabaseError)?
+                    .collect::<std::result::Result<HashSet<String>, _>>()
+                    .map_err(TingError::DatabaseError)?;
+                Ok(ids)
+            })
+            .await
+    }
+
+    /// Mark the chapter markers of a chapter as probed
+    pub async fn clear_marker_probe(&self, chapter_id: &str) -> Result<()> {
+        let chapter_id = chapter_id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.execute(
+                    "DELETE FROM chapter_marker_probes WHERE chapter_id = ?",
+                    [&chapter_id],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// File ranges of a chapter joined from several files, in play order
+    pub async fn find_segments(&self, chapter_id: &str) -> Result<Vec<ChapterSegment>> {
+        let chapter_id = chapter_id.to_string();
+        self.db
+            .execute(move |conn| {
+                let mut stmt = conn
+                    .prepare(
+                        "SELECT path, start_offset, end_offset FROM chapter_segments \
+                         WHERE chapter_id = ? ORDER BY position",
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                let segments = stmt
+                    .query_map([&chapter_id], |row| {
+                        Ok(ChapterSegment {
+                            path: row.get(0)?,
+                            start_offset: row.get(1)?,
+                            end_offset: row.get(2)?,
+                        })
+                    })
+                    .map_err(TingError::DatabaseError)?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(TingError::DatabaseError)?;
+                Ok(segments)
+            })
+            .await
+    }
+
+    /// Map each file of the book's joined chapters to the chapter that plays it
+    pub async fn find_segment_owners(&self, book_id: &str) -> Result<HashMap<String, String>> {
+        let book_id = book_id.to_string();
+        self.db
+            .execute(move |conn| {
+                let mut stmt = conn
+                    .prepare(
+                        "SELECT s.path, s.chapter_id FROM chapter_segments s \
+                         JOIN chapters c ON c.id = s.chapter_id WHERE c.book_id = ?",
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                let owners = stmt
+                    .query_map([&book_id], |row| Ok((row.get(0)?, row.get(1)?)))
+                    .map_err(TingError::DatabaseError)?
+                    .collect::<std::result::Result<HashMap<String, String>, _>>()
+                    .map_err(TingError::DatabaseError)?;
+                Ok(owners)
+            })
+            .await
+    }
+
+    /// Store the slices of a split chapter in one transaction.
+    ///
+    /// `slices[0]` keeps the original id. Later chapters of the same group move
+    /// back to make room, and saved progress and bookmarks follow the slice
+    /// they fall into.
+    pub async fn apply_split(&self, slices: Vec<Chapter>) -> Result<()> {
+        let Some(original) = slices.first().cloned() else {
+            return Ok(());
+        };
+        self.db
+            .transaction(move |tx| {
+                tx.execute(
+                    "UPDATE chapters SET chapter_index = chapter_index + ? \
+                     WHERE book_id = ? AND is_extra = ? AND chapter_index > ? AND id != ?",
+                    rusqlite::params![
+                        slices.len() as i32 - 1,
+                        &original.book_id,
+                        original.is_extra,
+                        original.chapter_index.unwrap_or(0),
+                        &original.id,
+                    ],
+                )
+                .map_err(TingError::DatabaseError)?;
+
+                update_chapter(tx, &original)?;
+                let base = original.start_offset.unwrap_or(0.0);
+                // Latest slice first, so each one only takes what is left behind it
+                for slice in slices.iter().skip(1).rev() {
+                    insert_chapter(tx, slice)?;
+                    let offset = slice.start_offset.unwrap_or(base) - base;
+                    tx.execute(
+                        "UPDATE progress SET chapter_id = ?, position = position - ?, duration = ? \
+                         WHERE chapter_id = ? AND position >= ?",
+                        rusqlite::params![
+                            &slice.id,
+                            offset,
+                            slice.slice_length(),
+                            &original.id,
+                            offset,
+                        ],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                    tx.execute(
+                        "UPDATE bookmarks SET chapter_id = ?1, position = position - ?2, \
+                         end_position = MIN(end_position - ?2, ?3) \
+                         WHERE chapter_id = ?4 AND position >= ?2",
+                        rusqlite::params![&slice.id, offset, slice.slice_length(), &original.id],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                }
+                tx.execute(
+                    "UPDATE progress SET duration = ? WHERE chapter_id = ?",
+                    rusqlite::params![original.slice_length(), &original.id],
+                )
+                .map_err(TingError::DatabaseError)?;
+                // Clips that ran past the split now end with their slice
+                tx.execute(
+                    "UPDATE bookmarks SET end_position = MIN(end_position, ?) WHERE chapter_id = ?",
+                    rusqlite::params![original.slice_length(), &original.id],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Store a joined chapter and drop the chapters it absorbed, in one transaction.
+    ///
+    /// `absorbed` pairs each removed chapter with where it starts inside
+    /// `joined` (seconds). Their progress moves to `joined`, keeping the most
+    /// recent row per user, their bookmarks move along, and the chapter indexes
+    /// they leave behind are closed.
+    /// `segments` lists the files of a chapter joined from several files and is
+    /// empty when the result is a single file or slice.
+    pub async fn apply_join(
+        &self,
+        joined: Chapter,
+        absorbed: Vec<(Chapter, f64)>,
+        segments: Vec<ChapterSegment>,
+    ) -> Result<()> {
+        self.db
+            .transaction(move |tx| {
+                update_chapter(tx, &joined)?;
+                tx.execute(
+                    "DELETE FROM chapter_segments WHERE chapter_id = ?",
+                    [&joined.id],
+                )
+                .map_err(TingError::DatabaseError)?;
+                for (position, segment) in segments.iter().enumerate() {
+                    tx.execute(
+                        "INSERT INTO chapter_segments (chapter_id, position, path, start_offset, end_offset) \
+                         VALUES (?, ?, ?, ?, ?)",
+                        rusqlite::params![
+                            &joined.id,
+                            position as i32,
+                            &segment.path,
+                            segment.start_offset,
+                            segment.end_offset,
+                        ],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                }
+                let length = joined.slice_length();
+
+                for (chapter, offset) in &absorbed {
+                    tx.execute(
+                        "DELETE FROM progress WHERE chapter_id = ?1 AND EXISTS ( \
+                             SELECT 1 FROM progress kept WHERE kept.chapter_id = ?2 \
+                             AND kept.user_id = progress.user_id AND kept.book_id = progress.book_id \
+                             AND kept.updated_at >= progress.updated_at)",
+                        rusqlite::params![&chapter.id, &joined.id],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                    tx.execute(
+                        "DELETE FROM progress WHERE chapter_id = ?2 AND EXISTS ( \
+                             SELECT 1 FROM progress moved WHERE moved.chapter_id = ?1 \
+                             AND moved.user_id = progress.user_id AND moved.book_id = progress.book_id)",
+                        rusqlite::params![&chapter.id, &joined.id],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                    tx.execute(
+                        "UPDATE progress SET chapter_id = ?, position = position + ? WHERE chapter_id = ?",
+                        rusqlite::params![&joined.id, offset, &chapter.id],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                    tx.execute(
+                        "UPDATE bookmarks SET chapter_id = ?1, position = position + ?2, \
+                         end_position = end_position + ?2 WHERE chapter_id = ?3",
+                        rusqlite::params![&joined.id, offset, &chapter.id],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                    tx.execute("DELETE FROM chapters WHERE id = ?", [&chapter.id])
+                        .map_err(TingError::DatabaseError)?;
+                }
+                tx.execute(
+                    "UPDATE progress SET duration = ? WHERE chapter_id = ?",
+                    rusqlite::params![length, &joined.id],
+                )
+                .map_err(TingError::DatabaseError)?;
+
+                let mut removed: Vec<&Chapter> = absorbed.iter().map(|(chapter, _)| chapter).collect();
+                removed.sort_by_key(|chapter| std::cmp::Reverse(chapter.chapter_index));
+                for chapter in removed {
+                    let Some(index) = chapter.chapter_index else {
+                        continue;
+                    };
+                    tx.execute(
+                        "UPDATE chapters SET chapter_index = chapter_index - 1 \
+                         WHERE book_id = ? AND is_extra = ? AND chapter_index > ?",
+                        rusqlite::params![&chapter.book_id, chapter.is_extra, index],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    /// Store the loudness analysis of a chapter
+    pub async fn set_loudness(
+        &self,
+        chapter_id: &str,
+        loudness_lufs: f64,
+        true_peak_dbtp: f64,
+    ) -> Result<()> {
+        let chapter_id = chapter_id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.execute(
+                    "UPDATE chapters SET loudness_lufs = ?, true_peak_dbtp = ? WHERE id = ?",
+                    rusqlite::params![loudness_lufs, true_peak_dbtp, &chapter_id],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Replace the skip ranges of a chapter; an empty list clears them
+    pub async fn set_skip_ranges(&self, chapter_id: &str, ranges: &[SkipRange]) -> Result<()> {
+        let chapter_id = chapter_id.to_string();
+        let ranges = skip_ranges_json(ranges)?;
+        self.db
+            .execute(move |conn| {
+                conn.execute(
+                    "UPDATE chapters SET skip_ranges = ? WHERE id = ?",
+                    rusqlite::params![ranges, &chapter_id],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Store the skip proposal of a book, replacing any earlier one
+    pub async fn save_skip_proposal(&self, proposal: &SkipProposal) -> Result<()> {
+        let chapters = serde_json::to_string(&proposal.chapters).map_err(|e| {
+            TingError::SerializationError(format!("Failed to serialize skip proposal: {}", e))
+        })?;
+        let proposal = proposal.clone();
+        self.db
+            .execute(move |conn| {
+                conn.execute(
+                    "INSERT INTO skip_proposals (book_id, skip_intro, skip_outro, chapters, created_at) \
+                     VALUES (?, ?, ?, ?, ?) \
+                     ON CONFLICT(book_id) DO UPDATE SET \
+                     skip_intro = excluded.skip_intro, skip_outro = excluded.skip_outro, \
+                     chapters = excluded.chapters, created_at = excluded.created_at",
+                    rusqlite::params![
+                        &proposal.book_id,
+                        proposal.skip_intro,
+                        proposal.skip_outro,
+                        &chapters,
+                        &proposal.created_at,
+                    ],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Find the pending skip proposal of a book
+    pub async fn find_skip_proposal(&self, book_id: &str) -> Result<Option<SkipProposal>> {
+        let book_id = book_id.to_string();
+        let row = self
+            .db
+            .execute(move |conn| {
+                conn.query_row(
+                    "SELECT book_id, skip_intro, skip_outro, chapters, created_at \
+                     FROM skip_proposals WHERE book_id = ?",
+                    [&book_id],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, Option<i32>>(1)?,
+                            row.get::<_, Option<i32>>(2)?,
+                            row.get::<_, String>(3)?,
+                            row.get::<_, String>(4)?,
+                        ))
+                    },
+                )
+                .optional()
+                .map_err(TingError::DatabaseError)
+            })
+            .await?;
+
+        row.map(|(book_id, skip_intro, skip_outro, chapters, created_at)| {
+            Ok(SkipProposal {
+                book_id,
+                skip_intro,
+                skip_outro,
+                chapters: serde_json::from_str(&chapters).map_err(|e| {
+                    TingError::SerializationError(format!("Invalid skip proposal: {}", e))
+                })?,
+                created_at,
+            })
+        })
+        .transpose()
+    }
+
+    /// Apply (part of) the skip proposal of a book in one transaction.
+    ///
+    /// `book_skips` are the `skip_intro`/`skip_outro` values to store on the
+    /// book, a `None` value keeping the current one, and `chapter_ranges` the
+    /// new skip ranges of each chapter. `remaining` is what is left of the
+    /// proposal afterwards; it is deleted once nothing is left.
+    pub async fn apply_skip_proposal(
+        &self,
+        book_skips: Option<(Option<i32>, Option<i32>)>,
+        chapter_ranges: Vec<(String, Vec<SkipRange>)>,
+        remaining: SkipProposal,
+    ) -> Result<()> {
+        let chapter_ranges = chapter_ranges
+            .into_iter()
+            .map(|(chapter_id, ranges)| Ok((chapter_id, skip_ranges_json(&ranges)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let remaining_chapters = serde_json::to_string(&remaining.chapters).map_err(|e| {
+            TingError::SerializationError(format!("Failed to serialize skip proposal: {}", e))
+        })?;
+        self.db
+            .transaction(move |tx| {
+                if let Some((skip_intro, skip_outro)) = book_skips {
+                    tx.execute(
+                        "UPDATE books SET skip_intro = COALESCE(?, skip_intro), \
+                         skip_outro = COALESCE(?, skip_outro) WHERE id = ?",
+                        rusqlite::params![skip_intro, skip_outro, &remaining.book_id],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                }
+                for (chapter_id, ranges) in &chapter_ranges {
+                    tx.execute(
+                        "UPDATE chapters SET skip_ranges = ? WHERE id = ?",
+                        rusqlite::params![ranges, chapter_id],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                }
+
+                if remaining.skip_intro.is_none()
+                    && remaining.skip_outro.is_none()
+                    && remaining.chapters.is_empty()
+                {
+                    tx.execute(
+                        "DELETE FROM skip_proposals WHERE book_id = ?",
+                        [&remaining.book_id],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                } else {
+                    tx.execute(
+                        "UPDATE skip_proposals SET skip_intro = ?, skip_outro = ?, chapters = ? \
+                         WHERE book_id = ?",
+                        rusqlite::params![
+                            remaining.skip_intro,
+                            remaining.skip_outro,
+                            &remaining_chapters,
+                            &remaining.book_id,
+                        ],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    /// Discard the skip proposal of a book
+    pub async fn delete_skip_proposal(&self, book_id: &str) -> Result<()> {
+        let book_id = book_id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.execute("DELETE FROM skip_proposals WHERE book_id = ?", [&book_id])
+                    .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
     /// Delete chapters by book ID
     pub async fn delete_by_book(&self, book_id: &str) -> Result<()> {
         let book_id = book_id.to_string();
@@ -95,7 +632,8 @@ impl ChapterRepository {
         self.db.execute(move |conn| {
             let mut stmt = conn.prepare(
                 "SELECT c.id, c.book_id, c.title, c.path, c.duration, c.chapter_index, c.is_extra, c.hash, c.created_at, \
-                 p.position, p.updated_at, c.manual_corrected \
+                 p.position, p.updated_at, c.manual_corrected, c.start_offset, c.end_offset, \
+                 c.loudness_lufs, c.true_peak_dbtp, c.skip_ranges \
                  FROM chapters c \
                  LEFT JOIN progress p ON c.id = p.chapter_id AND p.user_id = ? \
                  WHERE c.book_id = ? \
@@ -114,6 +652,11 @@ impl ChapterRepository {
                     hash: row.get(7)?,
                     created_at: row.get(8)?,
                     manual_corrected: row.get(11).unwrap_or(0),
+                    start_offset: row.get(12)?,
+                    end_offset: row.get(13)?,
+                    loudness_lufs: row.get(14)?,
+                    true_peak_dbtp: row.get(15)?,
+                    skip_ranges: row.get(16)?,
                 };
                 let progress_position: Option<f64> = row.get(9)?;
                 let progress_updated_at: Option<String> = row.get(10)?;
@@ -224,7 +767,8 @@ impl ChapterRepository {
         self.db.execute(move |conn| {
             let sql = format!(
                 "SELECT c.id, c.book_id, c.title, c.path, c.duration, c.chapter_index, c.is_extra, c.hash, c.created_at, \
-                 p.position, p.updated_at, c.manual_corrected \
+                 p.position, p.updated_at, c.manual_corrected, c.start_offset, c.end_offset, \
+                 c.loudness_lufs, c.true_peak_dbtp, c.skip_ranges \
                  FROM chapters c \
                  LEFT JOIN progress p ON c.id = p.chapter_id AND p.user_id = ?1 \
                  WHERE c.book_id = ?2 AND (?3 IS NULL OR c.is_extra = ?3) \
@@ -254,6 +798,11 @@ impl ChapterRepository {
                             hash: row.get(7)?,
                             created_at: row.get(8)?,
                             manual_corrected: row.get(11).unwrap_or(0),
+                            start_offset: row.get(12)?,
+                            end_offset: row.get(13)?,
+                            loudness_lufs: row.get(14)?,
+                            true_peak_dbtp: row.get(15)?,
+                            skip_ranges: row.get(16)?,
                         };
                         let progress_position: Option<f64> = row.get(9)?;
                         let progress_updated_at: Option<String> = row.get(10)?;
@@ -276,7 +825,8 @@ impl Repository<Chapter> for ChapterRepository {
         let id = id.to_string();
         self.db.execute(move |conn| {
             conn.query_row(
-                "SELECT id, book_id, title, path, duration, chapter_index, is_extra, hash, created_at, manual_corrected \
+                "SELECT id, book_id, title, path, duration, chapter_index, is_extra, hash, created_at, manual_corrected, start_offset, end_offset, \
+                 loudness_lufs, true_peak_dbtp, skip_ranges \
                  FROM chapters WHERE id = ?",
                 [&id],
                 map_chapter_row
@@ -288,7 +838,8 @@ impl Repository<Chapter> for ChapterRepository {
     async fn find_all(&self) -> Result<Vec<Chapter>> {
         self.db.execute(|conn| {
             let mut stmt = conn.prepare(
-                "SELECT id, book_id, title, path, duration, chapter_index, is_extra, hash, created_at, manual_corrected \
+                "SELECT id, book_id, title, path, duration, chapter_index, is_extra, hash, created_at, manual_corrected, start_offset, end_offset, \
+                 loudness_lufs, true_peak_dbtp, skip_ranges \
                  FROM chapters ORDER BY book_id, chapter_index ASC"
             ).map_err(TingError::DatabaseError)?;
 
@@ -303,48 +854,15 @@ impl Repository<Chapter> for ChapterRepository {
 
     async fn create(&self, chapter: &Chapter) -> Result<()> {
         let chapter = chapter.clone();
-        self.db.execute(move |conn| {
-            conn.execute(
-                "INSERT INTO chapters (id, book_id, title, path, duration, chapter_index, is_extra, hash, manual_corrected) \
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
-                rusqlite::params![
-                    &chapter.id,
-                    &chapter.book_id,
-                    &chapter.title,
-                    &chapter.path,
-                    chapter.duration,
-                    chapter.chapter_index,
-                    chapter.is_extra,
-                    &chapter.hash,
-                    chapter.manual_corrected,
-                ],
-            ).map_err(TingError::DatabaseError)?;
-            Ok(())
-        }).await
+        self.db
+            .execute(move |conn| insert_chapter(conn, &chapter))
+            .await
     }
 
     async fn update(&self, chapter: &Chapter) -> Result<()> {
         let chapter = chapter.clone();
         self.db
-            .execute(move |conn| {
-                conn.execute(
-                    "UPDATE chapters SET book_id = ?, title = ?, path = ?, duration = ?, \
-                 chapter_index = ?, is_extra = ?, hash = ?, manual_corrected = ? WHERE id = ?",
-                    rusqlite::params![
-                        &chapter.book_id,
-                        &chapter.title,
-                        &chapter.path,
-                        chapter.duration,
-                        chapter.chapter_index,
-                        chapter.is_extra,
-                        &chapter.hash,
-                        chapter.manual_corrected,
-                        &chapter.id,
-                    ],
-                )
-                .map_err(TingError::DatabaseError)?;
-                Ok(())
-            })
+            .execute(move |conn| update_chapter(conn, &chapter))
             .await
     }
 
@@ -416,4 +934,233 @@ mod tests {
             vec!["main-1", "main-2", "extra-1", "extra-2"]
         );
     }
+
+    #[tokio::test]
+    async fn split_and_join_move_progress_and_close_index_gaps() {
+        let db = Arc::new(DatabaseManager::new_in_memory().unwrap());
+        db.execute(|conn| {
+            conn.execute_batch(
+                "PRAGMA foreign_keys = OFF;
+                 INSERT INTO chapters (id, book_id, title, path, duration, chapter_index, is_extra) \
+                 VALUES ('long', 'book-1', 'Long', '/long.mp3', 300, 1, 0), \
+                        ('next', 'book-1', 'Next', '/next.mp3', 60, 2, 0);
+                 INSERT INTO progress (id, user_id, book_id, chapter_id, position, duration) \
+                 VALUES ('p1', 'user-1', 'book-1', 'long', 250, 300), \
+                        ('p2', 'user-2', 'book-1', 'long', 50, 300);
+                 INSERT INTO bookmarks (id, user_id, book_id, chapter_id, position, end_position) \
+                 VALUES ('clip', 'user-1', 'book-1', 'long', 90, 120);",
+            )
+            .map_err(TingError::DatabaseError)
+        })
+        .await
+        .unwrap();
+        let repository = ChapterRepository::new(db.clone());
+        let progress_of = |user: &'static str| {
+            let db = db.clone();
+            async move {
+                db.execute(move |conn| {
+                    conn.query_row(
+                        "SELECT chapter_id, position, duration FROM progress WHERE user_id = ?",
+                        [user],
+                        |row| {
+                            Ok((
+                                row.get::<_, String>(0)?,
+                                row.get::<_, f64>(1)?,
+                                row.get::<_, f64>(2)?,
+                            ))
+                        },
+                    )
+                    .map_err(TingError::DatabaseError)
+                })
+                .await
+                .unwrap()
+            }
+        };
+
+        let original = repository.find_by_id("long").await.unwrap().unwrap();
+        let slices: Vec<Chapter> = [(0.0, 100.0), (100.0, 300.0)]
+            .into_iter()
+            .enumerate()
+            .map(|(position, (start, end))| Chapter {
+                id: if position == 0 {
+                    "long".to_string()
+                } else {
+                    "tail".to_string()
+                },
+                chapter_index: Some(1 + position as i32),
+                start_offset: Some(start),
+                end_offset: Some(end),
+                ..original.clone()
+            })
+            .collect();
+        repository.apply_split(slices.clone()).await.unwrap();
+
+        assert_eq!(
+            progress_of("user-1").await,
+            ("tail".to_string(), 150.0, 200.0)
+        );
+        assert_eq!(
+            progress_of("user-2").await,
+            ("long".to_string(), 50.0, 100.0)
+        );
+        let next = repository.find_by_id("next").await.unwrap().unwrap();
+        assert_eq!(next.chapter_index, Some(3));
+        let clip_of = || {
+            let db = db.clone();
+            async move {
+                db.execute(|conn| {
+                    conn.query_row(
+                        "SELECT chapter_id, position, end_position FROM bookmarks WHERE id = 'clip'",
+                        [],
+                        |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, row.get::<_, f64>(2)?)),
+                    )
+                    .map_err(TingError::DatabaseError)
+                })
+                .await
+                .unwrap()
+            }
+        };
+        // The clip stays with the first slice and ends where it does
+        assert_eq!(clip_of().await, ("long".to_string(), 90.0, 100.0));
+
+        let mut joined = slices[0].clone();
+        joined.end_offset = Some(300.0);
+        repository
+            .apply_join(joined, vec![(slices[1].clone(), 100.0)], Vec::new())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            progress_of("user-1").await,
+            ("long".to_string(), 250.0, 300.0)
+        );
+        assert!(repository.find_by_id("tail").await.unwrap().is_none());
+        let next = repository.find_by_id("next").await.unwrap().unwrap();
+        assert_eq!(next.chapter_index, Some(2));
+
+        // Chapters of different files keep their ranges as segments
+        let mut joined = repository.find_by_id("long").await.unwrap().unwrap();
+        joined.start_offset = None;
+        joined.end_offset = None;
+        joined.duration = Some(360);
+        let segments = vec![
+            ChapterSegment {
+                path: "/long.mp3".to_string(),
+                start_offset: Some(0.0),
+                end_offset: Some(300.0),
+            },
+            ChapterSegment {
+                path: "/next.mp3".to_string(),
+                start_offset: None,
+                end_offset: None,
+            },
+        ];
+        repository
+            .apply_join(joined, vec![(next, 300.0)], segments.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(repository.find_segments("long").await.unwrap(), segments);
+        let owners = repository.find_segment_owners("book-1").await.unwrap();
+        assert_eq!(owners.get("/next.mp3").map(String::as_str), Some("long"));
+        assert!(repository.find_by_id("next").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn partially_applied_skip_proposal_keeps_the_rest() {
+        use crate::db::models::{ChapterSkipProposal, SkipKind};
+
+        let db = Arc::new(DatabaseManager::new_in_memory().unwrap());
+        db.execute(|conn| {
+            conn.execute_batch(
+                "PRAGMA foreign_keys = OFF;
+                 INSERT INTO books (id, library_id, title, path, hash, skip_intro, skip_outro) \
+                 VALUES ('book-1', 'library-1', 'Book', '/book', 'h', 0, 0);
+                 INSERT INTO chapters (id, book_id, title, path, duration, chapter_index, is_extra) \
+                 VALUES ('c1', 'book-1', 'One', '/c1.mp3', 300, 1, 0), \
+                        ('c2', 'book-1', 'Two', '/c2.mp3', 300, 2, 0);",
+            )
+            .map_err(TingError::DatabaseError)
+        })
+        .await
+        .unwrap();
+        let repository = ChapterRepository::new(db.clone());
+        let intro = |end: f64| SkipRange {
+            kind: SkipKind::Intro,
+            start: 0.0,
+            end,
+        };
+        let proposal = SkipProposal {
+            book_id: "book-1".to_string(),
+            skip_intro: Some(12),
+            skip_outro: Some(8),
+            chapters: vec![
+                ChapterSkipProposal {
+                    chapter_id: "c1".to_string(),
+                    ranges: vec![intro(10.0)],
+                },
+                ChapterSkipProposal {
+                    chapter_id: "c2".to_string(),
+                    ranges: vec![intro(11.0)],
+                },
+            ],
+            created_at: "2026-10-17T00:00:00Z".to_string(),
+        };
+        repository.save_skip_proposal(&proposal).await.unwrap();
+
+        // Only the first chapter, without the book-wide values
+        let remaining = SkipProposal {
+            chapters: proposal.chapters[1..].to_vec(),
+            ..proposal.clone()
+        };
+        repository
+            .apply_skip_proposal(None, vec![("c1".to_string(), vec![intro(10.0)])], remaining)
+            .await
+            .unwrap();
+
+        let c1 = repository.find_by_id("c1").await.unwrap().unwrap();
+        assert_eq!(c1.parsed_skip_ranges(), vec![intro(10.0)]);
+        let c2 = repository.find_by_id("c2").await.unwrap().unwrap();
+        assert!(c2.parsed_skip_ranges().is_empty());
+        let left = repository
+            .find_skip_proposal("book-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((left.skip_intro, left.skip_outro), (Some(12), Some(8)));
+        assert_eq!(left.chapters, proposal.chapters[1..].to_vec());
+
+        // Applying the rest removes the proposal
+        let remaining = SkipProposal {
+            skip_intro: None,
+            skip_outro: None,
+            chapters: Vec::new(),
+            ..proposal
+        };
+        repository
+            .apply_skip_proposal(
+                Some((Some(12), Some(8))),
+                vec![("c2".to_string(), vec![intro(11.0)])],
+                remaining,
+            )
+            .await
+            .unwrap();
+        let skips = db
+            .execute(|conn| {
+                conn.query_row(
+                    "SELECT skip_intro, skip_outro FROM books WHERE id = 'book-1'",
+                    [],
+                    |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?)),
+                )
+                .map_err(TingError::DatabaseError)
+            })
+            .await
+            .unwrap();
+        assert_eq!(skips, (12, 8));
+        assert!(repository
+            .find_skip_proposal("book-1")
+            .await
+            .unwrap()
+            .is_none());
+    }
 }
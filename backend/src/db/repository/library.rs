@@ -1,6 +1,7 @@
 use crate::core::error::{Result, TingError};
 use crate::db::manager::DatabaseManager;
-use crate::db::models::Library;
+use crate::db::models::{EpisodeDownload, Library, RssFeedValidators};
+use crate::db::repository::progress::{COMPLETION_RATIO, COMPLETION_TAIL_SECS};
 use rusqlite::OptionalExtension;
 use std::sync::Arc;
 
@@ -160,6 +161,173 @@ impl LibraryRepository {
         }).await
     }
 
+    /// Validators of the last RSS feed response for a library
+    pub async fn find_rss_validators(&self, id: &str) -> Result<Option<RssFeedValidators>> {
+        let id = id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.query_row(
+                    "SELECT etag, last_modified FROM rss_feed_validators WHERE library_id = ?",
+                    [&id],
+                    |row| {
+                        Ok(RssFeedValidators {
+                            etag: row.get(0)?,
+                            last_modified: row.get(1)?,
+                        })
+                    },
+                )
+                .optional()
+                .map_err(TingError::DatabaseError)
+            })
+            .await
+    }
+
+    /// Remember the validators of an RSS feed response, or forget them when
+    /// the server sent none
+    pub async fn save_rss_validators(
+        &self,
+        id: &str,
+        validators: &RssFeedValidators,
+    ) -> Result<()> {
+        let id = id.to_string();
+        let validators = validators.clone();
+        self.db
+            .execute(move |conn| {
+                if validators.etag.is_none() && validators.last_modified.is_none() {
+                    conn.execute("DELETE FROM rss_feed_validators WHERE library_id = ?", [&id])
+                        .map_err(TingError::DatabaseError)?;
+                } else {
+                    conn.execute(
+                        "INSERT INTO rss_feed_validators (library_id, etag, last_modified, updated_at) \
+                         VALUES (?, ?, ?, STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now')) \
+                         ON CONFLICT(library_id) DO UPDATE SET etag = excluded.etag, \
+                         last_modified = excluded.last_modified, updated_at = excluded.updated_at",
+                        rusqlite::params![&id, &validators.etag, &validators.last_modified],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    /// Find the local copy of an RSS episode
+    pub async fn find_episode_download(&self, chapter_id: &str) -> Result<Option<EpisodeDownload>> {
+        let chapter_id = chapter_id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.query_row(
+                    &format!(
+                        "SELECT {} FROM episode_downloads WHERE chapter_id = ?",
+                        EPISODE_DOWNLOAD_COLUMNS
+                    ),
+                    [&chapter_id],
+                    map_episode_download,
+                )
+                .optional()
+                .map_err(TingError::DatabaseError)
+            })
+            .await
+    }
+
+    /// All local episode copies of a library
+    pub async fn find_episode_downloads(&self, library_id: &str) -> Result<Vec<EpisodeDownload>> {
+        let library_id = library_id.to_string();
+        self.db
+            .execute(move |conn| {
+                let mut stmt = conn
+                    .prepare(&format!(
+                        "SELECT {} FROM episode_downloads WHERE library_id = ? ORDER BY downloaded_at",
+                        EPISODE_DOWNLOAD_COLUMNS
+                    ))
+                    .map_err(TingError::DatabaseError)?;
+                let downloads = stmt
+                    .query_map([&library_id], map_episode_download)
+                    .map_err(TingError::DatabaseError)?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(TingError::DatabaseError)?;
+                Ok(downloads)
+            })
+            .await
+    }
+
+    /// Record a downloaded episode, replacing an earlier copy
+    pub async fn save_episode_download(&self, download: &EpisodeDownload) -> Result<()> {
+        let download = download.clone();
+        self.db
+            .execute(move |conn| {
+                conn.execute(
+                    "INSERT INTO episode_downloads (chapter_id, library_id, file_path, file_size, source_url, downloaded_at) \
+                     VALUES (?, ?, ?, ?, ?, ?) \
+                     ON CONFLICT(chapter_id) DO UPDATE SET library_id = excluded.library_id, \
+                     file_path = excluded.file_path, file_size = excluded.file_size, \
+                     source_url = excluded.source_url, downloaded_at = excluded.downloaded_at",
+                    rusqlite::params![
+                        &download.chapter_id,
+                        &download.library_id,
+                        &download.file_path,
+                        download.file_size,
+                        &download.source_url,
+                        &download.downloaded_at,
+                    ],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Forget the local copy of an episode
+    pub async fn delete_episode_download(&self, chapter_id: &str) -> Result<()> {
+        let chapter_id = chapter_id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.execute(
+                    "DELETE FROM episode_downloads WHERE chapter_id = ?",
+                    [&chapter_id],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Downloaded episodes of a library that every user with access to them
+    /// has finished. Admins see every library, other users need library or
+    /// book access.
+    pub async fn find_episodes_played_by_all(&self, library_id: &str) -> Result<Vec<String>> {
+        let library_id = library_id.to_string();
+        self.db
+            .execute(move |conn| {
+                let listeners = "FROM users u WHERE (u.role = 'admin' \
+                     OR u.id IN (SELECT user_id FROM user_library_access WHERE library_id = d.library_id) \
+                     OR u.id IN (SELECT user_id FROM user_book_access WHERE book_id = c.book_id))";
+                let mut stmt = conn
+                    .prepare(&format!(
+                        "SELECT d.chapter_id FROM episode_downloads d JOIN chapters c ON c.id = d.chapter_id \
+                         WHERE d.library_id = ? \
+                           AND EXISTS (SELECT 1 {listeners}) \
+                           AND NOT EXISTS (SELECT 1 {listeners} \
+                               AND NOT EXISTS (SELECT 1 FROM progress p \
+                                   WHERE p.user_id = u.id AND p.chapter_id = c.id \
+                                     AND COALESCE(p.duration, c.duration) > 0 \
+                                     AND (p.position >= COALESCE(p.duration, c.duration) * {ratio} \
+                                          OR p.position >= COALESCE(p.duration, c.duration) - {tail})))",
+                        listeners = listeners,
+                        ratio = COMPLETION_RATIO,
+                        tail = COMPLETION_TAIL_SECS,
+                    ))
+                    .map_err(TingError::DatabaseError)?;
+                let ids = stmt
+                    .query_map([&library_id], |row| row.get(0))
+                    .map_err(TingError::DatabaseError)?
+                    .collect::<std::result::Result<Vec<String>, _>>()
+                    .map_err(TingError::DatabaseError)?;
+                Ok(ids)
+            })
+            .await
+    }
+
     /// Delete a library
     pub async fn delete(&self, id: &str) -> Result<()> {
         let id = id.to_string();
@@ -195,3 +363,59 @@ impl LibraryRepository {
         }).await
     }
 }
+
+const EPISODE_DOWNLOAD_COLUMNS: &str =
+    "chapter_id, library_id, file_path, file_size, source_url, downloaded_at";
+
+fn map_episode_download(row: &rusqlite::Row<'_>) -> rusqlite::Result<EpisodeDownload> {
+    Ok(EpisodeDownload {
+        chapter_id: row.get(0)?,
+        library_id: row.get(1)?,
+        file_path: row.get(2)?,
+        file_size: row.get(3)?,
+        source_url: row.get(4)?,
+        downloaded_at: row.get(5)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn episodes_count_as_played_once_every_listener_finished() {
+        let db = Arc::new(DatabaseManager::new_in_memory().expect("create test database"));
+        db.execute(|conn| {
+            conn.execute_batch(
+                "INSERT INTO users (id, username, password_hash, role) VALUES \
+                    ('admin', 'admin', 'hash', 'admin'), ('user-1', 'one', 'hash', 'user'), \
+                    ('user-2', 'two', 'hash', 'user'); \
+                 INSERT INTO libraries (id, name, type, url) VALUES ('podcast', 'Podcast', 'rss', 'https://example.com/feed'); \
+                 INSERT INTO user_library_access (user_id, library_id) VALUES ('user-1', 'podcast'); \
+                 INSERT INTO books (id, library_id, title, path, hash) VALUES ('show', 'podcast', 'Show', 'https://example.com/feed', 'show'); \
+                 INSERT INTO chapters (id, book_id, title, path, duration, chapter_index) VALUES \
+                    ('ep-1', 'show', 'One', 'https://example.com/1.mp3', 600, 1), \
+                    ('ep-2', 'show', 'Two', 'https://example.com/2.mp3', 600, 2), \
+                    ('ep-3', 'show', 'Three', 'https://example.com/3.mp3', 600, 3); \
+                 INSERT INTO episode_downloads (chapter_id, library_id, file_path, file_size, source_url, downloaded_at) VALUES \
+                    ('ep-1', 'podcast', '/episodes/ep-1.mp3', 1, 'https://example.com/1.mp3', '2026-01-01T00:00:00Z'), \
+                    ('ep-2', 'podcast', '/episodes/ep-2.mp3', 1, 'https://example.com/2.mp3', '2026-01-01T00:00:00Z'), \
+                    ('ep-3', 'podcast', '/episodes/ep-3.mp3', 1, 'https://example.com/3.mp3', '2026-01-01T00:00:00Z'); \
+                 INSERT INTO progress (id, user_id, book_id, chapter_id, position, duration) VALUES \
+                    ('p-1', 'admin', 'show', 'ep-1', 590, 600), ('p-2', 'user-1', 'show', 'ep-1', 580, 600), \
+                    ('p-3', 'admin', 'show', 'ep-2', 600, 600), ('p-4', 'user-1', 'show', 'ep-2', 120, 600), \
+                    ('p-5', 'user-2', 'show', 'ep-1', 10, 600);",
+            )?;
+            Ok(())
+        })
+        .await
+        .expect("seed test database");
+        let repo = LibraryRepository::new(db);
+
+        // user-2 cannot see the library, so their progress does not matter
+        assert_eq!(
+            repo.find_episodes_played_by_all("podcast").await.unwrap(),
+            vec!["ep-1".to_string()]
+        );
+    }
+}
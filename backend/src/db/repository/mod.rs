@@ -1,5 +1,6 @@
 pub mod base;
 pub mod book;
+pub mod bookmark;
 pub mod chapter;
 pub mod favorite;
 pub mod library;
@@ -9,11 +10,13 @@ pub mod progress;
 pub mod series;
 pub mod system_settings;
 pub mod task;
+pub mod task_schedule;
 pub mod user;
 pub mod user_settings;
 
 pub use base::Repository;
 pub use book::BookRepository;
+pub use bookmark::BookmarkRepository;
 pub use chapter::ChapterRepository;
 pub use favorite::FavoriteRepository;
 pub use library::LibraryRepository;
@@ -22,6 +25,7 @@ pub use playlist::PlaylistRepository;
 pub use progress::ProgressRepository;
 pub use series::SeriesRepository;
 pub use system_settings::SystemSettingsRepository;
-pub use task::TaskRepository;
+pub use task::{TaskEvent, TaskRepository};
+pub use task_schedule::TaskScheduleRepository;
 pub use user::UserRepository;
 pub use user_settings::UserSettingsRepository;
@@ -1,12 +1,22 @@
 use crate::core::error::{Result, TingError};
 use crate::db::manager::DatabaseManager;
-use crate::db::models::{Book, Playlist, PlaylistBook, PlaylistItem};
+use crate::db::models::{
+    Book, ListeningStatus, Playlist, PlaylistBook, PlaylistItem, SmartPlaylistRules,
+    SmartPlaylistSort,
+};
 use crate::db::repository::base::Repository;
 use crate::db::repository::book::map_book_row;
+use crate::db::repository::progress::{COMPLETION_RATIO, COMPLETION_TAIL_SECS};
 use async_trait::async_trait;
 use rusqlite::OptionalExtension;
 use std::sync::Arc;
 
+const PLAYLIST_COLUMNS: &str =
+    "id, user_id, title, description, playlist_type, smart_rules, created_at, updated_at";
+
+/// Most books a smart playlist resolves to
+pub const MAX_SMART_PLAYLIST_BOOKS: u32 = 500;
+
 /// Repository for user playlists
 pub struct PlaylistRepository {
     db: Arc<DatabaseManager>,
@@ -22,10 +32,11 @@ impl PlaylistRepository {
         self.db
             .execute(move |conn| {
                 let mut stmt = conn
-                    .prepare(
-                        "SELECT id, user_id, title, description, created_at, updated_at \
-                         FROM playlists WHERE user_id = ? ORDER BY updated_at DESC, created_at DESC",
-                    )
+                    .prepare(&format!(
+                        "SELECT {} FROM playlists WHERE user_id = ? \
+                         ORDER BY updated_at DESC, created_at DESC",
+                        PLAYLIST_COLUMNS
+                    ))
                     .map_err(TingError::DatabaseError)?;
 
                 let playlists = stmt
@@ -49,8 +60,10 @@ impl PlaylistRepository {
         self.db
             .execute(move |conn| {
                 conn.query_row(
-                    "SELECT id, user_id, title, description, created_at, updated_at \
-                     FROM playlists WHERE id = ? AND user_id = ?",
+                    &format!(
+                        "SELECT {} FROM playlists WHERE id = ? AND user_id = ?",
+                        PLAYLIST_COLUMNS
+                    ),
                     [&playlist_id, &user_id],
                     map_playlist_row,
                 )
@@ -107,6 +120,128 @@ impl PlaylistRepository {
             .await
     }
 
+    /// Books currently matching a smart playlist's rules, in the rules' order
+    pub async fn find_books_by_rules(
+        &self,
+        rules: &SmartPlaylistRules,
+        user_id: &str,
+        is_admin: bool,
+    ) -> Result<Vec<Book>> {
+        let rules = rules.clone();
+        let user_id = user_id.to_string();
+        self.db
+            .execute(move |conn| {
+                let mut query = "SELECT b.id, b.library_id, b.title, b.author, b.narrator, b.cover_url, b.theme_color, \
+                             b.description, b.skip_intro, b.skip_outro, b.path, b.hash, b.tags, b.genre, b.year, b.created_at, \
+                             b.manual_corrected, b.match_pattern, b.chapter_regex \
+                             FROM books b"
+                    .to_string();
+                let mut params: Vec<String> = Vec::new();
+                let mut conditions: Vec<String> = Vec::new();
+
+                if !rules.library_ids.is_empty() {
+                    conditions.push(format!(
+                        "b.library_id IN ({})",
+                        vec!["?"; rules.library_ids.len()].join(", ")
+                    ));
+                    params.extend(rules.library_ids.iter().cloned());
+                }
+
+                for (column, values) in [
+                    ("b.author", &rules.authors),
+                    ("b.narrator", &rules.narrators),
+                    ("b.tags", &rules.tags),
+                    ("b.genre", &rules.genres),
+                ] {
+                    if values.is_empty() {
+                        continue;
+                    }
+                    conditions.push(format!(
+                        "({})",
+                        vec![format!("{} LIKE ? ESCAPE '\\'", column); values.len()].join(" OR ")
+                    ));
+                    params.extend(values.iter().map(|value| contains_pattern(value)));
+                }
+
+                if let Some(year) = rules.year_from {
+                    conditions.push(format!("b.year >= {}", year));
+                }
+                if let Some(year) = rules.year_to {
+                    conditions.push(format!("b.year <= {}", year));
+                }
+                if let Some(days) = rules.added_within_days {
+                    conditions.push(format!(
+                        "DATETIME(b.created_at) >= DATETIME('now', '-{} days')",
+                        days
+                    ));
+                }
+
+                let started = "EXISTS (SELECT 1 FROM progress p WHERE p.user_id = ? AND p.book_id = b.id)";
+                let finished = finished_condition();
+                let status_conditions = match rules.listening_status {
+                    Some(ListeningStatus::NotStarted) => vec![format!("NOT {}", started)],
+                    Some(ListeningStatus::InProgress) => {
+                        vec![started.to_string(), format!("NOT {}", finished)]
+                    }
+                    Some(ListeningStatus::Finished) => vec![finished],
+                    None if rules.unfinished => vec![format!("NOT {}", finished)],
+                    None => Vec::new(),
+                };
+                for condition in status_conditions {
+                    conditions.push(condition);
+                    params.push(user_id.clone());
+                }
+
+                if !is_admin {
+                    conditions.push("(
+                        b.library_id IN (SELECT library_id FROM user_library_access WHERE user_id = ?)
+                        OR
+                        b.id IN (SELECT book_id FROM user_book_access WHERE user_id = ?)
+                    )".to_string());
+                    params.push(user_id.clone());
+                    params.push(user_id.clone());
+                }
+
+                if !conditions.is_empty() {
+                    query += " WHERE ";
+                    query += &conditions.join(" AND ");
+                }
+
+                let descending = rules.descending.unwrap_or(matches!(
+                    rules.sort,
+                    SmartPlaylistSort::Added | SmartPlaylistSort::LastPlayed
+                ));
+                let direction = if descending { "DESC" } else { "ASC" };
+                let key = match rules.sort {
+                    SmartPlaylistSort::Added => "b.created_at".to_string(),
+                    SmartPlaylistSort::Title => "b.title COLLATE NOCASE".to_string(),
+                    SmartPlaylistSort::Author => "b.author COLLATE NOCASE".to_string(),
+                    SmartPlaylistSort::Year => "b.year".to_string(),
+                    SmartPlaylistSort::LastPlayed => {
+                        params.push(user_id.clone());
+                        "(SELECT MAX(p.updated_at) FROM progress p WHERE p.user_id = ? AND p.book_id = b.id)"
+                            .to_string()
+                    }
+                };
+                query += &format!(
+                    " ORDER BY {} {}, b.title COLLATE NOCASE ASC, b.id ASC LIMIT {}",
+                    key,
+                    direction,
+                    rules.limit.unwrap_or(MAX_SMART_PLAYLIST_BOOKS).min(MAX_SMART_PLAYLIST_BOOKS)
+                );
+
+                let mut stmt = conn.prepare(&query).map_err(TingError::DatabaseError)?;
+                let books = stmt
+                    .query_map(rusqlite::params_from_iter(params.iter()), map_book_row)
+                    .map_err(TingError::DatabaseError)?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(TingError::DatabaseError)?;
+
+                Ok(books)
+            })
+            .await
+    }
+
     pub async fn find_items_by_playlist(&self, playlist_id: &str) -> Result<Vec<PlaylistItem>> {
         let playlist_id = playlist_id.to_string();
         self.db
@@ -482,14 +617,95 @@ impl PlaylistRepository {
     }
 }
 
+/// Parse the rule set stored on a smart playlist
+pub fn smart_rules(playlist: &Playlist) -> Result<SmartPlaylistRules> {
+    let rules = playlist.smart_rules.as_deref().unwrap_or("{}");
+    serde_json::from_str(rules).map_err(|e| {
+        TingError::SerializationError(format!(
+            "Invalid rules on smart playlist {}: {}",
+            playlist.id, e
+        ))
+    })
+}
+
+/// Validate a rule set and encode it for `playlists.smart_rules`
+pub fn encode_smart_rules(rules: SmartPlaylistRules) -> Result<String> {
+    let rules = normalize_smart_rules(rules)?;
+    serde_json::to_string(&rules).map_err(|e| {
+        TingError::SerializationError(format!("Failed to serialize smart playlist rules: {}", e))
+    })
+}
+
+/// Trim rule values and reject rule sets that cannot match anything sensible
+fn normalize_smart_rules(mut rules: SmartPlaylistRules) -> Result<SmartPlaylistRules> {
+    for values in [
+        &mut rules.library_ids,
+        &mut rules.authors,
+        &mut rules.narrators,
+        &mut rules.tags,
+        &mut rules.genres,
+    ] {
+        *values = values
+            .iter()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .collect();
+    }
+
+    if let (Some(from), Some(to)) = (rules.year_from, rules.year_to) {
+        if from > to {
+            return Err(TingError::InvalidRequest(
+                "year_from cannot be after year_to".to_string(),
+            ));
+        }
+    }
+    if rules.added_within_days == Some(0) {
+        return Err(TingError::InvalidRequest(
+            "added_within_days must be at least 1".to_string(),
+        ));
+    }
+    if let Some(limit) = rules.limit {
+        if limit == 0 || limit > MAX_SMART_PLAYLIST_BOOKS {
+            return Err(TingError::InvalidRequest(format!(
+                "limit must be between 1 and {}",
+                MAX_SMART_PLAYLIST_BOOKS
+            )));
+        }
+    }
+    if rules.unfinished && rules.listening_status == Some(ListeningStatus::Finished) {
+        return Err(TingError::InvalidRequest(
+            "unfinished cannot be combined with the finished listening status".to_string(),
+        ));
+    }
+
+    Ok(rules)
+}
+
+/// Whether the user's progress on book `b` reached the end of its last chapter
+fn finished_condition() -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM progress p JOIN chapters c ON c.id = p.chapter_id \
+         WHERE p.user_id = ? AND p.book_id = b.id AND c.is_extra = 0 \
+           AND NOT EXISTS (SELECT 1 FROM chapters n WHERE n.book_id = b.id AND n.is_extra = 0 \
+                           AND n.chapter_index > c.chapter_index) \
+           AND COALESCE(p.duration, c.duration) > 0 \
+           AND (p.position >= COALESCE(p.duration, c.duration) * {ratio} \
+                OR p.position >= COALESCE(p.duration, c.duration) - {tail}))",
+        ratio = COMPLETION_RATIO,
+        tail = COMPLETION_TAIL_SECS,
+    )
+}
+
 fn map_playlist_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Playlist> {
     Ok(Playlist {
         id: row.get(0)?,
         user_id: row.get(1)?,
         title: row.get(2)?,
         description: row.get(3)?,
-        created_at: row.get(4)?,
-        updated_at: row.get(5)?,
+        playlist_type: row.get(4)?,
+        smart_rules: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
     })
 }
 
@@ -500,8 +716,7 @@ impl Repository<Playlist> for PlaylistRepository {
         self.db
             .execute(move |conn| {
                 conn.query_row(
-                    "SELECT id, user_id, title, description, created_at, updated_at \
-                     FROM playlists WHERE id = ?",
+                    &format!("SELECT {} FROM playlists WHERE id = ?", PLAYLIST_COLUMNS),
                     [&id],
                     map_playlist_row,
                 )
@@ -515,10 +730,10 @@ impl Repository<Playlist> for PlaylistRepository {
         self.db
             .execute(|conn| {
                 let mut stmt = conn
-                    .prepare(
-                        "SELECT id, user_id, title, description, created_at, updated_at \
-                         FROM playlists ORDER BY updated_at DESC, created_at DESC",
-                    )
+                    .prepare(&format!(
+                        "SELECT {} FROM playlists ORDER BY updated_at DESC, created_at DESC",
+                        PLAYLIST_COLUMNS
+                    ))
                     .map_err(TingError::DatabaseError)?;
 
                 let playlists = stmt
@@ -537,13 +752,15 @@ impl Repository<Playlist> for PlaylistRepository {
         self.db
             .execute(move |conn| {
                 conn.execute(
-                    "INSERT INTO playlists (id, user_id, title, description, created_at, updated_at) \
-                     VALUES (?, ?, ?, ?, ?, ?)",
+                    "INSERT INTO playlists (id, user_id, title, description, playlist_type, \
+                     smart_rules, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
                     rusqlite::params![
                         &playlist.id,
                         &playlist.user_id,
                         &playlist.title,
                         &playlist.description,
+                        &playlist.playlist_type,
+                        &playlist.smart_rules,
                         &playlist.created_at,
                         &playlist.updated_at,
                     ],
@@ -559,11 +776,12 @@ impl Repository<Playlist> for PlaylistRepository {
         self.db
             .execute(move |conn| {
                 conn.execute(
-                    "UPDATE playlists SET title = ?, description = ?, \
+                    "UPDATE playlists SET title = ?, description = ?, smart_rules = ?, \
                      updated_at = STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ? AND user_id = ?",
                     rusqlite::params![
                         &playlist.title,
                         &playlist.description,
+                        &playlist.smart_rules,
                         &playlist.id,
                         &playlist.user_id,
                     ],
@@ -585,3 +803,166 @@ impl Repository<Playlist> for PlaylistRepository {
             .await
     }
 }
+
+/// `LIKE` pattern matching `value` anywhere, with its wildcards taken literally
+fn contains_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn create_repository() -> PlaylistRepository {
+        let db = Arc::new(DatabaseManager::new_in_memory().expect("create test database"));
+        db.execute(|conn| {
+            conn.execute_batch(
+                "INSERT INTO users (id, username, password_hash, role) VALUES ('user-1', 'tester', 'hash', 'user'); \
+                 INSERT INTO libraries (id, name, type, url) VALUES ('library-1', 'One', 'local', ''), ('library-2', 'Two', 'local', ''); \
+                 INSERT INTO user_library_access (user_id, library_id) VALUES ('user-1', 'library-1'); \
+                 INSERT INTO books (id, library_id, title, author, narrator, genre, year, path, hash, created_at) VALUES \
+                    ('book-a', 'library-1', 'Alpha', 'Ann Author', 'Ned', 'Fantasy', 1990, '/a', 'a', '2020-01-01 00:00:00'), \
+                    ('book-b', 'library-1', 'Beta', 'Ann Author', 'Nora', 'Fantasy', 2005, '/b', 'b', '2021-01-01 00:00:00'), \
+                    ('book-c', 'library-1', 'Gamma', 'Bob Writer', 'Ned', 'Mystery', 2010, '/c', 'c', '2022-01-01 00:00:00'), \
+                    ('book-d', 'library-2', 'Delta', 'Ann Author', 'Ned', 'Fantasy', 2015, '/d', 'd', '2023-01-01 00:00:00'); \
+                 INSERT INTO chapters (id, book_id, title, path, duration, chapter_index) VALUES \
+                    ('a-1', 'book-a', 'One', '/a1', 600, 1), ('a-2', 'book-a', 'Two', '/a2', 600, 2), \
+                    ('b-1', 'book-b', 'One', '/b1', 600, 1), ('b-2', 'book-b', 'Two', '/b2', 600, 2); \
+                 INSERT INTO progress (id, user_id, book_id, chapter_id, position, duration, updated_at) VALUES \
+                    ('p-a', 'user-1', 'book-a', 'a-2', 590, 600, '2024-01-02T00:00:00Z'), \
+                    ('p-b', 'user-1', 'book-b', 'b-1', 590, 600, '2024-01-03T00:00:00Z');",
+            )?;
+            Ok(())
+        })
+        .await
+        .expect("seed test database");
+        PlaylistRepository::new(db)
+    }
+
+    async fn titles(
+        repository: &PlaylistRepository,
+        rules: SmartPlaylistRules,
+        is_admin: bool,
+    ) -> Vec<String> {
+        repository
+            .find_books_by_rules(&rules, "user-1", is_admin)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|book| book.title)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn smart_rules_filter_by_metadata_and_access() {
+        let repository = create_repository().await;
+
+        let rules = SmartPlaylistRules {
+            authors: vec!["ann".to_string()],
+            genres: vec!["fantasy".to_string()],
+            sort: SmartPlaylistSort::Title,
+            ..Default::default()
+        };
+        assert_eq!(
+            titles(&repository, rules.clone(), false).await,
+            ["Alpha", "Beta"]
+        );
+        assert_eq!(
+            titles(&repository, rules, true).await,
+            ["Alpha", "Beta", "Delta"]
+        );
+
+        let rules = SmartPlaylistRules {
+            year_from: Some(2000),
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(titles(&repository, rules, false).await, ["Gamma"]);
+    }
+
+    #[tokio::test]
+    async fn smart_rules_match_wildcards_literally() {
+        let repository = create_repository().await;
+        repository
+            .db
+            .execute(|conn| {
+                conn.execute_batch(
+                    "UPDATE books SET tags = 'sleep_aid' WHERE id = 'book-a'; \
+                     UPDATE books SET tags = 'sleepyaid' WHERE id = 'book-b'; \
+                     UPDATE books SET tags = '100% cotton' WHERE id = 'book-c';",
+                )?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        let with_tag = |tag: &str| SmartPlaylistRules {
+            tags: vec![tag.to_string()],
+            sort: SmartPlaylistSort::Title,
+            ..Default::default()
+        };
+        assert_eq!(
+            titles(&repository, with_tag("sleep_aid"), false).await,
+            ["Alpha"]
+        );
+        assert_eq!(titles(&repository, with_tag("0%"), false).await, ["Gamma"]);
+        assert_eq!(titles(&repository, with_tag("%"), false).await, ["Gamma"]);
+    }
+
+    #[tokio::test]
+    async fn smart_rules_follow_listening_status() {
+        let repository = create_repository().await;
+        let with_status = |status| SmartPlaylistRules {
+            listening_status: Some(status),
+            sort: SmartPlaylistSort::Title,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            titles(&repository, with_status(ListeningStatus::Finished), false).await,
+            ["Alpha"]
+        );
+        assert_eq!(
+            titles(&repository, with_status(ListeningStatus::InProgress), false).await,
+            ["Beta"]
+        );
+        assert_eq!(
+            titles(&repository, with_status(ListeningStatus::NotStarted), false).await,
+            ["Gamma"]
+        );
+
+        let rules = SmartPlaylistRules {
+            unfinished: true,
+            sort: SmartPlaylistSort::LastPlayed,
+            ..Default::default()
+        };
+        assert_eq!(titles(&repository, rules, false).await, ["Beta", "Gamma"]);
+    }
+
+    #[test]
+    fn encoding_rules_trims_and_validates() {
+        let encoded = encode_smart_rules(SmartPlaylistRules {
+            tags: vec![" sleep ".to_string(), " ".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        let decoded: SmartPlaylistRules = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decoded.tags, ["sleep"]);
+
+        assert!(encode_smart_rules(SmartPlaylistRules {
+            year_from: Some(2020),
+            year_to: Some(2000),
+            ..Default::default()
+        })
+        .is_err());
+        assert!(encode_smart_rules(SmartPlaylistRules {
+            limit: Some(MAX_SMART_PLAYLIST_BOOKS + 1),
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
@@ -1,8 +1,19 @@
 use crate::core::error::{Result, TingError};
-use crate::db::{manager::DatabaseManager, models::Progress};
+use crate::core::time::local_date;
+use crate::db::{
+    manager::DatabaseManager,
+    models::{Progress, ProgressEvent},
+    repository::system_settings::application_time_zone,
+};
 use rusqlite::{params_from_iter, OptionalExtension, ToSql};
+use std::collections::HashMap;
 use std::sync::Arc;
 
+/// A chapter counts as finished once playback passes this share of it...
+pub const COMPLETION_RATIO: f64 = 0.95;
+/// ...or gets this close to its end
+pub const COMPLETION_TAIL_SECS: f64 = 30.0;
+
 /// Repository for Progress entities
 pub struct ProgressRepository {
     db: Arc<DatabaseManager>,
@@ -302,149 +313,322 @@ impl ProgressRepository {
     /// Upsert progress (insert or update)
     pub async fn upsert(&self, progress: &Progress) -> Result<()> {
         let progress = progress.clone();
-        self.db.transaction(move |conn| {
-            let progress_position: Option<f64> = if progress.chapter_id.is_none() {
-                conn.query_row(
-                    "SELECT position FROM progress WHERE user_id = ? AND book_id = ? AND chapter_id IS NULL",
-                    rusqlite::params![&progress.user_id, &progress.book_id],
-                    |row| row.get(0),
-                ).optional().map_err(TingError::DatabaseError)?
-            } else {
-                conn.query_row(
-                    "SELECT position FROM progress WHERE user_id = ? AND book_id = ? AND chapter_id = ?",
-                    rusqlite::params![&progress.user_id, &progress.book_id, &progress.chapter_id],
-                    |row| row.get(0),
-                ).optional().map_err(TingError::DatabaseError)?
-            };
-            let previous_position: Option<f64> = if progress_position.is_some() {
-                progress_position
-            } else if progress.chapter_id.is_none() {
-                conn.query_row(
-                    "SELECT position FROM listening_events WHERE user_id = ? AND book_id = ? AND chapter_id IS NULL ORDER BY created_at DESC LIMIT 1",
-                    rusqlite::params![&progress.user_id, &progress.book_id],
-                    |row| row.get(0),
-                ).optional().map_err(TingError::DatabaseError)?
-            } else {
-                conn.query_row(
-                    "SELECT position FROM listening_events WHERE user_id = ? AND book_id = ? AND chapter_id = ? ORDER BY created_at DESC LIMIT 1",
-                    rusqlite::params![&progress.user_id, &progress.book_id, &progress.chapter_id],
-                    |row| row.get(0),
-                ).optional().map_err(TingError::DatabaseError)?
-            };
-
-            let listen_seconds = if !progress.position.is_finite() || progress.position <= 0.0 {
-                0.0
-            } else if let Some(previous) = previous_position {
-                (progress.position - previous).max(0.0)
-            } else {
-                progress.position
-            };
-
-            conn.execute(
-                "INSERT INTO listening_events (\
-                    id, aggregate_key, user_id, book_id, chapter_id, activity_date, position, duration, \
-                    listen_seconds, progress_updates, created_at, last_active_at\
-                 ) VALUES (\
-                    ?, ? || CHAR(31) || ? || CHAR(31) || COALESCE(?, '') || CHAR(31) || STRFTIME('%Y-%m-%d', 'now'), \
-                    ?, ?, ?, STRFTIME('%Y-%m-%d', 'now'), ?, ?, ?, 1, \
-                    STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now'), STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now')\
-                 ) ON CONFLICT(aggregate_key) DO UPDATE SET \
-                    position = excluded.position, \
-                    duration = excluded.duration, \
-                    listen_seconds = listening_events.listen_seconds + excluded.listen_seconds, \
-                    progress_updates = listening_events.progress_updates + 1, \
-                    last_active_at = excluded.last_active_at",
-                rusqlite::params![
+        self.db
+            .transaction(move |conn| {
+                let now = chrono::Utc::now();
+                let chapter_id = progress.chapter_id.as_deref();
+                let previous_position =
+                    previous_position(conn, &progress.user_id, &progress.book_id, chapter_id)?;
+                let listen_seconds = listened_since(previous_position, progress.position);
+                record_listening(
+                    conn,
                     &progress.id,
                     &progress.user_id,
                     &progress.book_id,
-                    &progress.chapter_id,
-                    &progress.user_id,
-                    &progress.book_id,
-                    &progress.chapter_id,
+                    chapter_id,
                     progress.position,
                     progress.duration,
                     listen_seconds,
-                ],
+                    &now,
+                )?;
+                write_progress(conn, &progress, &sqlite_timestamp(&now), None)
+            })
+            .await
+    }
+
+    /// Merge progress recorded offline by one device and return the resulting rows.
+    ///
+    /// Events are replayed in the order they were recorded:
+    /// - events no newer than the device's last upload are skipped, so a
+    ///   retried batch is not counted twice;
+    /// - every other event adds the position gained since the chapter's
+    ///   previous position to the listening statistics of the day it was
+    ///   recorded;
+    /// - an event replaces the stored position only when it was recorded after
+    ///   the stored row was last written, so stale uploads never win.
+    ///
+    /// Returns how many events replaced a stored position, and the current
+    /// progress of every chapter the batch touched.
+    pub async fn sync_events(
+        &self,
+        user_id: &str,
+        device_id: &str,
+        mut events: Vec<ProgressEvent>,
+    ) -> Result<(usize, Vec<Progress>)> {
+        let user_id = user_id.to_string();
+        let device_id = device_id.to_string();
+        events.sort_by_key(|event| event.recorded_at);
+        self.db
+            .transaction(move |conn| {
+                let last_event_at: Option<String> = conn
+                    .query_row(
+                        "SELECT last_event_at FROM progress_sync_devices WHERE user_id = ? AND device_id = ?",
+                        rusqlite::params![&user_id, &device_id],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .map_err(TingError::DatabaseError)?;
+
+                let mut positions: HashMap<(String, Option<String>), Option<f64>> = HashMap::new();
+                let mut applied = 0;
+                let mut newest = None;
+                for event in &events {
+                    let recorded_at = sqlite_timestamp(&event.recorded_at);
+                    let key = (event.book_id.clone(), event.chapter_id.clone());
+                    let chapter_id = event.chapter_id.as_deref();
+                    if !positions.contains_key(&key) {
+                        let position = previous_position(conn, &user_id, &event.book_id, chapter_id)?;
+                        positions.insert(key.clone(), position);
+                    }
+                    if last_event_at
+                        .as_deref()
+                        .is_some_and(|last| recorded_at.as_str() <= last)
+                    {
+                        continue;
+                    }
+                    newest = Some(recorded_at.clone());
+
+                    let id = uuid::Uuid::new_v4().to_string();
+                    let listen_seconds = listened_since(positions[&key], event.position);
+                    positions.insert(key, Some(event.position));
+                    record_listening(
+                        conn,
+                        &id,
+                        &user_id,
+                        &event.book_id,
+                        chapter_id,
+                        event.position,
+                        event.duration,
+                        listen_seconds,
+                        &event.recorded_at,
+                    )?;
+
+                    let is_newer: bool = conn
+                        .query_row(
+                            "SELECT NOT EXISTS (SELECT 1 FROM progress WHERE user_id = ? AND book_id = ? \
+                             AND chapter_id IS ? AND julianday(updated_at) >= julianday(?))",
+                            rusqlite::params![&user_id, &event.book_id, chapter_id, &recorded_at],
+                            |row| row.get(0),
+                        )
+                        .map_err(TingError::DatabaseError)?;
+                    if is_newer {
+                        let progress = Progress {
+                            id,
+                            user_id: user_id.clone(),
+                            book_id: event.book_id.clone(),
+                            chapter_id: event.chapter_id.clone(),
+                            position: event.position,
+                            duration: event.duration,
+                            updated_at: recorded_at.clone(),
+                        };
+                        write_progress(conn, &progress, &recorded_at, Some(&device_id))?;
+                        applied += 1;
+                    }
+                }
+
+                if let Some(newest) = newest {
+                    conn.execute(
+                        "INSERT INTO progress_sync_devices (user_id, device_id, last_event_at) VALUES (?, ?, ?) \
+                         ON CONFLICT(user_id, device_id) DO UPDATE SET last_event_at = excluded.last_event_at",
+                        rusqlite::params![&user_id, &device_id, &newest],
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                }
+
+                let mut canonical = Vec::with_capacity(positions.len());
+                for (book_id, chapter_id) in positions.keys() {
+                    let progress = conn
+                        .query_row(
+                            "SELECT id, user_id, book_id, chapter_id, position, duration, updated_at \
+                             FROM progress WHERE user_id = ? AND book_id = ? AND chapter_id IS ?",
+                            rusqlite::params![&user_id, book_id, chapter_id],
+                            |row| {
+                                Ok(Progress {
+                                    id: row.get(0)?,
+                                    user_id: row.get(1)?,
+                                    book_id: row.get(2)?,
+                                    chapter_id: row.get(3)?,
+                                    position: row.get(4)?,
+                                    duration: row.get(5)?,
+                                    updated_at: row.get(6)?,
+                                })
+                            },
+                        )
+                        .optional()
+                        .map_err(TingError::DatabaseError)?;
+                    canonical.extend(progress);
+                }
+                canonical.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+                Ok((applied, canonical))
+            })
+            .await
+    }
+}
+
+/// Timestamp in the format SQLite's `STRFTIME('%Y-%m-%dT%H:%M:%fZ')` writes
+fn sqlite_timestamp(at: &chrono::DateTime<chrono::Utc>) -> String {
+    at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Seconds listened when playback moved from `previous` to `position`
+fn listened_since(previous: Option<f64>, position: f64) -> f64 {
+    if !position.is_finite() || position <= 0.0 {
+        0.0
+    } else if let Some(previous) = previous {
+        (position - previous).max(0.0)
+    } else {
+        position
+    }
+}
+
+/// Last known position of a chapter, from progress or else from the statistics
+fn previous_position(
+    conn: &rusqlite::Connection,
+    user_id: &str,
+    book_id: &str,
+    chapter_id: Option<&str>,
+) -> Result<Option<f64>> {
+    let progress_position: Option<f64> = conn
+        .query_row(
+            "SELECT position FROM progress WHERE user_id = ? AND book_id = ? AND chapter_id IS ?",
+            rusqlite::params![user_id, book_id, chapter_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(TingError::DatabaseError)?;
+    if progress_position.is_some() {
+        return Ok(progress_position);
+    }
+    conn.query_row(
+        "SELECT position FROM listening_events WHERE user_id = ? AND book_id = ? AND chapter_id IS ? \
+         ORDER BY created_at DESC LIMIT 1",
+        rusqlite::params![user_id, book_id, chapter_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(TingError::DatabaseError)
+}
+
+/// Add listening time to the daily events and the all-time totals.
+///
+/// `at` picks the day in the application time zone, so replayed offline events
+/// count where they happened.
+#[allow(clippy::too_many_arguments)]
+fn record_listening(
+    conn: &rusqlite::Connection,
+    event_id: &str,
+    user_id: &str,
+    book_id: &str,
+    chapter_id: Option<&str>,
+    position: f64,
+    duration: Option<f64>,
+    listen_seconds: f64,
+    at: &chrono::DateTime<chrono::Utc>,
+) -> Result<()> {
+    let date = local_date(at, application_time_zone(conn)?).to_string();
+    let timestamp = sqlite_timestamp(at);
+    conn.execute(
+        "INSERT INTO listening_events (\
+            id, aggregate_key, user_id, book_id, chapter_id, activity_date, position, duration, \
+            listen_seconds, progress_updates, created_at, last_active_at\
+         ) VALUES (\
+            ?1, ?2 || CHAR(31) || ?3 || CHAR(31) || COALESCE(?4, '') || CHAR(31) || ?5, \
+            ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1, ?9, ?9\
+         ) ON CONFLICT(aggregate_key) DO UPDATE SET \
+            position = CASE WHEN excluded.last_active_at >= listening_events.last_active_at \
+                THEN excluded.position ELSE listening_events.position END, \
+            duration = CASE WHEN excluded.last_active_at >= listening_events.last_active_at \
+                THEN excluded.duration ELSE listening_events.duration END, \
+            listen_seconds = listening_events.listen_seconds + excluded.listen_seconds, \
+            progress_updates = listening_events.progress_updates + 1, \
+            created_at = MIN(listening_events.created_at, excluded.created_at), \
+            last_active_at = MAX(listening_events.last_active_at, excluded.last_active_at)",
+        rusqlite::params![
+            event_id,
+            user_id,
+            book_id,
+            chapter_id,
+            &date,
+            position,
+            duration,
+            listen_seconds,
+            &timestamp,
+        ],
+    )
+    .map_err(TingError::DatabaseError)?;
+
+    conn.execute(
+        "INSERT INTO listening_totals (\
+            aggregate_key, user_id, book_id, chapter_id, listen_seconds, progress_updates, \
+            first_active_at, last_active_at\
+         ) VALUES (\
+            ?1 || CHAR(31) || ?2 || CHAR(31) || COALESCE(?3, ''), ?1, ?2, ?3, ?4, 1, ?5, ?5\
+         ) ON CONFLICT(aggregate_key) DO UPDATE SET \
+            listen_seconds = listening_totals.listen_seconds + excluded.listen_seconds, \
+            progress_updates = listening_totals.progress_updates + 1, \
+            first_active_at = MIN(listening_totals.first_active_at, excluded.first_active_at), \
+            last_active_at = MAX(listening_totals.last_active_at, excluded.last_active_at)",
+        rusqlite::params![user_id, book_id, chapter_id, listen_seconds, &timestamp],
+    )
+    .map_err(TingError::DatabaseError)?;
+    Ok(())
+}
+
+/// Store the position of a chapter, showing it in the history again.
+///
+/// `device_id` is set by offline sync and cleared by live updates.
+fn write_progress(
+    conn: &rusqlite::Connection,
+    progress: &Progress,
+    updated_at: &str,
+    device_id: Option<&str>,
+) -> Result<()> {
+    // Handle NULL chapter_id carefully since SQLite UNIQUE constraint treats NULLs as distinct
+    if progress.chapter_id.is_none() {
+        // If chapter_id is NULL, we just insert or update based on user_id and book_id
+        // This is a fallback case, normally chapter_id should be provided
+        let existing_id: Option<String> = conn
+            .query_row(
+                "SELECT id FROM progress WHERE user_id = ? AND book_id = ? AND chapter_id IS NULL",
+                rusqlite::params![&progress.user_id, &progress.book_id],
+                |row| row.get(0),
             )
-            .map_err(TingError::DatabaseError)?;
+            .optional()
+            .unwrap_or(None);
 
+        if let Some(id) = existing_id {
             conn.execute(
-                "INSERT INTO listening_totals (\
-                    aggregate_key, user_id, book_id, chapter_id, listen_seconds, progress_updates, \
-                    first_active_at, last_active_at\
-                 ) VALUES (\
-                    ? || CHAR(31) || ? || CHAR(31) || COALESCE(?, ''), ?, ?, ?, ?, 1, \
-                    STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now'), STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now')\
-                 ) ON CONFLICT(aggregate_key) DO UPDATE SET \
-                    listen_seconds = listening_totals.listen_seconds + excluded.listen_seconds, \
-                    progress_updates = listening_totals.progress_updates + 1, \
-                    last_active_at = excluded.last_active_at",
-                rusqlite::params![
-                    &progress.user_id,
-                    &progress.book_id,
-                    &progress.chapter_id,
-                    &progress.user_id,
-                    &progress.book_id,
-                    &progress.chapter_id,
-                    listen_seconds,
-                ],
+                "UPDATE progress \
+                 SET position = ?, duration = ?, updated_at = ?, device_id = ?, history_hidden_at = NULL \
+                 WHERE id = ?",
+                rusqlite::params![progress.position, progress.duration, updated_at, device_id, id],
             )
             .map_err(TingError::DatabaseError)?;
-
-            // Handle NULL chapter_id carefully since SQLite UNIQUE constraint treats NULLs as distinct
-            if progress.chapter_id.is_none() {
-                // If chapter_id is NULL, we just insert or update based on user_id and book_id
-                // This is a fallback case, normally chapter_id should be provided
-                let existing_id: Option<String> = conn.query_row(
-                    "SELECT id FROM progress WHERE user_id = ? AND book_id = ? AND chapter_id IS NULL",
-                    rusqlite::params![&progress.user_id, &progress.book_id],
-                    |row| row.get(0)
-                ).optional().unwrap_or(None);
-
-                if let Some(id) = existing_id {
-                    conn.execute(
-                        "UPDATE progress \
-                         SET position = ?, duration = ?, updated_at = STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now'), history_hidden_at = NULL \
-                         WHERE id = ?",
-                        rusqlite::params![progress.position, progress.duration, id],
-                    ).map_err(TingError::DatabaseError)?;
-                } else {
-                    conn.execute(
-                        "INSERT INTO progress (id, user_id, book_id, chapter_id, position, duration, updated_at, history_hidden_at) \
-                         VALUES (?, ?, ?, ?, ?, ?, STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now'), NULL)",
-                        rusqlite::params![
-                            &progress.id,
-                            &progress.user_id,
-                            &progress.book_id,
-                            &progress.chapter_id,
-                            progress.position,
-                            progress.duration,
-                        ],
-                    ).map_err(TingError::DatabaseError)?;
-                }
-            } else {
-                conn.execute(
-                    "INSERT INTO progress (id, user_id, book_id, chapter_id, position, duration, updated_at, history_hidden_at) \
-                     VALUES (?, ?, ?, ?, ?, ?, STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now'), NULL) \
-                     ON CONFLICT(user_id, book_id, chapter_id) DO UPDATE SET \
-                     position = excluded.position, \
-                     duration = excluded.duration, \
-                     updated_at = STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now'), \
-                     history_hidden_at = NULL",
-                    rusqlite::params![
-                        &progress.id,
-                        &progress.user_id,
-                        &progress.book_id,
-                        &progress.chapter_id,
-                        progress.position,
-                        progress.duration,
-                    ],
-                ).map_err(TingError::DatabaseError)?;
-            }
-            Ok(())
-        }).await
+            return Ok(());
+        }
     }
+    conn.execute(
+        "INSERT INTO progress (id, user_id, book_id, chapter_id, position, duration, updated_at, device_id, history_hidden_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, NULL) \
+         ON CONFLICT(user_id, book_id, chapter_id) DO UPDATE SET \
+         position = excluded.position, \
+         duration = excluded.duration, \
+         updated_at = excluded.updated_at, \
+         device_id = excluded.device_id, \
+         history_hidden_at = NULL",
+        rusqlite::params![
+            &progress.id,
+            &progress.user_id,
+            &progress.book_id,
+            &progress.chapter_id,
+            progress.position,
+            progress.duration,
+            updated_at,
+            device_id,
+        ],
+    )
+    .map_err(TingError::DatabaseError)?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -531,6 +715,112 @@ mod tests {
         assert_eq!(total_seconds, 12.0);
     }
 
+    fn event(position: f64, recorded_at: chrono::DateTime<chrono::Utc>) -> ProgressEvent {
+        ProgressEvent {
+            book_id: "book-1".to_string(),
+            chapter_id: Some("chapter-1".to_string()),
+            position,
+            duration: Some(100.0),
+            recorded_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn sync_keeps_newer_progress_and_replays_offline_listening() {
+        let (db, repository) = create_repository().await;
+        repository.upsert(&progress("event-1", 50.0)).await.unwrap();
+
+        let offline_at = chrono::Utc::now() - chrono::Duration::days(2);
+        let offline = vec![
+            event(30.0, offline_at + chrono::Duration::minutes(20)),
+            event(10.0, offline_at),
+        ];
+        let (accepted, canonical) = repository
+            .sync_events("user-1", "phone", offline.clone())
+            .await
+            .unwrap();
+        assert_eq!(accepted, 0);
+        assert_eq!(canonical.len(), 1);
+        assert_eq!(canonical[0].position, 50.0);
+
+        let offline_day = offline_at.format("%Y-%m-%d").to_string();
+        let (offline_seconds, offline_updates): (f64, i64) = db
+            .execute(move |conn| {
+                conn.query_row(
+                    "SELECT listen_seconds, progress_updates FROM listening_events WHERE activity_date = ?",
+                    [&offline_day],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .map_err(TingError::DatabaseError)
+            })
+            .await
+            .unwrap();
+        assert_eq!(offline_seconds, 20.0);
+        assert_eq!(offline_updates, 2);
+
+        // A retried upload is recognised and not counted again
+        let (accepted, _) = repository
+            .sync_events("user-1", "phone", offline)
+            .await
+            .unwrap();
+        assert_eq!(accepted, 0);
+
+        let (accepted, canonical) = repository
+            .sync_events("user-1", "phone", vec![event(60.0, chrono::Utc::now())])
+            .await
+            .unwrap();
+        assert_eq!(accepted, 1);
+        assert_eq!(canonical[0].position, 60.0);
+
+        let (updates, device_id): (i64, Option<String>) = db
+            .execute(|conn| {
+                conn.query_row(
+                    "SELECT (SELECT SUM(progress_updates) FROM listening_totals), \
+                     (SELECT device_id FROM progress)",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .map_err(TingError::DatabaseError)
+            })
+            .await
+            .unwrap();
+        assert_eq!(updates, 4);
+        assert_eq!(device_id.as_deref(), Some("phone"));
+    }
+
+    #[tokio::test]
+    async fn buckets_listening_by_the_application_time_zone() {
+        let (db, repository) = create_repository().await;
+        db.execute(|conn| {
+            conn.execute(
+                "INSERT INTO system_settings (key, value) VALUES ('application_time_zone', 'Asia/Shanghai')",
+                [],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        // 20:00 UTC is already the next day in Shanghai
+        let utc_day = (chrono::Utc::now() - chrono::Duration::days(2)).date_naive();
+        let recorded_at = utc_day.and_hms_opt(20, 0, 0).unwrap().and_utc();
+        repository
+            .sync_events("user-1", "phone", vec![event(10.0, recorded_at)])
+            .await
+            .unwrap();
+
+        let activity_date: String = db
+            .execute(|conn| {
+                conn.query_row("SELECT activity_date FROM listening_events", [], |row| {
+                    row.get(0)
+                })
+                .map_err(TingError::DatabaseError)
+            })
+            .await
+            .unwrap();
+        assert_eq!(activity_date, utc_day.succ_opt().unwrap().to_string());
+    }
+
     #[tokio::test]
     async fn creates_a_new_event_for_a_different_day() {
         let (db, repository) = create_repository().await;
@@ -1,5 +1,5 @@
 use crate::core::error::{Result, TingError};
-use crate::core::time::{parse_time_zone, DEFAULT_TIME_ZONE};
+use crate::core::time::{parse_time_zone, resolve_time_zone, DEFAULT_TIME_ZONE};
 use crate::db::manager::DatabaseManager;
 use rusqlite::OptionalExtension;
 use std::sync::Arc;
@@ -64,6 +64,21 @@ impl SystemSettingsRepository {
     }
 }
 
+/// Application time zone read on an open connection, UTC when unset or invalid
+pub fn application_time_zone(conn: &rusqlite::Connection) -> Result<chrono_tz::Tz> {
+    let time_zone: Option<String> = conn
+        .query_row(
+            "SELECT value FROM system_settings WHERE key = ?",
+            [APPLICATION_TIME_ZONE_KEY],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(TingError::DatabaseError)?;
+    Ok(resolve_time_zone(
+        time_zone.as_deref().unwrap_or(DEFAULT_TIME_ZONE),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::SystemSettingsRepository;
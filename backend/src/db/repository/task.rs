@@ -4,18 +4,91 @@ use crate::db::models::TaskRecord;
 use crate::db::repository::base::Repository;
 use async_trait::async_trait;
 use rusqlite::OptionalExtension;
+use serde::Serialize;
 use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber before the oldest are dropped
+const EVENT_CAPACITY: usize = 256;
+
+/// Change to a task, published once it has been written
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TaskEvent {
+    /// The task was created or its status changed
+    Status {
+        id: String,
+        /// Left out when only the status was updated
+        #[serde(skip_serializing_if = "Option::is_none")]
+        task_type: Option<String>,
+        status: String,
+        error: Option<String>,
+        retries: i32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parent_id: Option<String>,
+    },
+    /// The task reported progress
+    Progress {
+        id: String,
+        message: Option<String>,
+        message_key: Option<String>,
+        message_params: Option<serde_json::Value>,
+    },
+}
+
+impl TaskEvent {
+    /// Task the event is about
+    pub fn task_id(&self) -> &str {
+        match self {
+            TaskEvent::Status { id, .. } | TaskEvent::Progress { id, .. } => id,
+        }
+    }
+
+    fn for_record(task: &TaskRecord) -> Self {
+        TaskEvent::Status {
+            id: task.id.clone(),
+            task_type: Some(task.task_type.clone()),
+            status: task.status.clone(),
+            error: task.error.clone(),
+            retries: task.retries,
+            parent_id: task.parent_id.clone(),
+        }
+    }
+
+    /// Name of the event, as sent to subscribers
+    pub fn name(&self) -> &'static str {
+        match self {
+            TaskEvent::Status { .. } => "status",
+            TaskEvent::Progress { .. } => "progress",
+        }
+    }
+}
 
 /// Repository for TaskRecord entities
+///
+/// Clones share one event channel, so every status and progress write can be
+/// followed through `subscribe` without reading the tasks table.
 #[derive(Clone)]
 pub struct TaskRepository {
     db: Arc<DatabaseManager>,
+    events: broadcast::Sender<TaskEvent>,
 }
 
 impl TaskRepository {
     /// Create a new TaskRepository
     pub fn new(db: Arc<DatabaseManager>) -> Self {
-        Self { db }
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { db, events }
+    }
+
+    /// Receive the status and progress changes written from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.events.subscribe()
+    }
+
+    fn publish(&self, event: TaskEvent) {
+        // Nobody listening is not an error
+        let _ = self.events.send(event);
     }
 
     fn normalize_date(s: String) -> String {
@@ -64,31 +137,35 @@ impl TaskRepository {
         s
     }
 
+    fn map_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<TaskRecord> {
+        Ok(TaskRecord {
+            id: row.get(0)?,
+            task_type: row.get(1)?,
+            status: row.get(2)?,
+            payload: row.get(3)?,
+            message: row.get(4)?,
+            message_key: row.get(5)?,
+            message_params: row.get(6)?,
+            error: row.get(7)?,
+            retries: row.get(8)?,
+            max_retries: row.get(9)?,
+            created_at: Self::normalize_date(row.get(10)?),
+            updated_at: Self::normalize_date(row.get(11)?),
+            parent_id: row.get(12)?,
+            depends_on: row.get(13)?,
+        })
+    }
+
     /// Find tasks by status
     pub async fn find_by_status(&self, status: &str) -> Result<Vec<TaskRecord>> {
         let status = status.to_string();
         self.db.execute(move |conn| {
             let mut stmt = conn.prepare(
-                "SELECT id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at \
+                "SELECT id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at, parent_id, depends_on \
                  FROM tasks WHERE status = ? ORDER BY created_at DESC"
             ).map_err(TingError::DatabaseError)?;
 
-            let tasks = stmt.query_map([&status], |row| {
-                Ok(TaskRecord {
-                    id: row.get(0)?,
-                    task_type: row.get(1)?,
-                    status: row.get(2)?,
-                    payload: row.get(3)?,
-                    message: row.get(4)?,
-                    message_key: row.get(5)?,
-                    message_params: row.get(6)?,
-                    error: row.get(7)?,
-                    retries: row.get(8)?,
-                    max_retries: row.get(9)?,
-                    created_at: Self::normalize_date(row.get(10)?),
-                    updated_at: Self::normalize_date(row.get(11)?),
-                })
-            }).map_err(TingError::DatabaseError)?
+            let tasks = stmt.query_map([&status], Self::map_row).map_err(TingError::DatabaseError)?
             .collect::<std::result::Result<Vec<_>, _>>()
             .map_err(TingError::DatabaseError)?;
 
@@ -101,26 +178,11 @@ impl TaskRepository {
         let task_type = task_type.to_string();
         self.db.execute(move |conn| {
             let mut stmt = conn.prepare(
-                "SELECT id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at \
+                "SELECT id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at, parent_id, depends_on \
                  FROM tasks WHERE type = ? ORDER BY created_at DESC"
             ).map_err(TingError::DatabaseError)?;
 
-            let tasks = stmt.query_map([&task_type], |row| {
-                Ok(TaskRecord {
-                    id: row.get(0)?,
-                    task_type: row.get(1)?,
-                    status: row.get(2)?,
-                    payload: row.get(3)?,
-                    message: row.get(4)?,
-                    message_key: row.get(5)?,
-                    message_params: row.get(6)?,
-                    error: row.get(7)?,
-                    retries: row.get(8)?,
-                    max_retries: row.get(9)?,
-                    created_at: Self::normalize_date(row.get(10)?),
-                    updated_at: Self::normalize_date(row.get(11)?),
-                })
-            }).map_err(TingError::DatabaseError)?
+            let tasks = stmt.query_map([&task_type], Self::map_row).map_err(TingError::DatabaseError)?
             .collect::<std::result::Result<Vec<_>, _>>()
             .map_err(TingError::DatabaseError)?;
 
@@ -138,11 +200,11 @@ impl TaskRepository {
         sort_order: String,
     ) -> Result<(Vec<TaskRecord>, usize)> {
         self.db.execute(move |conn| {
-            // Build the WHERE clause
+            // Build the WHERE clause; workflow steps are listed under their workflow
             let where_clause = if status.is_some() {
-                "WHERE status = ?"
+                "WHERE parent_id IS NULL AND status = ?"
             } else {
-                ""
+                "WHERE parent_id IS NULL"
             };
 
             // Validate sort_by field
@@ -175,7 +237,7 @@ impl TaskRepository {
 
             // Build the main query with pagination
             let query = format!(
-                "SELECT id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at \
+                "SELECT id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at, parent_id, depends_on \
                  FROM tasks {} ORDER BY {} {} LIMIT ? OFFSET ?",
                 where_clause, sort_field, order
             );
@@ -185,44 +247,14 @@ impl TaskRepository {
             let tasks = if let Some(ref status_val) = status {
                 stmt.query_map(
                     rusqlite::params![status_val, page_size, offset],
-                    |row| {
-                        Ok(TaskRecord {
-                            id: row.get(0)?,
-                            task_type: row.get(1)?,
-                            status: row.get(2)?,
-                            payload: row.get(3)?,
-                            message: row.get(4)?,
-                            message_key: row.get(5)?,
-                            message_params: row.get(6)?,
-                            error: row.get(7)?,
-                            retries: row.get(8)?,
-                            max_retries: row.get(9)?,
-                            created_at: Self::normalize_date(row.get(10)?),
-                            updated_at: Self::normalize_date(row.get(11)?),
-                        })
-                    }
+                    Self::map_row
                 ).map_err(TingError::DatabaseError)?
                 .collect::<std::result::Result<Vec<_>, _>>()
                 .map_err(TingError::DatabaseError)?
             } else {
                 stmt.query_map(
                     rusqlite::params![page_size, offset],
-                    |row| {
-                        Ok(TaskRecord {
-                            id: row.get(0)?,
-                            task_type: row.get(1)?,
-                            status: row.get(2)?,
-                            payload: row.get(3)?,
-                            message: row.get(4)?,
-                            message_key: row.get(5)?,
-                            message_params: row.get(6)?,
-                            error: row.get(7)?,
-                            retries: row.get(8)?,
-                            max_retries: row.get(9)?,
-                            created_at: Self::normalize_date(row.get(10)?),
-                            updated_at: Self::normalize_date(row.get(11)?),
-                        })
-                    }
+                    Self::map_row
                 ).map_err(TingError::DatabaseError)?
                 .collect::<std::result::Result<Vec<_>, _>>()
                 .map_err(TingError::DatabaseError)?
@@ -232,8 +264,31 @@ impl TaskRepository {
         }).await
     }
 
+    /// Find the steps of a workflow in the order they were submitted
+    pub async fn find_children(&self, parent_id: &str) -> Result<Vec<TaskRecord>> {
+        let parent_id = parent_id.to_string();
+        self.db.execute(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at, parent_id, depends_on \
+                 FROM tasks WHERE parent_id = ? ORDER BY created_at, rowid"
+            ).map_err(TingError::DatabaseError)?;
+
+            let tasks = stmt.query_map([&parent_id], Self::map_row).map_err(TingError::DatabaseError)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(TingError::DatabaseError)?;
+
+            Ok(tasks)
+        }).await
+    }
+
     /// Update task progress message
     pub async fn update_progress(&self, id: &str, message: &str) -> Result<()> {
+        let event = TaskEvent::Progress {
+            id: id.to_string(),
+            message: Some(message.to_string()),
+            message_key: None,
+            message_params: None,
+        };
         let id = id.to_string();
         let message = message.to_string();
         self.db.execute(move |conn| {
@@ -242,7 +297,9 @@ impl TaskRepository {
                 rusqlite::params![&message, &id],
             ).map_err(TingError::DatabaseError)?;
             Ok(())
-        }).await
+        }).await?;
+        self.publish(event);
+        Ok(())
     }
 
     /// Update task progress with a frontend-localizable key.
@@ -252,6 +309,12 @@ impl TaskRepository {
         message_key: &str,
         message_params: serde_json::Value,
     ) -> Result<()> {
+        let event = TaskEvent::Progress {
+            id: id.to_string(),
+            message: None,
+            message_key: Some(message_key.to_string()),
+            message_params: Some(message_params.clone()),
+        };
         let id = id.to_string();
         let message_key = message_key.to_string();
         let message_params = message_params.to_string();
@@ -261,6 +324,21 @@ impl TaskRepository {
                 rusqlite::params![&message_key, &message_params, &id],
             ).map_err(TingError::DatabaseError)?;
             Ok(())
+        }).await?;
+        self.publish(event);
+        Ok(())
+    }
+
+    /// Replace the task payload, e.g. to store a checkpoint of its progress
+    pub async fn update_payload(&self, id: &str, payload: &str) -> Result<()> {
+        let id = id.to_string();
+        let payload = payload.to_string();
+        self.db.execute(move |conn| {
+            conn.execute(
+                "UPDATE tasks SET payload = ?, updated_at = STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?",
+                rusqlite::params![&payload, &id],
+            ).map_err(TingError::DatabaseError)?;
+            Ok(())
         }).await
     }
 
@@ -272,6 +350,14 @@ impl TaskRepository {
         error: Option<&str>,
         retries: i32,
     ) -> Result<()> {
+        let event = TaskEvent::Status {
+            id: id.to_string(),
+            task_type: None,
+            status: status.to_string(),
+            error: error.map(|s| s.to_string()),
+            retries,
+            parent_id: None,
+        };
         let id = id.to_string();
         let status = status.to_string();
         let error = error.map(|s| s.to_string());
@@ -281,7 +367,9 @@ impl TaskRepository {
                 rusqlite::params![&status, &error, retries, &id],
             ).map_err(TingError::DatabaseError)?;
             Ok(())
-        }).await
+        }).await?;
+        self.publish(event);
+        Ok(())
     }
 
     pub async fn delete_all(&self) -> Result<()> {
@@ -298,8 +386,12 @@ impl TaskRepository {
         let status = status.to_string();
         self.db
             .execute(move |conn| {
-                conn.execute("DELETE FROM tasks WHERE status = ?", [&status])
-                    .map_err(TingError::DatabaseError)?;
+                // Steps go with their workflow
+                conn.execute(
+                    "DELETE FROM tasks WHERE status = ? AND parent_id IS NULL",
+                    [&status],
+                )
+                .map_err(TingError::DatabaseError)?;
                 Ok(())
             })
             .await
@@ -330,25 +422,10 @@ impl Repository<TaskRecord> for TaskRepository {
         let id = id.to_string();
         self.db.execute(move |conn| {
             conn.query_row(
-                "SELECT id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at \
+                "SELECT id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at, parent_id, depends_on \
                  FROM tasks WHERE id = ?",
                 [&id],
-                |row| {
-                    Ok(TaskRecord {
-                        id: row.get(0)?,
-                        task_type: row.get(1)?,
-                        status: row.get(2)?,
-                        payload: row.get(3)?,
-                        message: row.get(4)?,
-                        message_key: row.get(5)?,
-                        message_params: row.get(6)?,
-                        error: row.get(7)?,
-                        retries: row.get(8)?,
-                        max_retries: row.get(9)?,
-                        created_at: Self::normalize_date(row.get(10)?),
-                        updated_at: Self::normalize_date(row.get(11)?),
-                    })
-                }
+                Self::map_row
             ).optional()
             .map_err(TingError::DatabaseError)
         }).await
@@ -357,26 +434,11 @@ impl Repository<TaskRecord> for TaskRepository {
     async fn find_all(&self) -> Result<Vec<TaskRecord>> {
         self.db.execute(|conn| {
             let mut stmt = conn.prepare(
-                "SELECT id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at \
+                "SELECT id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at, parent_id, depends_on \
                  FROM tasks ORDER BY created_at DESC"
             ).map_err(TingError::DatabaseError)?;
 
-            let tasks = stmt.query_map([], |row| {
-                Ok(TaskRecord {
-                    id: row.get(0)?,
-                    task_type: row.get(1)?,
-                    status: row.get(2)?,
-                    payload: row.get(3)?,
-                    message: row.get(4)?,
-                    message_key: row.get(5)?,
-                    message_params: row.get(6)?,
-                    error: row.get(7)?,
-                    retries: row.get(8)?,
-                    max_retries: row.get(9)?,
-                    created_at: Self::normalize_date(row.get(10)?),
-                    updated_at: Self::normalize_date(row.get(11)?),
-                })
-            }).map_err(TingError::DatabaseError)?
+            let tasks = stmt.query_map([], Self::map_row).map_err(TingError::DatabaseError)?
             .collect::<std::result::Result<Vec<_>, _>>()
             .map_err(TingError::DatabaseError)?;
 
@@ -385,11 +447,12 @@ impl Repository<TaskRecord> for TaskRepository {
     }
 
     async fn create(&self, task: &TaskRecord) -> Result<()> {
+        let event = TaskEvent::for_record(task);
         let task = task.clone();
         self.db.execute(move |conn| {
             conn.execute(
-                "INSERT INTO tasks (id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at) \
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                "INSERT INTO tasks (id, type, status, payload, message, message_key, message_params, error, retries, max_retries, created_at, updated_at, parent_id, depends_on) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
                 rusqlite::params![
                     &task.id,
                     &task.task_type,
@@ -403,13 +466,18 @@ impl Repository<TaskRecord> for TaskRepository {
                     &task.max_retries,
                     &task.created_at,
                     &task.updated_at,
+                    &task.parent_id,
+                    &task.depends_on,
                 ],
             ).map_err(TingError::DatabaseError)?;
             Ok(())
-        }).await
+        }).await?;
+        self.publish(event);
+        Ok(())
     }
 
     async fn update(&self, task: &TaskRecord) -> Result<()> {
+        let event = TaskEvent::for_record(task);
         let task = task.clone();
         self.db
             .execute(move |conn| {
@@ -433,7 +501,9 @@ impl Repository<TaskRecord> for TaskRepository {
                 .map_err(TingError::DatabaseError)?;
                 Ok(())
             })
-            .await
+            .await?;
+        self.publish(event);
+        Ok(())
     }
 
     async fn delete(&self, id: &str) -> Result<()> {
@@ -447,3 +517,55 @@ impl Repository<TaskRecord> for TaskRepository {
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn writes_are_published_to_subscribers() {
+        let repo = TaskRepository::new(Arc::new(DatabaseManager::new_in_memory().unwrap()));
+        let mut events = repo.subscribe();
+
+        repo.create(&TaskRecord {
+            id: "task-1".to_string(),
+            task_type: "library_scan".to_string(),
+            status: "queued".to_string(),
+            payload: None,
+            message: None,
+            message_key: None,
+            message_params: None,
+            error: None,
+            retries: 0,
+            max_retries: 3,
+            created_at: "2026-01-01T00:00:00.000Z".to_string(),
+            updated_at: "2026-01-01T00:00:00.000Z".to_string(),
+            parent_id: None,
+            depends_on: None,
+        })
+        .await
+        .unwrap();
+        repo.update_progress_key("task-1", "scan.local.scanning", serde_json::json!({}))
+            .await
+            .unwrap();
+        repo.update_payload("task-1", "{}").await.unwrap();
+        repo.update_status("task-1", "failed", Some("boom"), 1)
+            .await
+            .unwrap();
+
+        let created = events.recv().await.unwrap();
+        assert_eq!(created.name(), "status");
+        assert_eq!(
+            serde_json::to_value(&created).unwrap()["task_type"],
+            "library_scan"
+        );
+        let progress = serde_json::to_value(events.recv().await.unwrap()).unwrap();
+        assert_eq!(progress["event"], "progress");
+        assert_eq!(progress["message_key"], "scan.local.scanning");
+        let failed = serde_json::to_value(events.recv().await.unwrap()).unwrap();
+        assert_eq!(failed["status"], "failed");
+        assert_eq!(failed["error"], "boom");
+        assert!(failed.get("task_type").is_none());
+        assert!(events.try_recv().is_err());
+    }
+}
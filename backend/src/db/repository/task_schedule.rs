@@ -0,0 +1,274 @@
+use crate::core::error::{Result, TingError};
+use crate::db::manager::DatabaseManager;
+use crate::db::models::{TaskSchedule, TaskScheduleRun};
+use rusqlite::{OptionalExtension, Row};
+use std::sync::Arc;
+
+/// Runs kept per schedule; older history is pruned as new runs are recorded
+const MAX_RUNS_PER_SCHEDULE: i64 = 100;
+
+const SCHEDULE_COLUMNS: &str =
+    "id, name, cron, payload, enabled, last_run_at, created_at, updated_at";
+
+fn map_task_schedule_row(row: &Row<'_>) -> rusqlite::Result<TaskSchedule> {
+    Ok(TaskSchedule {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        cron: row.get(2)?,
+        payload: row.get(3)?,
+        enabled: row.get(4)?,
+        last_run_at: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+/// Repository for recurring task schedules and their run history
+pub struct TaskScheduleRepository {
+    db: Arc<DatabaseManager>,
+}
+
+impl TaskScheduleRepository {
+    pub fn new(db: Arc<DatabaseManager>) -> Self {
+        Self { db }
+    }
+
+    pub async fn find_all(&self) -> Result<Vec<TaskSchedule>> {
+        self.db
+            .execute(|conn| {
+                let mut stmt = conn
+                    .prepare(&format!(
+                        "SELECT {} FROM task_schedules ORDER BY created_at",
+                        SCHEDULE_COLUMNS
+                    ))
+                    .map_err(TingError::DatabaseError)?;
+                let schedules = stmt
+                    .query_map([], map_task_schedule_row)
+                    .map_err(TingError::DatabaseError)?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(TingError::DatabaseError)?;
+                Ok(schedules)
+            })
+            .await
+    }
+
+    pub async fn find_enabled(&self) -> Result<Vec<TaskSchedule>> {
+        self.db
+            .execute(|conn| {
+                let mut stmt = conn
+                    .prepare(&format!(
+                        "SELECT {} FROM task_schedules WHERE enabled = 1 ORDER BY created_at",
+                        SCHEDULE_COLUMNS
+                    ))
+                    .map_err(TingError::DatabaseError)?;
+                let schedules = stmt
+                    .query_map([], map_task_schedule_row)
+                    .map_err(TingError::DatabaseError)?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(TingError::DatabaseError)?;
+                Ok(schedules)
+            })
+            .await
+    }
+
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<TaskSchedule>> {
+        let id = id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.query_row(
+                    &format!(
+                        "SELECT {} FROM task_schedules WHERE id = ?",
+                        SCHEDULE_COLUMNS
+                    ),
+                    [&id],
+                    map_task_schedule_row,
+                )
+                .optional()
+                .map_err(TingError::DatabaseError)
+            })
+            .await
+    }
+
+    pub async fn create(&self, schedule: &TaskSchedule) -> Result<()> {
+        let schedule = schedule.clone();
+        self.db
+            .execute(move |conn| {
+                conn.execute(
+                    "INSERT INTO task_schedules (id, name, cron, payload, enabled, last_run_at, created_at, updated_at) \
+                     VALUES (?, ?, ?, ?, ?, NULL, STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now'), STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now'))",
+                    rusqlite::params![
+                        &schedule.id,
+                        &schedule.name,
+                        &schedule.cron,
+                        &schedule.payload,
+                        schedule.enabled,
+                    ],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    pub async fn update(&self, schedule: &TaskSchedule) -> Result<()> {
+        let schedule = schedule.clone();
+        self.db
+            .execute(move |conn| {
+                conn.execute(
+                    "UPDATE task_schedules SET name = ?, cron = ?, payload = ?, enabled = ?, \
+                     updated_at = STRFTIME('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?",
+                    rusqlite::params![
+                        &schedule.name,
+                        &schedule.cron,
+                        &schedule.payload,
+                        schedule.enabled,
+                        &schedule.id,
+                    ],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<()> {
+        let id = id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.execute("DELETE FROM task_schedules WHERE id = ?", [&id])
+                    .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Store a run, move the schedule's `last_run_at` to it and prune old history
+    pub async fn record_run(&self, run: &TaskScheduleRun) -> Result<()> {
+        let run = run.clone();
+        self.db
+            .transaction(move |tx| {
+                tx.execute(
+                    "INSERT INTO task_schedule_runs (id, schedule_id, triggered_by, triggered_at, task_id, error) \
+                     VALUES (?, ?, ?, ?, ?, ?)",
+                    rusqlite::params![
+                        &run.id,
+                        &run.schedule_id,
+                        &run.triggered_by,
+                        &run.triggered_at,
+                        &run.task_id,
+                        &run.error,
+                    ],
+                )
+                .map_err(TingError::DatabaseError)?;
+                tx.execute(
+                    "UPDATE task_schedules SET last_run_at = ? WHERE id = ?",
+                    rusqlite::params![&run.triggered_at, &run.schedule_id],
+                )
+                .map_err(TingError::DatabaseError)?;
+                tx.execute(
+                    "DELETE FROM task_schedule_runs WHERE schedule_id = ?1 AND id NOT IN ( \
+                         SELECT id FROM task_schedule_runs WHERE schedule_id = ?1 \
+                         ORDER BY triggered_at DESC LIMIT ?2)",
+                    rusqlite::params![&run.schedule_id, MAX_RUNS_PER_SCHEDULE],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Most recent runs first, joined with the tasks they submitted
+    pub async fn find_runs(&self, schedule_id: &str, limit: u32) -> Result<Vec<TaskScheduleRun>> {
+        let schedule_id = schedule_id.to_string();
+        self.db
+            .execute(move |conn| {
+                let mut stmt = conn
+                    .prepare(
+                        "SELECT r.id, r.schedule_id, r.triggered_by, r.triggered_at, r.task_id, r.error, \
+                         t.status, t.error \
+                         FROM task_schedule_runs r LEFT JOIN tasks t ON t.id = r.task_id \
+                         WHERE r.schedule_id = ? ORDER BY r.triggered_at DESC LIMIT ?",
+                    )
+                    .map_err(TingError::DatabaseError)?;
+                let runs = stmt
+                    .query_map(rusqlite::params![&schedule_id, limit], |row| {
+                        Ok(TaskScheduleRun {
+                            id: row.get(0)?,
+                            schedule_id: row.get(1)?,
+                            triggered_by: row.get(2)?,
+                            triggered_at: row.get(3)?,
+                            task_id: row.get(4)?,
+                            error: row.get(5)?,
+                            task_status: row.get(6)?,
+                            task_error: row.get(7)?,
+                        })
+                    })
+                    .map_err(TingError::DatabaseError)?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(TingError::DatabaseError)?;
+                Ok(runs)
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn runs_link_to_their_tasks_and_history_is_bounded() {
+        let db = Arc::new(DatabaseManager::new_in_memory().unwrap());
+        let repo = TaskScheduleRepository::new(db.clone());
+        repo.create(&TaskSchedule {
+            id: "nightly".to_string(),
+            name: "Nightly scan".to_string(),
+            cron: "0 3 * * *".to_string(),
+            payload: r#"{"Custom":{"task_type":"library_scan","data":{}}}"#.to_string(),
+            enabled: true,
+            last_run_at: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        })
+        .await
+        .unwrap();
+        db.execute(|conn| {
+            conn.execute(
+                "INSERT INTO tasks (id, type, status, error) VALUES ('task-1', 'library_scan', 'failed', 'boom')",
+                [],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        for index in 0..MAX_RUNS_PER_SCHEDULE + 5 {
+            repo.record_run(&TaskScheduleRun {
+                id: format!("run-{}", index),
+                schedule_id: "nightly".to_string(),
+                triggered_by: "schedule".to_string(),
+                triggered_at: format!("2026-01-01T00:{:02}:{:02}Z", index / 60, index % 60),
+                task_id: Some("task-1".to_string()).filter(|_| index == MAX_RUNS_PER_SCHEDULE + 4),
+                error: None,
+                task_status: None,
+                task_error: None,
+            })
+            .await
+            .unwrap();
+        }
+
+        let runs = repo.find_runs("nightly", 500).await.unwrap();
+        assert_eq!(runs.len(), MAX_RUNS_PER_SCHEDULE as usize);
+        assert_eq!(runs[0].task_status.as_deref(), Some("failed"));
+        assert_eq!(runs[0].task_error.as_deref(), Some("boom"));
+        assert_eq!(runs[1].task_status, None);
+        assert_eq!(
+            repo.find_by_id("nightly")
+                .await
+                .unwrap()
+                .unwrap()
+                .last_run_at,
+            Some(runs[0].triggered_at.clone())
+        );
+    }
+}
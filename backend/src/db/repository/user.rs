@@ -103,6 +103,85 @@ impl UserRepository {
             .await
     }
 
+    /// Set or clear the user's encrypted Subsonic password
+    pub async fn set_subsonic_password(
+        &self,
+        user_id: &str,
+        encrypted_password: Option<&str>,
+    ) -> Result<()> {
+        let user_id = user_id.to_string();
+        let encrypted_password = encrypted_password.map(str::to_string);
+        self.db
+            .execute(move |conn| {
+                conn.execute(
+                    "UPDATE users SET subsonic_password = ? WHERE id = ?",
+                    rusqlite::params![&encrypted_password, &user_id],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Current podcast feed token version of a user, `None` if the user does not exist
+    pub async fn find_feed_token_version(&self, user_id: &str) -> Result<Option<i64>> {
+        let user_id = user_id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.query_row(
+                    "SELECT feed_token_version FROM users WHERE id = ?",
+                    [&user_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(TingError::DatabaseError)
+            })
+            .await
+    }
+
+    /// Bump the user's podcast feed token version, revoking every feed link
+    /// signed with an older one
+    pub async fn bump_feed_token_version(&self, user_id: &str) -> Result<()> {
+        let user_id = user_id.to_string();
+        self.db
+            .execute(move |conn| {
+                conn.execute(
+                    "UPDATE users SET feed_token_version = feed_token_version + 1 WHERE id = ?",
+                    [&user_id],
+                )
+                .map_err(TingError::DatabaseError)?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Find a user by username together with their encrypted Subsonic password
+    pub async fn find_subsonic_user(
+        &self,
+        username: &str,
+    ) -> Result<Option<(User, Option<String>)>> {
+        let username = username.to_string();
+        self.db.execute(move |conn| {
+            conn.query_row(
+                "SELECT id, username, password_hash, role, created_at, subsonic_password FROM users WHERE username = ?",
+                [&username],
+                |row| {
+                    Ok((
+                        User {
+                            id: row.get(0)?,
+                            username: row.get(1)?,
+                            password_hash: row.get(2)?,
+                            role: row.get(3)?,
+                            created_at: row.get(4)?,
+                        },
+                        row.get(5)?,
+                    ))
+                }
+            ).optional()
+            .map_err(TingError::DatabaseError)
+        }).await
+    }
+
     /// Update user permissions (accessible libraries and books)
     pub async fn update_permissions(
         &self,
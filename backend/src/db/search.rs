@@ -0,0 +1,456 @@
+//! Full-text search over the `book_search` FTS5 indexes
+//!
+//! Triggers (migration v29) queue changed books in `book_search_dirty`;
+//! [`refresh_index`] rebuilds their documents before a search runs. Each book
+//! has two documents sharing one `doc_id`:
+//!
+//! - `book_search` (trigram) answers substring terms of three or more characters.
+//! - `book_search_terms` (unicode61) holds words, CJK unigrams/bigrams and
+//!   pinyin keys, so short terms such as `三叔` or `gc` also use an index.
+//!
+//! Pinyin keys let Latin input such as `dmbj` or `daomu` find `盗墓笔记`.
+
+use crate::core::error::{Result, TingError};
+use pinyin::ToPinyin;
+use rusqlite::{Connection, OptionalExtension, Transaction, TransactionBehavior};
+
+/// Trigram tokens need at least three characters; shorter terms use `book_search_terms`.
+const MIN_TRIGRAM_TERM_CHARS: usize = 3;
+
+/// Ranking for `book_search`; weights follow its column order
+/// (title, author, narrator, series, tags, chapters, description, pinyin).
+const TRIGRAM_RANK: &str = "bm25(book_search, 10.0, 5.0, 3.0, 6.0, 2.0, 1.0, 0.5, 4.0)";
+const TERMS_RANK: &str = "bm25(book_search_terms)";
+
+/// SQL fragments restricting `books b` to the matches of one user query.
+#[derive(Debug, PartialEq)]
+pub(crate) struct SearchFilter {
+    /// Joins to append after `FROM books b`
+    pub joins: String,
+    pub condition: String,
+    pub params: Vec<String>,
+    /// Relevance expression; higher is better
+    pub score: String,
+}
+
+/// Build the filter for a query; every whitespace-separated term must match.
+pub(crate) fn search_filter(query: &str) -> Option<SearchFilter> {
+    let mut trigram_terms = Vec::new();
+    let mut short_terms = Vec::new();
+
+    for term in query.split_whitespace() {
+        let quoted = format!("\"{}\"", term.replace('"', "\"\""));
+        if term.chars().count() >= MIN_TRIGRAM_TERM_CHARS {
+            trigram_terms.push(quoted);
+        } else if term.chars().any(is_cjk) {
+            // CJK unigrams and bigrams are indexed as whole tokens
+            short_terms.push(quoted);
+        } else {
+            short_terms.push(format!("{}*", quoted.to_lowercase()));
+        }
+    }
+
+    if trigram_terms.is_empty() && short_terms.is_empty() {
+        return None;
+    }
+
+    let mut joins = " JOIN book_search_docs d ON d.book_id = b.id".to_string();
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    let mut ranks = Vec::new();
+
+    if !trigram_terms.is_empty() {
+        joins += " JOIN book_search ON book_search.rowid = d.doc_id";
+        conditions.push("book_search MATCH ?");
+        params.push(trigram_terms.join(" "));
+        ranks.push(TRIGRAM_RANK);
+    }
+    if !short_terms.is_empty() {
+        joins += " JOIN book_search_terms ON book_search_terms.rowid = d.doc_id";
+        conditions.push("book_search_terms MATCH ?");
+        params.push(short_terms.join(" "));
+        ranks.push(TERMS_RANK);
+    }
+
+    Some(SearchFilter {
+        joins,
+        condition: format!("({})", conditions.join(" AND ")),
+        params,
+        score: format!("-({})", ranks.join(" + ")),
+    })
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF
+    )
+}
+
+/// Full pinyin and initials for every text containing Chinese characters,
+/// e.g. `盗墓笔记` becomes `daomubiji dmbj`.
+pub fn pinyin_keys<'a>(texts: impl IntoIterator<Item = &'a str>) -> String {
+    let mut keys: Vec<String> = Vec::new();
+
+    for text in texts {
+        let mut full = String::new();
+        let mut initials = String::new();
+        let mut has_han = false;
+
+        for ch in text.chars() {
+            if let Some(pinyin) = ch.to_pinyin() {
+                has_han = true;
+                full.push_str(pinyin.plain());
+                initials.push_str(pinyin.first_letter());
+            } else if ch.is_ascii_alphanumeric() {
+                let ch = ch.to_ascii_lowercase();
+                full.push(ch);
+                initials.push(ch);
+            }
+        }
+
+        if has_han {
+            for key in [full, initials] {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+    }
+
+    keys.join(" ")
+}
+
+/// Tokens for `book_search_terms`: lowercase words plus every CJK character
+/// and adjacent CJK pair, e.g. `盗墓笔记 2` becomes `盗 盗墓 墓 墓笔 笔 笔记 记 2`.
+pub fn index_terms<'a>(texts: impl IntoIterator<Item = &'a str>) -> String {
+    let mut terms = Vec::new();
+
+    for text in texts {
+        let mut word = String::new();
+        let mut previous_cjk: Option<char> = None;
+
+        for ch in text.chars() {
+            if is_cjk(ch) {
+                if !word.is_empty() {
+                    terms.push(std::mem::take(&mut word));
+                }
+                if let Some(previous) = previous_cjk {
+                    terms.push(format!("{}{}", previous, ch));
+                }
+                terms.push(ch.to_string());
+                previous_cjk = Some(ch);
+            } else {
+                previous_cjk = None;
+                if ch.is_alphanumeric() {
+                    word.extend(ch.to_lowercase());
+                } else if !word.is_empty() {
+                    terms.push(std::mem::take(&mut word));
+                }
+            }
+        }
+        if !word.is_empty() {
+            terms.push(word);
+        }
+    }
+
+    terms.join(" ")
+}
+
+struct SearchDocument {
+    title: String,
+    author: String,
+    narrator: String,
+    series: String,
+    tags: String,
+    chapters: String,
+    description: String,
+}
+
+fn load_document(conn: &Connection, book_id: &str) -> Result<Option<SearchDocument>> {
+    let book = conn
+        .query_row(
+            "SELECT COALESCE(title, ''), COALESCE(author, ''), COALESCE(narrator, ''), \
+             TRIM(COALESCE(tags, '') || ' ' || COALESCE(genre, '')), COALESCE(description, '') \
+             FROM books WHERE id = ?",
+            [book_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(TingError::DatabaseError)?;
+    let Some((title, author, narrator, tags, description)) = book else {
+        return Ok(None);
+    };
+
+    let joined = |sql: &str| -> Result<String> {
+        let mut stmt = conn.prepare(sql).map_err(TingError::DatabaseError)?;
+        let titles = stmt
+            .query_map([book_id], |row| row.get::<_, String>(0))
+            .map_err(TingError::DatabaseError)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(TingError::DatabaseError)?;
+        Ok(titles.join("\n"))
+    };
+
+    Ok(Some(SearchDocument {
+        title,
+        author,
+        narrator,
+        series: joined(
+            "SELECT s.title FROM series_books sb JOIN series s ON s.id = sb.series_id \
+             WHERE sb.book_id = ?",
+        )?,
+        tags,
+        chapters: joined(
+            "SELECT title FROM chapters WHERE book_id = ? AND title IS NOT NULL \
+             ORDER BY is_extra, chapter_index",
+        )?,
+        description,
+    }))
+}
+
+fn write_document(conn: &Connection, book_id: &str) -> Result<()> {
+    let delete_existing = |table: &str| {
+        conn.execute(
+            &format!(
+                "DELETE FROM {} WHERE rowid IN \
+                 (SELECT doc_id FROM book_search_docs WHERE book_id = ?)",
+                table
+            ),
+            [book_id],
+        )
+        .map_err(TingError::DatabaseError)
+    };
+    delete_existing("book_search")?;
+    delete_existing("book_search_terms")?;
+
+    let Some(doc) = load_document(conn, book_id)? else {
+        conn.execute("DELETE FROM book_search_docs WHERE book_id = ?", [book_id])
+            .map_err(TingError::DatabaseError)?;
+        return Ok(());
+    };
+
+    conn.execute(
+        "INSERT OR IGNORE INTO book_search_docs (book_id) VALUES (?)",
+        [book_id],
+    )
+    .map_err(TingError::DatabaseError)?;
+    let doc_id: i64 = conn
+        .query_row(
+            "SELECT doc_id FROM book_search_docs WHERE book_id = ?",
+            [book_id],
+            |row| row.get(0),
+        )
+        .map_err(TingError::DatabaseError)?;
+
+    let pinyin = pinyin_keys([
+        doc.title.as_str(),
+        doc.author.as_str(),
+        doc.narrator.as_str(),
+        doc.series.as_str(),
+    ]);
+    conn.execute(
+        "INSERT INTO book_search (rowid, title, author, narrator, series, tags, chapters, description, pinyin) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        rusqlite::params![
+            doc_id,
+            &doc.title,
+            &doc.author,
+            &doc.narrator,
+            &doc.series,
+            &doc.tags,
+            &doc.chapters,
+            &doc.description,
+            &pinyin
+        ],
+    )
+    .map_err(TingError::DatabaseError)?;
+
+    let terms = index_terms([
+        doc.title.as_str(),
+        doc.author.as_str(),
+        doc.narrator.as_str(),
+        doc.series.as_str(),
+        doc.tags.as_str(),
+        doc.chapters.as_str(),
+        pinyin.as_str(),
+    ]);
+    conn.execute(
+        "INSERT INTO book_search_terms (rowid, terms) VALUES (?, ?)",
+        rusqlite::params![doc_id, &terms],
+    )
+    .map_err(TingError::DatabaseError)?;
+    Ok(())
+}
+
+/// Rebuild the documents of every book queued in `book_search_dirty`.
+///
+/// Cheap when nothing is queued; otherwise runs in one immediate transaction
+/// so concurrent searches do not index the same books twice.
+pub fn refresh_index(conn: &Connection) -> Result<usize> {
+    let pending: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM book_search_dirty)",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(TingError::DatabaseError)?;
+    if !pending {
+        return Ok(0);
+    }
+
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)
+        .map_err(TingError::DatabaseError)?;
+    let book_ids = {
+        let mut stmt = tx
+            .prepare("SELECT book_id FROM book_search_dirty")
+            .map_err(TingError::DatabaseError)?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(TingError::DatabaseError)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(TingError::DatabaseError)?;
+        ids
+    };
+
+    for book_id in &book_ids {
+        write_document(&tx, book_id)?;
+    }
+    tx.execute("DELETE FROM book_search_dirty", [])
+        .map_err(TingError::DatabaseError)?;
+    tx.commit().map_err(TingError::DatabaseError)?;
+    Ok(book_ids.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::manager::DatabaseManager;
+    use crate::db::models::Book;
+    use crate::db::repository::{BookRepository, Repository};
+    use std::sync::Arc;
+
+    fn book(id: &str, title: &str, author: &str) -> Book {
+        Book {
+            id: id.to_string(),
+            library_id: "library-1".to_string(),
+            title: Some(title.to_string()),
+            author: Some(author.to_string()),
+            narrator: None,
+            cover_url: None,
+            theme_color: None,
+            description: None,
+            skip_intro: 0,
+            skip_outro: 0,
+            path: format!("/{}", id),
+            hash: format!("{}-hash", id),
+            tags: None,
+            genre: None,
+            year: None,
+            created_at: String::new(),
+            manual_corrected: 0,
+            match_pattern: None,
+            chapter_regex: None,
+        }
+    }
+
+    #[test]
+    fn pinyin_keys_include_full_spelling_and_initials() {
+        assert_eq!(pinyin_keys(["盗墓笔记"]), "daomubiji dmbj");
+        assert_eq!(
+            pinyin_keys(["鬼吹灯2", "Plain Title", "南派三叔"]),
+            "guichuideng2 gcd2 nanpaisanshu npss"
+        );
+        assert_eq!(pinyin_keys(["Dune"]), "");
+    }
+
+    #[test]
+    fn index_terms_split_cjk_into_unigrams_and_bigrams() {
+        assert_eq!(
+            index_terms(["盗墓笔记2", "Frank Herbert"]),
+            "盗 盗墓 墓 墓笔 笔 笔记 记 2 frank herbert"
+        );
+    }
+
+    #[test]
+    fn terms_are_routed_by_length_and_script() {
+        let filter = search_filter(r#"dmbj 三叔 ab say"hi""#).unwrap();
+        assert_eq!(
+            filter.params,
+            vec![
+                r#""dmbj" "say""hi""""#.to_string(),
+                r#""三叔" "ab"*"#.to_string()
+            ]
+        );
+        assert!(filter.joins.contains("JOIN book_search ON"));
+        assert!(filter.joins.contains("JOIN book_search_terms ON"));
+
+        let short = search_filter("盗墓").unwrap();
+        assert!(!short.joins.contains("JOIN book_search ON"));
+        assert_eq!(short.params, vec!["\"盗墓\"".to_string()]);
+
+        assert_eq!(search_filter("   "), None);
+    }
+
+    #[tokio::test]
+    async fn index_follows_books_chapters_and_pinyin() {
+        let db = Arc::new(DatabaseManager::new_in_memory().expect("create test database"));
+        db.execute(|conn| {
+            conn.execute(
+                "INSERT INTO libraries (id, name, type, url) VALUES ('library-1', 'Test', 'local', '')",
+                [],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        let repo = BookRepository::new(db.clone());
+        repo.create(&book("book-1", "盗墓笔记", "南派三叔"))
+            .await
+            .unwrap();
+        repo.create(&book("book-2", "Dune", "Frank Herbert"))
+            .await
+            .unwrap();
+        db.execute(|conn| {
+            conn.execute(
+                "INSERT INTO chapters (id, book_id, title, path) VALUES ('chapter-1', 'book-2', 'The Spice Must Flow', '/dune/1')",
+                [],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let search = |query: &'static str, user: &'static str, admin: bool| {
+            let repo = BookRepository::new(db.clone());
+            async move {
+                let (hits, total) = repo.search(user, admin, query, None, 10, 0).await.unwrap();
+                assert_eq!(hits.len() as u32, total);
+                hits.into_iter().map(|hit| hit.book.id).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(search("dmbj", "", true).await, vec!["book-1"]);
+        assert_eq!(search("盗墓", "", true).await, vec!["book-1"]);
+        assert_eq!(search("三叔", "", true).await, vec!["book-1"]);
+        assert_eq!(search("spice", "", true).await, vec!["book-2"]);
+        assert_eq!(search("fr", "", true).await, vec!["book-2"]);
+
+        repo.update(&book("book-1", "鬼吹灯", "天下霸唱"))
+            .await
+            .unwrap();
+        assert!(search("dmbj", "", true).await.is_empty());
+        assert_eq!(search("gcd", "", true).await, vec!["book-1"]);
+
+        repo.delete("book-2").await.unwrap();
+        assert!(search("spice", "", true).await.is_empty());
+        assert!(search("gcd", "user-1", false).await.is_empty());
+    }
+}
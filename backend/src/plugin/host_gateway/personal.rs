@@ -1,6 +1,7 @@
 use super::{required_string_param, string_param, usize_param, PluginHostGateway, PluginHostUser};
 use crate::core::error::{Result, TingError};
-use crate::db::models::{Favorite, Playlist, PlaylistItem, UserSettings};
+use crate::db::models::{Favorite, Playlist, PlaylistItem, SmartPlaylistRules, UserSettings};
+use crate::db::repository::playlist::{encode_smart_rules, smart_rules};
 use crate::db::repository::Repository;
 use serde_json::{Map, Value};
 use uuid::Uuid;
@@ -39,8 +40,7 @@ impl PluginHostGateway {
             .or_else(|_| required_string_param(params, "id"))?;
         let playlist = self.load_owned_playlist(user, &playlist_id).await?;
         let plugin_items = self
-            .playlist_repo
-            .find_items_by_playlist(&playlist.id)
+            .playlist_items(user, &playlist)
             .await?
             .into_iter()
             .map(plugin_host_playlist_item_value)
@@ -59,6 +59,33 @@ impl PluginHostGateway {
         params: &Value,
     ) -> Result<Value> {
         let name = required_playlist_name(params)?;
+        let playlist_type = string_param(params, "type")
+            .map(|value| value.to_ascii_lowercase())
+            .unwrap_or_else(|| "manual".to_string());
+        let raw_items = params
+            .get("items")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let smart_rules = match playlist_type.as_str() {
+            "manual" => None,
+            "smart" => {
+                if !raw_items.is_empty() {
+                    return Err(TingError::InvalidRequest(
+                        "smart playlists cannot have items".to_string(),
+                    ));
+                }
+                let rules = smart_rules_param(params)?.ok_or_else(|| {
+                    TingError::InvalidRequest("smart playlists require 'rules'".to_string())
+                })?;
+                Some(encode_smart_rules(rules)?)
+            }
+            _ => {
+                return Err(TingError::InvalidRequest(
+                    "playlist type must be 'manual' or 'smart'".to_string(),
+                ))
+            }
+        };
 
         let now = chrono::Utc::now().to_rfc3339();
         let playlist = Playlist {
@@ -66,17 +93,14 @@ impl PluginHostGateway {
             user_id: user.id.clone(),
             title: name,
             description: optional_trimmed_string(params, "description"),
+            playlist_type,
+            smart_rules,
             created_at: now.clone(),
             updated_at: now,
         };
 
         self.playlist_repo.create(&playlist).await?;
 
-        let raw_items = params
-            .get("items")
-            .and_then(Value::as_array)
-            .cloned()
-            .unwrap_or_default();
         if !raw_items.is_empty() {
             let items = parse_playlist_items(&raw_items)?;
             self.playlist_repo
@@ -85,8 +109,7 @@ impl PluginHostGateway {
         }
 
         let stored_items = self
-            .playlist_repo
-            .find_items_by_playlist(&playlist.id)
+            .playlist_items(user, &playlist)
             .await?
             .into_iter()
             .map(plugin_host_playlist_item_value)
@@ -127,11 +150,23 @@ impl PluginHostGateway {
             None => existing.description.clone(),
         };
 
+        let smart_rules = match smart_rules_param(params)? {
+            Some(rules) if existing.is_smart() => Some(encode_smart_rules(rules)?),
+            Some(_) => {
+                return Err(TingError::InvalidRequest(
+                    "only smart playlists have rules".to_string(),
+                ))
+            }
+            None => existing.smart_rules.clone(),
+        };
+
         let updated = Playlist {
             id: existing.id.clone(),
             user_id: existing.user_id.clone(),
             title,
             description,
+            playlist_type: existing.playlist_type.clone(),
+            smart_rules,
             created_at: existing.created_at.clone(),
             updated_at: chrono::Utc::now().to_rfc3339(),
         };
@@ -163,6 +198,7 @@ impl PluginHostGateway {
     ) -> Result<Value> {
         let playlist_id = required_string_param(params, "playlist_id")?;
         let existing = self.load_owned_playlist(user, &playlist_id).await?;
+        ensure_manual_playlist(&existing)?;
 
         let (item_type, item_id) = required_playlist_item_pair(params)?;
         let item_order = params
@@ -196,6 +232,7 @@ impl PluginHostGateway {
     ) -> Result<Value> {
         let playlist_id = required_string_param(params, "playlist_id")?;
         let existing = self.load_owned_playlist(user, &playlist_id).await?;
+        ensure_manual_playlist(&existing)?;
 
         let (item_type, item_id) = required_playlist_item_pair(params)?;
         self.playlist_repo
@@ -371,14 +408,50 @@ impl PluginHostGateway {
         }
         Ok(playlist)
     }
+
+    /// Stored items of a manual playlist, or the books a smart playlist resolves to
+    async fn playlist_items(
+        &self,
+        user: &PluginHostUser,
+        playlist: &Playlist,
+    ) -> Result<Vec<PlaylistItem>> {
+        if !playlist.is_smart() {
+            return self
+                .playlist_repo
+                .find_items_by_playlist(&playlist.id)
+                .await;
+        }
+
+        let rules = smart_rules(playlist)?;
+        let books = self
+            .playlist_repo
+            .find_books_by_rules(&rules, &user.id, user.is_admin())
+            .await?;
+        Ok(books
+            .into_iter()
+            .enumerate()
+            .map(|(idx, book)| PlaylistItem {
+                playlist_id: playlist.id.clone(),
+                item_type: "book".to_string(),
+                item_id: book.id,
+                item_order: (idx as i32) + 1,
+            })
+            .collect())
+    }
 }
 
 fn plugin_host_playlist_value(playlist: Playlist) -> Value {
+    let rules = playlist
+        .smart_rules
+        .as_deref()
+        .and_then(|rules| serde_json::from_str::<Value>(rules).ok());
     serde_json::json!({
         "id": playlist.id,
         "user_id": playlist.user_id,
         "name": playlist.title,
         "description": playlist.description,
+        "type": playlist.playlist_type,
+        "rules": rules,
         "created_at": playlist.created_at,
         "updated_at": playlist.updated_at,
     })
@@ -415,6 +488,24 @@ fn required_playlist_name(params: &Value) -> Result<String> {
     })
 }
 
+fn smart_rules_param(params: &Value) -> Result<Option<SmartPlaylistRules>> {
+    match params.get("rules") {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value.clone())
+            .map(Some)
+            .map_err(|e| TingError::InvalidRequest(format!("invalid playlist rules: {}", e))),
+    }
+}
+
+fn ensure_manual_playlist(playlist: &Playlist) -> Result<()> {
+    if playlist.is_smart() {
+        return Err(TingError::InvalidRequest(
+            "smart playlist items come from its rules".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 fn optional_trimmed_string(params: &Value, key: &str) -> Option<String> {
     params
         .get(key)
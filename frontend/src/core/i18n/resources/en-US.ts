@@ -570,6 +570,10 @@ const enUS = {
         label: "Scan Completed",
         description: "A library scan completed",
       },
+      libraryNewEpisodes: {
+        label: "New Episodes",
+        description: "An RSS library scan found new episodes",
+      },
     },
   },
   playlists: {
@@ -1067,6 +1071,7 @@ const enUS = {
     running: "Running",
     cancelled: "Cancelled",
     queued: "Queued",
+    waiting: "Waiting for dependencies",
     unknownStatus: "Unknown Status",
     messages: {
       "logging.initialized": "Logging initialized",
@@ -1279,8 +1284,10 @@ const enUS = {
       "scan.webdav.scanning": "Scanning WebDAV directory...",
       "scan.rss.fetching": "Fetching RSS: {{url}}",
       "scan.rss.fetched": "RSS fetched, found {{count}} audio items",
+      "scan.rss.not_modified": "RSS unchanged since the last scan, skipped",
       "scan.rss.completed":
         "RSS library scan completed with {{episodes}} audio items",
+      "scan.resuming": "Resuming interrupted scan, {{count}} directories already done",
       "scan.audio_dirs.found": "Found {{count}} directories with audio files",
       "scan.item.processing": "Processing ({{current}}/{{total}}): {{name}}",
       "scan.chapter.processing": "Processing chapter {{current}}/{{total}}",
@@ -1289,6 +1296,19 @@ const enUS = {
         "Scan completed: {{total}} total, {{created}} created, {{updated}} updated, {{deleted}} deleted, {{errors}} errors",
       "scan.library.completed":
         'Library "{{library_name}}" scan completed: {{created}} created, {{updated}} updated, {{deleted}} deleted',
+      "workflow.progress": "{{completed}}/{{total}} steps completed",
+      "episodes.download.progress":
+        "Downloading episode {{current}}/{{total}}: {{title}}",
+      "episodes.download.completed":
+        'Episodes of "{{library_name}}" updated: {{downloaded}} downloaded, {{deleted}} deleted, {{failed}} failed',
+      "loudness.analysis.progress":
+        "Analysing loudness {{current}}/{{total}}: {{title}}",
+      "loudness.analysis.completed":
+        "Loudness analysis completed: {{analysed}} analysed, {{skipped}} skipped, {{failed}} failed",
+      "skip.analysis.progress":
+        "Looking for intros and outros {{current}}/{{total}}: {{title}}",
+      "skip.analysis.completed":
+        "Intro/outro analysis completed: {{chapters}} chapters, {{intros}} intros and {{outros}} outros found for review",
       "library.watcher.start_failed":
         "Library watcher failed to start: {{error}}",
       "metadata.chapter.writing":
@@ -533,6 +533,10 @@ const zhCN = {
         label: "扫描完成",
         description: "媒体库扫描任务完成",
       },
+      libraryNewEpisodes: {
+        label: "RSS 新单集",
+        description: "RSS 媒体库扫描发现新单集",
+      },
     },
   },
   playlists: {
@@ -1025,6 +1029,7 @@ const zhCN = {
     running: "进行中",
     cancelled: "已取消",
     queued: "等待中",
+    waiting: "等待前置任务",
     unknownStatus: "未知状态",
     messages: {
       "logging.initialized": "日志系统初始化完成",
@@ -1197,7 +1202,9 @@ const zhCN = {
       "scan.webdav.scanning": "正在扫描 WebDAV 目录...",
       "scan.rss.fetching": "正在获取 RSS：{{url}}",
       "scan.rss.fetched": "RSS 获取完成，发现 {{count}} 个音频条目",
+      "scan.rss.not_modified": "RSS 自上次扫描以来没有变化，已跳过",
       "scan.rss.completed": "RSS 库扫描完成，发现 {{episodes}} 个音频条目",
+      "scan.resuming": "继续上次中断的扫描，已完成 {{count}} 个目录",
       "scan.audio_dirs.found": "找到 {{count}} 个包含音频文件的目录",
       "scan.item.processing": "处理中 ({{current}}/{{total}})：{{name}}",
       "scan.chapter.processing": "处理章节 {{current}}/{{total}}",
@@ -1206,6 +1213,16 @@ const zhCN = {
         "扫描完成：共 {{total}} 本，新增 {{created}} 本，更新 {{updated}} 本，删除 {{deleted}} 本，错误 {{errors}} 个",
       "scan.library.completed":
         "存储库「{{library_name}}」扫描完成，新增 {{created}} 本，更新 {{updated}} 本，删除 {{deleted}} 本",
+      "workflow.progress": "{{completed}}/{{total}} 步已完成",
+      "episodes.download.progress": "正在下载单集 {{current}}/{{total}}：{{title}}",
+      "episodes.download.completed":
+        "存储库「{{library_name}}」单集已更新，下载 {{downloaded}} 集，删除 {{deleted}} 集，失败 {{failed}} 集",
+      "loudness.analysis.progress": "正在分析响度 {{current}}/{{total}}：{{title}}",
+      "loudness.analysis.completed":
+        "响度分析完成，分析 {{analysed}} 章，跳过 {{skipped}} 章，失败 {{failed}} 章",
+      "skip.analysis.progress": "正在分析片头片尾 {{current}}/{{total}}：{{title}}",
+      "skip.analysis.completed":
+        "片头片尾分析完成，分析 {{chapters}} 章，发现片头 {{intros}} 章、片尾 {{outros}} 章，待审核",
       "library.watcher.start_failed": "启动库监听器失败：{{error}}",
       "metadata.chapter.writing":
         "正在写入第 {{current}}/{{total}} 章：{{chapter_title}}",
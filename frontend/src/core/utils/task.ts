@@ -26,6 +26,7 @@ type TaskStatusLabels = {
     running: string;
     cancelled: string;
     queued: string;
+    waiting: string;
     unknown: string;
 };
 
@@ -45,6 +46,7 @@ const defaultTaskStatusLabels: TaskStatusLabels = {
     running: 'Running',
     cancelled: 'Cancelled',
     queued: 'Queued',
+    waiting: 'Waiting for dependencies',
     unknown: 'Unknown status',
 };
 
@@ -98,6 +100,7 @@ export const getTaskStatusText = (
         case 'running': return labels.running;
         case 'cancelled': return labels.cancelled;
         case 'queued': return labels.queued;
+        case 'waiting': return labels.waiting;
         default: return labels.unknown;
     }
 };
@@ -35,7 +35,7 @@ interface LogEntry {
   message_params?: Record<string, unknown>;
   fields?: Record<string, unknown>;
   task_id?: string;
-  task_status?: 'queued' | 'running' | 'completed' | 'failed' | 'cancelled';
+  task_status?: 'waiting' | 'queued' | 'running' | 'completed' | 'failed' | 'cancelled';
   task_type?: string;
 }
 
@@ -204,6 +204,7 @@ const LogsPage: React.FC = () => {
       case 'running': return t('adminLogs.running');
       case 'cancelled': return t('adminLogs.cancelled');
       case 'queued': return t('adminLogs.queued');
+      case 'waiting': return t('adminLogs.waiting');
       default: return t('adminLogs.unknownStatus');
     }
   };
@@ -482,7 +483,7 @@ const LogsPage: React.FC = () => {
                         </div>
                         
                         <div className="flex items-center gap-2 order-3 sm:order-2 mt-1 mb-1">
-                          {(log.task_status === 'running' || log.task_status === 'queued') ? (
+                          {(log.task_status === 'running' || log.task_status === 'queued' || log.task_status === 'waiting') ? (
                             <button
                               onClick={() => handleCancelTask(log.task_id as string)}
                               className="p-1.5 text-red-500 hover:bg-red-50 dark:hover:bg-red-900/20 rounded-lg transition-colors"
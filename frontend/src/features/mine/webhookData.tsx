@@ -143,6 +143,7 @@ export const fallbackEvents: NotificationEventOption[] = [
   { id: 'book.created', label: 'Book Imported', description: 'A work was created or imported' },
   { id: 'book.deleted', label: 'Book Deleted', description: 'A work was deleted' },
   { id: 'library.scan_completed', label: 'Scan Completed', description: 'A library scan completed' },
+  { id: 'library.new_episodes', label: 'New Episodes', description: 'An RSS library scan found new episodes' },
 ];
 
 export const createEmptyDraft = (defaultEvent = 'user.login'): WebhookDraft => ({
@@ -163,6 +164,7 @@ export const notificationEventKeyById: Record<string, string> = {
   'book.created': 'bookCreated',
   'book.deleted': 'bookDeleted',
   'library.scan_completed': 'libraryScanCompleted',
+  'library.new_episodes': 'libraryNewEpisodes',
 };
 
 export const translateEventOption = (event: NotificationEventOption, t: TFunction): NotificationEventOption => {